@@ -1,38 +1,170 @@
-// The tasks for this chapter are split into the different methods of Application.
-// Go through the methods from top to bottom.
-// Once all your methods are fully implemented, start your application and make sure
-// it displays two white triangles.
-// You can of course already try running your application inbetween to ensure no
-// validation errors are raised.
-// Afterwards, continue with adjusting your shaders in `application.wgsl`.
+// The setup of the application is split into the different methods of Application.
+// Go through the methods from top to bottom to follow how a frame gets onto the screen.
 //
 // Refer to https://docs.rs/wgpu/latest/wgpu/ to learn about a type's constructor,
 // methods and attributes.
 use std::{borrow::Cow, sync::Arc};
 
+use cgmath::Point3;
 use color_eyre::{
     eyre::{Context, OptionExt},
     Result,
 };
 use wgpu::{
-    Backends, BlendState, ColorWrites, CommandEncoderDescriptor, DeviceDescriptor, Features,
-    FragmentState, Instance, InstanceDescriptor, InstanceFlags, MultisampleState,
-    PipelineCompilationOptions, PipelineLayoutDescriptor, PowerPreference, PrimitiveState,
-    RenderBundleDescriptor, RenderPassDescriptor, RenderPipeline, RequestAdapterOptions,
-    ShaderModuleDescriptor, VertexState,
+    util::{BufferInitDescriptor, DeviceExt},
+    Backends, BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayoutDescriptor,
+    BindGroupLayoutEntry, BindingType, BlendState, Buffer, BufferBindingType, BufferUsages, Color,
+    ColorWrites, CommandEncoder, CommandEncoderDescriptor, CompareFunction, DepthStencilState,
+    DeviceDescriptor, Extent3d, Features, FragmentState, Instance, InstanceDescriptor,
+    InstanceFlags, Limits, LoadOp, MultisampleState, Operations, PipelineCompilationOptions,
+    PipelineLayoutDescriptor, PowerPreference, PresentMode, PrimitiveState,
+    RenderPassColorAttachment, RenderPassDepthStencilAttachment, RenderPassDescriptor,
+    RenderPipeline, RequestAdapterOptions, ShaderModuleDescriptor, ShaderStages, StoreOp,
+    TextureDescriptor, TextureDimension, TextureFormat, TextureUsages, TextureView,
+    TextureViewDescriptor, VertexState,
 };
-use winit::{dpi::PhysicalSize, window::Window};
+use winit::{dpi::PhysicalSize, event::MouseButton, keyboard::KeyCode, window::Window};
+
+pub const DEPTH_FORMAT: TextureFormat = TextureFormat::Depth32Float;
+/// Distance in physical pixels the cursor may move between pressing and releasing
+/// the left mouse button for the press to count as a click rather than a drag.
+const CLICK_TOLERANCE: f64 = 4.0;
+
+use crate::{
+    benchmark::{Benchmark, Report},
+    billboards::BillboardRenderer,
+    camera::{Camera, CameraUniform},
+    camera_controller::OrbitController,
+    clock::FixedClock,
+    clusters::LightClusters,
+    color_grading::{ColorGrading, Lut},
+    decals::DecalRenderer,
+    deferred::{DeferredLighting, RenderPath},
+    environment::EnvironmentMap,
+    fog::VolumetricFog,
+    fractal::FractalViewer,
+    frame_pacing::FramePacer,
+    globals::Globals,
+    gpu_timer::GpuTimer,
+    ground::GroundPlane,
+    hud::Hud,
+    input::{InputEvent, InputRecorder, InputReplay},
+    life::GameOfLife,
+    light_debug::LightDebug,
+    lights::Lights,
+    meshes::MeshRenderer,
+    oit::WeightedBlendedOit,
+    options::Options,
+    outline::SelectionOutline,
+    overlay::Overlay,
+    paintings::PaintingRenderer,
+    primitives::{DepthMode, PrimitiveRenderer},
+    probes::ReflectionProbes,
+    raymarch::RayMarching,
+    reflections::PlanarReflection,
+    render_scale::RenderScale,
+    scene::{
+        Hit, Scene, TENTACLE_JOINTS, TENTACLE_LENGTH, TENTACLE_RADIUS, WATER_CENTER, WATER_EXTENT,
+    },
+    shadertoy::ShaderToy,
+    shapes::ShapeRenderer,
+    skinning::{SkinnedMesh, SkinnedMeshData},
+    ssr::ScreenSpaceReflections,
+    streaming::TextureStreamer,
+    surface::SurfaceSupport,
+    terrain::DisplacedTerrain,
+    text::TextRenderer,
+    water::WaterSurface,
+};
+
+/// What the application shows, selected with the number keys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Demo {
+    /// The lit 3D scene.
+    Scene,
+    /// Conway's Game of Life, see [`GameOfLife`].
+    Life,
+    /// A scene ray marched in `raymarch.wgsl`, see [`RayMarching`].
+    RayMarching,
+    /// The Mandelbrot set and its Julia sets, see [`FractalViewer`].
+    Fractal,
+    /// An image shader written against ShaderToy's inputs, see [`ShaderToy`].
+    ShaderToy,
+}
 
 pub struct Application {
     surface_config: wgpu::SurfaceConfiguration,
     surface: wgpu::Surface<'static>,
+    surface_support: SurfaceSupport,
+    adapter_info: wgpu::AdapterInfo,
+    /// Limits granted to the device.
+    limits: Limits,
     device: wgpu::Device,
     queue: wgpu::Queue,
+    render_scale: RenderScale,
+    color_grading: ColorGrading,
+    depth_view: TextureView,
+    oit: WeightedBlendedOit,
     render_pipeline: RenderPipeline,
+    ground_plane: GroundPlane,
+    primitive_renderer: PrimitiveRenderer,
+    mesh_renderer: MeshRenderer,
+    lights: Lights,
+    reflection_probes: ReflectionProbes,
+    light_clusters: LightClusters,
+    deferred_lighting: DeferredLighting,
+    environment: EnvironmentMap,
+    ssr: ScreenSpaceReflections,
+    render_path: RenderPath,
+    /// Whether the forward paths draw the meshes' depth in a prepass before shading them.
+    depth_prepass: bool,
+    billboard_renderer: BillboardRenderer,
+    painting_renderer: PaintingRenderer,
+    texture_streamer: TextureStreamer,
+    text_renderer: TextRenderer,
+    shape_renderer: ShapeRenderer,
+    hud: Hud,
+    decal_renderer: DecalRenderer,
+    fog: VolumetricFog,
+    light_debug: LightDebug,
+    reflection: PlanarReflection,
+    water: WaterSurface,
+    overlay: Overlay,
+    outline: SelectionOutline,
+    demo: Demo,
+    scene: Scene,
+    terrain: DisplacedTerrain,
+    tentacle: SkinnedMesh,
+    life: GameOfLife,
+    ray_marching: RayMarching,
+    fractal: FractalViewer,
+    shadertoy: ShaderToy,
+    globals: Globals,
+    camera: Camera,
+    /// Position of the camera before the last tick, see [`FixedClock::alpha`].
+    previous_eye: Point3<f32>,
+    camera_buffer: Buffer,
+    camera_bind_group: BindGroup,
+    camera_controller: OrbitController,
+    /// Last cursor position in physical pixels, for picking.
+    cursor: Option<(f64, f64)>,
+    /// Cursor position when the left mouse button was pressed, to tell clicks from drags.
+    click_start: Option<(f64, f64)>,
+    clock: FixedClock,
+    pending_input: Vec<InputEvent>,
+    input_recorder: Option<InputRecorder>,
+    input_replay: Option<InputReplay>,
+    frame_pacer: FramePacer,
+    gpu_timer: Option<GpuTimer>,
+    benchmark: Option<Benchmark>,
 }
 
 impl Application {
-    pub async fn new(window: Arc<Window>, size: PhysicalSize<u32>) -> Result<Self> {
+    pub async fn new(
+        window: Arc<Window>,
+        size: PhysicalSize<u32>,
+        options: &Options,
+    ) -> Result<Self> {
         // 1. We first must create a `wgpu::Instance`.
         // This is the entrypoint to all communication with wgpu.
         let instance = Instance::new(InstanceDescriptor {
@@ -68,33 +200,79 @@ impl Application {
         // executing any GPU commands we feed it.
         // This logical handle is called a "device" and can be requested from the adapter
         // we created above.
-        // As we have no special requirements at this moment we just request the default
-        // features and limits.
+        // We request every WebGPU feature the adapter supports (such as timestamp queries
+        // for measuring GPU frame times) and the limits we make use of, see `required_limits`.
+        // Requesting a feature or a limit the adapter does not support would fail, so the
+        // mask is restricted to the adapter's features, and the limits to its limits.
         // Requesting a device from an adapter returns a tuple containing both the device
         // and a queue to which we can submit GPU commands.
         // Note that requesting a device again is an asynchronous operation.
+        let adapter_info = adapter.get_info();
+        log::info!(
+            "Adapter: {} ({:?})",
+            adapter_info.name,
+            adapter_info.backend
+        );
         let (device, queue) = adapter
             .request_device(
                 &DeviceDescriptor {
                     label: Some("GPU Device"),
-                    required_features: Features::all_webgpu_mask(),
+                    required_features: adapter.features() & Features::all_webgpu_mask(),
+                    required_limits: required_limits(&adapter.limits()),
                     ..Default::default()
                 },
                 None,
             )
-            .await?;
+            .await
+            .wrap_err("failed to request device")?;
+        // What the device was actually granted, which is what subsystems must stay within
+        let limits = device.limits();
+        log::info!(
+            "Limits: {} texture size, {} bind groups, {} storage buffer binding size",
+            limits.max_texture_dimension_2d,
+            limits.max_bind_groups,
+            limits.max_storage_buffer_binding_size
+        );
 
         // 5. Get the default config for our adapter from the surface, using the size
         // we got as parameter to our constructor. Make sure the size has a width and
-        // height of at least 1, otherwise creating the surface may fail.
+        // height of at least 1, otherwise creating the surface may fail, and fits into
+        // a texture.
         // This only returns None if the surface and adapter are incompatible.
         // As we requested the adapter with `compatible_surface`, this is never the case.
-        let surface_config = surface
-            .get_default_config(&adapter, size.width.min(1), size.height.min(1))
+        let (width, height) = surface_size(&limits, size.width, size.height);
+        let mut surface_config = surface
+            .get_default_config(&adapter, width, height)
             .expect("surface is compatible");
+        let surface_support = SurfaceSupport::new(&surface, &adapter);
+        log::info!("Surface supports {surface_support}");
+
+        // Benchmarks must not be limited by the display's refresh rate.
+        let benchmark = options.benchmark_frames.map(Benchmark::new);
+        if benchmark.is_some() {
+            surface_config.present_mode = PresentMode::AutoNoVsync;
+        }
+        // Fewer frames in flight lower the latency, more frames keep the GPU busier.
+        if let Some(frames_in_flight) = options.frames_in_flight {
+            surface_config.desired_maximum_frame_latency = frames_in_flight;
+        }
+        let frame_pacer = FramePacer::new(surface_config.desired_maximum_frame_latency);
 
         // 6. Configure the surface using our logical device and the surface config.
+        // Alongside the surface, we create a depth buffer, so that 3D geometry occludes
+        // what lies behind it. The scene is rendered at a multiple of the surface size,
+        // see `RenderScale`, which the depth buffer and everything drawing the scene share.
         surface.configure(&device, &surface_config);
+        let render_scale = RenderScale::new(
+            &device,
+            surface_config.format,
+            options.render_scale.unwrap_or(1.0),
+            limits.max_texture_dimension_2d,
+            surface_config.width,
+            surface_config.height,
+        );
+        let (width, height) = render_scale.size();
+        let depth_view = create_depth_view(&device, width, height);
 
         // 7. Load the shader source code from `application.wgsl` and create a shader module
         // on our logical device to which we pass the loaded code as source.
@@ -103,13 +281,49 @@ impl Application {
         // this particular shader module.
         let shader_module = device.create_shader_module(ShaderModuleDescriptor {
             label: Some("Shader module"),
-            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("./application.wgsl"))),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(concat!(
+                include_str!("./camera.wgsl"),
+                include_str!("./sky.wgsl"),
+                include_str!("./application.wgsl")
+            ))),
         });
 
         // 8. Define the layout for our pipeline by creating a pipeline layout on our device.
-        // Our layout is very basic for now, so it is sufficient to use the PipelineLayoutDescriptor's
-        // default initializer.
-        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor::default());
+        // Our shader reads the camera from a uniform buffer, which is made available
+        // through a bind group in slot 0.
+        let camera = Camera::new(surface_config.width as f32 / surface_config.height as f32);
+        let camera_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Camera buffer"),
+            contents: bytemuck::bytes_of(&CameraUniform::new(&camera, width, height)),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+        let camera_bind_group_layout =
+            device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+                label: Some("Camera bind group layout"),
+                entries: &[BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::VERTEX_FRAGMENT | ShaderStages::COMPUTE,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }],
+            });
+        let camera_bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Camera bind group"),
+            layout: &camera_bind_group_layout,
+            entries: &[BindGroupEntry {
+                binding: 0,
+                resource: camera_buffer.as_entire_binding(),
+            }],
+        });
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Pipeline layout"),
+            bind_group_layouts: &[&camera_bind_group_layout],
+            push_constant_ranges: &[],
+        });
 
         // 9. Next, create the render pipeline itself on the device.
         // This requires:
@@ -142,7 +356,17 @@ impl Application {
                 buffers: &[],
             },
             primitive: PrimitiveState::default(),
-            depth_stencil: None,
+            // The background is drawn behind everything else, so it doesn't write to
+            // the depth buffer and only covers pixels without geometry, which still have
+            // the cleared depth of the far plane. That way it can also be drawn after the
+            // deferred path has filled the depth buffer.
+            depth_stencil: Some(DepthStencilState {
+                format: DEPTH_FORMAT,
+                depth_write_enabled: false,
+                depth_compare: CompareFunction::GreaterEqual,
+                stencil: Default::default(),
+                bias: Default::default(),
+            }),
             multisample: MultisampleState::default(),
             fragment: Some(FragmentState {
                 module: &shader_module,
@@ -158,13 +382,278 @@ impl Application {
             cache: None,
         });
 
+        let ground_plane = GroundPlane::new(
+            &device,
+            &camera_bind_group_layout,
+            surface_config.format,
+            DEPTH_FORMAT,
+        );
+        let primitive_renderer = PrimitiveRenderer::new(
+            &device,
+            &camera_bind_group_layout,
+            surface_config.format,
+            DEPTH_FORMAT,
+            DepthMode::Write,
+        );
+        let lights = Lights::new(&device);
+        let reflection_probes = ReflectionProbes::new(
+            &device,
+            &camera_bind_group_layout,
+            surface_config.format,
+            DEPTH_FORMAT,
+        );
+        let light_clusters = LightClusters::new(
+            &device,
+            &camera_bind_group_layout,
+            lights.bind_group_layout(),
+        );
+        let mut mesh_renderer = MeshRenderer::new(
+            &device,
+            &camera_bind_group_layout,
+            lights.bind_group_layout(),
+            reflection_probes.bind_group_layout(),
+            light_clusters.bind_group_layout(),
+            surface_config.format,
+            DEPTH_FORMAT,
+        );
+        let deferred_lighting = DeferredLighting::new(
+            &device,
+            [
+                &camera_bind_group_layout,
+                lights.bind_group_layout(),
+                reflection_probes.bind_group_layout(),
+            ],
+            surface_config.format,
+            &depth_view,
+            width,
+            height,
+        );
+        let environment = EnvironmentMap::new(&device, &queue);
+        let mut ssr = ScreenSpaceReflections::new(
+            &device,
+            &camera_bind_group_layout,
+            &deferred_lighting,
+            &environment,
+            surface_config.format,
+            width,
+            height,
+        );
+        ssr.settings = options.ssr;
+        let mut texture_streamer = TextureStreamer::new(
+            &device,
+            options
+                .texture_budget
+                .unwrap_or(TextureStreamer::DEFAULT_BUDGET_MIB),
+        );
+        let scene = Scene::new(&device, &queue, &mut mesh_renderer, &mut texture_streamer);
+        let terrain = DisplacedTerrain::new(
+            &device,
+            &queue,
+            &camera_bind_group_layout,
+            &mut mesh_renderer,
+        );
+        let tentacle = SkinnedMesh::new(
+            &device,
+            &mut mesh_renderer,
+            &SkinnedMeshData::tentacle(TENTACLE_JOINTS, TENTACLE_LENGTH, TENTACLE_RADIUS),
+        );
+        let life = GameOfLife::new(
+            &device,
+            &queue,
+            &camera_bind_group_layout,
+            surface_config.format,
+        );
+        let globals = Globals::new(&device);
+        let ray_marching = RayMarching::new(
+            &device,
+            &camera_bind_group_layout,
+            globals.bind_group_layout(),
+            surface_config.format,
+        );
+        let fractal = FractalViewer::new(
+            &device,
+            surface_config.format,
+            surface_config.width,
+            surface_config.height,
+        );
+        let shadertoy = ShaderToy::new(
+            &device,
+            surface_config.format,
+            surface_config.width,
+            surface_config.height,
+            options.shadertoy.as_deref(),
+        );
+        let luts = options
+            .luts
+            .iter()
+            .map(|path| Lut::load(path))
+            .collect::<Result<Vec<_>>>()?;
+        let color_grading = ColorGrading::new(
+            &device,
+            &queue,
+            surface_config.format,
+            surface_config.width,
+            surface_config.height,
+            luts,
+        );
+        let billboard_renderer = BillboardRenderer::new(
+            &device,
+            &queue,
+            &camera_bind_group_layout,
+            surface_config.format,
+            DEPTH_FORMAT,
+        );
+        let painting_renderer = PaintingRenderer::new(
+            &device,
+            &camera_bind_group_layout,
+            &texture_streamer,
+            surface_config.format,
+            DEPTH_FORMAT,
+        );
+        let text_renderer = TextRenderer::new(
+            &device,
+            &queue,
+            &camera_bind_group_layout,
+            surface_config.format,
+            DEPTH_FORMAT,
+        );
+        let shape_renderer = ShapeRenderer::new(
+            &device,
+            &camera_bind_group_layout,
+            surface_config.format,
+            DEPTH_FORMAT,
+        );
+        let decal_renderer = DecalRenderer::new(
+            &device,
+            &camera_bind_group_layout,
+            surface_config.format,
+            &depth_view,
+        );
+        let fog = VolumetricFog::new(
+            &device,
+            &camera_bind_group_layout,
+            surface_config.format,
+            &depth_view,
+            options.fog_quality,
+        );
+        let light_debug = LightDebug::new(
+            &device,
+            &camera_bind_group_layout,
+            lights.bind_group_layout(),
+            light_clusters.bind_group_layout(),
+            surface_config.format,
+            &depth_view,
+        );
+        let reflection = PlanarReflection::new(
+            &device,
+            &camera_bind_group_layout,
+            surface_config.format,
+            DEPTH_FORMAT,
+            WATER_CENTER.y,
+            width,
+            height,
+        );
+        let water = WaterSurface::new(
+            &device,
+            &camera_bind_group_layout,
+            lights.bind_group_layout(),
+            &reflection,
+            surface_config.format,
+            DEPTH_FORMAT,
+        );
+        let oit =
+            WeightedBlendedOit::new(&device, surface_config.format, DEPTH_FORMAT, width, height);
+        let overlay = Overlay::new(
+            &device,
+            &camera_bind_group_layout,
+            surface_config.format,
+            DEPTH_FORMAT,
+        );
+
+        let outline = SelectionOutline::new(&device, surface_config.format, width, height);
+
+        let gpu_timer = GpuTimer::new(&device, &queue);
+        if benchmark.is_some() && gpu_timer.is_none() {
+            log::warn!("Timestamp queries are not supported, GPU times will not be measured");
+        }
+
+        let camera_controller = OrbitController::new(&camera);
+        let input_recorder = options
+            .record
+            .as_deref()
+            .map(InputRecorder::create)
+            .transpose()?;
+        let input_replay = options
+            .replay
+            .as_deref()
+            .map(InputReplay::load)
+            .transpose()?;
+
         // Save these for later use
         Ok(Self {
             surface_config,
             surface,
+            surface_support,
+            adapter_info,
+            limits,
             device,
             queue,
+            render_scale,
+            color_grading,
+            depth_view,
+            oit,
             render_pipeline,
+            ground_plane,
+            primitive_renderer,
+            mesh_renderer,
+            lights,
+            reflection_probes,
+            light_clusters,
+            deferred_lighting,
+            environment,
+            ssr,
+            render_path: RenderPath::ForwardClustered,
+            depth_prepass: false,
+            billboard_renderer,
+            painting_renderer,
+            texture_streamer,
+            text_renderer,
+            shape_renderer,
+            hud: Hud::default(),
+            decal_renderer,
+            fog,
+            light_debug,
+            reflection,
+            water,
+            overlay,
+            outline,
+            demo: if options.shadertoy.is_some() {
+                Demo::ShaderToy
+            } else {
+                Demo::Scene
+            },
+            scene,
+            terrain,
+            tentacle,
+            life,
+            ray_marching,
+            fractal,
+            shadertoy,
+            globals,
+            previous_eye: camera.eye,
+            camera,
+            camera_buffer,
+            camera_bind_group,
+            camera_controller,
+            cursor: None,
+            click_start: None,
+            clock: FixedClock::new(FixedClock::DEFAULT_STEP),
+            pending_input: Vec::new(),
+            input_recorder,
+            input_replay,
+            frame_pacer,
+            gpu_timer,
+            benchmark,
         })
     }
 
@@ -175,27 +664,370 @@ impl Application {
         // Note that in rare scenarios, we may receive a width or height
         // of zero. Ensure the configured surface has a width and height
         // of at least one, otherwise we will run into validation issues.
-        todo!();
+        // Windows larger than the textures the device supports are rendered
+        // at the largest supported size and stretched.
+        (self.surface_config.width, self.surface_config.height) =
+            surface_size(&self.limits, width, height);
+        self.camera.aspect = self.surface_config.width as f32 / self.surface_config.height as f32;
 
         // 2. Reconfigure our surface using the updated surface_config
-        todo!();
+        // and recreate the depth buffer to match the render scale.
+        self.surface.configure(&self.device, &self.surface_config);
+        self.render_scale.resize(
+            &self.device,
+            self.surface_config.width,
+            self.surface_config.height,
+        );
+        self.resize_scaled();
+        self.color_grading.resize(
+            &self.device,
+            self.surface_config.width,
+            self.surface_config.height,
+        );
+        self.fractal.resize(
+            &self.device,
+            self.surface_config.width,
+            self.surface_config.height,
+        );
+        self.shadertoy.resize(
+            &self.device,
+            self.surface_config.width,
+            self.surface_config.height,
+        );
+    }
+
+    /// Recreates the depth buffer and everything else rendered at the render scale to match
+    /// its size.
+    fn resize_scaled(&mut self) {
+        let (width, height) = self.render_scale.size();
+        self.depth_view = create_depth_view(&self.device, width, height);
+        self.deferred_lighting
+            .resize(&self.device, &self.depth_view, width, height);
+        self.ssr
+            .resize(&self.device, &self.environment, width, height);
+        self.decal_renderer.resize(&self.device, &self.depth_view);
+        self.fog.resize(&self.device, &self.depth_view);
+        self.light_debug.resize(&self.device, &self.depth_view);
+        self.reflection.resize(&self.device, width, height);
+        self.water.resize(&self.device, &self.reflection);
+        self.oit.resize(&self.device, width, height);
+        self.outline.resize(&self.device, width, height);
+    }
+
+    /// What the surface supports, e.g. to offer only valid options in settings.
+    pub fn surface_support(&self) -> &SurfaceSupport {
+        &self.surface_support
     }
 
     pub fn handle_event(
         &mut self,
-        window: &winit::window::Window,
+        _window: &winit::window::Window,
         winit_event: &winit::event::WindowEvent,
     ) -> bool {
-        false
+        let Some(event) = InputEvent::from_window_event(winit_event) else {
+            return false;
+        };
+        // Live input is ignored while replaying, it would break determinism
+        if self.input_replay.is_none() {
+            self.pending_input.push(event);
+        }
+        true
+    }
+
+    /// Advances the simulation by the ticks that are due.
+    ///
+    /// Input is only ever applied at tick boundaries, so recording the tick along with
+    /// the event is enough to replay a session exactly.
+    fn update(&mut self) {
+        // Benchmarks simulate exactly one tick per frame, so replayed input
+        // yields identical frames on every run.
+        let ticks = if self.benchmark.is_some() {
+            self.clock.advance(self.clock.step())
+        } else {
+            self.clock.advance_realtime()
+        };
+        let dt = self.clock.step().as_secs_f32();
+
+        for tick in ticks {
+            let events = match &mut self.input_replay {
+                Some(replay) => replay.events_for(tick),
+                None => std::mem::take(&mut self.pending_input),
+            };
+            for event in &events {
+                if let Some(recorder) = &mut self.input_recorder {
+                    recorder.record(tick, event);
+                }
+                self.handle_input(event);
+            }
+            self.previous_eye = self.camera.eye;
+            self.camera_controller.update(&mut self.camera, dt);
+            self.scene.update(dt);
+            if self.demo == Demo::Life {
+                self.life.tick();
+            }
+
+            if self
+                .input_replay
+                .as_ref()
+                .is_some_and(InputReplay::is_finished)
+            {
+                log::info!("Replay finished at tick {tick}, switching to live input");
+                self.input_replay = None;
+            }
+        }
+    }
+
+    /// Reacts to input events during a tick.
+    fn handle_input(&mut self, event: &InputEvent) {
+        // The fractal viewer and ShaderToy shaders use the mouse on their own
+        match self.demo {
+            Demo::Fractal => self.fractal.handle_input(event),
+            Demo::ShaderToy => self.shadertoy.handle_input(event),
+            Demo::Scene | Demo::Life | Demo::RayMarching => {
+                self.camera_controller.handle_input(event)
+            }
+        }
+        match *event {
+            InputEvent::Key {
+                code,
+                pressed: true,
+            } => match code {
+                KeyCode::Digit1 => self.demo = Demo::Scene,
+                KeyCode::Digit2 => {
+                    // Every visit starts from a new random grid
+                    self.demo = Demo::Life;
+                    self.life.reset(&self.queue);
+                }
+                KeyCode::Digit3 => self.demo = Demo::RayMarching,
+                KeyCode::Digit4 => self.demo = Demo::Fractal,
+                KeyCode::Digit5 => {
+                    self.demo = Demo::ShaderToy;
+                    self.shadertoy.restart();
+                }
+                KeyCode::KeyG => self.overlay.show_grid = !self.overlay.show_grid,
+                KeyCode::KeyH => self.hud.enabled = !self.hud.enabled,
+                KeyCode::KeyP => self.ground_plane.enabled = !self.ground_plane.enabled,
+                KeyCode::KeyF => self.fog.enabled = !self.fog.enabled,
+                KeyCode::KeyE => self.ssr.enabled = !self.ssr.enabled,
+                KeyCode::KeyL => {
+                    self.mesh_renderer.lod_cross_fade = !self.mesh_renderer.lod_cross_fade
+                }
+                KeyCode::KeyM => self.water.material = self.water.material.next(),
+                KeyCode::KeyX => {
+                    self.light_debug.view = self.light_debug.view.next();
+                    log::info!("Lighting debug view: {:?}", self.light_debug.view);
+                }
+                KeyCode::KeyR => {
+                    self.render_path = self.render_path.next();
+                    log::info!("Render path: {:?}", self.render_path);
+                }
+                KeyCode::KeyZ => {
+                    self.depth_prepass = !self.depth_prepass;
+                    log::info!("Depth prepass: {}", self.depth_prepass);
+                }
+                KeyCode::Minus | KeyCode::Equal => {
+                    let step = if code == KeyCode::Minus {
+                        -RenderScale::STEP
+                    } else {
+                        RenderScale::STEP
+                    };
+                    let scale = self.render_scale.scale() + step;
+                    self.render_scale.set_scale(&self.device, scale);
+                    self.resize_scaled();
+                    log::info!("Render scale: {}", self.render_scale.scale());
+                }
+                KeyCode::KeyB => {
+                    self.reflection_probes.invalidate();
+                    log::info!("Baking the reflection probes");
+                }
+                KeyCode::KeyN => {
+                    self.scene.place_probe(self.camera.eye);
+                    log::info!("Placed a reflection probe at {:?}", self.camera.eye);
+                }
+                KeyCode::KeyT => self.color_grading.next_lut(&self.device, &self.queue),
+                KeyCode::Comma | KeyCode::Period => {
+                    let step = if code == KeyCode::Comma {
+                        -ColorGrading::STRENGTH_STEP
+                    } else {
+                        ColorGrading::STRENGTH_STEP
+                    };
+                    let strength = self.color_grading.strength() + step;
+                    self.color_grading.set_strength(&self.queue, strength);
+                    log::info!("Color grading strength: {}", self.color_grading.strength());
+                }
+                KeyCode::KeyK => {
+                    let frames_in_flight = self.frame_pacer.max_frames_in_flight()
+                        % FramePacer::MAX_FRAMES_IN_FLIGHT
+                        + FramePacer::MIN_FRAMES_IN_FLIGHT;
+                    self.frame_pacer.set_max_frames_in_flight(frames_in_flight);
+                    self.surface_config.desired_maximum_frame_latency = frames_in_flight;
+                    self.surface.configure(&self.device, &self.surface_config);
+                    log::info!("Frames in flight: {frames_in_flight}");
+                }
+                KeyCode::KeyV => {
+                    self.surface_config.present_mode = self
+                        .surface_support()
+                        .next_present_mode(self.surface_config.present_mode);
+                    self.surface.configure(&self.device, &self.surface_config);
+                    log::info!("Present mode: {:?}", self.surface_config.present_mode);
+                }
+                _ => {}
+            },
+            InputEvent::CursorMoved { x, y } => self.cursor = Some((x, y)),
+            // Right clicking projects a decal onto the surface under the cursor
+            InputEvent::MouseButton {
+                button: MouseButton::Right,
+                pressed: true,
+            } if self.demo == Demo::Scene => {
+                if let Some(hit) = self.pick_under_cursor() {
+                    self.scene.spawn_decal(&hit);
+                }
+            }
+            // Left clicking selects the object under the cursor, dragging orbits the camera
+            InputEvent::MouseButton {
+                button: MouseButton::Left,
+                pressed,
+            } if self.demo == Demo::Scene => {
+                if pressed {
+                    self.click_start = self.cursor;
+                } else if let (Some((start_x, start_y)), Some((x, y))) =
+                    (self.click_start.take(), self.cursor)
+                {
+                    if (x - start_x).hypot(y - start_y) <= CLICK_TOLERANCE {
+                        let hit = self.pick_under_cursor();
+                        self.scene.select(hit.as_ref());
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn pick_under_cursor(&self) -> Option<Hit> {
+        let (x, y) = self.cursor?;
+        // Picks with the camera of the last tick rather than the interpolated one, so that clicks
+        // hit the same objects when they are replayed
+        let direction = self.camera.view_ray(
+            x as f32,
+            y as f32,
+            self.surface_config.width,
+            self.surface_config.height,
+        );
+        self.scene
+            .pick(self.camera.eye, direction, self.ground_plane.enabled)
+    }
+
+    /// Returns the benchmark report once all benchmark frames have been rendered.
+    pub fn benchmark_report(&mut self) -> Option<Report> {
+        let benchmark = self
+            .benchmark
+            .as_mut()
+            .filter(|benchmark| benchmark.is_finished())?;
+        // Wait for the timestamps of the last frames
+        if let Some(gpu_timer) = &mut self.gpu_timer {
+            self.device.poll(wgpu::Maintain::Wait);
+            benchmark.add_gpu_times(gpu_timer.collect());
+        }
+        Some(benchmark.report(
+            &self.adapter_info,
+            self.surface_config.width,
+            self.surface_config.height,
+        ))
     }
 
-    pub fn render(&mut self, window: &winit::window::Window) -> Result<(), wgpu::SurfaceError> {
+    pub fn render(&mut self, _window: &winit::window::Window) -> Result<(), wgpu::SurfaceError> {
         // Relevant wgpu types for this method:
         // - SurfaceTexture, Texture, TextureView
         // - CommandEncoder, CommandEncoderDescriptor
         // - RenderPass, RenderPassDescriptor
         // - RenderPassColorAttachment, Operations, LoadOp, StoreOp, Color
 
+        if self.benchmark.as_ref().is_some_and(Benchmark::is_finished) {
+            return Ok(());
+        }
+        // Input is applied only once an earlier frame has finished, so it is not delayed by
+        // more frames in flight than allowed.
+        let started = self.frame_pacer.begin_frame(&self.device);
+        self.update();
+        self.hud.begin_frame(self.frame_pacer.latency());
+        let frame_start = self.benchmark.as_mut().map(|benchmark| {
+            // A replay takes over the camera from the fixed benchmark path,
+            // which moves it once per frame, leaving nothing to interpolate
+            if self.input_replay.is_none() {
+                benchmark.update_camera(&mut self.camera);
+                self.previous_eye = self.camera.eye;
+            }
+            benchmark.begin_frame()
+        });
+
+        // The state drawn lies between the states before and after the last tick
+        let alpha = self.clock.alpha();
+        self.scene.interpolate(alpha);
+        let camera = self.camera.orbit_from(self.previous_eye, alpha);
+        let (width, height) = self.render_scale.size();
+        self.queue.write_buffer(
+            &self.camera_buffer,
+            0,
+            bytemuck::bytes_of(&CameraUniform::new(&camera, width, height)),
+        );
+        self.scene.draw_meshes(&mut self.mesh_renderer, &camera);
+        self.terrain.draw(&mut self.mesh_renderer);
+        self.scene
+            .draw_tentacle(&mut self.tentacle, &mut self.mesh_renderer);
+        self.tentacle.prepare(&self.queue);
+        self.mesh_renderer
+            .prepare(&self.device, &self.queue, &camera);
+        self.scene.draw_lights(&mut self.lights);
+        self.lights.prepare(&self.device, &self.queue);
+        // Probes are only baked while the scene is shown
+        if matches!(self.demo, Demo::Scene) {
+            self.scene.draw_probes(&mut self.reflection_probes);
+            self.reflection_probes.prepare(&self.queue);
+            // Likewise, textures are only streamed for the paintings in view
+            self.scene.draw_paintings(&mut self.painting_renderer);
+            self.painting_renderer.prepare(
+                &self.device,
+                &self.queue,
+                &camera,
+                height,
+                &mut self.texture_streamer,
+            );
+            self.texture_streamer.update(&self.device, &self.queue);
+        }
+        self.scene.draw_primitives(&mut self.primitive_renderer);
+        self.primitive_renderer.prepare(&self.device, &self.queue);
+        self.scene.draw_decals(&mut self.decal_renderer);
+        self.decal_renderer.prepare(&self.device, &self.queue);
+        // Picks up edits to the shaders while they are shown, before their inputs are prepared
+        #[cfg(not(target_arch = "wasm32"))]
+        match self.demo {
+            Demo::RayMarching => self.ray_marching.reload_if_modified(&self.device),
+            Demo::ShaderToy => self.shadertoy.reload_if_modified(&self.device),
+            Demo::Scene | Demo::Life | Demo::Fractal => {}
+        }
+        self.globals.prepare(&self.queue, self.scene.time());
+        self.fractal.prepare(&self.queue);
+        self.shadertoy.prepare(&self.queue, self.scene.time());
+        self.fog.prepare(&self.queue, self.scene.time());
+        self.ssr.prepare(&self.queue);
+        self.reflection.prepare(&self.queue, &camera);
+        self.water
+            .prepare(&self.queue, WATER_CENTER, WATER_EXTENT, self.scene.time());
+        self.scene.draw_billboards(&mut self.billboard_renderer);
+        self.billboard_renderer
+            .prepare(&self.device, &self.queue, &camera);
+        self.scene.draw_labels(&mut self.text_renderer);
+        self.text_renderer
+            .prepare(&self.device, &self.queue, &camera);
+        self.hud.draw(
+            &mut self.shape_renderer,
+            width,
+            matches!(self.demo, Demo::Scene).then(|| self.texture_streamer.stats()),
+        );
+        self.shape_renderer.prepare(&self.device, &self.queue);
+        self.overlay
+            .prepare(&self.device, &self.queue, &camera, width, height);
+
         // 1. To render something to the screen, we must first request the current
         // texture from our surface.
         let surface_texture = self.surface.get_current_texture()?;
@@ -214,11 +1046,223 @@ impl Application {
         // 3. All commands to be enqueued to our GPU's queue must first be encoded
         // so they are compatible with our logical device.
         // For this, we create a command encoder using our device.
-        let command_encoder = self
+        let mut command_encoder = self
             .device
             .create_command_encoder(&CommandEncoderDescriptor {
                 label: Some("Command encoder"),
             });
+        if let Some(gpu_timer) = &mut self.gpu_timer {
+            gpu_timer.begin(&mut command_encoder);
+        }
+
+        // The demos seen through the camera are rendered at the render scale, then scaled to
+        // the surface. The fractal viewer and ShaderToy shaders work in surface pixels.
+        // While color grading, all demos end up in its image, which is graded into the surface.
+        let graded_view = self.color_grading.view().unwrap_or(&texture_view);
+        let scaled_view = self.render_scale.view().unwrap_or(graded_view);
+        match self.demo {
+            Demo::Scene => self.render_scene(&mut command_encoder, scaled_view),
+            Demo::Life => {
+                self.life
+                    .render(&mut command_encoder, &self.camera_bind_group, scaled_view)
+            }
+            Demo::RayMarching => {
+                self.ray_marching.render(
+                    &mut command_encoder,
+                    &self.camera_bind_group,
+                    self.globals.bind_group(),
+                    scaled_view,
+                );
+            }
+            Demo::Fractal => self.fractal.render(&mut command_encoder, graded_view),
+            Demo::ShaderToy => self.shadertoy.render(&mut command_encoder, graded_view),
+        }
+        if matches!(self.demo, Demo::Scene | Demo::Life | Demo::RayMarching) {
+            self.render_scale.blit(&mut command_encoder, graded_view);
+        }
+        self.color_grading
+            .apply(&mut command_encoder, &texture_view);
+
+        // 8. Finish the command encoder, returning a command buffer.
+        // Then, submit the command buffer to our GPU queue.
+        if let Some(gpu_timer) = &mut self.gpu_timer {
+            gpu_timer.end(&mut command_encoder);
+        }
+        let submission = self.queue.submit([command_encoder.finish()]);
+        self.frame_pacer.end_frame(&self.queue, submission, started);
+
+        // 9. Present the frame (our SurfaceTexture)
+        surface_texture.present();
+
+        if let Some(gpu_timer) = &mut self.gpu_timer {
+            gpu_timer.map();
+            self.device.poll(wgpu::Maintain::Poll);
+            let gpu_times = gpu_timer.collect();
+            if let Some(benchmark) = &mut self.benchmark {
+                benchmark.add_gpu_times(gpu_times);
+            }
+        }
+        if let (Some(benchmark), Some(frame_start)) = (&mut self.benchmark, frame_start) {
+            benchmark.end_frame(frame_start);
+        }
+
+        Ok(())
+    }
+
+    /// Records the passes drawing the scene into the surface texture.
+    fn render_scene(&self, command_encoder: &mut CommandEncoder, texture_view: &TextureView) {
+        // The terrain's vertices are computed before anything draws the meshes, as are the
+        // tentacle's skinned vertices, which every pass below reuses.
+        self.terrain
+            .compute(command_encoder, &self.camera_bind_group);
+        self.tentacle.skin(command_encoder);
+
+        // Reflection probes that were placed or moved are baked next, by capturing the
+        // background, the meshes and the ground around them, see `ReflectionProbes`.
+        for &slot in self.reflection_probes.pending() {
+            for face in 0..6 {
+                let camera_bind_group = self.reflection_probes.camera_bind_group(slot, face);
+                let mut capture_pass = self
+                    .reflection_probes
+                    .begin_capture_pass(command_encoder, face);
+                capture_pass.set_pipeline(&self.render_pipeline);
+                capture_pass.set_bind_group(0, camera_bind_group, &[]);
+                capture_pass.draw(0..6, 0..1);
+                self.mesh_renderer.render_forward(
+                    &mut capture_pass,
+                    camera_bind_group,
+                    self.lights.bind_group(),
+                    self.reflection_probes.empty_bind_group(),
+                    false,
+                );
+                self.ground_plane
+                    .render(&mut capture_pass, camera_bind_group);
+                drop(capture_pass);
+            }
+            self.reflection_probes.filter(command_encoder, slot);
+        }
+
+        // The reflection of the scene in the water is rendered next, with the mirrored camera.
+        // Only the background, the meshes and the lines are reflected, the ground lies below
+        // the water and the particles are too small to be noticed.
+        let mut reflection_pass = self.reflection.begin_pass(command_encoder);
+        reflection_pass.set_pipeline(&self.render_pipeline);
+        reflection_pass.set_bind_group(0, self.reflection.camera_bind_group(), &[]);
+        reflection_pass.draw(0..6, 0..1);
+        self.mesh_renderer.render_forward(
+            &mut reflection_pass,
+            self.reflection.camera_bind_group(),
+            self.lights.bind_group(),
+            self.reflection_probes.bind_group(),
+            false,
+        );
+        self.primitive_renderer
+            .render(&mut reflection_pass, self.reflection.camera_bind_group());
+        drop(reflection_pass);
+
+        // The Forward+ path bins the lights into clusters before drawing the meshes.
+        if self.render_path == RenderPath::ForwardClustered {
+            self.light_clusters.cull(
+                command_encoder,
+                &self.camera_bind_group,
+                self.lights.bind_group(),
+            );
+        }
+
+        // The deferred path draws the meshes into the G-buffer and the depth buffer first,
+        // then shades them into the surface texture before the render pass below.
+        let deferred = self.render_path == RenderPath::Deferred;
+        if deferred {
+            let mut geometry_pass = self
+                .deferred_lighting
+                .begin_geometry_pass(command_encoder, &self.depth_view);
+            self.mesh_renderer.render_gbuffer(
+                &mut geometry_pass,
+                &self.camera_bind_group,
+                self.lights.bind_group(),
+            );
+            drop(geometry_pass);
+
+            let mut decal_pass = self.deferred_lighting.begin_decal_pass(command_encoder);
+            self.decal_renderer
+                .render_gbuffer(&mut decal_pass, &self.camera_bind_group);
+            drop(decal_pass);
+
+            // With reflections, the lit scene is shaded into a texture of its own first,
+            // as the reflection pass reads it.
+            let mut lighting_pass = command_encoder.begin_render_pass(&RenderPassDescriptor {
+                label: Some("Deferred lighting pass"),
+                color_attachments: &[Some(RenderPassColorAttachment {
+                    view: if self.ssr.enabled {
+                        self.ssr.scene_view()
+                    } else {
+                        texture_view
+                    },
+                    resolve_target: None,
+                    ops: Operations {
+                        load: LoadOp::Clear(Color::BLACK),
+                        store: StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            self.deferred_lighting.resolve(
+                &mut lighting_pass,
+                &self.camera_bind_group,
+                self.lights.bind_group(),
+                self.reflection_probes.bind_group(),
+            );
+            drop(lighting_pass);
+
+            if self.ssr.enabled {
+                let mut reflection_pass =
+                    command_encoder.begin_render_pass(&RenderPassDescriptor {
+                        label: Some("Screen-space reflection pass"),
+                        color_attachments: &[Some(RenderPassColorAttachment {
+                            view: texture_view,
+                            resolve_target: None,
+                            ops: Operations {
+                                load: LoadOp::Clear(Color::BLACK),
+                                store: StoreOp::Store,
+                            },
+                        })],
+                        depth_stencil_attachment: None,
+                        timestamp_writes: None,
+                        occlusion_query_set: None,
+                    });
+                self.ssr.resolve(
+                    &mut reflection_pass,
+                    &self.camera_bind_group,
+                    &self.deferred_lighting,
+                );
+                drop(reflection_pass);
+            }
+        }
+
+        // With the depth prepass, the forward paths draw the meshes' depth first, so that the
+        // render pass below shades each pixel covered by meshes only once, testing for equal depth.
+        let depth_prepass = self.depth_prepass && !deferred;
+        if depth_prepass {
+            let mut prepass = command_encoder.begin_render_pass(&RenderPassDescriptor {
+                label: Some("Depth prepass"),
+                color_attachments: &[],
+                depth_stencil_attachment: Some(RenderPassDepthStencilAttachment {
+                    view: &self.depth_view,
+                    depth_ops: Some(Operations {
+                        load: LoadOp::Clear(0.0),
+                        store: StoreOp::Store,
+                    }),
+                    stencil_ops: None,
+                }),
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            self.mesh_renderer
+                .render_depth(&mut prepass, &self.camera_bind_group);
+            drop(prepass);
+        }
 
         // 4. Defining rendering commands for a GPU happens in form of a render pass.
         // We create a render pass by "beginning" it on the command encoder.
@@ -227,36 +1271,276 @@ impl Application {
         // This color attachment receives the view we created for our surface texture earlier.
         // We then tell it what operations (ops) to perform on this view:
         // - On load, clear the surface texture using a black color
+        //   (unless the deferred path has already rendered into it)
         // - On store, overwrite the contents of the surface texture (simply called "Store")
-        let render_pass = command_encoder.begin_render_pass(&RenderPassDescriptor {
+        // The depth buffer is cleared or kept the same way, also after the depth prepass.
+        let mut render_pass = command_encoder.begin_render_pass(&RenderPassDescriptor {
             label: Some("Render pass"),
-            color_attachments: todo!(),
-            depth_stencil_attachment: todo!(),
-            timestamp_writes: todo!(),
-            occlusion_query_set: todo!(),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: texture_view,
+                resolve_target: None,
+                ops: Operations {
+                    load: if deferred {
+                        LoadOp::Load
+                    } else {
+                        LoadOp::Clear(Color::BLACK)
+                    },
+                    store: StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: Some(RenderPassDepthStencilAttachment {
+                view: &self.depth_view,
+                depth_ops: Some(Operations {
+                    load: if deferred || depth_prepass {
+                        LoadOp::Load
+                    } else {
+                        LoadOp::Clear(0.0)
+                    },
+                    store: StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
+            timestamp_writes: None,
+            occlusion_query_set: None,
         });
 
         // 5. To let the render pass know of the structure of our pipeline, such as
         // shaders, or geometric primitives, set its pipeline to the render pipeline
         // we created in our constructor.
-        todo!();
+        // The camera bind group is set into the slot our pipeline layout declared for it.
+        render_pass.set_pipeline(&self.render_pipeline);
+        render_pass.set_bind_group(0, &self.camera_bind_group, &[]);
 
         // 6. Tell the render pass to draw six vertices (must be passed as a range 0 to 6)
         // for one instance (again, as a range 0 to 1).
         // Instancing will not be covered in this workshop.
-        todo!();
+        render_pass.draw(0..6, 0..1);
+
+        // The forward paths shade the meshes while drawing them.
+        // The ground plane is drawn on top of the background.
+        match self.render_path {
+            RenderPath::Forward => self.mesh_renderer.render_forward(
+                &mut render_pass,
+                &self.camera_bind_group,
+                self.lights.bind_group(),
+                self.reflection_probes.bind_group(),
+                depth_prepass,
+            ),
+            RenderPath::ForwardClustered => self.mesh_renderer.render_forward_clustered(
+                &mut render_pass,
+                &self.camera_bind_group,
+                self.lights.bind_group(),
+                self.reflection_probes.bind_group(),
+                &self.light_clusters,
+                depth_prepass,
+            ),
+            RenderPath::Deferred => {}
+        }
+        self.ground_plane
+            .render(&mut render_pass, &self.camera_bind_group);
+        self.painting_renderer.render(
+            &mut render_pass,
+            &self.camera_bind_group,
+            &self.texture_streamer,
+        );
+        self.water.render(
+            &mut render_pass,
+            &self.camera_bind_group,
+            self.lights.bind_group(),
+        );
+        drop(render_pass);
+
+        // Decals and fog read the depth of the opaque scene, so they are drawn in a pass
+        // without depth attachment. The deferred path has already applied the decals to the
+        // G-buffer. The fog's froxels are computed beforehand, from the same depth buffer.
+        if self.fog.enabled {
+            self.fog.compute(command_encoder, &self.camera_bind_group);
+        }
+        let mut composite_pass = command_encoder.begin_render_pass(&RenderPassDescriptor {
+            label: Some("Decal and fog pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: texture_view,
+                resolve_target: None,
+                ops: Operations {
+                    load: LoadOp::Load,
+                    store: StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        if !deferred {
+            self.decal_renderer
+                .render_forward(&mut composite_pass, &self.camera_bind_group);
+        }
+        if self.fog.enabled {
+            self.fog
+                .composite(&mut composite_pass, &self.camera_bind_group);
+        }
+        self.light_debug.render(
+            &mut composite_pass,
+            &self.camera_bind_group,
+            self.lights.bind_group(),
+            &self.light_clusters,
+            self.render_path,
+        );
+        drop(composite_pass);
+
+        // The lines and points of the scene are drawn on top of the opaque scene,
+        // followed by the sorted transparent meshes and billboards, which don't write depth.
+        // Meshes and billboards are sorted separately, so billboards end up in front.
+        let mut render_pass = command_encoder.begin_render_pass(&RenderPassDescriptor {
+            label: Some("Primitive pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: texture_view,
+                resolve_target: None,
+                ops: Operations {
+                    load: LoadOp::Load,
+                    store: StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: Some(RenderPassDepthStencilAttachment {
+                view: &self.depth_view,
+                depth_ops: Some(Operations {
+                    load: LoadOp::Load,
+                    store: StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        self.primitive_renderer
+            .render(&mut render_pass, &self.camera_bind_group);
+        self.mesh_renderer.render_sorted(
+            &mut render_pass,
+            &self.camera_bind_group,
+            self.lights.bind_group(),
+            self.reflection_probes.bind_group(),
+        );
+        self.billboard_renderer
+            .render(&mut render_pass, &self.camera_bind_group);
 
         // 7. Before finishing our command encoder, we must drop the
         // render pass so it knows it is complete.
-        todo!();
+        drop(render_pass);
 
-        // 8. Finish the command encoder, returning a command buffer.
-        // Then, submit the command buffer to our GPU queue.
-        todo!();
+        // Order-independent transparent surfaces are accumulated in a pass of their own,
+        // then composited onto the scene before the overlay is drawn on top.
+        let mut oit_pass = self
+            .oit
+            .begin_accumulation_pass(command_encoder, &self.depth_view);
+        self.mesh_renderer.render_oit(
+            &mut oit_pass,
+            &self.camera_bind_group,
+            self.lights.bind_group(),
+            self.reflection_probes.bind_group(),
+        );
+        self.billboard_renderer
+            .render_oit(&mut oit_pass, &self.camera_bind_group);
+        drop(oit_pass);
 
-        // 9. Present the frame (our SurfaceTexture)
-        todo!();
+        let mut overlay_pass = command_encoder.begin_render_pass(&RenderPassDescriptor {
+            label: Some("Overlay pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: texture_view,
+                resolve_target: None,
+                ops: Operations {
+                    load: LoadOp::Load,
+                    store: StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: Some(RenderPassDepthStencilAttachment {
+                view: &self.depth_view,
+                depth_ops: Some(Operations {
+                    load: LoadOp::Load,
+                    store: StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        self.oit.resolve(&mut overlay_pass);
+        // Labels are drawn after the transparent surfaces, so they stay readable behind them.
+        self.text_renderer
+            .render(&mut overlay_pass, &self.camera_bind_group);
+        self.shape_renderer
+            .render(&mut overlay_pass, &self.camera_bind_group);
+        self.overlay
+            .render(&mut overlay_pass, &self.camera_bind_group);
+        drop(overlay_pass);
 
-        Ok(())
+        // The selected object is outlined on top of everything else.
+        if self.scene.has_selection() {
+            let mut mask_pass = self.outline.begin_mask_pass(command_encoder);
+            self.mesh_renderer
+                .render_selection_mask(&mut mask_pass, &self.camera_bind_group);
+            drop(mask_pass);
+
+            let mut outline_pass = command_encoder.begin_render_pass(&RenderPassDescriptor {
+                label: Some("Outline pass"),
+                color_attachments: &[Some(RenderPassColorAttachment {
+                    view: texture_view,
+                    resolve_target: None,
+                    ops: Operations {
+                        load: LoadOp::Load,
+                        store: StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            self.outline.render(&mut outline_pass);
+            drop(outline_pass);
+        }
+    }
+}
+
+/// Limits requested for the device: the downlevel defaults, which every adapter with compute
+/// shaders meets, with the adapter's texture sizes, except for the limits below, which are raised
+/// to what the application can make use of as far as the adapter supports it. Subsystems scale
+/// down to what the device was granted instead of the request failing.
+fn required_limits(adapter: &Limits) -> Limits {
+    // Larger surfaces than 16K are not to be expected
+    const MAX_TEXTURE_DIMENSION: u32 = 16384;
+    const MAX_STORAGE_BUFFER_SIZE: u32 = 256 << 20;
+
+    Limits {
+        max_texture_dimension_2d: adapter.max_texture_dimension_2d.min(MAX_TEXTURE_DIMENSION),
+        max_storage_buffer_binding_size: adapter
+            .max_storage_buffer_binding_size
+            .min(MAX_STORAGE_BUFFER_SIZE),
+        max_buffer_size: adapter.max_buffer_size.min(MAX_STORAGE_BUFFER_SIZE.into()),
+        ..Limits::downlevel_defaults().using_resolution(adapter.clone())
     }
 }
+
+/// Size of the surface for a window of the given size, which must neither be empty nor exceed the
+/// largest texture the device supports.
+fn surface_size(limits: &Limits, width: u32, height: u32) -> (u32, u32) {
+    let max = limits.max_texture_dimension_2d;
+    (width.clamp(1, max), height.clamp(1, max))
+}
+
+fn create_depth_view(device: &wgpu::Device, width: u32, height: u32) -> TextureView {
+    device
+        .create_texture(&TextureDescriptor {
+            label: Some("Depth texture"),
+            size: Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: DEPTH_FORMAT,
+            // The deferred lighting pass and decals reconstruct positions from depth
+            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        })
+        .create_view(&TextureViewDescriptor::default())
+}
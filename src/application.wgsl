@@ -1,36 +1,28 @@
-// Tasks:
-// 1. Bring colors to the screen by editing your fragment shader.
-// 2. Change the vertices of our two triangles so they cover the whole screen.
-// 3. Experiment with different colors based on the coordinates of a pixel.
-//    You can, for example, create gradients by dividing the pixel's position
-//    by the width/height of our surface. Unfortunately, we can't dynamically
-//    access the width and height from our shaders yet, so you must use hardcoded
-//    values, e.g.:
-//      let width = 1280.0;
-//      let height = 720.0;
-// 
-//    You could also try plotting a function, by comparing the Y-coordinate against
-//    the value of some f(x). Beware floating point accuracy, you will need some tolerance
-//    in your comparisons.
-//    The distance of your Y towards the real result could also be visualized by adjusting
-//    the intensity of your returned color (the lower the components, the darker the color).
-// 
+// Draws the background of our scene: a sky seen through the camera.
+//
+// Two triangles cover the whole screen, and for every pixel the fragment shader
+// reconstructs the direction of the view ray from the camera's inverse
+// view-projection matrix, which is then used to shade a simple sky.
+//
 // If you have semantic or syntactical errors in your shader, the application will crash on launch.
 // Scroll past the panic's stack trace to see the actual errors.
-// 
-// These resources may help you with your tasks:
+//
+// Expects `camera.wgsl` and `sky.wgsl` to be prepended, which declare the camera uniform
+// and the colors of the sky.
+//
+// These resources may help you when editing the shader:
 // - https://google.github.io/tour-of-wgsl/ (don't mind the "WebGPU is not supported in this browser")
 // - https://webgpufundamentals.org/webgpu/lessons/webgpu-wgsl-function-reference.html
 
-// two triangles (not yet) covering the screen
+// two triangles covering the screen
 const positions: array<vec2<f32>, 6> = array<vec2<f32>, 6>(
-    vec2<f32>(-0.5, -0.7), // bottom left
-    vec2<f32>(0.7, -0.7), // bottom right
-    vec2<f32>(0.7, 0.5), // top right
+    vec2<f32>(-1.0, -1.0), // bottom left
+    vec2<f32>(1.0, -1.0), // bottom right
+    vec2<f32>(1.0, 1.0), // top right
 
-    vec2<f32>(0.7, 0.8), // top right
-    vec2<f32>(-0.7, 0.6), // top left
-    vec2<f32>(-0.9, -0.6), // bottom left
+    vec2<f32>(1.0, 1.0), // top right
+    vec2<f32>(-1.0, 1.0), // top left
+    vec2<f32>(-1.0, -1.0), // bottom left
 );
 
 @vertex
@@ -43,8 +35,8 @@ fn vs_main(
 ) -> @builtin(position) vec4<f32> {
     // Vertex coordinates are X Y Z W, where the visible screen
     // for X and Y ranges from -1.0 to 1.0.
-    // Z is only relevant for depth testing, which we won't cover today.
-    // W is only relevant for homogeneous coordinates, which we won't cover today (leave at 1.0)
+    // Z is only relevant for depth testing, the background lies on the far plane.
+    // W is only relevant for homogeneous coordinates (leave at 1.0)
     return vec4<f32>(positions[in_vertex_index], 0.0, 1.0);
 }
 
@@ -54,10 +46,8 @@ fn fs_main(
     // for every pixel of our window.
     // The coordinates are in screen space, meaning X and Y range from 0.0 to the width/height
     // of our surface.
-    // With our initial window size, that means X ranges from 0 to 1280, and Y from 0 to 720.
-    // You can access a vector's individual components as position.x, position.y, ...
     @builtin(position) position: vec4<f32>
 ) -> @location(0) vec4<f32> {
     // We return a color value in RGBA format, where every component ranges from 0.0 to 1.0.
-    return vec4<f32>(1.0, 1.0, 1.0, 1.0);
+    return vec4<f32>(sky(view_ray(position.xy)), 1.0);
 }
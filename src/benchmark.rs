@@ -0,0 +1,236 @@
+use std::{f32::consts::TAU, fmt::Write};
+
+use cgmath::Point3;
+
+use crate::{camera::Camera, clock::Instant};
+
+/// Renders a fixed number of frames along a fixed camera path and
+/// collects frame time statistics along the way.
+pub struct Benchmark {
+    frames: u32,
+    frame: u32,
+    last_frame_start: Option<Instant>,
+    frame_times: Vec<f64>,
+    cpu_times: Vec<f64>,
+    gpu_times: Vec<f64>,
+}
+
+impl Benchmark {
+    pub fn new(frames: u32) -> Self {
+        Self {
+            frames,
+            frame: 0,
+            last_frame_start: None,
+            frame_times: Vec::with_capacity(frames as usize),
+            cpu_times: Vec::with_capacity(frames as usize),
+            gpu_times: Vec::with_capacity(frames as usize),
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.frame >= self.frames
+    }
+
+    /// Moves the camera to its position on the benchmark path for the current frame.
+    /// The path depends only on the frame number, so every run sees the same images.
+    pub fn update_camera(&self, camera: &mut Camera) {
+        let t = self.frame as f32 / self.frames.max(1) as f32;
+        let angle = t * TAU;
+        let radius = 6.0 + 2.0 * (2.0 * angle).sin();
+        camera.eye = Point3::new(
+            radius * angle.cos(),
+            2.0 + angle.sin(),
+            radius * angle.sin(),
+        );
+        camera.target = Point3::new(0.0, 0.0, 0.0);
+    }
+
+    /// Marks the start of a frame, returning the instant to pass to [`Benchmark::end_frame`].
+    pub fn begin_frame(&mut self) -> Instant {
+        let now = Instant::now();
+        if let Some(last_frame_start) = self.last_frame_start.replace(now) {
+            self.frame_times
+                .push(now.duration_since(last_frame_start).as_secs_f64() * 1000.0);
+        }
+        now
+    }
+
+    /// Marks the end of the CPU work of a frame started at `frame_start`.
+    pub fn end_frame(&mut self, frame_start: Instant) {
+        self.cpu_times
+            .push(frame_start.elapsed().as_secs_f64() * 1000.0);
+        self.frame += 1;
+    }
+
+    pub fn add_gpu_times(&mut self, times: impl IntoIterator<Item = f64>) {
+        self.gpu_times.extend(times);
+    }
+
+    pub fn report(&self, adapter: &wgpu::AdapterInfo, width: u32, height: u32) -> Report {
+        Report {
+            adapter: adapter.name.clone(),
+            backend: adapter.backend.to_str().to_owned(),
+            width,
+            height,
+            frames: self.frame,
+            frame_time: Statistics::from_samples(&self.frame_times),
+            cpu_time: Statistics::from_samples(&self.cpu_times),
+            gpu_time: Statistics::from_samples(&self.gpu_times),
+        }
+    }
+}
+
+/// Summary of a series of timings, in milliseconds.
+#[derive(Debug, Clone, Copy)]
+pub struct Statistics {
+    pub samples: usize,
+    pub min: f64,
+    pub avg: f64,
+    pub median: f64,
+    pub p99: f64,
+}
+
+impl Statistics {
+    /// Returns `None` if there are no samples.
+    pub fn from_samples(samples: &[f64]) -> Option<Self> {
+        if samples.is_empty() {
+            return None;
+        }
+        let mut sorted = samples.to_vec();
+        sorted.sort_by(f64::total_cmp);
+        let len = sorted.len();
+        let median = if len.is_multiple_of(2) {
+            (sorted[len / 2 - 1] + sorted[len / 2]) / 2.0
+        } else {
+            sorted[len / 2]
+        };
+        // Nearest-rank percentile
+        let p99 = sorted[((len as f64 * 0.99).ceil() as usize).clamp(1, len) - 1];
+
+        Some(Self {
+            samples: len,
+            min: sorted[0],
+            avg: sorted.iter().sum::<f64>() / len as f64,
+            median,
+            p99,
+        })
+    }
+
+    fn to_json(self) -> String {
+        format!(
+            r#"{{"samples":{},"min":{:.4},"avg":{:.4},"median":{:.4},"p99":{:.4}}}"#,
+            self.samples, self.min, self.avg, self.median, self.p99
+        )
+    }
+}
+
+pub struct Report {
+    pub adapter: String,
+    pub backend: String,
+    pub width: u32,
+    pub height: u32,
+    pub frames: u32,
+    pub frame_time: Option<Statistics>,
+    pub cpu_time: Option<Statistics>,
+    pub gpu_time: Option<Statistics>,
+}
+
+impl Report {
+    /// Serializes the report as a single line of JSON.
+    /// Statistics without samples (e.g. GPU times without timestamp query support) are `null`.
+    pub fn to_json(&self) -> String {
+        let statistics = |stats: Option<Statistics>| {
+            stats.map_or_else(|| "null".to_owned(), Statistics::to_json)
+        };
+        format!(
+            r#"{{"adapter":{},"backend":{},"width":{},"height":{},"frames":{},"frame_time_ms":{},"cpu_time_ms":{},"gpu_time_ms":{}}}"#,
+            json_string(&self.adapter),
+            json_string(&self.backend),
+            self.width,
+            self.height,
+            self.frames,
+            statistics(self.frame_time),
+            statistics(self.cpu_time),
+            statistics(self.gpu_time),
+        )
+    }
+}
+
+fn json_string(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len() + 2);
+    escaped.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if c.is_control() => {
+                let _ = write!(escaped, "\\u{:04x}", c as u32);
+            }
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn no_samples_have_no_statistics() {
+        assert!(Statistics::from_samples(&[]).is_none());
+    }
+
+    #[test]
+    fn median_of_odd_count_is_middle_sample() {
+        let stats = Statistics::from_samples(&[3.0, 1.0, 2.0]).unwrap();
+        assert_eq!(stats.samples, 3);
+        assert_eq!(stats.min, 1.0);
+        assert_eq!(stats.avg, 2.0);
+        assert_eq!(stats.median, 2.0);
+    }
+
+    #[test]
+    fn median_of_even_count_averages_middle_samples() {
+        let stats = Statistics::from_samples(&[4.0, 1.0, 3.0, 2.0]).unwrap();
+        assert_eq!(stats.median, 2.5);
+    }
+
+    #[test]
+    fn p99_is_nearest_rank() {
+        let samples: Vec<f64> = (1..=200).rev().map(f64::from).collect();
+        assert_eq!(Statistics::from_samples(&samples).unwrap().p99, 198.0);
+
+        let samples: Vec<f64> = (1..=100).map(f64::from).collect();
+        assert_eq!(Statistics::from_samples(&samples).unwrap().p99, 99.0);
+
+        assert_eq!(Statistics::from_samples(&[5.0]).unwrap().p99, 5.0);
+    }
+
+    #[test]
+    fn json_string_escapes_quotes_backslashes_and_control_characters() {
+        assert_eq!(json_string("GPU"), r#""GPU""#);
+        assert_eq!(json_string(r#"a "b""#), r#""a \"b\"""#);
+        assert_eq!(json_string(r"C:\gpu"), r#""C:\\gpu""#);
+        assert_eq!(json_string("a\nb\t\u{7f}"), r#""a\u000ab\u0009\u007f""#);
+    }
+
+    #[test]
+    fn report_without_samples_has_null_statistics() {
+        let report = Report {
+            adapter: "GPU".to_owned(),
+            backend: "vulkan".to_owned(),
+            width: 800,
+            height: 600,
+            frames: 0,
+            frame_time: None,
+            cpu_time: None,
+            gpu_time: None,
+        };
+        assert_eq!(
+            report.to_json(),
+            r#"{"adapter":"GPU","backend":"vulkan","width":800,"height":600,"frames":0,"frame_time_ms":null,"cpu_time_ms":null,"gpu_time_ms":null}"#
+        );
+    }
+}
@@ -0,0 +1,447 @@
+use std::{borrow::Cow, ops::Range};
+
+use cgmath::{InnerSpace, Point3, Vector3};
+use wgpu::{
+    util::{DeviceExt, TextureDataOrder},
+    AddressMode, BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout,
+    BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingResource, BindingType, BlendState,
+    Buffer, BufferAddress, BufferDescriptor, BufferUsages, ColorTargetState, ColorWrites,
+    CompareFunction, DepthStencilState, Device, Extent3d, FilterMode, FragmentState,
+    MultisampleState, PipelineCompilationOptions, PipelineLayout, PipelineLayoutDescriptor,
+    PrimitiveState, PrimitiveTopology, Queue, RenderPass, RenderPipeline, RenderPipelineDescriptor,
+    SamplerBindingType, SamplerDescriptor, ShaderModule, ShaderModuleDescriptor, ShaderStages,
+    TextureDescriptor, TextureDimension, TextureFormat, TextureSampleType, TextureUsages,
+    TextureViewDescriptor, TextureViewDimension, VertexBufferLayout, VertexState, VertexStepMode,
+};
+
+use crate::{
+    camera::Camera,
+    oit::{Transparency, WeightedBlendedOit},
+};
+
+// The procedural sprite atlas is a row of square sprites
+const SPRITE_SIZE: u32 = 64;
+const SPRITES: [Sprite; 4] = [Sprite::Disc, Sprite::Glow, Sprite::Ring, Sprite::Flame];
+
+/// Sprites contained in the billboard texture atlas.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sprite {
+    /// Hard edged circle.
+    Disc,
+    /// Soft, round falloff, e.g. for particles.
+    Glow,
+    /// Circle outline, e.g. as light icon.
+    Ring,
+    /// Teardrop shape pointing upwards, for axis locked billboards.
+    Flame,
+}
+
+impl Sprite {
+    /// Texture coordinates of the sprite's top left and bottom right corners.
+    fn uv_rect(self) -> [f32; 4] {
+        let index = SPRITES.iter().position(|&sprite| sprite == self).unwrap() as f32;
+        let width = 1.0 / SPRITES.len() as f32;
+        [index * width, 0.0, (index + 1.0) * width, 1.0]
+    }
+
+    /// Coverage of the sprite at `(x, y)`, both ranging from -1 to 1.
+    fn coverage(self, x: f32, y: f32) -> f32 {
+        let radius = (x * x + y * y).sqrt();
+        let smooth = |edge0: f32, edge1: f32, value: f32| {
+            let t = ((value - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+            t * t * (3.0 - 2.0 * t)
+        };
+        match self {
+            Sprite::Disc => 1.0 - smooth(0.9, 0.97, radius),
+            Sprite::Glow => (1.0 - radius.min(1.0)).powi(2),
+            Sprite::Ring => {
+                smooth(0.6, 0.68, radius) * (1.0 - smooth(0.88, 0.96, radius))
+                    + 0.6 * (1.0 - smooth(0.2, 0.3, radius))
+            }
+            Sprite::Flame => {
+                // Circle at the bottom, narrowing towards the top
+                let width = 0.6 * (1.0 - (y + 1.0) / 2.0).clamp(0.0, 1.0).sqrt() + 0.05;
+                let bottom = (x * x + (y + 0.35) * (y + 0.35)).sqrt();
+                let body = 1.0 - smooth(width - 0.1, width, x.abs());
+                (1.0 - smooth(0.5, 0.6, bottom)).max(body * smooth(-0.4, -0.3, y))
+                    * (1.0 - smooth(0.85, 0.95, y))
+            }
+        }
+    }
+}
+
+/// How a billboard is oriented towards the camera.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Orientation {
+    /// Always faces the camera.
+    Full,
+    /// Only rotates around the given axis to face the camera.
+    AxisLocked(Vector3<f32>),
+}
+
+/// How the size of a billboard is interpreted.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BillboardSize {
+    /// In world units, so the billboard gets smaller with increasing distance.
+    World(f32, f32),
+    /// In pixels, so the billboard keeps its size on screen regardless of the distance.
+    Screen(f32, f32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Billboard {
+    /// World space position of the billboard's center.
+    pub position: Point3<f32>,
+    pub size: BillboardSize,
+    pub orientation: Orientation,
+    pub sprite: Sprite,
+    /// Multiplied with the sprite's color, which is white.
+    pub color: [f32; 4],
+    pub transparency: Transparency,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct BillboardInstance {
+    position: [f32; 3],
+    flags: u32,
+    axis: [f32; 3],
+    size: [f32; 2],
+    color: [f32; 4],
+    uv_rect: [f32; 4],
+}
+
+impl BillboardInstance {
+    const FLAG_AXIS_LOCKED: u32 = 1;
+    const FLAG_SCREEN_SIZE: u32 = 2;
+
+    const ATTRIBUTES: [wgpu::VertexAttribute; 6] = wgpu::vertex_attr_array![
+        0 => Float32x3,
+        1 => Uint32,
+        2 => Float32x3,
+        3 => Float32x2,
+        4 => Float32x4,
+        5 => Float32x4,
+    ];
+
+    fn layout() -> VertexBufferLayout<'static> {
+        VertexBufferLayout {
+            array_stride: size_of::<Self>() as BufferAddress,
+            step_mode: VertexStepMode::Instance,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+
+    fn new(billboard: &Billboard) -> Self {
+        let (axis, axis_flag) = match billboard.orientation {
+            Orientation::Full => (Vector3::unit_y(), 0),
+            Orientation::AxisLocked(axis) => (axis, Self::FLAG_AXIS_LOCKED),
+        };
+        let (size, size_flag) = match billboard.size {
+            BillboardSize::World(width, height) => ([width, height], 0),
+            BillboardSize::Screen(width, height) => ([width, height], Self::FLAG_SCREEN_SIZE),
+        };
+        Self {
+            position: billboard.position.into(),
+            flags: axis_flag | size_flag,
+            axis: axis.into(),
+            size,
+            color: billboard.color,
+            uv_rect: billboard.sprite.uv_rect(),
+        }
+    }
+}
+
+/// Draws billboards submitted during a frame as instanced, textured quads.
+///
+/// Billboards are depth tested against the scene but don't write depth.
+/// Depending on their [`Transparency`], they are either sorted back to front so that
+/// their transparent edges blend correctly, or drawn into the accumulation pass
+/// of [`WeightedBlendedOit`] by [`BillboardRenderer::render_oit`].
+pub struct BillboardRenderer {
+    pipeline: RenderPipeline,
+    oit_pipeline: RenderPipeline,
+    atlas_bind_group: BindGroup,
+    instance_buffer: Buffer,
+    billboards: Vec<Billboard>,
+    instances: Vec<BillboardInstance>,
+    // Instances are uploaded sorted ones first, followed by the order-independent ones
+    sorted_count: u32,
+    oit_count: u32,
+}
+
+impl BillboardRenderer {
+    const INITIAL_CAPACITY: BufferAddress = 256 * size_of::<BillboardInstance>() as BufferAddress;
+
+    pub fn new(
+        device: &Device,
+        queue: &Queue,
+        camera_bind_group_layout: &BindGroupLayout,
+        color_format: TextureFormat,
+        depth_format: TextureFormat,
+    ) -> Self {
+        let atlas_bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Billboard atlas bind group layout"),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Float { filterable: true },
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+        let atlas_bind_group = create_atlas_bind_group(device, queue, &atlas_bind_group_layout);
+
+        let shader_module = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("Billboard shader module"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(concat!(
+                include_str!("./camera.wgsl"),
+                include_str!("./oit.wgsl"),
+                include_str!("./billboards.wgsl")
+            ))),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Billboard pipeline layout"),
+            bind_group_layouts: &[camera_bind_group_layout, &atlas_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = create_pipeline(
+            device,
+            "Billboard pipeline",
+            &pipeline_layout,
+            &shader_module,
+            "fs_main",
+            &[Some(ColorTargetState {
+                format: color_format,
+                blend: Some(BlendState::ALPHA_BLENDING),
+                write_mask: ColorWrites::default(),
+            })],
+            depth_format,
+        );
+        let oit_pipeline = create_pipeline(
+            device,
+            "Billboard OIT pipeline",
+            &pipeline_layout,
+            &shader_module,
+            "fs_oit",
+            &WeightedBlendedOit::color_targets(),
+            depth_format,
+        );
+
+        Self {
+            pipeline,
+            oit_pipeline,
+            atlas_bind_group,
+            instance_buffer: create_instance_buffer(device, Self::INITIAL_CAPACITY),
+            billboards: Vec::new(),
+            instances: Vec::new(),
+            sorted_count: 0,
+            oit_count: 0,
+        }
+    }
+
+    pub fn draw(&mut self, billboard: Billboard) {
+        self.billboards.push(billboard);
+    }
+
+    /// Sorts and uploads the billboards submitted since the previous call.
+    pub fn prepare(&mut self, device: &Device, queue: &Queue, camera: &Camera) {
+        let forward = camera.forward();
+        let depth = |billboard: &Billboard| (billboard.position - camera.eye).dot(forward);
+        // Order-independent billboards keep their order, behind the sorted ones
+        self.billboards.sort_by(|a, b| {
+            let order_independent =
+                |billboard: &Billboard| billboard.transparency == Transparency::WeightedBlended;
+            order_independent(a)
+                .cmp(&order_independent(b))
+                .then_with(|| match a.transparency {
+                    Transparency::Sorted => depth(b).total_cmp(&depth(a)),
+                    Transparency::WeightedBlended => std::cmp::Ordering::Equal,
+                })
+        });
+        self.sorted_count = self
+            .billboards
+            .iter()
+            .take_while(|billboard| billboard.transparency == Transparency::Sorted)
+            .count() as u32;
+        self.oit_count = self.billboards.len() as u32 - self.sorted_count;
+
+        self.instances.clear();
+        self.instances.extend(
+            self.billboards
+                .drain(..)
+                .map(|billboard| BillboardInstance::new(&billboard)),
+        );
+
+        let size = (self.instances.len() * size_of::<BillboardInstance>()) as BufferAddress;
+        if size > self.instance_buffer.size() {
+            self.instance_buffer = create_instance_buffer(device, size.next_power_of_two());
+        }
+        if size > 0 {
+            queue.write_buffer(
+                &self.instance_buffer,
+                0,
+                bytemuck::cast_slice(&self.instances),
+            );
+        }
+    }
+
+    /// Draws the sorted billboards.
+    pub fn render(&self, render_pass: &mut RenderPass, camera_bind_group: &BindGroup) {
+        self.draw_instances(
+            render_pass,
+            camera_bind_group,
+            &self.pipeline,
+            0..self.sorted_count,
+        );
+    }
+
+    /// Draws the order-independent billboards into the accumulation pass of [`WeightedBlendedOit`].
+    pub fn render_oit(&self, render_pass: &mut RenderPass, camera_bind_group: &BindGroup) {
+        self.draw_instances(
+            render_pass,
+            camera_bind_group,
+            &self.oit_pipeline,
+            self.sorted_count..self.sorted_count + self.oit_count,
+        );
+    }
+
+    fn draw_instances(
+        &self,
+        render_pass: &mut RenderPass,
+        camera_bind_group: &BindGroup,
+        pipeline: &RenderPipeline,
+        instances: Range<u32>,
+    ) {
+        if instances.is_empty() {
+            return;
+        }
+        render_pass.set_pipeline(pipeline);
+        render_pass.set_bind_group(0, camera_bind_group, &[]);
+        render_pass.set_bind_group(1, &self.atlas_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.instance_buffer.slice(..));
+        render_pass.draw(0..4, instances);
+    }
+}
+
+fn create_pipeline(
+    device: &Device,
+    label: &str,
+    layout: &PipelineLayout,
+    module: &ShaderModule,
+    fragment_entry_point: &str,
+    targets: &[Option<ColorTargetState>],
+    depth_format: TextureFormat,
+) -> RenderPipeline {
+    device.create_render_pipeline(&RenderPipelineDescriptor {
+        label: Some(label),
+        layout: Some(layout),
+        vertex: VertexState {
+            module,
+            entry_point: Some("vs_main"),
+            compilation_options: PipelineCompilationOptions::default(),
+            buffers: &[BillboardInstance::layout()],
+        },
+        primitive: PrimitiveState {
+            topology: PrimitiveTopology::TriangleStrip,
+            ..Default::default()
+        },
+        depth_stencil: Some(DepthStencilState {
+            format: depth_format,
+            depth_write_enabled: false,
+            depth_compare: CompareFunction::GreaterEqual,
+            stencil: Default::default(),
+            bias: Default::default(),
+        }),
+        multisample: MultisampleState::default(),
+        fragment: Some(FragmentState {
+            module,
+            entry_point: Some(fragment_entry_point),
+            compilation_options: PipelineCompilationOptions::default(),
+            targets,
+        }),
+        multiview: None,
+        cache: None,
+    })
+}
+
+fn create_instance_buffer(device: &Device, size: BufferAddress) -> Buffer {
+    device.create_buffer(&BufferDescriptor {
+        label: Some("Billboard instance buffer"),
+        size,
+        usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+/// Renders the sprites into a texture atlas and creates its bind group.
+fn create_atlas_bind_group(device: &Device, queue: &Queue, layout: &BindGroupLayout) -> BindGroup {
+    let width = SPRITE_SIZE * SPRITES.len() as u32;
+    let mut pixels = Vec::with_capacity((width * SPRITE_SIZE * 4) as usize);
+    for y in 0..SPRITE_SIZE {
+        for x in 0..width {
+            let sprite = SPRITES[(x / SPRITE_SIZE) as usize];
+            let to_unit =
+                |pixel: u32| ((pixel % SPRITE_SIZE) as f32 + 0.5) / SPRITE_SIZE as f32 * 2.0 - 1.0;
+            // Texture rows go from top to bottom, sprite coordinates from bottom to top
+            let coverage = sprite.coverage(to_unit(x), -to_unit(y)).clamp(0.0, 1.0);
+            pixels.extend([255, 255, 255, (coverage * 255.0).round() as u8]);
+        }
+    }
+
+    let texture = device.create_texture_with_data(
+        queue,
+        &TextureDescriptor {
+            label: Some("Billboard atlas"),
+            size: Extent3d {
+                width,
+                height: SPRITE_SIZE,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: TextureFormat::Rgba8Unorm,
+            usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
+            view_formats: &[],
+        },
+        TextureDataOrder::LayerMajor,
+        &pixels,
+    );
+    let sampler = device.create_sampler(&SamplerDescriptor {
+        label: Some("Billboard atlas sampler"),
+        address_mode_u: AddressMode::ClampToEdge,
+        address_mode_v: AddressMode::ClampToEdge,
+        mag_filter: FilterMode::Linear,
+        min_filter: FilterMode::Linear,
+        ..Default::default()
+    });
+
+    device.create_bind_group(&BindGroupDescriptor {
+        label: Some("Billboard atlas bind group"),
+        layout,
+        entries: &[
+            BindGroupEntry {
+                binding: 0,
+                resource: BindingResource::TextureView(
+                    &texture.create_view(&TextureViewDescriptor::default()),
+                ),
+            },
+            BindGroupEntry {
+                binding: 1,
+                resource: BindingResource::Sampler(&sampler),
+            },
+        ],
+    })
+}
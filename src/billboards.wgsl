@@ -0,0 +1,83 @@
+// Draws camera facing quads ("billboards") with a texture, one instance per billboard.
+// Expects `camera.wgsl` and `oit.wgsl` to be prepended.
+
+// Flags of a billboard instance, see `BillboardInstance`
+const flag_axis_locked: u32 = 1u;
+const flag_screen_size: u32 = 2u;
+
+@group(1) @binding(0)
+var atlas_texture: texture_2d<f32>;
+@group(1) @binding(1)
+var atlas_sampler: sampler;
+
+struct Instance {
+    @location(0) position: vec3<f32>,
+    @location(1) flags: u32,
+    @location(2) axis: vec3<f32>,
+    @location(3) size: vec2<f32>,
+    @location(4) color: vec4<f32>,
+    @location(5) uv_rect: vec4<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+    @location(1) color: vec4<f32>,
+    @location(2) view_depth: f32,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) in_vertex_index: u32, instance: Instance) -> VertexOutput {
+    // Two triangles forming a quad, as a triangle strip
+    let corner = vec2<f32>(f32(in_vertex_index & 1u), f32((in_vertex_index >> 1u) & 1u));
+
+    let to_camera = camera.position.xyz - instance.position;
+    let forward = -vec3<f32>(camera.view[0].z, camera.view[1].z, camera.view[2].z);
+    var right: vec3<f32>;
+    var up: vec3<f32>;
+    if (instance.flags & flag_axis_locked) != 0u {
+        // Rotate around the axis only, e.g. for trees or flames
+        up = normalize(instance.axis);
+        right = normalize(cross(up, to_camera));
+    } else {
+        // The rows of the view matrix are the camera's axes in world space
+        right = vec3<f32>(camera.view[0].x, camera.view[1].x, camera.view[2].x);
+        up = vec3<f32>(camera.view[0].y, camera.view[1].y, camera.view[2].y);
+    }
+
+    var size = instance.size;
+    if (instance.flags & flag_screen_size) != 0u {
+        // The size is given in pixels, convert it to world units at the billboard's distance
+        let distance = dot(-to_camera, forward);
+        size *= 2.0 * distance / (camera.projection[1].y * camera.viewport.y);
+    }
+
+    let offset = (corner - 0.5) * size;
+    let world_position = instance.position + right * offset.x + up * offset.y;
+
+    var out: VertexOutput;
+    out.position = camera.view_projection * vec4<f32>(world_position, 1.0);
+    out.uv = mix(instance.uv_rect.xy, instance.uv_rect.zw, vec2<f32>(corner.x, 1.0 - corner.y));
+    out.color = instance.color;
+    out.view_depth = dot(world_position - camera.position.xyz, forward);
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(atlas_texture, atlas_sampler, in.uv) * in.color;
+    if color.a < 0.01 {
+        discard;
+    }
+    return color;
+}
+
+// Accumulates the billboard for weighted blended order-independent transparency
+@fragment
+fn fs_oit(in: VertexOutput) -> OitOutput {
+    let color = textureSample(atlas_texture, atlas_sampler, in.uv) * in.color;
+    if color.a < 0.01 {
+        discard;
+    }
+    return oit_output(color, in.view_depth);
+}
@@ -0,0 +1,208 @@
+use std::f32::consts::PI;
+
+use cgmath::{Deg, InnerSpace, Matrix4, Point3, SquareMatrix, Vector3, Vector4};
+
+// cgmath produces OpenGL style clip space with a depth range of -1.0 to 1.0,
+// while wgpu expects depth to range from 0.0 to 1.0.
+// The range is also reversed, the near plane maps to 1.0 and the far plane to 0.0
+// ("reverse-Z"): floating point precision, which is highest close to 0.0, then offsets
+// the precision lost to the perspective division far from the camera.
+// Depth is cleared to 0.0 and tested with `GreaterEqual`, or `Equal` after the depth prepass.
+#[rustfmt::skip]
+const OPENGL_TO_WGPU_MATRIX: Matrix4<f32> = Matrix4::new(
+    1.0, 0.0, 0.0, 0.0,
+    0.0, 1.0, 0.0, 0.0,
+    0.0, 0.0, -0.5, 0.0,
+    0.0, 0.0, 0.5, 1.0,
+);
+
+/// Orthographic projection to wgpu's clip space.
+pub fn orthographic(
+    left: f32,
+    right: f32,
+    bottom: f32,
+    top: f32,
+    near: f32,
+    far: f32,
+) -> Matrix4<f32> {
+    OPENGL_TO_WGPU_MATRIX * cgmath::ortho(left, right, bottom, top, near, far)
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Camera {
+    pub eye: Point3<f32>,
+    pub target: Point3<f32>,
+    pub up: Vector3<f32>,
+    pub fovy: Deg<f32>,
+    pub aspect: f32,
+    pub znear: f32,
+    pub zfar: f32,
+}
+
+impl Camera {
+    pub fn new(aspect: f32) -> Self {
+        Self {
+            eye: Point3::new(0.0, 2.0, 6.0),
+            target: Point3::new(0.0, 0.0, 0.0),
+            up: Vector3::unit_y(),
+            fovy: Deg(60.0),
+            aspect,
+            znear: 0.1,
+            zfar: 1000.0,
+        }
+    }
+
+    pub fn view(&self) -> Matrix4<f32> {
+        Matrix4::look_at_rh(self.eye, self.target, self.up)
+    }
+
+    /// Normalized direction the camera is looking in.
+    pub fn forward(&self) -> Vector3<f32> {
+        (self.target - self.eye).normalize()
+    }
+
+    pub fn projection(&self) -> Matrix4<f32> {
+        OPENGL_TO_WGPU_MATRIX * cgmath::perspective(self.fovy, self.aspect, self.znear, self.zfar)
+    }
+
+    /// Returns this camera with its eye between `previous_eye` and `eye`, at `alpha` from 0 to 1.
+    ///
+    /// The eye orbits the target rather than cutting across: the direction from the target turns
+    /// along the arc between its previous and current direction, while the distance changes
+    /// linearly, so a camera rotating around the target keeps its distance in between.
+    pub fn orbit_from(&self, previous_eye: Point3<f32>, alpha: f32) -> Self {
+        let lerp = previous_eye + (self.eye - previous_eye) * alpha;
+        let (previous_offset, offset) = (previous_eye - self.target, self.eye - self.target);
+        let angle = previous_offset.angle(offset).0;
+        // The arc is undefined without an offset or between opposite directions, and a line
+        // close enough to it without a turn
+        if !(1e-4..PI - 1e-4).contains(&angle) {
+            return Self { eye: lerp, ..*self };
+        }
+        let direction = (previous_offset.normalize() * ((1.0 - alpha) * angle).sin()
+            + offset.normalize() * (alpha * angle).sin())
+            / angle.sin();
+        let distance = previous_offset.magnitude()
+            + (offset.magnitude() - previous_offset.magnitude()) * alpha;
+        Self {
+            eye: self.target + direction * distance,
+            ..*self
+        }
+    }
+
+    /// Returns the normalized world space direction of the view ray through a pixel
+    /// of a render target with the given size, like `view_ray` of `camera.wgsl`.
+    pub fn view_ray(&self, x: f32, y: f32, width: u32, height: u32) -> Vector3<f32> {
+        let ndc = Vector4::new(
+            2.0 * x / width as f32 - 1.0,
+            1.0 - 2.0 * y / height as f32,
+            0.0,
+            1.0,
+        );
+        let far = (self.projection() * self.view())
+            .invert()
+            .unwrap_or(Matrix4::identity())
+            * ndc;
+        (Point3::from_homogeneous(far) - self.eye).normalize()
+    }
+}
+
+/// Camera data as seen by the shaders, bound at `@group(0) @binding(0)`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct CameraUniform {
+    view: [[f32; 4]; 4],
+    projection: [[f32; 4]; 4],
+    view_projection: [[f32; 4]; 4],
+    inverse_view_projection: [[f32; 4]; 4],
+    // World space position of the camera, `w` is unused.
+    position: [f32; 4],
+    // Width, height, and their reciprocals of the render target.
+    viewport: [f32; 4],
+}
+
+impl CameraUniform {
+    pub fn new(camera: &Camera, width: u32, height: u32) -> Self {
+        Self::from_matrices(
+            camera.view(),
+            camera.projection(),
+            camera.eye,
+            width,
+            height,
+        )
+    }
+
+    /// Creates the uniform for a view that is not described by a [`Camera`].
+    pub fn from_matrices(
+        view: Matrix4<f32>,
+        projection: Matrix4<f32>,
+        position: Point3<f32>,
+        width: u32,
+        height: u32,
+    ) -> Self {
+        let view_projection = projection * view;
+        let inverse_view_projection = view_projection.invert().unwrap_or(Matrix4::identity());
+        let (width, height) = (width.max(1) as f32, height.max(1) as f32);
+
+        Self {
+            view: view.into(),
+            projection: projection.into(),
+            view_projection: view_projection.into(),
+            inverse_view_projection: inverse_view_projection.into(),
+            position: position.to_homogeneous().into(),
+            viewport: [width, height, 1.0 / width, 1.0 / height],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(a: Point3<f32>, b: Point3<f32>) {
+        assert!((a - b).magnitude() < 1e-5, "{a:?} != {b:?}");
+    }
+
+    #[test]
+    fn orbit_keeps_the_endpoints() {
+        let camera = Camera::new(1.0);
+        let previous_eye = Point3::new(6.0, 2.0, 0.0);
+        assert_near(camera.orbit_from(previous_eye, 0.0).eye, previous_eye);
+        assert_near(camera.orbit_from(previous_eye, 1.0).eye, camera.eye);
+    }
+
+    #[test]
+    fn orbit_keeps_the_distance_to_the_target() {
+        let camera = Camera {
+            eye: Point3::new(0.0, 0.0, 4.0),
+            ..Camera::new(1.0)
+        };
+        let eye = camera.orbit_from(Point3::new(4.0, 0.0, 0.0), 0.5).eye;
+        assert!(((eye - camera.target).magnitude() - 4.0).abs() < 1e-5);
+        assert_near(eye, Point3::new(8f32.sqrt(), 0.0, 8f32.sqrt()));
+    }
+
+    #[test]
+    fn orbit_interpolates_the_distance() {
+        let camera = Camera {
+            eye: Point3::new(0.0, 0.0, 2.0),
+            ..Camera::new(1.0)
+        };
+        let eye = camera.orbit_from(Point3::new(4.0, 0.0, 0.0), 0.5).eye;
+        assert!(((eye - camera.target).magnitude() - 3.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn orbit_without_turning_is_linear() {
+        let camera = Camera::new(1.0);
+        let previous_eye = Point3::new(0.0, 4.0, 12.0);
+        assert_near(
+            camera.orbit_from(previous_eye, 0.5).eye,
+            Point3::new(0.0, 3.0, 9.0),
+        );
+        assert_near(
+            camera.orbit_from(camera.target, 0.5).eye,
+            Point3::new(0.0, 1.0, 3.0),
+        );
+    }
+}
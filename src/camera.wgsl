@@ -0,0 +1,27 @@
+// Camera uniform shared by all shaders drawing the scene, see `CameraUniform`.
+
+struct Camera {
+    view: mat4x4<f32>,
+    projection: mat4x4<f32>,
+    view_projection: mat4x4<f32>,
+    inverse_view_projection: mat4x4<f32>,
+    // World space position, w is unused
+    position: vec4<f32>,
+    // Width, height, 1 / width, 1 / height
+    viewport: vec4<f32>,
+}
+
+@group(0) @binding(0)
+var<uniform> camera: Camera;
+
+// Returns the normalized world space direction of the view ray through a pixel.
+fn view_ray(position: vec2<f32>) -> vec3<f32> {
+    // Screen space to normalized device coordinates, flipping Y as it points down in screen space.
+    let ndc = vec2<f32>(position.x, camera.viewport.y - position.y) * camera.viewport.zw * 2.0 - 1.0;
+    // The direction is derived from the projection's field of view rather than by unprojecting
+    // a point on the far plane, which oblique clip planes may move behind the camera.
+    let direction = vec3<f32>(ndc.x / camera.projection[0][0], ndc.y / camera.projection[1][1], -1.0);
+    // The view matrix is orthonormal, so its transpose inverts its rotation
+    let rotation = mat3x3<f32>(camera.view[0].xyz, camera.view[1].xyz, camera.view[2].xyz);
+    return normalize(transpose(rotation) * direction);
+}
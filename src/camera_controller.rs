@@ -0,0 +1,100 @@
+use std::f32::consts::FRAC_PI_2;
+
+use cgmath::{InnerSpace, Vector3};
+use winit::{event::MouseButton, keyboard::KeyCode};
+
+use crate::{camera::Camera, input::InputEvent};
+
+const ROTATION_PER_PIXEL: f32 = 0.005;
+const ROTATION_PER_SECOND: f32 = 1.5;
+const ZOOM_PER_LINE: f32 = 0.9;
+const MIN_DISTANCE: f32 = 0.5;
+const MAX_DISTANCE: f32 = 500.0;
+// Keep away from the poles, where the view direction becomes parallel to the up vector.
+const MAX_PITCH: f32 = FRAC_PI_2 - 0.01;
+
+/// Orbits the camera around its target.
+///
+/// Dragging with the left mouse button or holding the arrow/WASD keys rotates,
+/// scrolling zooms.
+/// Input is collected between ticks and applied in [`OrbitController::update`].
+pub struct OrbitController {
+    yaw: f32,
+    pitch: f32,
+    distance: f32,
+    dragging: bool,
+    cursor: Option<(f64, f64)>,
+    drag_delta: (f32, f32),
+    scroll: f32,
+    left: bool,
+    right: bool,
+    up: bool,
+    down: bool,
+}
+
+impl OrbitController {
+    pub fn new(camera: &Camera) -> Self {
+        let offset = camera.eye - camera.target;
+        let distance = offset.magnitude();
+        Self {
+            yaw: offset.z.atan2(offset.x),
+            pitch: (offset.y / distance).asin(),
+            distance,
+            dragging: false,
+            cursor: None,
+            drag_delta: (0.0, 0.0),
+            scroll: 0.0,
+            left: false,
+            right: false,
+            up: false,
+            down: false,
+        }
+    }
+
+    pub fn handle_input(&mut self, event: &InputEvent) {
+        match *event {
+            InputEvent::Key { code, pressed } => match code {
+                KeyCode::ArrowLeft | KeyCode::KeyA => self.left = pressed,
+                KeyCode::ArrowRight | KeyCode::KeyD => self.right = pressed,
+                KeyCode::ArrowUp | KeyCode::KeyW => self.up = pressed,
+                KeyCode::ArrowDown | KeyCode::KeyS => self.down = pressed,
+                _ => {}
+            },
+            InputEvent::MouseButton {
+                button: MouseButton::Left,
+                pressed,
+            } => self.dragging = pressed,
+            InputEvent::MouseButton { .. } => {}
+            InputEvent::CursorMoved { x, y } => {
+                if let (true, Some((last_x, last_y))) = (self.dragging, self.cursor) {
+                    self.drag_delta.0 += (x - last_x) as f32;
+                    self.drag_delta.1 += (y - last_y) as f32;
+                }
+                self.cursor = Some((x, y));
+            }
+            InputEvent::Scroll { y, .. } => self.scroll += y,
+        }
+    }
+
+    /// Applies the input received since the previous update to the camera.
+    pub fn update(&mut self, camera: &mut Camera, dt: f32) {
+        let axis = |positive: bool, negative: bool| positive as i8 as f32 - negative as i8 as f32;
+        let (drag_x, drag_y) = std::mem::take(&mut self.drag_delta);
+
+        self.yaw +=
+            drag_x * ROTATION_PER_PIXEL + axis(self.right, self.left) * ROTATION_PER_SECOND * dt;
+        self.pitch = (self.pitch
+            + drag_y * ROTATION_PER_PIXEL
+            + axis(self.up, self.down) * ROTATION_PER_SECOND * dt)
+            .clamp(-MAX_PITCH, MAX_PITCH);
+        self.distance = (self.distance * ZOOM_PER_LINE.powf(std::mem::take(&mut self.scroll)))
+            .clamp(MIN_DISTANCE, MAX_DISTANCE);
+
+        let direction = Vector3::new(
+            self.pitch.cos() * self.yaw.cos(),
+            self.pitch.sin(),
+            self.pitch.cos() * self.yaw.sin(),
+        );
+        camera.eye = camera.target + direction * self.distance;
+    }
+}
@@ -0,0 +1,119 @@
+use std::{ops::Range, time::Duration};
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use std::time::Instant;
+#[cfg(target_arch = "wasm32")]
+pub use web_time::Instant;
+
+// Upper bound for the ticks simulated per frame, so a long stall (e.g. a breakpoint,
+// or the window being dragged) does not cause an ever growing backlog of ticks.
+const MAX_TICKS_PER_FRAME: u64 = 8;
+
+/// Fixed timestep clock driving the simulation.
+///
+/// Real time is accumulated and consumed in steps of a fixed duration ("ticks"),
+/// so the simulation advances identically regardless of the frame rate.
+pub struct FixedClock {
+    step: Duration,
+    accumulator: Duration,
+    tick: u64,
+    last_advance: Option<Instant>,
+}
+
+impl FixedClock {
+    pub const DEFAULT_STEP: Duration = Duration::from_micros(16_667);
+
+    pub fn new(step: Duration) -> Self {
+        Self {
+            step,
+            accumulator: Duration::ZERO,
+            tick: 0,
+            last_advance: None,
+        }
+    }
+
+    pub fn step(&self) -> Duration {
+        self.step
+    }
+
+    /// Fraction of a tick passed since the last simulated tick, from 0 to 1.
+    ///
+    /// Rendering lags a tick behind the simulation and interpolates between the states before
+    /// and after the last tick by this fraction, so motion stays smooth when the frame rate
+    /// differs from the tick rate.
+    pub fn alpha(&self) -> f32 {
+        self.accumulator.as_secs_f32() / self.step.as_secs_f32()
+    }
+
+    /// Advances the clock by the real time passed since the previous call.
+    /// Returns the ticks to simulate.
+    pub fn advance_realtime(&mut self) -> Range<u64> {
+        let now = Instant::now();
+        let elapsed = self
+            .last_advance
+            .replace(now)
+            .map_or(Duration::ZERO, |last_advance| now - last_advance);
+        self.advance(elapsed)
+    }
+
+    /// Advances the clock by `elapsed`, returning the ticks to simulate.
+    pub fn advance(&mut self, elapsed: Duration) -> Range<u64> {
+        self.accumulator += elapsed;
+        let mut ticks = 0;
+        while self.accumulator >= self.step {
+            self.accumulator -= self.step;
+            ticks += 1;
+        }
+        if ticks > MAX_TICKS_PER_FRAME {
+            log::debug!("Dropping {} ticks", ticks - MAX_TICKS_PER_FRAME);
+            ticks = MAX_TICKS_PER_FRAME;
+        }
+        let first = self.tick;
+        self.tick += ticks;
+        first..self.tick
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STEP: Duration = Duration::from_millis(10);
+
+    #[test]
+    fn advance_returns_contiguous_ticks() {
+        let mut clock = FixedClock::new(STEP);
+        assert_eq!(clock.advance(Duration::ZERO), 0..0);
+        assert_eq!(clock.advance(STEP * 2), 0..2);
+        assert_eq!(clock.advance(STEP), 2..3);
+        assert_eq!(clock.advance(STEP * 3), 3..6);
+    }
+
+    #[test]
+    fn advance_keeps_remainder() {
+        let mut clock = FixedClock::new(STEP);
+        assert_eq!(clock.advance(Duration::from_millis(15)), 0..1);
+        assert_eq!(clock.alpha(), 0.5);
+        assert_eq!(clock.advance(Duration::from_millis(4)), 1..1);
+        assert_eq!(clock.advance(Duration::from_millis(1)), 1..2);
+        assert_eq!(clock.alpha(), 0.0);
+    }
+
+    #[test]
+    fn advance_caps_ticks_per_frame() {
+        let mut clock = FixedClock::new(STEP);
+        assert_eq!(clock.advance(STEP * 100), 0..MAX_TICKS_PER_FRAME);
+        // The dropped ticks are not made up for later
+        assert_eq!(
+            clock.advance(STEP),
+            MAX_TICKS_PER_FRAME..MAX_TICKS_PER_FRAME + 1
+        );
+    }
+
+    #[test]
+    fn capping_keeps_remainder() {
+        let mut clock = FixedClock::new(STEP);
+        clock.advance(STEP * 100 + Duration::from_millis(5));
+        assert_eq!(clock.alpha(), 0.5);
+    }
+}
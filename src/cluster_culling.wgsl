@@ -0,0 +1,44 @@
+// Bins the point lights into the clusters of the view frustum, one invocation per cluster.
+// Expects `camera.wgsl`, `lighting.wgsl` and `clusters.wgsl` to be prepended.
+
+@group(2) @binding(0)
+var<storage, read_write> clusters: Clusters;
+
+@compute @workgroup_size(64)
+fn cs_main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let index = id.x;
+    if index >= cluster_grid.x * cluster_grid.y * cluster_grid.z {
+        return;
+    }
+    let tile = vec2<u32>(index % cluster_grid.x, (index / cluster_grid.x) % cluster_grid.y);
+    let slice = index / (cluster_grid.x * cluster_grid.y);
+
+    // Bounds of the tile in normalized device coordinates, tiles are counted from the top
+    let grid = vec2<f32>(cluster_grid.xy);
+    let ndc_min = vec2<f32>(-1.0 + 2.0 * f32(tile.x) / grid.x, 1.0 - 2.0 * f32(tile.y + 1u) / grid.y);
+    let ndc_max = ndc_min + 2.0 / grid;
+
+    // View space bounding box of the cluster, a view space point at distance d
+    // along the view direction projects to ndc.xy = xy * projection.xy / d
+    let near = slice_distance(slice);
+    let far = slice_distance(slice + 1u);
+    let scale = 1.0 / vec2<f32>(camera.projection[0][0], camera.projection[1][1]);
+    let low = ndc_min * scale;
+    let high = ndc_max * scale;
+    let box_min = vec3<f32>(min(low * near, low * far), -far);
+    let box_max = vec3<f32>(max(high * near, high * far), -near);
+
+    let capacity = clusters.capacity;
+    let start = cluster_start(index, capacity);
+    var count = 0u;
+    for (var i = 0u; i < light_list.count && count < capacity; i++) {
+        let light = light_list.lights[i];
+        let center = (camera.view * vec4<f32>(light.position, 1.0)).xyz;
+        let offset = center - clamp(center, box_min, box_max);
+        if dot(offset, offset) < light.radius * light.radius {
+            clusters.data[start + 1u + count] = i;
+            count++;
+        }
+    }
+    clusters.data[start] = count;
+}
@@ -0,0 +1,161 @@
+use std::borrow::Cow;
+
+use wgpu::{
+    BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
+    BindGroupLayoutEntry, BindingType, Buffer, BufferAddress, BufferBindingType, BufferDescriptor,
+    BufferUsages, CommandEncoder, ComputePassDescriptor, ComputePipeline,
+    ComputePipelineDescriptor, Device, PipelineCompilationOptions, PipelineLayoutDescriptor,
+    ShaderModuleDescriptor, ShaderStages,
+};
+
+/// Number of clusters along the screen's width and height and the view direction.
+///
+/// Must match `cluster_grid` of `clusters.wgsl`.
+const CLUSTER_GRID: [u32; 3] = [16, 9, 24];
+/// Lights a cluster has room for, unless the device's storage buffer bindings are too small.
+const MAX_LIGHTS_PER_CLUSTER: u32 = 127;
+
+const CLUSTER_COUNT: u32 = CLUSTER_GRID[0] * CLUSTER_GRID[1] * CLUSTER_GRID[2];
+// The capacity of the clusters, see `Clusters` of `clusters.wgsl`
+const HEADER_SIZE: BufferAddress = 4;
+const WORKGROUP_SIZE: u32 = 64;
+
+/// Light culling of the Forward+ path.
+///
+/// The view frustum is partitioned into clusters, and a compute pass bins the point lights
+/// into every cluster their sphere of influence intersects. The forward shader then only
+/// evaluates the lights of the cluster a fragment lies in, rather than all lights.
+pub struct LightClusters {
+    pipeline: ComputePipeline,
+    compute_bind_group: BindGroup,
+    bind_group_layout: BindGroupLayout,
+    bind_group: BindGroup,
+}
+
+impl LightClusters {
+    pub fn new(
+        device: &Device,
+        camera_bind_group_layout: &BindGroupLayout,
+        lights_bind_group_layout: &BindGroupLayout,
+    ) -> Self {
+        // Each cluster holds its light count followed by the light indices
+        let max_size = BufferAddress::from(device.limits().max_storage_buffer_binding_size);
+        let max_capacity = ((max_size - HEADER_SIZE) / (CLUSTER_COUNT as BufferAddress * 4))
+            .saturating_sub(1) as u32;
+        let capacity = MAX_LIGHTS_PER_CLUSTER.min(max_capacity);
+        if capacity < MAX_LIGHTS_PER_CLUSTER {
+            log::warn!(
+                "Binning at most {capacity} lights per cluster to fit the storage buffer binding size"
+            );
+        }
+        let buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("Light cluster buffer"),
+            size: HEADER_SIZE
+                + CLUSTER_COUNT as BufferAddress * (1 + capacity as BufferAddress) * 4,
+            usage: BufferUsages::STORAGE,
+            mapped_at_creation: true,
+        });
+        buffer
+            .slice(..HEADER_SIZE)
+            .get_mapped_range_mut()
+            .copy_from_slice(bytemuck::bytes_of(&capacity));
+        buffer.unmap();
+
+        // Written by the culling pass, read by the forward shader
+        let (compute_bind_group_layout, compute_bind_group) =
+            create_bind_group(device, &buffer, ShaderStages::COMPUTE, false);
+        let (bind_group_layout, bind_group) =
+            create_bind_group(device, &buffer, ShaderStages::FRAGMENT, true);
+
+        let shader_module = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("Light culling shader module"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(concat!(
+                include_str!("./camera.wgsl"),
+                include_str!("./lighting.wgsl"),
+                include_str!("./clusters.wgsl"),
+                include_str!("./cluster_culling.wgsl")
+            ))),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Light culling pipeline layout"),
+            bind_group_layouts: &[
+                camera_bind_group_layout,
+                lights_bind_group_layout,
+                &compute_bind_group_layout,
+            ],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
+            label: Some("Light culling pipeline"),
+            layout: Some(&pipeline_layout),
+            module: &shader_module,
+            entry_point: None,
+            compilation_options: PipelineCompilationOptions::default(),
+            cache: None,
+        });
+
+        Self {
+            pipeline,
+            compute_bind_group,
+            bind_group_layout,
+            bind_group,
+        }
+    }
+
+    /// Layout of [`LightClusters::bind_group`], read by `clusters` of `meshes.wgsl`.
+    pub fn bind_group_layout(&self) -> &BindGroupLayout {
+        &self.bind_group_layout
+    }
+
+    pub fn bind_group(&self) -> &BindGroup {
+        &self.bind_group
+    }
+
+    /// Bins the lights into the clusters of the current camera.
+    pub fn cull(
+        &self,
+        command_encoder: &mut CommandEncoder,
+        camera_bind_group: &BindGroup,
+        lights_bind_group: &BindGroup,
+    ) {
+        let mut compute_pass = command_encoder.begin_compute_pass(&ComputePassDescriptor {
+            label: Some("Light culling pass"),
+            timestamp_writes: None,
+        });
+        compute_pass.set_pipeline(&self.pipeline);
+        compute_pass.set_bind_group(0, camera_bind_group, &[]);
+        compute_pass.set_bind_group(1, lights_bind_group, &[]);
+        compute_pass.set_bind_group(2, &self.compute_bind_group, &[]);
+        compute_pass.dispatch_workgroups(CLUSTER_COUNT.div_ceil(WORKGROUP_SIZE), 1, 1);
+    }
+}
+
+fn create_bind_group(
+    device: &Device,
+    buffer: &Buffer,
+    visibility: ShaderStages,
+    read_only: bool,
+) -> (BindGroupLayout, BindGroup) {
+    let layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+        label: Some("Light cluster bind group layout"),
+        entries: &[BindGroupLayoutEntry {
+            binding: 0,
+            visibility,
+            ty: BindingType::Buffer {
+                ty: BufferBindingType::Storage { read_only },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        }],
+    });
+    let bind_group = device.create_bind_group(&BindGroupDescriptor {
+        label: Some("Light cluster bind group"),
+        layout: &layout,
+        entries: &[BindGroupEntry {
+            binding: 0,
+            resource: buffer.as_entire_binding(),
+        }],
+    });
+    (layout, bind_group)
+}
@@ -0,0 +1,55 @@
+// Partition of the view frustum into clusters for the Forward+ path, see `LightClusters`.
+// Expects `camera.wgsl` to be prepended.
+//
+// The screen is divided into tiles, and the depth range of each tile into slices growing
+// exponentially with the distance to the camera, so that clusters are roughly cubic.
+
+// Must match `CLUSTER_GRID`
+const cluster_grid: vec3<u32> = vec3<u32>(16u, 9u, 24u);
+
+// Must match the buffer of `LightClusters`
+struct Clusters {
+    // Lights each cluster has room for, fewer than `MAX_LIGHTS_PER_CLUSTER` if the buffer would
+    // not fit into a storage buffer binding of the device otherwise
+    capacity: u32,
+    // Per cluster, the number of its lights followed by room for `capacity` indices into the
+    // light list
+    data: array<u32>,
+}
+
+// Returns the index into `Clusters::data` at which a cluster begins.
+fn cluster_start(cluster: u32, capacity: u32) -> u32 {
+    return cluster * (1u + capacity);
+}
+
+// Near and far plane distances, recovered from the perspective projection of
+// `Camera::projection`, whose third row maps view space z to depth as
+// depth = (a * z + b) / -z with a = near / (far - near) and b = far * near / (far - near).
+fn near_far() -> vec2<f32> {
+    let a = camera.projection[2][2];
+    let b = camera.projection[3][2];
+    return vec2<f32>(b / (a + 1.0), b / a);
+}
+
+// Distance from the camera at which the given depth slice begins.
+fn slice_distance(slice: u32) -> f32 {
+    let near_far = near_far();
+    return near_far.x * pow(near_far.y / near_far.x, f32(slice) / f32(cluster_grid.z));
+}
+
+// Returns the index of the cluster containing a fragment at a screen space position
+// and distance from the camera along its view direction.
+fn cluster_index(position: vec2<f32>, view_depth: f32) -> u32 {
+    let near_far = near_far();
+    let tile = vec2<u32>(clamp(
+        position * camera.viewport.zw * vec2<f32>(cluster_grid.xy),
+        vec2<f32>(0.0),
+        vec2<f32>(cluster_grid.xy - 1u),
+    ));
+    let slice = u32(clamp(
+        log(view_depth / near_far.x) / log(near_far.y / near_far.x) * f32(cluster_grid.z),
+        0.0,
+        f32(cluster_grid.z - 1u),
+    ));
+    return (slice * cluster_grid.y + tile.y) * cluster_grid.x + tile.x;
+}
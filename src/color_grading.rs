@@ -0,0 +1,653 @@
+use std::{borrow::Cow, collections::HashMap, fs, fs::File, io::BufReader, path::Path};
+
+use color_eyre::{
+    eyre::{bail, Context, OptionExt},
+    Result,
+};
+use half::f16;
+use wgpu::{
+    util::{BufferInitDescriptor, DeviceExt, TextureDataOrder},
+    AddressMode, BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout,
+    BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingResource, BindingType, Buffer,
+    BufferBindingType, BufferUsages, Color, ColorTargetState, ColorWrites, CommandEncoder, Device,
+    Extent3d, FilterMode, FragmentState, LoadOp, MultisampleState, Operations,
+    PipelineCompilationOptions, PipelineLayoutDescriptor, PrimitiveState, Queue,
+    RenderPassColorAttachment, RenderPassDescriptor, RenderPipeline, RenderPipelineDescriptor,
+    Sampler, SamplerBindingType, SamplerDescriptor, ShaderModuleDescriptor, ShaderStages, StoreOp,
+    TextureDescriptor, TextureDimension, TextureFormat, TextureSampleType, TextureUsages,
+    TextureView, TextureViewDescriptor, TextureViewDimension, VertexState,
+};
+
+/// Entries along each axis of the built-in LUTs.
+const BUILT_IN_SIZE: u32 = 33;
+/// Largest LUT accepted from a file, 256 entries per axis already take 64 MiB.
+const MAX_SIZE: u32 = 256;
+
+/// A 3D lookup table (LUT) mapping colors, encoded as sRGB, to graded colors.
+pub struct Lut {
+    pub name: String,
+    /// Entries along each axis.
+    size: u32,
+    /// Encoded colors mapped to the first and the last entry along each axis.
+    domain: [[f32; 3]; 2],
+    /// Graded colors, red changing fastest, then green, then blue, like in `.cube` files.
+    colors: Vec<[f32; 3]>,
+}
+
+impl Lut {
+    /// Creates a LUT by grading the color of each entry with `grade`.
+    pub fn from_fn(name: &str, size: u32, grade: impl Fn([f32; 3]) -> [f32; 3]) -> Self {
+        let step = 1.0 / (size - 1) as f32;
+        let colors = (0..size * size * size)
+            .map(|index| {
+                let [r, g, b] = [index % size, index / size % size, index / (size * size)];
+                grade([r as f32 * step, g as f32 * step, b as f32 * step])
+            })
+            .collect();
+        Self {
+            name: name.to_owned(),
+            size,
+            domain: [[0.0; 3], [1.0; 3]],
+            colors,
+        }
+    }
+
+    /// Loads a LUT from a PNG strip if the file ends in `.png`, see [`Lut::load_strip`], and from
+    /// a `.cube` file otherwise, see [`Lut::load_cube`].
+    pub fn load(path: &Path) -> Result<Self> {
+        if path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("png"))
+        {
+            Self::load_strip(path)
+        } else {
+            Self::load_cube(path)
+        }
+    }
+
+    /// Loads a LUT from a `.cube` file as written by most color grading tools.
+    pub fn load_cube(path: &Path) -> Result<Self> {
+        let source = fs::read_to_string(path)
+            .wrap_err_with(|| format!("failed to read LUT {}", path.display()))?;
+        Self::parse_cube(&file_name(path), &source)
+            .wrap_err_with(|| format!("invalid LUT {}", path.display()))
+    }
+
+    /// Loads a LUT from a PNG image holding its N slices of N×N entries side by side, as
+    /// game engines store them: red increases to the right within a slice, green downwards, and
+    /// blue from slice to slice. The image is N² pixels wide and N pixels high.
+    pub fn load_strip(path: &Path) -> Result<Self> {
+        let file =
+            File::open(path).wrap_err_with(|| format!("failed to read LUT {}", path.display()))?;
+        let mut decoder = png::Decoder::new(BufReader::new(file));
+        // Palettes and fewer than 8 bits per channel are expanded to 8 bits
+        decoder.set_transformations(png::Transformations::EXPAND);
+        let decode = || -> Result<Self> {
+            let mut reader = decoder.read_info()?;
+            let mut bytes = vec![0; reader.output_buffer_size()];
+            let info = reader.next_frame(&mut bytes)?;
+            let channels = info.color_type.samples();
+            let samples: Vec<f32> = match info.bit_depth {
+                png::BitDepth::Sixteen => bytes[..info.buffer_size()]
+                    .chunks_exact(2)
+                    .map(|sample| u16::from_be_bytes([sample[0], sample[1]]) as f32 / 65535.0)
+                    .collect(),
+                _ => bytes[..info.buffer_size()]
+                    .iter()
+                    .map(|&sample| sample as f32 / 255.0)
+                    .collect(),
+            };
+            let pixels = samples
+                .chunks_exact(channels)
+                .map(|pixel| match pixel {
+                    // Gray, with or without alpha
+                    [gray] | [gray, _] => [*gray; 3],
+                    [r, g, b, ..] => [*r, *g, *b],
+                    _ => unreachable!("PNG pixels have from 1 to 4 channels"),
+                })
+                .collect::<Vec<_>>();
+            Self::from_strip(&file_name(path), info.width, info.height, &pixels)
+        };
+        decode().wrap_err_with(|| format!("invalid LUT {}", path.display()))
+    }
+
+    /// Rearranges the pixels of a strip of slices, row by row, into a LUT, see
+    /// [`Lut::load_strip`].
+    fn from_strip(name: &str, width: u32, height: u32, pixels: &[[f32; 3]]) -> Result<Self> {
+        let size = height;
+        if width != size * size {
+            bail!(
+                "a strip of {size} slices must be {} pixels wide, found {width}",
+                size * size
+            );
+        }
+        if !(2..=MAX_SIZE).contains(&size) {
+            bail!("size must be from 2 to {MAX_SIZE}");
+        }
+        let colors = (0..size * size * size)
+            .map(|index| {
+                let [r, g, b] = [index % size, index / size % size, index / (size * size)];
+                pixels[(g * width + b * size + r) as usize]
+            })
+            .collect();
+        Ok(Self {
+            name: name.to_owned(),
+            size,
+            domain: [[0.0; 3], [1.0; 3]],
+            colors,
+        })
+    }
+
+    /// Parses the contents of a `.cube` file, which lists the graded colors of a 3D LUT after
+    /// keywords describing it, and may contain comments starting with `#`.
+    pub fn parse_cube(name: &str, source: &str) -> Result<Self> {
+        let mut size = None;
+        let mut domain = [[0.0; 3], [1.0; 3]];
+        let mut colors = Vec::new();
+        for (line_number, line) in source.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut parse = || -> Result<()> {
+                let words: Vec<&str> = line.split_whitespace().collect();
+                match words[0] {
+                    "TITLE" => {}
+                    "LUT_3D_SIZE" => {
+                        let entries: u32 = words.get(1).ok_or_eyre("missing size")?.parse()?;
+                        if !(2..=MAX_SIZE).contains(&entries) {
+                            bail!("size must be from 2 to {MAX_SIZE}");
+                        }
+                        size = Some(entries);
+                    }
+                    "LUT_1D_SIZE" => bail!("1D LUTs are not supported"),
+                    "DOMAIN_MIN" => domain[0] = parse_color(&words[1..])?,
+                    "DOMAIN_MAX" => domain[1] = parse_color(&words[1..])?,
+                    keyword if keyword.starts_with(|c: char| c.is_ascii_alphabetic()) => {
+                        log::warn!("{name}: ignoring unknown keyword {keyword}");
+                    }
+                    _ => colors.push(parse_color(&words)?),
+                }
+                Ok(())
+            };
+            parse().wrap_err_with(|| format!("line {}: {line:?}", line_number + 1))?;
+        }
+
+        let size = size.ok_or_eyre("missing LUT_3D_SIZE")?;
+        if colors.len() != (size * size * size) as usize {
+            bail!(
+                "expected {} colors for a size of {size}, found {}",
+                size * size * size,
+                colors.len()
+            );
+        }
+        if (0..3).any(|channel| domain[0][channel] >= domain[1][channel]) {
+            bail!("DOMAIN_MIN must be below DOMAIN_MAX");
+        }
+        Ok(Self {
+            name: name.to_owned(),
+            size,
+            domain,
+            colors,
+        })
+    }
+
+    /// LUTs that come with the application.
+    pub fn built_in() -> Vec<Self> {
+        let luminance = |[r, g, b]: [f32; 3]| 0.2126 * r + 0.7152 * g + 0.0722 * b;
+        vec![
+            Self::from_fn("Warm", BUILT_IN_SIZE, |[r, g, b]| {
+                [r * 1.06 + 0.02, g * 1.01, b * 0.86]
+            }),
+            // Halfway desaturated with raised contrast, like skipping the bleaching of film
+            Self::from_fn("Bleach bypass", BUILT_IN_SIZE, |color| {
+                let gray = luminance(color);
+                color.map(|channel| {
+                    let channel = channel + 0.5 * (gray - channel);
+                    channel * channel * (3.0 - 2.0 * channel)
+                })
+            }),
+            Self::from_fn("Sepia", BUILT_IN_SIZE, |[r, g, b]| {
+                [
+                    0.393 * r + 0.769 * g + 0.189 * b,
+                    0.349 * r + 0.686 * g + 0.168 * b,
+                    0.272 * r + 0.534 * g + 0.131 * b,
+                ]
+            }),
+        ]
+    }
+}
+
+/// Names a LUT after its file.
+fn file_name(path: &Path) -> String {
+    path.file_stem().map_or_else(
+        || path.display().to_string(),
+        |stem| stem.to_string_lossy().into(),
+    )
+}
+
+/// Parses the three channels of a color in a `.cube` file.
+fn parse_color(words: &[&str]) -> Result<[f32; 3]> {
+    let [r, g, b] = words else {
+        bail!("expected three channels, found {}", words.len());
+    };
+    Ok([r.parse()?, g.parse()?, b.parse()?])
+}
+
+/// The parameters of the grading as seen by `color_grading.wgsl`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct ColorGradingUniform {
+    domain_min: [f32; 4],
+    domain_max: [f32; 4],
+    strength: f32,
+    padding: [u32; 3],
+}
+
+/// Grades the final image through a 3D LUT, after everything else has been drawn.
+///
+/// While a LUT is selected, the image is rendered into a texture of the size of the surface
+/// instead, which [`ColorGrading::apply`] grades into the surface. The LUTs are switched at
+/// runtime, and the grading blended with the original image by its strength.
+pub struct ColorGrading {
+    luts: Vec<Lut>,
+    /// Index of the applied LUT, `None` while the image is not graded.
+    current: Option<usize>,
+    uniform: ColorGradingUniform,
+    uniform_buffer: Buffer,
+    lut_view: Option<TextureView>,
+    image_view: Option<TextureView>,
+    bind_group: Option<BindGroup>,
+    sampler: Sampler,
+    bind_group_layout: BindGroupLayout,
+    pipeline: RenderPipeline,
+    color_format: TextureFormat,
+    width: u32,
+    height: u32,
+}
+
+impl ColorGrading {
+    /// Change of the strength per key press.
+    pub const STRENGTH_STEP: f32 = 0.1;
+
+    /// Creates the grading with the built-in LUTs after `luts`, starting with the first of
+    /// `luts` if there are any, and without grading otherwise.
+    pub fn new(
+        device: &Device,
+        queue: &Queue,
+        color_format: TextureFormat,
+        width: u32,
+        height: u32,
+        luts: Vec<Lut>,
+    ) -> Self {
+        let current = (!luts.is_empty()).then_some(0);
+        let luts = luts.into_iter().chain(Lut::built_in()).collect();
+        let uniform = ColorGradingUniform {
+            domain_min: [0.0; 4],
+            domain_max: [1.0; 4],
+            strength: 1.0,
+            padding: [0; 3],
+        };
+        let uniform_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Color grading uniform buffer"),
+            contents: bytemuck::bytes_of(&uniform),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+        let sampler = device.create_sampler(&SamplerDescriptor {
+            label: Some("Color grading LUT sampler"),
+            address_mode_u: AddressMode::ClampToEdge,
+            address_mode_v: AddressMode::ClampToEdge,
+            address_mode_w: AddressMode::ClampToEdge,
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            ..Default::default()
+        });
+        let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Color grading bind group layout"),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Float { filterable: false },
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Float { filterable: true },
+                        view_dimension: TextureViewDimension::D3,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+
+        let shader_module = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("Color grading shader module"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("./color_grading.wgsl"))),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Color grading pipeline layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let constants = HashMap::from([(
+            "srgb_surface".to_owned(),
+            color_format.is_srgb() as u32 as f64,
+        )]);
+        let pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("Color grading pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: VertexState {
+                module: &shader_module,
+                entry_point: None,
+                compilation_options: PipelineCompilationOptions::default(),
+                buffers: &[],
+            },
+            primitive: PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+            fragment: Some(FragmentState {
+                module: &shader_module,
+                entry_point: None,
+                compilation_options: PipelineCompilationOptions {
+                    constants: &constants,
+                    ..Default::default()
+                },
+                targets: &[Some(ColorTargetState {
+                    format: color_format,
+                    blend: None,
+                    write_mask: ColorWrites::default(),
+                })],
+            }),
+            multiview: None,
+            cache: None,
+        });
+
+        let mut color_grading = Self {
+            luts,
+            current: None,
+            uniform,
+            uniform_buffer,
+            lut_view: None,
+            image_view: None,
+            bind_group: None,
+            sampler,
+            bind_group_layout,
+            pipeline,
+            color_format,
+            width,
+            height,
+        };
+        color_grading.select(device, queue, current);
+        color_grading
+    }
+
+    /// Switches to the next LUT, or turns the grading off after the last one.
+    pub fn next_lut(&mut self, device: &Device, queue: &Queue) {
+        let next = match self.current {
+            None => Some(0),
+            Some(index) => Some(index + 1).filter(|&next| next < self.luts.len()),
+        };
+        self.select(device, queue, next);
+        match self.current {
+            Some(index) => log::info!("Color grading: {}", self.luts[index].name),
+            None => log::info!("Color grading: off"),
+        }
+    }
+
+    pub fn strength(&self) -> f32 {
+        self.uniform.strength
+    }
+
+    /// Blends the graded image with the original one, by a strength from 0 to 1.
+    pub fn set_strength(&mut self, queue: &Queue, strength: f32) {
+        self.uniform.strength = strength.clamp(0.0, 1.0);
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::bytes_of(&self.uniform));
+    }
+
+    /// Recreates the texture the image is rendered into to match the size of the surface.
+    pub fn resize(&mut self, device: &Device, width: u32, height: u32) {
+        self.width = width;
+        self.height = height;
+        if self.current.is_some() {
+            self.create_image(device);
+        }
+    }
+
+    /// The texture to render the image into instead of the surface, or `None` while the image
+    /// is not graded.
+    pub fn view(&self) -> Option<&TextureView> {
+        self.image_view.as_ref()
+    }
+
+    /// Grades the image rendered into [`ColorGrading::view`] into the surface, if there is one.
+    pub fn apply(&self, command_encoder: &mut CommandEncoder, texture_view: &TextureView) {
+        let Some(bind_group) = &self.bind_group else {
+            return;
+        };
+        let mut render_pass = command_encoder.begin_render_pass(&RenderPassDescriptor {
+            label: Some("Color grading pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: texture_view,
+                resolve_target: None,
+                ops: Operations {
+                    load: LoadOp::Clear(Color::BLACK),
+                    store: StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+
+    /// Uploads the LUT with the given index, or frees the textures if there is none.
+    fn select(&mut self, device: &Device, queue: &Queue, index: Option<usize>) {
+        self.current = index;
+        let Some(lut) = index.map(|index| &self.luts[index]) else {
+            self.lut_view = None;
+            self.image_view = None;
+            self.bind_group = None;
+            return;
+        };
+
+        // Half floats keep the precision of the entries, which the shader interpolates between,
+        // and colors outside of 0 to 1 that some LUTs map to
+        let texels: Vec<[f16; 4]> = lut
+            .colors
+            .iter()
+            .map(|&[r, g, b]| [r, g, b, 1.0].map(f16::from_f32))
+            .collect();
+        let lut_texture = device.create_texture_with_data(
+            queue,
+            &TextureDescriptor {
+                label: Some("Color grading LUT texture"),
+                size: Extent3d {
+                    width: lut.size,
+                    height: lut.size,
+                    depth_or_array_layers: lut.size,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: TextureDimension::D3,
+                format: TextureFormat::Rgba16Float,
+                usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
+                view_formats: &[],
+            },
+            TextureDataOrder::LayerMajor,
+            bytemuck::cast_slice(&texels),
+        );
+        self.lut_view = Some(lut_texture.create_view(&TextureViewDescriptor::default()));
+
+        let [min, max] = lut.domain;
+        self.uniform.domain_min = [min[0], min[1], min[2], 0.0];
+        self.uniform.domain_max = [max[0], max[1], max[2], 0.0];
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::bytes_of(&self.uniform));
+        self.create_image(device);
+    }
+
+    fn create_image(&mut self, device: &Device) {
+        let Some(lut_view) = &self.lut_view else {
+            return;
+        };
+        let image_view = device
+            .create_texture(&TextureDescriptor {
+                label: Some("Color grading image texture"),
+                size: Extent3d {
+                    width: self.width,
+                    height: self.height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: TextureDimension::D2,
+                format: self.color_format,
+                usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            })
+            .create_view(&TextureViewDescriptor::default());
+        self.bind_group = Some(device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Color grading bind group"),
+            layout: &self.bind_group_layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: self.uniform_buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::TextureView(&image_view),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: BindingResource::TextureView(lut_view),
+                },
+                BindGroupEntry {
+                    binding: 3,
+                    resource: BindingResource::Sampler(&self.sampler),
+                },
+            ],
+        }));
+        self.image_view = Some(image_view);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_cube() {
+        let lut = Lut::parse_cube(
+            "test",
+            "# comment\nTITLE \"Test LUT\"\n\nLUT_3D_SIZE 2\n  # indented comment\n\
+             0 0 0\n1 0 0\n0 1 0\n1 1 0\n0 0 1\n1 0 1\n0 1 1\n1 1 1\n",
+        )
+        .unwrap();
+        assert_eq!(lut.name, "test");
+        assert_eq!(lut.size, 2);
+        assert_eq!(lut.domain, [[0.0; 3], [1.0; 3]]);
+        assert_eq!(lut.colors.len(), 8);
+        assert_eq!(lut.colors[1], [1.0, 0.0, 0.0]);
+        assert_eq!(lut.colors[6], [0.0, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn parses_domain() {
+        let lut = Lut::parse_cube(
+            "test",
+            &format!(
+                "DOMAIN_MIN -0.5 0 0.25\nDOMAIN_MAX 2 1 1.5\nLUT_3D_SIZE 2\n{}",
+                "0.5 0.5 0.5\n".repeat(8)
+            ),
+        )
+        .unwrap();
+        assert_eq!(lut.domain, [[-0.5, 0.0, 0.25], [2.0, 1.0, 1.5]]);
+    }
+
+    #[test]
+    fn rejects_inverted_domain() {
+        let source = format!(
+            "DOMAIN_MIN 0 1 0\nDOMAIN_MAX 1 1 1\nLUT_3D_SIZE 2\n{}",
+            "0 0 0\n".repeat(8)
+        );
+        assert!(Lut::parse_cube("test", &source).is_err());
+    }
+
+    #[test]
+    fn rejects_wrong_color_count() {
+        let source = format!("LUT_3D_SIZE 2\n{}", "0 0 0\n".repeat(7));
+        assert!(Lut::parse_cube("test", &source).is_err());
+        let source = format!("LUT_3D_SIZE 2\n{}", "0 0 0\n".repeat(9));
+        assert!(Lut::parse_cube("test", &source).is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_sizes() {
+        let colors = "0 0 0\n".repeat(8);
+        assert!(Lut::parse_cube("test", &colors).is_err());
+        for size in ["1", "257", "two", ""] {
+            let source = format!("LUT_3D_SIZE {size}\n{colors}");
+            assert!(Lut::parse_cube("test", &source).is_err(), "{size:?}");
+        }
+    }
+
+    #[test]
+    fn rejects_1d_luts() {
+        let source = format!("LUT_1D_SIZE 2\n{}", "0 0 0\n".repeat(2));
+        assert!(Lut::parse_cube("test", &source).is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_colors() {
+        let source = format!("LUT_3D_SIZE 2\n0 0\n{}", "0 0 0\n".repeat(7));
+        assert!(Lut::parse_cube("test", &source).is_err());
+        let source = format!("LUT_3D_SIZE 2\n0 0 x\n{}", "0 0 0\n".repeat(7));
+        assert!(Lut::parse_cube("test", &source).is_err());
+    }
+
+    #[test]
+    fn rearranges_strip() {
+        // Two slices of 2×2, the pixel of each entry encoding its red, green and blue index
+        let pixels: Vec<[f32; 3]> = (0..2)
+            .flat_map(|g| (0..4).map(move |x| [(x % 2) as f32, g as f32, (x / 2) as f32]))
+            .collect();
+        let lut = Lut::from_strip("test", 4, 2, &pixels).unwrap();
+        assert_eq!(lut.size, 2);
+        assert_eq!(
+            lut.colors,
+            Lut::from_fn("identity", 2, |color| color).colors
+        );
+    }
+
+    #[test]
+    fn rejects_strip_of_wrong_width() {
+        assert!(Lut::from_strip("test", 8, 2, &[[0.0; 3]; 16]).is_err());
+    }
+}
@@ -0,0 +1,63 @@
+// Grades the final image through a 3D lookup table (LUT) with a single full-screen triangle.
+//
+// LUTs map colors as they are displayed, i.e. encoded as sRGB, to graded colors, which are
+// blended with the original ones by the strength of the grading.
+
+struct ColorGrading {
+    // Encoded colors mapped to the first and the last entry of the LUT, w is unused
+    domain_min: vec4<f32>,
+    domain_max: vec4<f32>,
+    // 0 leaves the image as it is, 1 replaces it with the graded image
+    strength: f32,
+}
+
+@group(0) @binding(0)
+var<uniform> grading: ColorGrading;
+@group(0) @binding(1)
+var image_texture: texture_2d<f32>;
+@group(0) @binding(2)
+var lut_texture: texture_3d<f32>;
+@group(0) @binding(3)
+var lut_sampler: sampler;
+
+// Whether the surface encodes colors as sRGB, so the image is decoded when it is loaded,
+// and must be encoded to look the colors up.
+override srgb_surface: bool = false;
+
+fn srgb_encode(color: vec3<f32>) -> vec3<f32> {
+    let c = saturate(color);
+    return select(1.055 * pow(c, vec3<f32>(1.0 / 2.4)) - 0.055, 12.92 * c, c <= vec3<f32>(0.0031308));
+}
+
+fn srgb_decode(color: vec3<f32>) -> vec3<f32> {
+    return select(pow((color + 0.055) / 1.055, vec3<f32>(2.4)), color / 12.92, color <= vec3<f32>(0.04045));
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) in_vertex_index: u32) -> @builtin(position) vec4<f32> {
+    // A triangle covering the whole screen, its excess is clipped
+    let uv = vec2<f32>(f32((in_vertex_index << 1u) & 2u), f32(in_vertex_index & 2u));
+    return vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+}
+
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let color = textureLoad(image_texture, vec2<i32>(position.xy), 0);
+    var encoded = color.rgb;
+    if srgb_surface {
+        encoded = srgb_encode(color.rgb);
+    }
+
+    // Sample the centers of the first and the last entry at the ends of the domain, so the
+    // filtering interpolates between the entries without bleeding into the border
+    let size = f32(textureDimensions(lut_texture).x);
+    let normalized = saturate((encoded - grading.domain_min.rgb) / (grading.domain_max.rgb - grading.domain_min.rgb));
+    let coordinates = (normalized * (size - 1.0) + 0.5) / size;
+    var graded = textureSampleLevel(lut_texture, lut_sampler, coordinates, 0.0).rgb;
+
+    graded = mix(encoded, graded, grading.strength);
+    if srgb_surface {
+        graded = srgb_decode(graded);
+    }
+    return vec4<f32>(graded, color.a);
+}
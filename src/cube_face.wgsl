@@ -0,0 +1,43 @@
+// Renders into the faces of a cube map, one full-screen triangle per face.
+//
+// The face is selected by the range of vertices drawn, vertices 3 * face to 3 * face + 2.
+// The instance index is passed on to the fragment shader, e.g. to select what to render.
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    // Texture coordinates on the face, Y pointing down
+    @location(0) uv: vec2<f32>,
+    @location(1) @interpolate(flat) face: u32,
+    @location(2) @interpolate(flat) instance: u32,
+}
+
+@vertex
+fn vs_main(
+    @builtin(vertex_index) in_vertex_index: u32,
+    @builtin(instance_index) in_instance_index: u32,
+) -> VertexOutput {
+    // A triangle covering the whole face, its excess is clipped
+    let corner = in_vertex_index % 3u;
+    let uv = vec2<f32>(f32((corner << 1u) & 2u), f32(corner & 2u));
+
+    var out: VertexOutput;
+    out.position = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    out.uv = uv;
+    out.face = in_vertex_index / 3u;
+    out.instance = in_instance_index;
+    return out;
+}
+
+// Returns the direction through a point on a face, in the order +X, -X, +Y, -Y, +Z, -Z.
+fn face_direction(face: u32, uv: vec2<f32>) -> vec3<f32> {
+    let s = uv.x * 2.0 - 1.0;
+    let t = uv.y * 2.0 - 1.0;
+    switch face {
+        case 0u: { return vec3<f32>(1.0, -t, -s); }
+        case 1u: { return vec3<f32>(-1.0, -t, s); }
+        case 2u: { return vec3<f32>(s, 1.0, t); }
+        case 3u: { return vec3<f32>(s, -1.0, -t); }
+        case 4u: { return vec3<f32>(s, -t, 1.0); }
+        default: { return vec3<f32>(-s, -t, -1.0); }
+    }
+}
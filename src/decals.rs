@@ -0,0 +1,325 @@
+use std::borrow::Cow;
+
+use cgmath::{InnerSpace, Matrix4, Point3, Rad, SquareMatrix, Vector3};
+use wgpu::{
+    util::{BufferInitDescriptor, DeviceExt},
+    BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
+    BindGroupLayoutEntry, BindingResource, BindingType, BlendComponent, BlendFactor,
+    BlendOperation, BlendState, Buffer, BufferAddress, BufferDescriptor, BufferUsages,
+    ColorTargetState, ColorWrites, Device, Face, FragmentState, IndexFormat, MultisampleState,
+    PipelineCompilationOptions, PipelineLayout, PipelineLayoutDescriptor, PrimitiveState, Queue,
+    RenderPass, RenderPipeline, RenderPipelineDescriptor, ShaderModule, ShaderModuleDescriptor,
+    ShaderStages, TextureFormat, TextureSampleType, TextureView, TextureViewDimension,
+    VertexBufferLayout, VertexState, VertexStepMode,
+};
+
+use crate::{
+    deferred::DeferredLighting,
+    meshes::{MeshData, MeshVertex},
+};
+
+/// A splat projected onto whatever geometry lies within its box.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Decal {
+    /// Center of the decal, usually on a surface.
+    pub position: Point3<f32>,
+    /// Direction the decal is projected along, usually the surface normal.
+    pub normal: Vector3<f32>,
+    /// Side length on the surface, the box reaches half as far along the normal.
+    pub size: f32,
+    /// Rotation around the normal.
+    pub angle: Rad<f32>,
+    /// Linear RGB color and opacity.
+    pub color: [f32; 4],
+}
+
+impl Decal {
+    /// Transforms the unit cube into the decal's box, with the normal as local Y axis.
+    fn transform(&self) -> Matrix4<f32> {
+        let normal = self.normal.normalize();
+        let reference = if normal.x.abs() < 0.9 {
+            Vector3::unit_x()
+        } else {
+            Vector3::unit_z()
+        };
+        let tangent = normal.cross(reference).normalize();
+        let bitangent = tangent.cross(normal);
+        let (sin, cos) = self.angle.0.sin_cos();
+        let tangent = tangent * cos + bitangent * sin;
+        let bitangent = tangent.cross(normal);
+        Matrix4::from_cols(
+            (tangent * self.size).extend(0.0),
+            (normal * self.size * 0.5).extend(0.0),
+            (bitangent * self.size).extend(0.0),
+            self.position.to_homogeneous(),
+        )
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct DecalInstance {
+    model: [[f32; 4]; 4],
+    inverse_model: [[f32; 4]; 4],
+    color: [f32; 4],
+}
+
+impl DecalInstance {
+    const ATTRIBUTES: [wgpu::VertexAttribute; 9] = wgpu::vertex_attr_array![
+        2 => Float32x4,
+        3 => Float32x4,
+        4 => Float32x4,
+        5 => Float32x4,
+        6 => Float32x4,
+        7 => Float32x4,
+        8 => Float32x4,
+        9 => Float32x4,
+        10 => Float32x4,
+    ];
+
+    fn layout() -> VertexBufferLayout<'static> {
+        VertexBufferLayout {
+            array_stride: size_of::<Self>() as BufferAddress,
+            step_mode: VertexStepMode::Instance,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+}
+
+/// Draws screen-space decals.
+///
+/// Every decal is a box, whose back faces are rasterized so that it also works while the
+/// camera is inside of it. Each covered pixel reconstructs its position from the depth
+/// buffer and only keeps the part of the decal lying inside the box, projecting it onto
+/// the geometry drawn before.
+///
+/// The forward paths tint the shaded scene. The deferred path instead blends the decal's
+/// albedo and normal into the G-buffer before it is shaded, which only affects meshes,
+/// as the ground plane is not part of the G-buffer.
+pub struct DecalRenderer {
+    forward_pipeline: RenderPipeline,
+    gbuffer_pipeline: RenderPipeline,
+    depth_bind_group_layout: BindGroupLayout,
+    depth_bind_group: BindGroup,
+    vertex_buffer: Buffer,
+    index_buffer: Buffer,
+    index_count: u32,
+    instance_buffer: Buffer,
+    instances: Vec<DecalInstance>,
+    instance_count: u32,
+}
+
+impl DecalRenderer {
+    const INITIAL_CAPACITY: BufferAddress = 32 * size_of::<DecalInstance>() as BufferAddress;
+
+    pub fn new(
+        device: &Device,
+        camera_bind_group_layout: &BindGroupLayout,
+        color_format: TextureFormat,
+        depth_view: &TextureView,
+    ) -> Self {
+        // Like the G-buffer, depth is read as an unfilterable float texture
+        let depth_bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Decal depth bind group layout"),
+            entries: &[BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Texture {
+                    sample_type: TextureSampleType::Float { filterable: false },
+                    view_dimension: TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            }],
+        });
+
+        let shader_module = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("Decal shader module"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(concat!(
+                include_str!("./camera.wgsl"),
+                include_str!("./decals.wgsl")
+            ))),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Decal pipeline layout"),
+            bind_group_layouts: &[camera_bind_group_layout, &depth_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        // Multiplies the scene's color, keeping its alpha
+        let forward_pipeline = create_pipeline(
+            device,
+            "Decal forward pipeline",
+            &pipeline_layout,
+            &shader_module,
+            "fs_forward",
+            &[Some(ColorTargetState {
+                format: color_format,
+                blend: Some(BlendState {
+                    color: BlendComponent {
+                        src_factor: BlendFactor::Dst,
+                        dst_factor: BlendFactor::Zero,
+                        operation: BlendOperation::Add,
+                    },
+                    alpha: BlendComponent {
+                        src_factor: BlendFactor::Zero,
+                        dst_factor: BlendFactor::One,
+                        operation: BlendOperation::Add,
+                    },
+                }),
+                write_mask: ColorWrites::default(),
+            })],
+        );
+        let gbuffer_pipeline = create_pipeline(
+            device,
+            "Decal G-buffer pipeline",
+            &pipeline_layout,
+            &shader_module,
+            "fs_gbuffer",
+            &DeferredLighting::decal_color_targets(),
+        );
+
+        let cube = MeshData::cube();
+        let vertex_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Decal vertex buffer"),
+            contents: bytemuck::cast_slice(&cube.vertices),
+            usage: BufferUsages::VERTEX,
+        });
+        let index_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Decal index buffer"),
+            contents: bytemuck::cast_slice(&cube.indices),
+            usage: BufferUsages::INDEX,
+        });
+        let depth_bind_group = create_bind_group(device, &depth_bind_group_layout, depth_view);
+
+        Self {
+            forward_pipeline,
+            gbuffer_pipeline,
+            depth_bind_group_layout,
+            depth_bind_group,
+            vertex_buffer,
+            index_buffer,
+            index_count: cube.indices.len() as u32,
+            instance_buffer: create_instance_buffer(device, Self::INITIAL_CAPACITY),
+            instances: Vec::new(),
+            instance_count: 0,
+        }
+    }
+
+    /// Binds the new depth buffer after the surface was resized.
+    pub fn resize(&mut self, device: &Device, depth_view: &TextureView) {
+        self.depth_bind_group =
+            create_bind_group(device, &self.depth_bind_group_layout, depth_view);
+    }
+
+    pub fn draw(&mut self, decal: &Decal) {
+        let model = decal.transform();
+        self.instances.push(DecalInstance {
+            model: model.into(),
+            inverse_model: model.invert().unwrap_or(Matrix4::identity()).into(),
+            color: decal.color,
+        });
+    }
+
+    /// Uploads the decals drawn since the previous call.
+    pub fn prepare(&mut self, device: &Device, queue: &Queue) {
+        let size = (self.instances.len() * size_of::<DecalInstance>()) as BufferAddress;
+        if size > self.instance_buffer.size() {
+            self.instance_buffer = create_instance_buffer(device, size.next_power_of_two());
+        }
+        if size > 0 {
+            queue.write_buffer(
+                &self.instance_buffer,
+                0,
+                bytemuck::cast_slice(&self.instances),
+            );
+        }
+        self.instance_count = self.instances.len() as u32;
+        self.instances.clear();
+    }
+
+    /// Tints the shaded scene, for the forward paths.
+    ///
+    /// The pass must not use the depth buffer as attachment, as it is read here.
+    pub fn render_forward(&self, render_pass: &mut RenderPass, camera_bind_group: &BindGroup) {
+        render_pass.set_pipeline(&self.forward_pipeline);
+        self.draw_decals(render_pass, camera_bind_group);
+    }
+
+    /// Modifies the G-buffer in the decal pass of [`DeferredLighting`].
+    pub fn render_gbuffer(&self, render_pass: &mut RenderPass, camera_bind_group: &BindGroup) {
+        render_pass.set_pipeline(&self.gbuffer_pipeline);
+        self.draw_decals(render_pass, camera_bind_group);
+    }
+
+    fn draw_decals(&self, render_pass: &mut RenderPass, camera_bind_group: &BindGroup) {
+        if self.instance_count == 0 {
+            return;
+        }
+        render_pass.set_bind_group(0, camera_bind_group, &[]);
+        render_pass.set_bind_group(1, &self.depth_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
+        render_pass.set_index_buffer(self.index_buffer.slice(..), IndexFormat::Uint32);
+        render_pass.draw_indexed(0..self.index_count, 0, 0..self.instance_count);
+    }
+}
+
+fn create_bind_group(
+    device: &Device,
+    layout: &BindGroupLayout,
+    depth_view: &TextureView,
+) -> BindGroup {
+    device.create_bind_group(&BindGroupDescriptor {
+        label: Some("Decal depth bind group"),
+        layout,
+        entries: &[BindGroupEntry {
+            binding: 0,
+            resource: BindingResource::TextureView(depth_view),
+        }],
+    })
+}
+
+fn create_instance_buffer(device: &Device, size: BufferAddress) -> Buffer {
+    device.create_buffer(&BufferDescriptor {
+        label: Some("Decal instance buffer"),
+        size,
+        usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+// Decals read the depth buffer instead of testing against it, so there is no depth state.
+fn create_pipeline(
+    device: &Device,
+    label: &str,
+    layout: &PipelineLayout,
+    module: &ShaderModule,
+    fragment_entry_point: &str,
+    targets: &[Option<ColorTargetState>],
+) -> RenderPipeline {
+    device.create_render_pipeline(&RenderPipelineDescriptor {
+        label: Some(label),
+        layout: Some(layout),
+        vertex: VertexState {
+            module,
+            entry_point: Some("vs_main"),
+            compilation_options: PipelineCompilationOptions::default(),
+            buffers: &[MeshVertex::layout(), DecalInstance::layout()],
+        },
+        primitive: PrimitiveState {
+            cull_mode: Some(Face::Front),
+            ..Default::default()
+        },
+        depth_stencil: None,
+        multisample: MultisampleState::default(),
+        fragment: Some(FragmentState {
+            module,
+            entry_point: Some(fragment_entry_point),
+            compilation_options: PipelineCompilationOptions::default(),
+            targets,
+        }),
+        multiview: None,
+        cache: None,
+    })
+}
@@ -0,0 +1,128 @@
+// Projects decals onto the geometry in the depth buffer, drawing the back faces of one box per decal.
+// Expects `camera.wgsl` to be prepended.
+//
+// Every pixel covered by a box reconstructs its world space position from depth and is
+// discarded unless that position lies inside the box. The decal's pattern is projected
+// along the box's local Y axis, which points away from the surface.
+
+@group(1) @binding(0)
+var depth_texture: texture_2d<f32>;
+
+// How strongly the pattern's height perturbs the surface normal
+const bump_strength: f32 = 0.08;
+
+struct Instance {
+    @location(2) model_0: vec4<f32>,
+    @location(3) model_1: vec4<f32>,
+    @location(4) model_2: vec4<f32>,
+    @location(5) model_3: vec4<f32>,
+    @location(6) inverse_model_0: vec4<f32>,
+    @location(7) inverse_model_1: vec4<f32>,
+    @location(8) inverse_model_2: vec4<f32>,
+    @location(9) inverse_model_3: vec4<f32>,
+    @location(10) color: vec4<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) @interpolate(flat) inverse_model_0: vec4<f32>,
+    @location(1) @interpolate(flat) inverse_model_1: vec4<f32>,
+    @location(2) @interpolate(flat) inverse_model_2: vec4<f32>,
+    @location(3) @interpolate(flat) inverse_model_3: vec4<f32>,
+    @location(4) @interpolate(flat) color: vec4<f32>,
+    // Orthonormal frame of the decal in world space
+    @location(5) @interpolate(flat) tangent: vec3<f32>,
+    @location(6) @interpolate(flat) normal: vec3<f32>,
+    @location(7) @interpolate(flat) bitangent: vec3<f32>,
+}
+
+@vertex
+fn vs_main(@location(0) position: vec3<f32>, instance: Instance) -> VertexOutput {
+    let model = mat4x4<f32>(instance.model_0, instance.model_1, instance.model_2, instance.model_3);
+
+    var out: VertexOutput;
+    out.position = camera.view_projection * model * vec4<f32>(position, 1.0);
+    out.inverse_model_0 = instance.inverse_model_0;
+    out.inverse_model_1 = instance.inverse_model_1;
+    out.inverse_model_2 = instance.inverse_model_2;
+    out.inverse_model_3 = instance.inverse_model_3;
+    out.color = instance.color;
+    out.tangent = normalize(instance.model_0.xyz);
+    out.normal = normalize(instance.model_1.xyz);
+    out.bitangent = normalize(instance.model_2.xyz);
+    return out;
+}
+
+// Splat with a wavy outline, 1 inside and 0 outside, for `uv` ranging from 0 to 1.
+fn splat(uv: vec2<f32>) -> f32 {
+    let p = uv * 2.0 - 1.0;
+    let angle = atan2(p.y, p.x);
+    let edge = 0.65 + 0.12 * sin(5.0 * angle) + 0.06 * sin(11.0 * angle + 1.3);
+    return 1.0 - smoothstep(edge - 0.1, edge, length(p));
+}
+
+struct DecalSample {
+    // Opacity of the decal at the pixel, 0 outside of it
+    coverage: f32,
+    // World space normal of the decal's surface
+    normal: vec3<f32>,
+}
+
+fn sample_decal(in: VertexOutput) -> DecalSample {
+    let depth = textureLoad(depth_texture, vec2<i32>(in.position.xy), 0).r;
+    let ndc = vec2<f32>(in.position.x, camera.viewport.y - in.position.y) * camera.viewport.zw * 2.0 - 1.0;
+    let world = camera.inverse_view_projection * vec4<f32>(ndc, depth, 1.0);
+    let world_position = world.xyz / world.w;
+
+    // Normal of the underlying geometry, to fade the decal out on surfaces
+    // parallel to the projection, where it would be stretched
+    let surface_normal = normalize(cross(dpdy(world_position), dpdx(world_position)));
+    let facing = smoothstep(0.2, 0.6, dot(surface_normal, in.normal));
+
+    let inverse_model = mat4x4<f32>(in.inverse_model_0, in.inverse_model_1, in.inverse_model_2, in.inverse_model_3);
+    let local = (inverse_model * vec4<f32>(world_position, 1.0)).xyz;
+    let inside = all(abs(local) <= vec3<f32>(0.5)) && depth > 0.0;
+    let uv = local.xz + 0.5;
+    let thickness_fade = 1.0 - smoothstep(0.3, 0.5, abs(local.y));
+
+    // Embossed outline, from the gradient of the pattern
+    let step = 0.01;
+    let gradient = vec2<f32>(
+        splat(uv + vec2<f32>(step, 0.0)) - splat(uv - vec2<f32>(step, 0.0)),
+        splat(uv + vec2<f32>(0.0, step)) - splat(uv - vec2<f32>(0.0, step)),
+    ) / (2.0 * step);
+
+    var sample: DecalSample;
+    sample.coverage = select(0.0, splat(uv) * facing * thickness_fade * in.color.a, inside);
+    sample.normal = normalize(in.normal - bump_strength * (gradient.x * in.tangent + gradient.y * in.bitangent));
+    return sample;
+}
+
+// Tints the shaded scene, blended by multiplication, which for diffuse surfaces
+// is equivalent to modifying their albedo
+@fragment
+fn fs_forward(in: VertexOutput) -> @location(0) vec4<f32> {
+    let sample = sample_decal(in);
+    if sample.coverage <= 0.0 {
+        discard;
+    }
+    return vec4<f32>(mix(vec3<f32>(1.0), in.color.rgb, sample.coverage), 1.0);
+}
+
+struct GBufferOutput {
+    @location(0) albedo: vec4<f32>,
+    @location(1) normal: vec4<f32>,
+}
+
+// Blends the decal's albedo and normal into the G-buffer before it is shaded
+@fragment
+fn fs_gbuffer(in: VertexOutput) -> GBufferOutput {
+    let sample = sample_decal(in);
+    if sample.coverage <= 0.0 {
+        discard;
+    }
+    var out: GBufferOutput;
+    out.albedo = vec4<f32>(in.color.rgb, sample.coverage);
+    out.normal = vec4<f32>(sample.normal, sample.coverage);
+    return out;
+}
@@ -0,0 +1,338 @@
+use std::borrow::Cow;
+
+use wgpu::{
+    BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
+    BindGroupLayoutEntry, BindingResource, BindingType, BlendState, Color, ColorTargetState,
+    ColorWrites, CommandEncoder, Device, Extent3d, FragmentState, LoadOp, MultisampleState,
+    Operations, PipelineCompilationOptions, PipelineLayoutDescriptor, PrimitiveState, RenderPass,
+    RenderPassColorAttachment, RenderPassDepthStencilAttachment, RenderPassDescriptor,
+    RenderPipeline, RenderPipelineDescriptor, ShaderModuleDescriptor, ShaderStages, StoreOp,
+    TextureDescriptor, TextureDimension, TextureFormat, TextureSampleType, TextureUsages,
+    TextureView, TextureViewDescriptor, TextureViewDimension, VertexState,
+};
+
+/// How lit meshes are shaded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenderPath {
+    /// Every mesh fragment is shaded with all lights while drawing it.
+    Forward,
+    /// Forward+, every mesh fragment is shaded with the lights of its cluster
+    /// while drawing it, see [`LightClusters`](crate::clusters::LightClusters).
+    ForwardClustered,
+    /// Meshes only write their surface attributes into a G-buffer,
+    /// which is shaded once per pixel afterwards, see [`DeferredLighting`].
+    Deferred,
+}
+
+impl RenderPath {
+    pub fn next(self) -> Self {
+        match self {
+            RenderPath::Forward => RenderPath::ForwardClustered,
+            RenderPath::ForwardClustered => RenderPath::Deferred,
+            RenderPath::Deferred => RenderPath::Forward,
+        }
+    }
+}
+
+/// The G-buffer of the deferred path and the pass resolving it.
+///
+/// Meshes are drawn into the G-buffer by a geometry pass, which also fills the
+/// scene's depth buffer. The lighting pass then reconstructs each pixel's position
+/// from depth and shades it with all lights, so that the cost of lighting no longer
+/// depends on how many mesh fragments overlap.
+pub struct DeferredLighting {
+    albedo_view: TextureView,
+    normal_view: TextureView,
+    material_view: TextureView,
+    gbuffer_bind_group_layout: BindGroupLayout,
+    gbuffer_bind_group: BindGroup,
+    lighting_pipeline: RenderPipeline,
+}
+
+impl DeferredLighting {
+    /// Linear RGB albedo.
+    pub const ALBEDO_FORMAT: TextureFormat = TextureFormat::Rgba8Unorm;
+    /// World space normal.
+    pub const NORMAL_FORMAT: TextureFormat = TextureFormat::Rgba16Float;
+    /// Roughness and metallic.
+    pub const MATERIAL_FORMAT: TextureFormat = TextureFormat::Rgba8Unorm;
+
+    /// The lighting pass binds the camera, the lights and the reflection probes,
+    /// in the order of `lighting_bind_group_layouts`.
+    pub fn new(
+        device: &Device,
+        lighting_bind_group_layouts: [&BindGroupLayout; 3],
+        color_format: TextureFormat,
+        depth_view: &TextureView,
+        width: u32,
+        height: u32,
+    ) -> Self {
+        let texture_entry = |binding, sample_type| BindGroupLayoutEntry {
+            binding,
+            visibility: ShaderStages::FRAGMENT,
+            ty: BindingType::Texture {
+                sample_type,
+                view_dimension: TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        };
+        let color = TextureSampleType::Float { filterable: false };
+        let gbuffer_bind_group_layout =
+            device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+                label: Some("G-buffer bind group layout"),
+                entries: &[
+                    texture_entry(0, color),
+                    texture_entry(1, color),
+                    texture_entry(2, color),
+                    // Depth is read as a float texture, which unlike depth textures
+                    // can also be loaded from on the GL backend
+                    texture_entry(3, color),
+                ],
+            });
+
+        let shader_module = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("Deferred lighting shader module"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(concat!(
+                include_str!("./camera.wgsl"),
+                include_str!("./lighting.wgsl"),
+                include_str!("./probes.wgsl"),
+                include_str!("./deferred.wgsl")
+            ))),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Deferred lighting pipeline layout"),
+            bind_group_layouts: &[
+                lighting_bind_group_layouts[0],
+                lighting_bind_group_layouts[1],
+                lighting_bind_group_layouts[2],
+                &gbuffer_bind_group_layout,
+            ],
+            push_constant_ranges: &[],
+        });
+        // The lighting pass reads the depth buffer, so it has no depth attachment
+        let lighting_pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("Deferred lighting pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: VertexState {
+                module: &shader_module,
+                entry_point: None,
+                compilation_options: PipelineCompilationOptions::default(),
+                buffers: &[],
+            },
+            primitive: PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+            fragment: Some(FragmentState {
+                module: &shader_module,
+                entry_point: None,
+                compilation_options: PipelineCompilationOptions::default(),
+                targets: &[Some(ColorTargetState {
+                    format: color_format,
+                    blend: None,
+                    write_mask: ColorWrites::default(),
+                })],
+            }),
+            multiview: None,
+            cache: None,
+        });
+
+        let (albedo_view, normal_view, material_view) = create_targets(device, width, height);
+        let gbuffer_bind_group = create_bind_group(
+            device,
+            &gbuffer_bind_group_layout,
+            [&albedo_view, &normal_view, &material_view, depth_view],
+        );
+        Self {
+            albedo_view,
+            normal_view,
+            material_view,
+            gbuffer_bind_group_layout,
+            gbuffer_bind_group,
+            lighting_pipeline,
+        }
+    }
+
+    /// Recreates the G-buffer to match the size of the surface and its new depth buffer.
+    pub fn resize(&mut self, device: &Device, depth_view: &TextureView, width: u32, height: u32) {
+        (self.albedo_view, self.normal_view, self.material_view) =
+            create_targets(device, width, height);
+        self.gbuffer_bind_group = create_bind_group(
+            device,
+            &self.gbuffer_bind_group_layout,
+            [
+                &self.albedo_view,
+                &self.normal_view,
+                &self.material_view,
+                depth_view,
+            ],
+        );
+    }
+
+    /// Layout of [`DeferredLighting::gbuffer_bind_group`], for other passes reading the G-buffer.
+    pub fn gbuffer_bind_group_layout(&self) -> &BindGroupLayout {
+        &self.gbuffer_bind_group_layout
+    }
+
+    /// The albedo, normal, material and depth textures, bound as in `deferred.wgsl`.
+    pub fn gbuffer_bind_group(&self) -> &BindGroup {
+        &self.gbuffer_bind_group
+    }
+
+    /// Color targets of pipelines drawing into the geometry pass.
+    pub fn color_targets() -> [Option<ColorTargetState>; 3] {
+        [
+            Self::ALBEDO_FORMAT,
+            Self::NORMAL_FORMAT,
+            Self::MATERIAL_FORMAT,
+        ]
+        .map(|format| {
+            Some(ColorTargetState {
+                format,
+                blend: None,
+                write_mask: ColorWrites::default(),
+            })
+        })
+    }
+
+    /// Color targets of pipelines drawing into the decal pass, blending over
+    /// the albedo and normal while keeping their alpha.
+    pub fn decal_color_targets() -> [Option<ColorTargetState>; 2] {
+        [Self::ALBEDO_FORMAT, Self::NORMAL_FORMAT].map(|format| {
+            Some(ColorTargetState {
+                format,
+                blend: Some(BlendState::ALPHA_BLENDING),
+                write_mask: ColorWrites::COLOR,
+            })
+        })
+    }
+
+    /// Begins the pass filling the G-buffer, which also clears and fills `depth_view`.
+    pub fn begin_geometry_pass<'encoder>(
+        &self,
+        command_encoder: &'encoder mut CommandEncoder,
+        depth_view: &TextureView,
+    ) -> RenderPass<'encoder> {
+        let attachment = |view| {
+            Some(RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: Operations {
+                    load: LoadOp::Clear(Color::TRANSPARENT),
+                    store: StoreOp::Store,
+                },
+            })
+        };
+        command_encoder.begin_render_pass(&RenderPassDescriptor {
+            label: Some("G-buffer pass"),
+            color_attachments: &[
+                attachment(&self.albedo_view),
+                attachment(&self.normal_view),
+                attachment(&self.material_view),
+            ],
+            depth_stencil_attachment: Some(RenderPassDepthStencilAttachment {
+                view: depth_view,
+                depth_ops: Some(Operations {
+                    load: LoadOp::Clear(0.0),
+                    store: StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        })
+    }
+
+    /// Begins the pass modifying the albedo and normal of the filled G-buffer.
+    ///
+    /// It has no depth attachment, so that decals can read the depth buffer.
+    pub fn begin_decal_pass<'encoder>(
+        &self,
+        command_encoder: &'encoder mut CommandEncoder,
+    ) -> RenderPass<'encoder> {
+        let attachment = |view| {
+            Some(RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: Operations {
+                    load: LoadOp::Load,
+                    store: StoreOp::Store,
+                },
+            })
+        };
+        command_encoder.begin_render_pass(&RenderPassDescriptor {
+            label: Some("G-buffer decal pass"),
+            color_attachments: &[attachment(&self.albedo_view), attachment(&self.normal_view)],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        })
+    }
+
+    /// Shades the G-buffer onto the render pass' color attachment, lit by the lights and the
+    /// reflection probes.
+    ///
+    /// The pass must not use the depth buffer as attachment, as it is read here.
+    pub fn resolve(
+        &self,
+        render_pass: &mut RenderPass,
+        camera_bind_group: &BindGroup,
+        lights_bind_group: &BindGroup,
+        probes_bind_group: &BindGroup,
+    ) {
+        render_pass.set_pipeline(&self.lighting_pipeline);
+        render_pass.set_bind_group(0, camera_bind_group, &[]);
+        render_pass.set_bind_group(1, lights_bind_group, &[]);
+        render_pass.set_bind_group(2, probes_bind_group, &[]);
+        render_pass.set_bind_group(3, &self.gbuffer_bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
+
+fn create_targets(
+    device: &Device,
+    width: u32,
+    height: u32,
+) -> (TextureView, TextureView, TextureView) {
+    let create_view = |label, format| {
+        device
+            .create_texture(&TextureDescriptor {
+                label: Some(label),
+                size: Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: TextureDimension::D2,
+                format,
+                usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            })
+            .create_view(&TextureViewDescriptor::default())
+    };
+    (
+        create_view("G-buffer albedo texture", DeferredLighting::ALBEDO_FORMAT),
+        create_view("G-buffer normal texture", DeferredLighting::NORMAL_FORMAT),
+        create_view(
+            "G-buffer material texture",
+            DeferredLighting::MATERIAL_FORMAT,
+        ),
+    )
+}
+
+fn create_bind_group(
+    device: &Device,
+    layout: &BindGroupLayout,
+    views: [&TextureView; 4],
+) -> BindGroup {
+    device.create_bind_group(&BindGroupDescriptor {
+        label: Some("G-buffer bind group"),
+        layout,
+        entries: &[0, 1, 2, 3].map(|binding| BindGroupEntry {
+            binding,
+            resource: BindingResource::TextureView(views[binding as usize]),
+        }),
+    })
+}
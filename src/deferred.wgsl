@@ -0,0 +1,44 @@
+// Shades the G-buffer written by the deferred path with a single full-screen triangle.
+// Expects `camera.wgsl`, `lighting.wgsl` and `probes.wgsl` to be prepended.
+//
+// The world space position of every pixel is reconstructed from the depth buffer,
+// pixels without geometry are discarded and left to the sky.
+
+@group(3) @binding(0)
+var albedo_texture: texture_2d<f32>;
+@group(3) @binding(1)
+var normal_texture: texture_2d<f32>;
+@group(3) @binding(2)
+var material_texture: texture_2d<f32>;
+@group(3) @binding(3)
+var depth_texture: texture_2d<f32>;
+
+@vertex
+fn vs_main(@builtin(vertex_index) in_vertex_index: u32) -> @builtin(position) vec4<f32> {
+    // A triangle covering the whole screen, its excess is clipped
+    let uv = vec2<f32>(f32((in_vertex_index << 1u) & 2u), f32(in_vertex_index & 2u));
+    return vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+}
+
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let coords = vec2<i32>(position.xy);
+    let depth = textureLoad(depth_texture, coords, 0).r;
+    if depth <= 0.0 {
+        discard;
+    }
+
+    // Screen space to normalized device coordinates, flipping Y as it points down in screen space
+    let ndc = vec2<f32>(position.x, camera.viewport.y - position.y) * camera.viewport.zw * 2.0 - 1.0;
+    let world_position = camera.inverse_view_projection * vec4<f32>(ndc, depth, 1.0);
+
+    let material = textureLoad(material_texture, coords, 0);
+    let surface = Surface(
+        world_position.xyz / world_position.w,
+        normalize(textureLoad(normal_texture, coords, 0).xyz),
+        textureLoad(albedo_texture, coords, 0).rgb,
+        material.r,
+        material.g,
+    );
+    return vec4<f32>(shade_probes(surface) + shade_point_lights(surface), 1.0);
+}
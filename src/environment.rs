@@ -0,0 +1,130 @@
+use std::borrow::Cow;
+
+use wgpu::{
+    Color, ColorTargetState, ColorWrites, CommandEncoderDescriptor, Device, Extent3d,
+    FragmentState, LoadOp, MultisampleState, Operations, PipelineCompilationOptions,
+    PipelineLayoutDescriptor, PrimitiveState, Queue, RenderPassColorAttachment,
+    RenderPassDescriptor, RenderPipelineDescriptor, ShaderModuleDescriptor, StoreOp,
+    TextureDescriptor, TextureDimension, TextureFormat, TextureUsages, TextureView,
+    TextureViewDescriptor, TextureViewDimension, VertexState,
+};
+
+/// Side length of the largest mip level of each face.
+const SIZE: u32 = 64;
+/// The sun is brighter than what fits into 8 bits.
+const FORMAT: TextureFormat = TextureFormat::Rgba16Float;
+
+/// The sky baked into a cube map, for reflections of surfaces which the scene doesn't cover.
+///
+/// Every mip level is rendered from the sky directly rather than filtered, so sampling
+/// a smaller level gives a blurrier, though not physically prefiltered, reflection.
+pub struct EnvironmentMap {
+    view: TextureView,
+}
+
+impl EnvironmentMap {
+    /// Number of mip levels, down to a single texel per face.
+    pub const MIP_LEVEL_COUNT: u32 = SIZE.ilog2() + 1;
+
+    pub fn new(device: &Device, queue: &Queue) -> Self {
+        let texture = device.create_texture(&TextureDescriptor {
+            label: Some("Environment texture"),
+            size: Extent3d {
+                width: SIZE,
+                height: SIZE,
+                depth_or_array_layers: 6,
+            },
+            mip_level_count: Self::MIP_LEVEL_COUNT,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: FORMAT,
+            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+
+        let shader_module = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("Environment shader module"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(concat!(
+                include_str!("./sky.wgsl"),
+                include_str!("./cube_face.wgsl"),
+                include_str!("./environment.wgsl")
+            ))),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Environment pipeline layout"),
+            bind_group_layouts: &[],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("Environment pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: VertexState {
+                module: &shader_module,
+                entry_point: None,
+                compilation_options: PipelineCompilationOptions::default(),
+                buffers: &[],
+            },
+            primitive: PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+            fragment: Some(FragmentState {
+                module: &shader_module,
+                entry_point: None,
+                compilation_options: PipelineCompilationOptions::default(),
+                targets: &[Some(ColorTargetState {
+                    format: FORMAT,
+                    blend: None,
+                    write_mask: ColorWrites::default(),
+                })],
+            }),
+            multiview: None,
+            cache: None,
+        });
+
+        let mut command_encoder = device.create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("Environment command encoder"),
+        });
+        for mip_level in 0..Self::MIP_LEVEL_COUNT {
+            for face in 0..6 {
+                let face_view = texture.create_view(&TextureViewDescriptor {
+                    label: Some("Environment face view"),
+                    dimension: Some(TextureViewDimension::D2),
+                    base_mip_level: mip_level,
+                    mip_level_count: Some(1),
+                    base_array_layer: face,
+                    array_layer_count: Some(1),
+                    ..Default::default()
+                });
+                let mut render_pass = command_encoder.begin_render_pass(&RenderPassDescriptor {
+                    label: Some("Environment pass"),
+                    color_attachments: &[Some(RenderPassColorAttachment {
+                        view: &face_view,
+                        resolve_target: None,
+                        ops: Operations {
+                            load: LoadOp::Clear(Color::BLACK),
+                            store: StoreOp::Store,
+                        },
+                    })],
+                    depth_stencil_attachment: None,
+                    timestamp_writes: None,
+                    occlusion_query_set: None,
+                });
+                render_pass.set_pipeline(&pipeline);
+                render_pass.draw(face * 3..face * 3 + 3, 0..1);
+            }
+        }
+        queue.submit([command_encoder.finish()]);
+
+        Self {
+            view: texture.create_view(&TextureViewDescriptor {
+                label: Some("Environment view"),
+                dimension: Some(TextureViewDimension::Cube),
+                ..Default::default()
+            }),
+        }
+    }
+
+    pub fn view(&self) -> &TextureView {
+        &self.view
+    }
+}
@@ -0,0 +1,7 @@
+// Bakes the sky into the faces of a cube map.
+// Expects `sky.wgsl` and `cube_face.wgsl` to be prepended.
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(sky(normalize(face_direction(in.face, in.uv))), 1.0);
+}
@@ -0,0 +1,472 @@
+use std::{borrow::Cow, str::FromStr};
+
+use color_eyre::eyre::{bail, Report};
+use wgpu::{
+    util::{BufferInitDescriptor, DeviceExt},
+    BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
+    BindGroupLayoutEntry, BindingResource, BindingType, BlendComponent, BlendFactor,
+    BlendOperation, BlendState, Buffer, BufferBindingType, BufferUsages, ColorTargetState,
+    ColorWrites, CommandEncoder, ComputePassDescriptor, ComputePipeline, ComputePipelineDescriptor,
+    Device, Extent3d, FragmentState, MultisampleState, PipelineCompilationOptions,
+    PipelineLayoutDescriptor, PrimitiveState, Queue, RenderPass, RenderPipeline,
+    RenderPipelineDescriptor, ShaderModuleDescriptor, ShaderStages, StorageTextureAccess,
+    TextureDescriptor, TextureDimension, TextureFormat, TextureSampleType, TextureUsages,
+    TextureView, TextureViewDescriptor, TextureViewDimension, VertexState,
+};
+
+/// Workgroup size of `fog_inject.wgsl`.
+const INJECT_WORKGROUP_SIZE: u32 = 4;
+/// Workgroup size of `fog_integrate.wgsl`.
+const INTEGRATE_WORKGROUP_SIZE: u32 = 8;
+/// Holds the scattered light and either extinction or transmittance of each froxel.
+///
+/// Slices of froxels are stored as the layers of 2D array textures rather than 3D textures,
+/// as the GL backend only binds a single slice of 3D storage textures.
+const FROXEL_FORMAT: TextureFormat = TextureFormat::Rgba16Float;
+
+/// Resolution of the volumetric fog, trading detail for GPU time.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FogQuality {
+    Low,
+    #[default]
+    Medium,
+    High,
+}
+
+impl FogQuality {
+    /// Number of froxels along the screen's width and height and the view direction.
+    fn grid(self) -> [u32; 3] {
+        match self {
+            FogQuality::Low => [64, 36, 32],
+            FogQuality::Medium => [128, 72, 64],
+            FogQuality::High => [160, 90, 128],
+        }
+    }
+
+    /// Samples of the depth buffer per froxel when searching for occluders of the sun.
+    fn shadow_steps(self) -> u32 {
+        match self {
+            FogQuality::Low => 4,
+            FogQuality::Medium => 8,
+            FogQuality::High => 16,
+        }
+    }
+}
+
+impl FromStr for FogQuality {
+    type Err = Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "low" => FogQuality::Low,
+            "medium" => FogQuality::Medium,
+            "high" => FogQuality::High,
+            _ => bail!("invalid fog quality {s:?}, expected low, medium or high"),
+        })
+    }
+}
+
+/// Fog parameters as seen by the shaders, see `fog.wgsl`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct FogUniform {
+    grid: [u32; 3],
+    shadow_steps: u32,
+    density: f32,
+    height_falloff: f32,
+    far: f32,
+    time: f32,
+}
+
+/// Height fog lit by the sun, with light shafts where the scene occludes it.
+///
+/// The view frustum is divided into froxels. One compute pass injects the density and
+/// in-scattered light of every froxel, a second one integrates them front to back.
+/// Compositing then only needs a single lookup into the integrated froxels per pixel,
+/// at the depth of the scene.
+pub struct VolumetricFog {
+    pub enabled: bool,
+    uniform: FogUniform,
+    uniform_buffer: Buffer,
+    inject_pipeline: ComputePipeline,
+    inject_bind_group_layout: BindGroupLayout,
+    inject_bind_group: BindGroup,
+    integrate_pipeline: ComputePipeline,
+    integrate_bind_group: BindGroup,
+    composite_pipeline: RenderPipeline,
+    composite_bind_group_layout: BindGroupLayout,
+    composite_bind_group: BindGroup,
+    scattering_view: TextureView,
+    integrated_view: TextureView,
+}
+
+impl VolumetricFog {
+    pub fn new(
+        device: &Device,
+        camera_bind_group_layout: &BindGroupLayout,
+        color_format: TextureFormat,
+        depth_view: &TextureView,
+        quality: FogQuality,
+    ) -> Self {
+        // The froxels of each depth slice are a layer of a 2D texture array
+        let limits = device.limits();
+        let [width, height, depth] = quality.grid();
+        let uniform = FogUniform {
+            grid: [
+                width.min(limits.max_texture_dimension_2d),
+                height.min(limits.max_texture_dimension_2d),
+                depth.min(limits.max_texture_array_layers),
+            ],
+            shadow_steps: quality.shadow_steps(),
+            density: 0.03,
+            height_falloff: 0.5,
+            far: 40.0,
+            time: 0.0,
+        };
+        let uniform_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Fog uniform buffer"),
+            contents: bytemuck::bytes_of(&uniform),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+
+        let uniform_entry = |visibility| BindGroupLayoutEntry {
+            binding: 0,
+            visibility,
+            ty: BindingType::Buffer {
+                ty: BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        // All textures are only loaded from. Like the G-buffer, depth is read as a float texture,
+        // and the froxels are interpolated manually, which keeps them readable on the GL backend.
+        let texture_entry = |binding, visibility, view_dimension| BindGroupLayoutEntry {
+            binding,
+            visibility,
+            ty: BindingType::Texture {
+                sample_type: TextureSampleType::Float { filterable: false },
+                view_dimension,
+                multisampled: false,
+            },
+            count: None,
+        };
+        let storage_entry = |binding| BindGroupLayoutEntry {
+            binding,
+            visibility: ShaderStages::COMPUTE,
+            ty: BindingType::StorageTexture {
+                access: StorageTextureAccess::WriteOnly,
+                format: FROXEL_FORMAT,
+                view_dimension: TextureViewDimension::D2Array,
+            },
+            count: None,
+        };
+        let inject_bind_group_layout =
+            device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+                label: Some("Fog injection bind group layout"),
+                entries: &[
+                    uniform_entry(ShaderStages::COMPUTE),
+                    texture_entry(1, ShaderStages::COMPUTE, TextureViewDimension::D2),
+                    storage_entry(2),
+                ],
+            });
+        let integrate_bind_group_layout =
+            device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+                label: Some("Fog integration bind group layout"),
+                entries: &[
+                    uniform_entry(ShaderStages::COMPUTE),
+                    texture_entry(1, ShaderStages::COMPUTE, TextureViewDimension::D2Array),
+                    storage_entry(2),
+                ],
+            });
+        let composite_bind_group_layout =
+            device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+                label: Some("Fog composite bind group layout"),
+                entries: &[
+                    uniform_entry(ShaderStages::FRAGMENT),
+                    texture_entry(1, ShaderStages::FRAGMENT, TextureViewDimension::D2),
+                    texture_entry(2, ShaderStages::FRAGMENT, TextureViewDimension::D2Array),
+                ],
+            });
+
+        let create_compute_pipeline = |label, source, layout| {
+            let shader_module = device.create_shader_module(ShaderModuleDescriptor {
+                label: Some(label),
+                source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(source)),
+            });
+            let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+                label: Some(label),
+                bind_group_layouts: &[camera_bind_group_layout, layout],
+                push_constant_ranges: &[],
+            });
+            device.create_compute_pipeline(&ComputePipelineDescriptor {
+                label: Some(label),
+                layout: Some(&pipeline_layout),
+                module: &shader_module,
+                entry_point: None,
+                compilation_options: PipelineCompilationOptions::default(),
+                cache: None,
+            })
+        };
+        let inject_pipeline = create_compute_pipeline(
+            "Fog injection pipeline",
+            concat!(
+                include_str!("./camera.wgsl"),
+                include_str!("./fog.wgsl"),
+                include_str!("./fog_inject.wgsl")
+            ),
+            &inject_bind_group_layout,
+        );
+        let integrate_pipeline = create_compute_pipeline(
+            "Fog integration pipeline",
+            concat!(
+                include_str!("./camera.wgsl"),
+                include_str!("./fog.wgsl"),
+                include_str!("./fog_integrate.wgsl")
+            ),
+            &integrate_bind_group_layout,
+        );
+
+        let shader_module = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("Fog composite shader module"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(concat!(
+                include_str!("./camera.wgsl"),
+                include_str!("./fog.wgsl"),
+                include_str!("./fog_composite.wgsl")
+            ))),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Fog composite pipeline layout"),
+            bind_group_layouts: &[camera_bind_group_layout, &composite_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        // Compositing reads the depth buffer, so it has no depth attachment.
+        // The scene is attenuated by the transmittance in alpha, then the scattered light added.
+        let composite_pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("Fog composite pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: VertexState {
+                module: &shader_module,
+                entry_point: None,
+                compilation_options: PipelineCompilationOptions::default(),
+                buffers: &[],
+            },
+            primitive: PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+            fragment: Some(FragmentState {
+                module: &shader_module,
+                entry_point: None,
+                compilation_options: PipelineCompilationOptions::default(),
+                targets: &[Some(ColorTargetState {
+                    format: color_format,
+                    blend: Some(BlendState {
+                        color: BlendComponent {
+                            src_factor: BlendFactor::One,
+                            dst_factor: BlendFactor::SrcAlpha,
+                            operation: BlendOperation::Add,
+                        },
+                        alpha: BlendComponent {
+                            src_factor: BlendFactor::Zero,
+                            dst_factor: BlendFactor::One,
+                            operation: BlendOperation::Add,
+                        },
+                    }),
+                    write_mask: ColorWrites::default(),
+                })],
+            }),
+            multiview: None,
+            cache: None,
+        });
+
+        let [width, height, depth] = uniform.grid;
+        let create_froxels = |label| {
+            device
+                .create_texture(&TextureDescriptor {
+                    label: Some(label),
+                    size: Extent3d {
+                        width,
+                        height,
+                        depth_or_array_layers: depth,
+                    },
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: TextureDimension::D2,
+                    format: FROXEL_FORMAT,
+                    usage: TextureUsages::STORAGE_BINDING | TextureUsages::TEXTURE_BINDING,
+                    view_formats: &[],
+                })
+                .create_view(&TextureViewDescriptor {
+                    dimension: Some(TextureViewDimension::D2Array),
+                    ..Default::default()
+                })
+        };
+        let scattering_view = create_froxels("Fog scattering texture");
+        let integrated_view = create_froxels("Fog integrated texture");
+
+        let integrate_bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Fog integration bind group"),
+            layout: &integrate_bind_group_layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::TextureView(&scattering_view),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: BindingResource::TextureView(&integrated_view),
+                },
+            ],
+        });
+        let inject_bind_group = create_inject_bind_group(
+            device,
+            &inject_bind_group_layout,
+            &uniform_buffer,
+            depth_view,
+            &scattering_view,
+        );
+        let composite_bind_group = create_composite_bind_group(
+            device,
+            &composite_bind_group_layout,
+            &uniform_buffer,
+            depth_view,
+            &integrated_view,
+        );
+
+        Self {
+            enabled: true,
+            uniform,
+            uniform_buffer,
+            inject_pipeline,
+            inject_bind_group_layout,
+            inject_bind_group,
+            integrate_pipeline,
+            integrate_bind_group,
+            composite_pipeline,
+            composite_bind_group_layout,
+            composite_bind_group,
+            scattering_view,
+            integrated_view,
+        }
+    }
+
+    /// Binds the new depth buffer after the surface was resized.
+    ///
+    /// The froxel grid does not depend on the size of the surface.
+    pub fn resize(&mut self, device: &Device, depth_view: &TextureView) {
+        self.inject_bind_group = create_inject_bind_group(
+            device,
+            &self.inject_bind_group_layout,
+            &self.uniform_buffer,
+            depth_view,
+            &self.scattering_view,
+        );
+        self.composite_bind_group = create_composite_bind_group(
+            device,
+            &self.composite_bind_group_layout,
+            &self.uniform_buffer,
+            depth_view,
+            &self.integrated_view,
+        );
+    }
+
+    /// Updates the time moving the fog.
+    pub fn prepare(&mut self, queue: &Queue, time: f32) {
+        self.uniform.time = time;
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::bytes_of(&self.uniform));
+    }
+
+    /// Injects and integrates the froxels, once the depth buffer contains the opaque scene.
+    pub fn compute(&self, command_encoder: &mut CommandEncoder, camera_bind_group: &BindGroup) {
+        let [width, height, depth] = self.uniform.grid;
+        let mut compute_pass = command_encoder.begin_compute_pass(&ComputePassDescriptor {
+            label: Some("Fog pass"),
+            timestamp_writes: None,
+        });
+        compute_pass.set_bind_group(0, camera_bind_group, &[]);
+
+        compute_pass.set_pipeline(&self.inject_pipeline);
+        compute_pass.set_bind_group(1, &self.inject_bind_group, &[]);
+        compute_pass.dispatch_workgroups(
+            width.div_ceil(INJECT_WORKGROUP_SIZE),
+            height.div_ceil(INJECT_WORKGROUP_SIZE),
+            depth.div_ceil(INJECT_WORKGROUP_SIZE),
+        );
+
+        compute_pass.set_pipeline(&self.integrate_pipeline);
+        compute_pass.set_bind_group(1, &self.integrate_bind_group, &[]);
+        compute_pass.dispatch_workgroups(
+            width.div_ceil(INTEGRATE_WORKGROUP_SIZE),
+            height.div_ceil(INTEGRATE_WORKGROUP_SIZE),
+            1,
+        );
+    }
+
+    /// Applies the fog onto the render pass' color attachment.
+    ///
+    /// The pass must not use the depth buffer as attachment, as it is read here.
+    pub fn composite(&self, render_pass: &mut RenderPass, camera_bind_group: &BindGroup) {
+        render_pass.set_pipeline(&self.composite_pipeline);
+        render_pass.set_bind_group(0, camera_bind_group, &[]);
+        render_pass.set_bind_group(1, &self.composite_bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
+
+fn create_inject_bind_group(
+    device: &Device,
+    layout: &BindGroupLayout,
+    uniform_buffer: &Buffer,
+    depth_view: &TextureView,
+    scattering_view: &TextureView,
+) -> BindGroup {
+    device.create_bind_group(&BindGroupDescriptor {
+        label: Some("Fog injection bind group"),
+        layout,
+        entries: &[
+            BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            },
+            BindGroupEntry {
+                binding: 1,
+                resource: BindingResource::TextureView(depth_view),
+            },
+            BindGroupEntry {
+                binding: 2,
+                resource: BindingResource::TextureView(scattering_view),
+            },
+        ],
+    })
+}
+
+fn create_composite_bind_group(
+    device: &Device,
+    layout: &BindGroupLayout,
+    uniform_buffer: &Buffer,
+    depth_view: &TextureView,
+    integrated_view: &TextureView,
+) -> BindGroup {
+    device.create_bind_group(&BindGroupDescriptor {
+        label: Some("Fog composite bind group"),
+        layout,
+        entries: &[
+            BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            },
+            BindGroupEntry {
+                binding: 1,
+                resource: BindingResource::TextureView(depth_view),
+            },
+            BindGroupEntry {
+                binding: 2,
+                resource: BindingResource::TextureView(integrated_view),
+            },
+        ],
+    })
+}
@@ -0,0 +1,40 @@
+// Froxel grid of the volumetric fog, shared by its passes, see `FogUniform`.
+// Expects `camera.wgsl` to be prepended.
+//
+// Froxels are the cells of a grid aligned with the view frustum, spanning the screen's
+// width and height and, with increasing length, the view distance up to `fog.far`.
+
+struct Fog {
+    // Number of froxels along the screen's width and height and the view direction
+    grid: vec3<u32>,
+    // Samples of the scene's depth buffer towards the sun, per froxel
+    shadow_steps: u32,
+    // Extinction per meter at the ground
+    density: f32,
+    // How quickly the density decreases with height
+    height_falloff: f32,
+    // View distance covered by the grid
+    far: f32,
+    // Seconds simulated so far, moving the fog
+    time: f32,
+}
+
+@group(1) @binding(0)
+var<uniform> fog: Fog;
+
+// View distance at which the given slice of froxels starts.
+// Slices are distributed quadratically, so that they are the thinnest near the camera.
+fn fog_slice_distance(slice: f32) -> f32 {
+    let t = slice / f32(fog.grid.z);
+    return fog.far * t * t;
+}
+
+// Inverse of `fog_slice_distance`, normalized to the depth of the grid.
+fn fog_depth_coordinate(view_distance: f32) -> f32 {
+    return sqrt(clamp(view_distance / fog.far, 0.0, 1.0));
+}
+
+// World space direction the camera is looking at.
+fn camera_forward() -> vec3<f32> {
+    return -vec3<f32>(camera.view[0].z, camera.view[1].z, camera.view[2].z);
+}
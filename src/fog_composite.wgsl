@@ -0,0 +1,52 @@
+// Applies the integrated fog to the scene with a single full-screen triangle.
+// Expects `camera.wgsl` and `fog.wgsl` to be prepended.
+//
+// The fog is blended as `scene * transmittance + scattered`.
+
+@group(1) @binding(1)
+var depth_texture: texture_2d<f32>;
+@group(1) @binding(2)
+var integrated_texture: texture_2d_array<f32>;
+
+@vertex
+fn vs_main(@builtin(vertex_index) in_vertex_index: u32) -> @builtin(position) vec4<f32> {
+    // A triangle covering the whole screen, its excess is clipped
+    let uv = vec2<f32>(f32((in_vertex_index << 1u) & 2u), f32(in_vertex_index & 2u));
+    return vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+}
+
+fn load_froxel(froxel: vec3<u32>) -> vec4<f32> {
+    return textureLoad(integrated_texture, froxel.xy, froxel.z, 0);
+}
+
+// Trilinearly interpolates the integrated froxels at normalized coordinates.
+fn sample_froxels(coordinates: vec3<f32>) -> vec4<f32> {
+    let texel = clamp(coordinates * vec3<f32>(fog.grid) - 0.5, vec3<f32>(0.0), vec3<f32>(fog.grid - 1u));
+    let low = vec3<u32>(texel);
+    let high = min(low + 1u, fog.grid - 1u);
+    let t = fract(texel);
+    let near = mix(
+        mix(load_froxel(low), load_froxel(vec3<u32>(high.x, low.yz)), t.x),
+        mix(load_froxel(vec3<u32>(low.x, high.y, low.z)), load_froxel(vec3<u32>(high.xy, low.z)), t.x),
+        t.y,
+    );
+    let far = mix(
+        mix(load_froxel(vec3<u32>(low.xy, high.z)), load_froxel(vec3<u32>(high.x, low.y, high.z)), t.x),
+        mix(load_froxel(vec3<u32>(low.x, high.yz)), load_froxel(high), t.x),
+        t.y,
+    );
+    return mix(near, far, t.z);
+}
+
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let depth = textureLoad(depth_texture, vec2<i32>(position.xy), 0).r;
+    let uv = position.xy * camera.viewport.zw;
+    let ndc = uv * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0);
+    let world = camera.inverse_view_projection * vec4<f32>(ndc, depth, 1.0);
+    let view_distance = dot(world.xyz / world.w - camera.position.xyz, camera_forward());
+
+    // Froxels store the integral up to their far end, which lies half a froxel past their center
+    let depth_coordinate = fog_depth_coordinate(view_distance) - 0.5 / f32(fog.grid.z);
+    return sample_froxels(vec3<f32>(uv, depth_coordinate));
+}
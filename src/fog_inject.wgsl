@@ -0,0 +1,110 @@
+// Accumulates the density and in-scattered sun light of every froxel, one invocation per froxel.
+// Expects `camera.wgsl` and `fog.wgsl` to be prepended.
+//
+// Without shadow maps, light shafts are carved out of the fog by marching from each froxel
+// towards the sun and testing the samples against the scene's depth buffer.
+
+@group(1) @binding(1)
+var depth_texture: texture_2d<f32>;
+@group(1) @binding(2)
+var scattering_texture: texture_storage_2d_array<rgba16float, write>;
+
+// Matches the sun and ambient light of `lighting.wgsl`
+const sun_direction: vec3<f32> = vec3<f32>(0.48, 0.6, -0.64);
+const sun_color: vec3<f32> = vec3<f32>(1.0, 0.95, 0.85);
+const sun_intensity: f32 = 0.6;
+const ambient: vec3<f32> = vec3<f32>(0.08, 0.1, 0.13);
+
+// How far the depth buffer is searched for occluders of the sun
+const shadow_distance: f32 = 6.0;
+// Assumed thickness of the surfaces in the depth buffer
+const occluder_thickness: f32 = 1.5;
+// Anisotropy of the Henyey-Greenstein phase function, scattering mostly forward
+const phase_anisotropy: f32 = 0.6;
+// Direction and speed the fog drifts in
+const wind: vec3<f32> = vec3<f32>(0.4, 0.0, 0.15);
+
+fn hash(p: vec3<f32>) -> f32 {
+    return fract(sin(dot(p, vec3<f32>(127.1, 311.7, 74.7))) * 43758.5453);
+}
+
+// Value noise from 0 to 1.
+fn noise(p: vec3<f32>) -> f32 {
+    let cell = floor(p);
+    let f = fract(p);
+    let t = f * f * (3.0 - 2.0 * f);
+    return mix(
+        mix(
+            mix(hash(cell), hash(cell + vec3<f32>(1.0, 0.0, 0.0)), t.x),
+            mix(hash(cell + vec3<f32>(0.0, 1.0, 0.0)), hash(cell + vec3<f32>(1.0, 1.0, 0.0)), t.x),
+            t.y,
+        ),
+        mix(
+            mix(hash(cell + vec3<f32>(0.0, 0.0, 1.0)), hash(cell + vec3<f32>(1.0, 0.0, 1.0)), t.x),
+            mix(hash(cell + vec3<f32>(0.0, 1.0, 1.0)), hash(cell + vec3<f32>(1.0, 1.0, 1.0)), t.x),
+            t.y,
+        ),
+        t.z,
+    );
+}
+
+fn henyey_greenstein(cos_theta: f32, g: f32) -> f32 {
+    let denominator = 1.0 + g * g - 2.0 * g * cos_theta;
+    return (1.0 - g * g) / (4.0 * 3.14159265 * pow(denominator, 1.5));
+}
+
+// Distance along the view direction of the surface in the depth buffer at a pixel.
+fn scene_view_distance(pixel: vec2<i32>) -> f32 {
+    let depth = textureLoad(depth_texture, pixel, 0).r;
+    let ndc = (vec2<f32>(pixel) + 0.5) * camera.viewport.zw * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0);
+    let world = camera.inverse_view_projection * vec4<f32>(ndc, depth, 1.0);
+    return dot(world.xyz / world.w - camera.position.xyz, camera_forward());
+}
+
+// Fraction of sun light reaching a position, 0 if the depth buffer occludes it.
+fn sun_visibility(position: vec3<f32>) -> f32 {
+    let step = normalize(sun_direction) * shadow_distance / f32(fog.shadow_steps);
+    for (var i = 1u; i <= fog.shadow_steps; i++) {
+        let clip = camera.view_projection * vec4<f32>(position + step * f32(i), 1.0);
+        if clip.w <= 0.0 {
+            break;
+        }
+        let ndc = clip.xy / clip.w;
+        if any(abs(ndc) > vec2<f32>(1.0)) {
+            continue;
+        }
+        let pixel = vec2<i32>((ndc * vec2<f32>(0.5, -0.5) + 0.5) * camera.viewport.xy);
+        // For a perspective projection, w is the view distance
+        let behind = clip.w - scene_view_distance(min(pixel, vec2<i32>(camera.viewport.xy) - 1));
+        if behind > 0.05 && behind < occluder_thickness {
+            return 0.0;
+        }
+    }
+    return 1.0;
+}
+
+@compute @workgroup_size(4, 4, 4)
+fn cs_main(@builtin(global_invocation_id) id: vec3<u32>) {
+    if any(id >= fog.grid) {
+        return;
+    }
+
+    // Center of the froxel, froxels are counted from the top of the screen
+    let uv = (vec2<f32>(id.xy) + 0.5) / vec2<f32>(fog.grid.xy);
+    let ndc = uv * vec2<f32>(2.0, -2.0) + vec2<f32>(-1.0, 1.0);
+    let far = camera.inverse_view_projection * vec4<f32>(ndc, 0.0, 1.0);
+    let direction = normalize(far.xyz / far.w - camera.position.xyz);
+    let distance = fog_slice_distance(f32(id.z) + 0.5);
+    let position = camera.position.xyz + direction * distance / dot(direction, camera_forward());
+
+    let height = exp(-fog.height_falloff * max(position.y, 0.0));
+    let variation = 0.5 + noise(position * 0.35 + wind * fog.time);
+    let density = fog.density * height * variation;
+
+    // Scaled so that isotropic scattering passes on the sun's intensity unchanged
+    let phase = 4.0 * 3.14159265 * henyey_greenstein(dot(direction, normalize(sun_direction)), phase_anisotropy);
+    let sun = sun_color * sun_intensity * sun_visibility(position) * phase;
+    // Scattering makes up all of the extinction, nothing is absorbed
+    let scattering = density * (sun + ambient);
+    textureStore(scattering_texture, id.xy, id.z, vec4<f32>(scattering, density));
+}
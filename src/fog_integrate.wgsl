@@ -0,0 +1,31 @@
+// Integrates the froxels front to back, one invocation per column of froxels.
+// Expects `camera.wgsl` and `fog.wgsl` to be prepended.
+//
+// Every froxel then holds the light scattered towards the camera and the transmittance
+// from the camera up to its far end.
+
+@group(1) @binding(1)
+var scattering_texture: texture_2d_array<f32>;
+@group(1) @binding(2)
+var integrated_texture: texture_storage_2d_array<rgba16float, write>;
+
+@compute @workgroup_size(8, 8, 1)
+fn cs_main(@builtin(global_invocation_id) id: vec3<u32>) {
+    if any(id.xy >= fog.grid.xy) {
+        return;
+    }
+
+    var scattered = vec3<f32>(0.0);
+    var transmittance = 1.0;
+    for (var slice = 0u; slice < fog.grid.z; slice++) {
+        let froxel = textureLoad(scattering_texture, id.xy, slice, 0);
+        let length = fog_slice_distance(f32(slice + 1u)) - fog_slice_distance(f32(slice));
+        let extinction = max(froxel.a, 1e-5);
+        let slice_transmittance = exp(-extinction * length);
+        // Analytic integral of the scattered light over the slice, which unlike
+        // multiplying with its length stays energy conserving for thick slices
+        scattered += transmittance * froxel.rgb * (1.0 - slice_transmittance) / extinction;
+        transmittance *= slice_transmittance;
+        textureStore(integrated_texture, id.xy, slice, vec4<f32>(scattered, transmittance));
+    }
+}
@@ -0,0 +1,427 @@
+use std::borrow::Cow;
+
+use wgpu::{
+    util::{BufferInitDescriptor, DeviceExt},
+    BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
+    BindGroupLayoutEntry, BindingResource, BindingType, Buffer, BufferBindingType, BufferUsages,
+    Color, ColorTargetState, ColorWrites, CommandEncoder, ComputePassDescriptor, ComputePipeline,
+    ComputePipelineDescriptor, Device, Extent3d, FragmentState, LoadOp, MultisampleState,
+    Operations, PipelineCompilationOptions, PipelineLayoutDescriptor, PrimitiveState, Queue,
+    RenderPassColorAttachment, RenderPassDescriptor, RenderPipeline, RenderPipelineDescriptor,
+    ShaderModuleDescriptor, ShaderStages, StorageTextureAccess, StoreOp, TextureDescriptor,
+    TextureDimension, TextureFormat, TextureSampleType, TextureUsages, TextureView,
+    TextureViewDescriptor, TextureViewDimension, VertexState,
+};
+use winit::{event::MouseButton, keyboard::KeyCode};
+
+use crate::input::InputEvent;
+
+/// Workgroup size of `fractal_iterate.wgsl` along both axes.
+const WORKGROUP_SIZE: u32 = 8;
+/// Height of the initial view in the complex plane.
+const INITIAL_HEIGHT: f64 = 3.0;
+const ZOOM_PER_LINE: f64 = 0.8;
+/// Smallest distance between pixels in the complex plane, where even the emulated
+/// double precision runs out.
+const MIN_SCALE: f64 = 1e-13;
+/// Distance between pixels in the complex plane below which the iterations need more
+/// precision than `f32` has.
+const HIGH_PRECISION_SCALE: f64 = 1e-5;
+const MIN_ITERATIONS: u32 = 32;
+const MAX_ITERATIONS: u32 = 4096;
+const PALETTE_COUNT: u32 = 3;
+
+/// Which fractal is shown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FractalKind {
+    Mandelbrot,
+    /// The Julia set of the constant at the center of the Mandelbrot view it was entered from.
+    Julia,
+}
+
+/// Part of the complex plane that is shown.
+#[derive(Debug, Clone, Copy)]
+struct View {
+    center: [f64; 2],
+    /// Distance between pixels.
+    scale: f64,
+}
+
+/// Fractal parameters as seen by the shaders, see `fractal_iterate.wgsl`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct FractalUniform {
+    center: [f32; 4],
+    julia: [f32; 2],
+    scale: f32,
+    max_iterations: u32,
+    kind: u32,
+    palette: u32,
+    high_precision: u32,
+    /// Always 1, see `opaque` of `fractal_iterate.wgsl`.
+    one: f32,
+}
+
+/// Explorer of the Mandelbrot set and its Julia sets.
+///
+/// Dragging with the left mouse button pans, scrolling zooms towards the cursor, `J` switches
+/// between the Mandelbrot set and the Julia set of its center, `[` and `]` halve and double the
+/// iterations, and `C` cycles through the palettes.
+///
+/// A compute pass iterates every pixel into a texture, only when the view changes. Drawing then
+/// merely colors the iterations. Beyond the precision of `f32`, the compute pass switches to
+/// emulated double precision, allowing zooms by about 11 orders of magnitude.
+pub struct FractalViewer {
+    kind: FractalKind,
+    /// Views of the Mandelbrot set and of the Julia set, respectively.
+    views: [View; 2],
+    julia: [f64; 2],
+    max_iterations: u32,
+    palette: u32,
+    size: [u32; 2],
+    dragging: bool,
+    cursor: Option<(f64, f64)>,
+    /// Whether the iterations have to be computed again before drawing.
+    dirty: bool,
+    uniform_buffer: Buffer,
+    iterate_bind_group_layout: BindGroupLayout,
+    iterate_bind_group: BindGroup,
+    draw_bind_group_layout: BindGroupLayout,
+    draw_bind_group: BindGroup,
+    iterate_pipeline: ComputePipeline,
+    draw_pipeline: RenderPipeline,
+}
+
+impl FractalViewer {
+    pub fn new(device: &Device, color_format: TextureFormat, width: u32, height: u32) -> Self {
+        let uniform_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Fractal uniform buffer"),
+            contents: bytemuck::bytes_of(&FractalUniform {
+                center: [0.0; 4],
+                julia: [0.0; 2],
+                scale: 0.0,
+                max_iterations: 0,
+                kind: 0,
+                palette: 0,
+                high_precision: 0,
+                one: 1.0,
+            }),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+
+        let uniform_entry = |visibility| BindGroupLayoutEntry {
+            binding: 0,
+            visibility,
+            ty: BindingType::Buffer {
+                ty: BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let iterate_bind_group_layout =
+            device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+                label: Some("Fractal iteration bind group layout"),
+                entries: &[
+                    uniform_entry(ShaderStages::COMPUTE),
+                    BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: ShaderStages::COMPUTE,
+                        ty: BindingType::StorageTexture {
+                            access: StorageTextureAccess::WriteOnly,
+                            format: TextureFormat::R32Float,
+                            view_dimension: TextureViewDimension::D2,
+                        },
+                        count: None,
+                    },
+                ],
+            });
+        let draw_bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Fractal draw bind group layout"),
+            entries: &[
+                uniform_entry(ShaderStages::FRAGMENT),
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Float { filterable: false },
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+            ],
+        });
+        let iterations_view = create_iterations_view(device, width, height);
+        let (iterate_bind_group, draw_bind_group) = create_bind_groups(
+            device,
+            &iterate_bind_group_layout,
+            &draw_bind_group_layout,
+            &uniform_buffer,
+            &iterations_view,
+        );
+
+        let iterate_shader_module = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("Fractal iteration shader module"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("./fractal_iterate.wgsl"))),
+        });
+        let iterate_pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Fractal iteration pipeline layout"),
+            bind_group_layouts: &[&iterate_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let iterate_pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
+            label: Some("Fractal iteration pipeline"),
+            layout: Some(&iterate_pipeline_layout),
+            module: &iterate_shader_module,
+            entry_point: Some("cs_iterate"),
+            compilation_options: PipelineCompilationOptions::default(),
+            cache: None,
+        });
+
+        let draw_shader_module = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("Fractal draw shader module"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("./fractal.wgsl"))),
+        });
+        let draw_pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Fractal draw pipeline layout"),
+            bind_group_layouts: &[&draw_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let draw_pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("Fractal draw pipeline"),
+            layout: Some(&draw_pipeline_layout),
+            vertex: VertexState {
+                module: &draw_shader_module,
+                entry_point: Some("vs_main"),
+                compilation_options: PipelineCompilationOptions::default(),
+                buffers: &[],
+            },
+            primitive: PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+            fragment: Some(FragmentState {
+                module: &draw_shader_module,
+                entry_point: Some("fs_main"),
+                compilation_options: PipelineCompilationOptions::default(),
+                targets: &[Some(ColorTargetState {
+                    format: color_format,
+                    blend: None,
+                    write_mask: ColorWrites::default(),
+                })],
+            }),
+            multiview: None,
+            cache: None,
+        });
+
+        let initial_view = |center| View {
+            center,
+            scale: INITIAL_HEIGHT / height.max(1) as f64,
+        };
+        Self {
+            kind: FractalKind::Mandelbrot,
+            views: [initial_view([-0.5, 0.0]), initial_view([0.0, 0.0])],
+            julia: [0.0; 2],
+            max_iterations: 256,
+            palette: 0,
+            size: [width, height],
+            dragging: false,
+            cursor: None,
+            dirty: true,
+            uniform_buffer,
+            iterate_bind_group_layout,
+            iterate_bind_group,
+            draw_bind_group_layout,
+            draw_bind_group,
+            iterate_pipeline,
+            draw_pipeline,
+        }
+    }
+
+    pub fn resize(&mut self, device: &Device, width: u32, height: u32) {
+        let iterations_view = create_iterations_view(device, width, height);
+        (self.iterate_bind_group, self.draw_bind_group) = create_bind_groups(
+            device,
+            &self.iterate_bind_group_layout,
+            &self.draw_bind_group_layout,
+            &self.uniform_buffer,
+            &iterations_view,
+        );
+        self.size = [width, height];
+        self.dirty = true;
+    }
+
+    pub fn handle_input(&mut self, event: &InputEvent) {
+        match *event {
+            InputEvent::Key {
+                code,
+                pressed: true,
+            } => match code {
+                KeyCode::KeyJ => {
+                    self.kind = match self.kind {
+                        FractalKind::Mandelbrot => {
+                            self.julia = self.views[0].center;
+                            FractalKind::Julia
+                        }
+                        FractalKind::Julia => FractalKind::Mandelbrot,
+                    };
+                    self.dirty = true;
+                }
+                KeyCode::BracketLeft => {
+                    self.max_iterations = (self.max_iterations / 2).max(MIN_ITERATIONS);
+                    self.dirty = true;
+                }
+                KeyCode::BracketRight => {
+                    self.max_iterations = (self.max_iterations * 2).min(MAX_ITERATIONS);
+                    self.dirty = true;
+                }
+                KeyCode::KeyC => self.palette = (self.palette + 1) % PALETTE_COUNT,
+                _ => {}
+            },
+            InputEvent::MouseButton {
+                button: MouseButton::Left,
+                pressed,
+            } => self.dragging = pressed,
+            InputEvent::CursorMoved { x, y } => {
+                if let (true, Some((last_x, last_y))) = (self.dragging, self.cursor) {
+                    // The point under the cursor follows it
+                    let view = self.view_mut();
+                    view.center[0] -= (x - last_x) * view.scale;
+                    view.center[1] += (y - last_y) * view.scale;
+                    self.dirty = true;
+                }
+                self.cursor = Some((x, y));
+            }
+            InputEvent::Scroll { y, .. } => {
+                // The point under the cursor stays in place
+                let (x, y_pixel) = self
+                    .cursor
+                    .unwrap_or((self.size[0] as f64 / 2.0, self.size[1] as f64 / 2.0));
+                let offset = [
+                    x - self.size[0] as f64 / 2.0,
+                    self.size[1] as f64 / 2.0 - y_pixel,
+                ];
+                let view = self.view_mut();
+                let scale = (view.scale * ZOOM_PER_LINE.powf(y as f64)).max(MIN_SCALE);
+                view.center[0] += offset[0] * (view.scale - scale);
+                view.center[1] += offset[1] * (view.scale - scale);
+                view.scale = scale;
+                self.dirty = true;
+            }
+            _ => {}
+        }
+    }
+
+    fn view_mut(&mut self) -> &mut View {
+        &mut self.views[self.kind as usize]
+    }
+
+    pub fn prepare(&self, queue: &Queue) {
+        let view = self.views[self.kind as usize];
+        // Splits a double into a float and the float closest to the remainder
+        let split = |value: f64| {
+            let high = value as f32;
+            [high, (value - high as f64) as f32]
+        };
+        let [x_high, x_low] = split(view.center[0]);
+        let [y_high, y_low] = split(view.center[1]);
+        queue.write_buffer(
+            &self.uniform_buffer,
+            0,
+            bytemuck::bytes_of(&FractalUniform {
+                center: [x_high, x_low, y_high, y_low],
+                julia: self.julia.map(|value| value as f32),
+                scale: view.scale as f32,
+                max_iterations: self.max_iterations,
+                kind: self.kind as u32,
+                palette: self.palette,
+                high_precision: (view.scale < HIGH_PRECISION_SCALE) as u32,
+                one: 1.0,
+            }),
+        );
+    }
+
+    /// Computes the iterations if the view changed, then draws them into the whole target.
+    pub fn render(&mut self, command_encoder: &mut CommandEncoder, target: &TextureView) {
+        if self.dirty {
+            let mut compute_pass = command_encoder.begin_compute_pass(&ComputePassDescriptor {
+                label: Some("Fractal iteration pass"),
+                timestamp_writes: None,
+            });
+            compute_pass.set_pipeline(&self.iterate_pipeline);
+            compute_pass.set_bind_group(0, &self.iterate_bind_group, &[]);
+            compute_pass.dispatch_workgroups(
+                self.size[0].div_ceil(WORKGROUP_SIZE),
+                self.size[1].div_ceil(WORKGROUP_SIZE),
+                1,
+            );
+            self.dirty = false;
+        }
+
+        let mut render_pass = command_encoder.begin_render_pass(&RenderPassDescriptor {
+            label: Some("Fractal draw pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: Operations {
+                    load: LoadOp::Clear(Color::BLACK),
+                    store: StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        render_pass.set_pipeline(&self.draw_pipeline);
+        render_pass.set_bind_group(0, &self.draw_bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
+
+fn create_iterations_view(device: &Device, width: u32, height: u32) -> TextureView {
+    device
+        .create_texture(&TextureDescriptor {
+            label: Some("Fractal iterations texture"),
+            size: Extent3d {
+                width: width.max(1),
+                height: height.max(1),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: TextureFormat::R32Float,
+            usage: TextureUsages::TEXTURE_BINDING | TextureUsages::STORAGE_BINDING,
+            view_formats: &[],
+        })
+        .create_view(&TextureViewDescriptor::default())
+}
+
+fn create_bind_groups(
+    device: &Device,
+    iterate_bind_group_layout: &BindGroupLayout,
+    draw_bind_group_layout: &BindGroupLayout,
+    uniform_buffer: &Buffer,
+    iterations_view: &TextureView,
+) -> (BindGroup, BindGroup) {
+    let create_bind_group = |label, layout| {
+        device.create_bind_group(&BindGroupDescriptor {
+            label: Some(label),
+            layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::TextureView(iterations_view),
+                },
+            ],
+        })
+    };
+    (
+        create_bind_group("Fractal iteration bind group", iterate_bind_group_layout),
+        create_bind_group("Fractal draw bind group", draw_bind_group_layout),
+    )
+}
@@ -0,0 +1,61 @@
+// Colors the iterations computed by `fractal_iterate.wgsl` with a palette, into the whole surface.
+//
+// The palettes are cosine gradients, see https://iquilezles.org/articles/palettes/
+
+const pi: f32 = 3.14159265;
+
+// Iterations per cycle through the palette
+const palette_period: f32 = 32.0;
+
+struct Fractal {
+    center: vec4<f32>,
+    julia: vec2<f32>,
+    scale: f32,
+    max_iterations: u32,
+    kind: u32,
+    // Index of the palette, see `FractalViewer::palette`
+    palette: u32,
+    high_precision: u32,
+    one: f32,
+}
+
+@group(0) @binding(0)
+var<uniform> fractal: Fractal;
+@group(0) @binding(1)
+var iterations: texture_2d<f32>;
+
+// One triangle covering the whole surface
+@vertex
+fn vs_main(@builtin(vertex_index) in_vertex_index: u32) -> @builtin(position) vec4<f32> {
+    let uv = vec2<f32>(f32((in_vertex_index << 1u) & 2u), f32(in_vertex_index & 2u));
+    return vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+}
+
+fn cosine_palette(t: f32, a: vec3<f32>, b: vec3<f32>, c: vec3<f32>, d: vec3<f32>) -> vec3<f32> {
+    return a + b * cos(2.0 * pi * (c * t + d));
+}
+
+fn palette(t: f32) -> vec3<f32> {
+    switch fractal.palette {
+        case 0u: {
+            return cosine_palette(t, vec3<f32>(0.5), vec3<f32>(0.5), vec3<f32>(1.0), vec3<f32>(0.0, 0.1, 0.2));
+        }
+        case 1u: {
+            return cosine_palette(t, vec3<f32>(0.5), vec3<f32>(0.5), vec3<f32>(1.0, 0.7, 0.4), vec3<f32>(0.0, 0.15, 0.2));
+        }
+        default: {
+            return cosine_palette(t, vec3<f32>(0.5), vec3<f32>(0.5), vec3<f32>(1.0), vec3<f32>(0.3, 0.2, 0.2)) * vec3<f32>(0.4, 0.8, 1.0);
+        }
+    }
+}
+
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let value = textureLoad(iterations, vec2<i32>(position.xy), 0).r;
+    if value < 0.0 {
+        return vec4<f32>(0.0, 0.0, 0.0, 1.0);
+    }
+    // Fade in from black, so the outermost bands do not flicker while zooming
+    let fade = clamp(value / 4.0, 0.0, 1.0);
+    return vec4<f32>(palette(value / palette_period) * fade, 1.0);
+}
@@ -0,0 +1,149 @@
+// Iterates the Mandelbrot or a Julia set for every pixel of the view, storing the smoothed
+// number of iterations until the orbit escapes, or -1 for points that never do.
+//
+// Deep zooms need more precision than `f32` has, so the pixel's position and the orbit are
+// then kept as pairs of `f32`, whose sum approximates a double, see
+// https://andrewthall.org/papers/df64_qf128.pdf
+
+// Kinds of fractals, see `FractalKind`
+const kind_mandelbrot: u32 = 0u;
+
+const escape_radius: f32 = 256.0;
+
+struct Fractal {
+    // Real and imaginary part of the center, each as a high and a low part
+    center: vec4<f32>,
+    // Constant of the Julia set
+    julia: vec2<f32>,
+    // Distance between pixels in the complex plane
+    scale: f32,
+    max_iterations: u32,
+    kind: u32,
+    palette: u32,
+    // Whether to iterate with emulated double precision
+    high_precision: u32,
+    // Always 1, see `opaque`
+    one: f32,
+}
+
+@group(0) @binding(0)
+var<uniform> fractal: Fractal;
+@group(0) @binding(1)
+var iterations: texture_storage_2d<r32float, write>;
+
+// Returns the value unchanged, in a way that compilers cannot see through. Otherwise they
+// may simplify the rounding errors computed below to zero, e.g. `(a + b) - a` to `b`.
+fn opaque(value: f32) -> f32 {
+    return value * fractal.one;
+}
+
+// Exact sum of two floats, as the rounded sum and its rounding error
+fn two_sum(a: f32, b: f32) -> vec2<f32> {
+    let s = opaque(a + b);
+    let v = opaque(s - a);
+    return vec2<f32>(s, (a - (s - v)) + (b - v));
+}
+
+// Like `two_sum`, if `a` is known to be at least as large as `b`
+fn quick_two_sum(a: f32, b: f32) -> vec2<f32> {
+    let s = opaque(a + b);
+    return vec2<f32>(s, b - (s - a));
+}
+
+// Splits a float into two halves of its mantissa, whose products are exact
+fn split(a: f32) -> vec2<f32> {
+    let t = opaque(4097.0 * a);
+    let high = t - opaque(t - a);
+    return vec2<f32>(high, a - high);
+}
+
+// Exact product of two floats, as the rounded product and its rounding error
+fn two_product(a: f32, b: f32) -> vec2<f32> {
+    let p = opaque(a * b);
+    let sa = split(a);
+    let sb = split(b);
+    let error = ((sa.x * sb.x - p) + sa.x * sb.y + sa.y * sb.x) + sa.y * sb.y;
+    return vec2<f32>(p, error);
+}
+
+fn df_add(a: vec2<f32>, b: vec2<f32>) -> vec2<f32> {
+    let s = two_sum(a.x, b.x);
+    let t = two_sum(a.y, b.y);
+    let r = quick_two_sum(s.x, s.y + t.x);
+    return quick_two_sum(r.x, r.y + t.y);
+}
+
+fn df_mul(a: vec2<f32>, b: vec2<f32>) -> vec2<f32> {
+    let p = two_product(a.x, b.x);
+    return quick_two_sum(p.x, p.y + (a.x * b.y + a.y * b.x));
+}
+
+// Smoothed iteration count, continuous across the bands of equal iteration counts
+fn smooth_iterations(i: u32, length_squared: f32) -> f32 {
+    return f32(i) + 1.0 - log2(log2(length_squared) / 2.0);
+}
+
+fn iterate(offset: vec2<f32>) -> f32 {
+    let point = fractal.center.xz + offset;
+    var z = point;
+    var c = fractal.julia;
+    if fractal.kind == kind_mandelbrot {
+        z = vec2<f32>(0.0);
+        c = point;
+    }
+    for (var i = 0u; i < fractal.max_iterations; i++) {
+        z = vec2<f32>(z.x * z.x - z.y * z.y, 2.0 * z.x * z.y) + c;
+        let length_squared = dot(z, z);
+        if length_squared > escape_radius * escape_radius {
+            return smooth_iterations(i, length_squared);
+        }
+    }
+    return -1.0;
+}
+
+fn iterate_high_precision(offset: vec2<f32>) -> f32 {
+    let point_x = df_add(fractal.center.xy, vec2<f32>(offset.x, 0.0));
+    let point_y = df_add(fractal.center.zw, vec2<f32>(offset.y, 0.0));
+    var z_x = point_x;
+    var z_y = point_y;
+    var c_x = vec2<f32>(fractal.julia.x, 0.0);
+    var c_y = vec2<f32>(fractal.julia.y, 0.0);
+    if fractal.kind == kind_mandelbrot {
+        z_x = vec2<f32>(0.0);
+        z_y = vec2<f32>(0.0);
+        c_x = point_x;
+        c_y = point_y;
+    }
+    for (var i = 0u; i < fractal.max_iterations; i++) {
+        let x_squared = df_mul(z_x, z_x);
+        let y_squared = df_mul(z_y, z_y);
+        let xy = df_mul(z_x, z_y);
+        z_x = df_add(df_add(x_squared, -y_squared), c_x);
+        z_y = df_add(df_add(xy, xy), c_y);
+        // Escaping needs no precision
+        let length_squared = z_x.x * z_x.x + z_y.x * z_y.x;
+        if length_squared > escape_radius * escape_radius {
+            return smooth_iterations(i, length_squared);
+        }
+    }
+    return -1.0;
+}
+
+@compute @workgroup_size(8, 8, 1)
+fn cs_iterate(@builtin(global_invocation_id) id: vec3<u32>) {
+    let size = textureDimensions(iterations);
+    if any(id.xy >= size) {
+        return;
+    }
+    // Pixels to the complex plane, flipping Y as the imaginary axis points up
+    let pixel = vec2<f32>(id.xy) + 0.5 - vec2<f32>(size) / 2.0;
+    let offset = vec2<f32>(pixel.x, -pixel.y) * fractal.scale;
+
+    var value: f32;
+    if fractal.high_precision != 0u {
+        value = iterate_high_precision(offset);
+    } else {
+        value = iterate(offset);
+    }
+    textureStore(iterations, id.xy, vec4<f32>(value, 0.0, 0.0, 0.0));
+}
@@ -0,0 +1,111 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, OnceLock},
+    time::Duration,
+};
+
+use wgpu::{Device, Maintain, Queue, SubmissionIndex};
+
+use crate::clock::Instant;
+
+struct Submission {
+    index: SubmissionIndex,
+    /// When the frame started, before its input was applied.
+    start: Instant,
+    /// When the GPU finished the frame's commands, set by a callback while polling the device.
+    finished: Arc<OnceLock<Instant>>,
+}
+
+/// Limits how many frames the CPU may run ahead of the GPU, and measures their latency.
+///
+/// Every frame queued up for the GPU delays how soon the input applied in a frame is seen,
+/// but keeps the GPU busy while the CPU prepares the next frame. The surface limits the frames
+/// waiting to be presented, see `desired_maximum_frame_latency` of `SurfaceConfiguration`, but
+/// only blocks once a frame acquires its surface texture, after its input has been applied.
+/// Waiting for the submission of an earlier frame before applying the input avoids that delay.
+pub struct FramePacer {
+    max_frames_in_flight: u32,
+    in_flight: VecDeque<Submission>,
+    /// Time from the start of the most recent finished frame until the GPU finished it.
+    /// The device is polled at the start of each frame, so this is measured in whole frames
+    /// unless the GPU is waited for.
+    latency: Option<Duration>,
+}
+
+impl FramePacer {
+    pub const MIN_FRAMES_IN_FLIGHT: u32 = 1;
+    pub const MAX_FRAMES_IN_FLIGHT: u32 = 3;
+
+    pub fn new(max_frames_in_flight: u32) -> Self {
+        Self {
+            max_frames_in_flight,
+            in_flight: VecDeque::new(),
+            latency: None,
+        }
+    }
+
+    pub fn max_frames_in_flight(&self) -> u32 {
+        self.max_frames_in_flight
+    }
+
+    pub fn set_max_frames_in_flight(&mut self, max_frames_in_flight: u32) {
+        self.max_frames_in_flight = max_frames_in_flight;
+    }
+
+    pub fn latency(&self) -> Option<Duration> {
+        self.latency
+    }
+
+    /// Waits until fewer than the maximum number of frames are in flight, then returns the
+    /// start of the frame, which is to be passed to [`FramePacer::end_frame`].
+    pub fn begin_frame(&mut self, device: &Device) -> Instant {
+        device.poll(Maintain::Poll);
+        self.collect_finished();
+        while self.in_flight.len() >= self.max_frames_in_flight as usize {
+            let Some(index) = self
+                .in_flight
+                .front()
+                .map(|submission| submission.index.clone())
+            else {
+                break;
+            };
+            device.poll(Maintain::wait_for(index));
+            // Waiting does not block on the web, where the browser paces the frames instead
+            if !self.collect_finished() {
+                self.in_flight.pop_front();
+            }
+        }
+        Instant::now()
+    }
+
+    /// Tracks the submission of a frame's commands, until the GPU finished them.
+    pub fn end_frame(&mut self, queue: &Queue, index: SubmissionIndex, start: Instant) {
+        let finished = Arc::new(OnceLock::new());
+        queue.on_submitted_work_done({
+            let finished = finished.clone();
+            move || {
+                let _ = finished.set(Instant::now());
+            }
+        });
+        self.in_flight.push_back(Submission {
+            index,
+            start,
+            finished,
+        });
+    }
+
+    /// Stops tracking finished frames, measuring their latency.
+    /// Returns whether any frame had finished.
+    fn collect_finished(&mut self) -> bool {
+        let mut collected = false;
+        while let Some(submission) = self.in_flight.front() {
+            let Some(&finished) = submission.finished.get() else {
+                break;
+            };
+            self.latency = Some(finished - submission.start);
+            self.in_flight.pop_front();
+            collected = true;
+        }
+        collected
+    }
+}
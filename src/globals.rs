@@ -0,0 +1,86 @@
+use wgpu::{
+    util::{BufferInitDescriptor, DeviceExt},
+    BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
+    BindGroupLayoutEntry, BindingType, Buffer, BufferBindingType, BufferUsages, Device, Queue,
+    ShaderStages,
+};
+
+/// Per-frame values as seen by the shaders, see `globals.wgsl`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct GlobalsUniform {
+    time: f32,
+    delta_time: f32,
+    frame: u32,
+    padding: u32,
+}
+
+/// Time and frame count for shaders that animate on their own, bound at `@group(1)`.
+///
+/// The time is the simulation's, so replays and benchmarks animate identically on every run.
+pub struct Globals {
+    uniform: GlobalsUniform,
+    buffer: Buffer,
+    bind_group_layout: BindGroupLayout,
+    bind_group: BindGroup,
+}
+
+impl Globals {
+    pub fn new(device: &Device) -> Self {
+        let uniform = GlobalsUniform {
+            time: 0.0,
+            delta_time: 0.0,
+            frame: 0,
+            padding: 0,
+        };
+        let buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Globals buffer"),
+            contents: bytemuck::bytes_of(&uniform),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+        let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Globals bind group layout"),
+            entries: &[BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStages::VERTEX_FRAGMENT | ShaderStages::COMPUTE,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Globals bind group"),
+            layout: &bind_group_layout,
+            entries: &[BindGroupEntry {
+                binding: 0,
+                resource: buffer.as_entire_binding(),
+            }],
+        });
+
+        Self {
+            uniform,
+            buffer,
+            bind_group_layout,
+            bind_group,
+        }
+    }
+
+    pub fn bind_group_layout(&self) -> &BindGroupLayout {
+        &self.bind_group_layout
+    }
+
+    pub fn bind_group(&self) -> &BindGroup {
+        &self.bind_group
+    }
+
+    /// Uploads the values of a new frame, given the current simulation time in seconds.
+    pub fn prepare(&mut self, queue: &Queue, time: f32) {
+        self.uniform.delta_time = time - self.uniform.time;
+        self.uniform.time = time;
+        queue.write_buffer(&self.buffer, 0, bytemuck::bytes_of(&self.uniform));
+        self.uniform.frame = self.uniform.frame.wrapping_add(1);
+    }
+}
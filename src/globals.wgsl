@@ -0,0 +1,14 @@
+// Per-frame values shared by shaders that animate on their own, see `GlobalsUniform`.
+
+struct Globals {
+    // Seconds simulated so far
+    time: f32,
+    // Seconds simulated since the previous frame
+    delta_time: f32,
+    // Index of the frame being drawn, counting from zero
+    frame: u32,
+    padding: u32,
+}
+
+@group(1) @binding(0)
+var<uniform> globals: Globals;
@@ -0,0 +1,156 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+use wgpu::{
+    BufferDescriptor, BufferUsages, ComputePassDescriptor, ComputePassTimestampWrites, Device,
+    Features, MapMode, QuerySetDescriptor, QueryType, Queue, QUERY_RESOLVE_BUFFER_ALIGNMENT,
+    QUERY_SIZE,
+};
+
+use crate::frame_pacing::FramePacer;
+
+// Number of frames whose timestamps may be waiting for readback at the same time: every frame the
+// pacer lets the GPU lag behind, and the frame finished last, which is collected after the next
+// frame is submitted. Frames beyond that are not measured.
+const SLOTS: usize = FramePacer::MAX_FRAMES_IN_FLIGHT as usize + 1;
+
+struct Slot {
+    readback_buffer: wgpu::Buffer,
+    mapped: Arc<AtomicBool>,
+    in_flight: bool,
+}
+
+/// Measures the GPU time of a whole frame using timestamp queries.
+///
+/// Timestamps are written by empty compute passes at the start and end of the
+/// frame's command encoder, so the measurement covers every pass in between.
+pub struct GpuTimer {
+    query_set: wgpu::QuerySet,
+    resolve_buffer: wgpu::Buffer,
+    slots: Vec<Slot>,
+    current: Option<usize>,
+    period: f32,
+}
+
+impl GpuTimer {
+    /// Returns `None` if the device was not created with `Features::TIMESTAMP_QUERY`.
+    pub fn new(device: &Device, queue: &Queue) -> Option<Self> {
+        if !device.features().contains(Features::TIMESTAMP_QUERY) {
+            return None;
+        }
+
+        let query_set = device.create_query_set(&QuerySetDescriptor {
+            label: Some("GPU timer queries"),
+            ty: QueryType::Timestamp,
+            count: 2 * SLOTS as u32,
+        });
+        let resolve_buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("GPU timer resolve buffer"),
+            size: QUERY_RESOLVE_BUFFER_ALIGNMENT * SLOTS as u64,
+            usage: BufferUsages::QUERY_RESOLVE | BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let slots = (0..SLOTS)
+            .map(|_| Slot {
+                readback_buffer: device.create_buffer(&BufferDescriptor {
+                    label: Some("GPU timer readback buffer"),
+                    size: 2 * QUERY_SIZE as u64,
+                    usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+                    mapped_at_creation: false,
+                }),
+                mapped: Arc::new(AtomicBool::new(false)),
+                in_flight: false,
+            })
+            .collect();
+
+        Some(Self {
+            query_set,
+            resolve_buffer,
+            slots,
+            current: None,
+            period: queue.get_timestamp_period(),
+        })
+    }
+
+    /// Writes the start timestamp of a frame, if a readback slot is available.
+    pub fn begin(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        self.current = self.slots.iter().position(|slot| !slot.in_flight);
+        if let Some(index) = self.current {
+            self.write_timestamp(encoder, 2 * index as u32);
+        }
+    }
+
+    /// Writes the end timestamp of a frame and copies both timestamps for readback.
+    pub fn end(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        let Some(index) = self.current else {
+            return;
+        };
+        let first_query = 2 * index as u32;
+        let offset = QUERY_RESOLVE_BUFFER_ALIGNMENT * index as u64;
+        self.write_timestamp(encoder, first_query + 1);
+        encoder.resolve_query_set(
+            &self.query_set,
+            first_query..first_query + 2,
+            &self.resolve_buffer,
+            offset,
+        );
+        encoder.copy_buffer_to_buffer(
+            &self.resolve_buffer,
+            offset,
+            &self.slots[index].readback_buffer,
+            0,
+            2 * QUERY_SIZE as u64,
+        );
+    }
+
+    /// Requests the readback of the current frame's timestamps.
+    /// Must be called after the frame's command buffer has been submitted.
+    pub fn map(&mut self) {
+        let Some(index) = self.current.take() else {
+            return;
+        };
+        let slot = &mut self.slots[index];
+        slot.in_flight = true;
+        let mapped = slot.mapped.clone();
+        slot.readback_buffer
+            .slice(..)
+            .map_async(MapMode::Read, move |result| {
+                if result.is_ok() {
+                    mapped.store(true, Ordering::Release);
+                }
+            });
+    }
+
+    /// Returns the GPU times in milliseconds of all frames whose readback completed.
+    /// The device must be polled for readbacks to make progress.
+    pub fn collect(&mut self) -> Vec<f64> {
+        let mut times = Vec::new();
+        for slot in self.slots.iter_mut().filter(|slot| slot.in_flight) {
+            if !slot.mapped.swap(false, Ordering::Acquire) {
+                continue;
+            }
+            {
+                let data = slot.readback_buffer.slice(..).get_mapped_range();
+                let timestamps: &[u64] = bytemuck::cast_slice(&data);
+                let ticks = timestamps[1].wrapping_sub(timestamps[0]);
+                times.push(ticks as f64 * self.period as f64 / 1_000_000.0);
+            }
+            slot.readback_buffer.unmap();
+            slot.in_flight = false;
+        }
+        times
+    }
+
+    fn write_timestamp(&self, encoder: &mut wgpu::CommandEncoder, query_index: u32) {
+        encoder.begin_compute_pass(&ComputePassDescriptor {
+            label: Some("GPU timer pass"),
+            timestamp_writes: Some(ComputePassTimestampWrites {
+                query_set: &self.query_set,
+                beginning_of_pass_write_index: Some(query_index),
+                end_of_pass_write_index: None,
+            }),
+        });
+    }
+}
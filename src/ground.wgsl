@@ -0,0 +1,68 @@
+// Draws an infinite ground plane at y = 0 with a single full-screen triangle.
+// Expects `camera.wgsl` to be prepended.
+//
+// Every pixel intersects its view ray with the plane. Pixels whose ray misses the plane
+// are discarded, all others write the depth of the intersection, so that geometry drawn
+// before or after intersects the plane correctly.
+
+const ground_height: f32 = 0.0;
+const tile_size: f32 = 1.0;
+const albedo_light: vec3<f32> = vec3<f32>(0.42, 0.4, 0.37);
+const albedo_dark: vec3<f32> = vec3<f32>(0.36, 0.34, 0.31);
+// The plane fades out between these distances from the camera...
+const fade_start: f32 = 60.0;
+const fade_end: f32 = 250.0;
+// ...and when viewed at an angle flatter than this (sine of the angle).
+const fade_grazing: f32 = 0.04;
+// Relative distance by which the written depth is pushed back, so that lines drawn
+// exactly on the plane (such as the grid overlay) are not hidden by it.
+const depth_bias: f32 = 1e-4;
+
+struct FragmentOutput {
+    @location(0) color: vec4<f32>,
+    @builtin(frag_depth) depth: f32,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) in_vertex_index: u32) -> @builtin(position) vec4<f32> {
+    // A triangle covering the whole screen, its excess is clipped
+    let uv = vec2<f32>(f32((in_vertex_index << 1u) & 2u), f32(in_vertex_index & 2u));
+    return vec4<f32>(uv * 2.0 - 1.0, 1.0, 1.0);
+}
+
+// Box filtered checkerboard, see https://iquilezles.org/articles/checkerfiltering/
+fn checkerboard(p: vec2<f32>, filter_width: vec2<f32>) -> f32 {
+    let w = max(filter_width, vec2<f32>(1e-4));
+    let i = 2.0 * (abs(fract((p - 0.5 * w) * 0.5) - 0.5) - abs(fract((p + 0.5 * w) * 0.5) - 0.5)) / w;
+    return 0.5 - 0.5 * i.x * i.y;
+}
+
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> FragmentOutput {
+    let origin = camera.position.xyz;
+    let direction = view_ray(position.xy);
+
+    // Derivatives must be computed in uniform control flow, so pixels missing
+    // the plane are only discarded at the very end.
+    let denominator = select(direction.y, 1e-6, abs(direction.y) < 1e-6);
+    let t = (ground_height - origin.y) / denominator;
+    let hit = origin + direction * max(t, 0.0);
+
+    let p = hit.xz / tile_size;
+    let checker = checkerboard(p, fwidth(p));
+    let albedo = mix(albedo_light, albedo_dark, checker);
+
+    let fade = (1.0 - smoothstep(fade_start, fade_end, t))
+        * smoothstep(0.0, fade_grazing, abs(direction.y));
+
+    let clip = camera.view_projection * vec4<f32>(origin + direction * t * (1.0 + depth_bias), 1.0);
+
+    if t <= 0.0 || fade <= 0.0 {
+        discard;
+    }
+
+    var out: FragmentOutput;
+    out.color = vec4<f32>(albedo, fade);
+    out.depth = clamp(clip.z / clip.w, 0.0, 1.0);
+    return out;
+}
@@ -0,0 +1,69 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use wgpu::{Device, ErrorFilter};
+
+/// Watches a shader file, to rebuild what is created from it whenever the file is saved.
+pub struct ShaderWatcher {
+    path: PathBuf,
+    /// Modification time of the file when it was last loaded.
+    modified: Option<SystemTime>,
+}
+
+impl ShaderWatcher {
+    /// Watches a file whose current contents are already in use, e.g. through `include_str!`.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        Self {
+            modified: modified(&path),
+            path,
+        }
+    }
+
+    /// Watches a file that has not been loaded yet, so the first [`ShaderWatcher::reload`]
+    /// loads it.
+    pub fn unloaded(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            modified: None,
+        }
+    }
+
+    /// Calls `create` with the source of the file if it was saved since it was last loaded.
+    ///
+    /// Returns what `create` returned, unless the file could not be read or `create` caused a
+    /// validation error, e.g. because the shader does not compile. Such errors are logged,
+    /// instead of panicking like uncaptured errors do, so they can be fixed in the file.
+    pub fn reload<T>(&mut self, device: &Device, create: impl FnOnce(&str) -> T) -> Option<T> {
+        let modified = modified(&self.path);
+        if modified.is_none() || modified == self.modified {
+            return None;
+        }
+        self.modified = modified;
+
+        let source = fs::read_to_string(&self.path)
+            .inspect_err(|error| log::error!("Could not read {}: {error}", self.path.display()))
+            .ok()?;
+        device.push_error_scope(ErrorFilter::Validation);
+        let created = create(&source);
+        match futures::executor::block_on(device.pop_error_scope()) {
+            Some(error) => {
+                log::error!("Could not load {}: {error}", self.path.display());
+                None
+            }
+            None => {
+                log::info!("Loaded {}", self.path.display());
+                Some(created)
+            }
+        }
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
@@ -0,0 +1,272 @@
+use std::{collections::VecDeque, f32::consts::TAU, time::Duration};
+
+use cgmath::{Point2, Rad};
+
+use crate::{
+    clock::Instant,
+    shapes::{ShapeRenderer, ShapeStyle},
+    streaming::StreamingStats,
+};
+
+/// Number of frames shown in the frame time graph.
+const HISTORY: usize = 120;
+/// Frame time at the top of the graph, twice the budget of 60 frames per second.
+const GRAPH_MAX_MILLISECONDS: f32 = 1000.0 / 30.0;
+const BUDGET_MILLISECONDS: f32 = 1000.0 / 60.0;
+const PANEL_SIZE: [f32; 2] = [260.0, 80.0];
+/// Height of the row below the graph holding the latency bar.
+const LATENCY_HEIGHT: f32 = 14.0;
+/// Latency at the right end of the latency bar, in frame budgets.
+const LATENCY_MAX_FRAMES: u32 = 4;
+/// Height of the row below the latency bar holding the texture residency.
+const STREAMING_HEIGHT: f32 = 16.0;
+/// Widest cell of a texture in the residency row.
+const TEXTURE_CELL_WIDTH: f32 = 10.0;
+const MARGIN: f32 = 12.0;
+const PADDING: f32 = 10.0;
+
+const PANEL_COLOR: [f32; 4] = [0.05, 0.05, 0.08, 0.7];
+const BORDER_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 0.25];
+const BUDGET_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 0.35];
+const GOOD_COLOR: [f32; 4] = [0.3, 0.9, 0.4, 1.0];
+const BAD_COLOR: [f32; 4] = [1.0, 0.35, 0.25, 1.0];
+const LATENCY_COLOR: [f32; 4] = [0.35, 0.65, 1.0, 1.0];
+const RESIDENT_COLOR: [f32; 4] = [0.75, 0.5, 1.0, 1.0];
+const STREAMING_COLOR: [f32; 4] = [1.0, 0.75, 0.2, 1.0];
+
+/// Heads-up display in the top right corner, showing the time between recent frames
+/// as a graph, and their average as a gauge filling up towards twice the frame budget.
+/// Below the graph, a bar with a tick per frame budget shows the latency of the frames.
+/// While textures are streamed, a last row shows the video memory they occupy against the
+/// budget, with a tick at what the view needs, and a cell per texture filled up to its finest
+/// resident level, green once it reaches the level the view needs, amber while streaming,
+/// and grey if the texture isn't used.
+pub struct Hud {
+    pub enabled: bool,
+    frame_times: VecDeque<f32>,
+    last_frame: Option<Instant>,
+    /// Latency of the most recent finished frame in milliseconds.
+    latency: Option<f32>,
+}
+
+impl Default for Hud {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            frame_times: VecDeque::with_capacity(HISTORY),
+            last_frame: None,
+            latency: None,
+        }
+    }
+}
+
+impl Hud {
+    /// Records the time since the previous frame, and the latency of the most recent finished
+    /// frame, see [`FramePacer::latency`].
+    ///
+    /// [`FramePacer::latency`]: crate::frame_pacing::FramePacer::latency
+    pub fn begin_frame(&mut self, latency: Option<Duration>) {
+        self.latency = latency.map(|latency| latency.as_secs_f32() * 1000.0);
+        let now = Instant::now();
+        if let Some(last_frame) = self.last_frame.replace(now) {
+            if self.frame_times.len() == HISTORY {
+                self.frame_times.pop_front();
+            }
+            self.frame_times
+                .push_back((now - last_frame).as_secs_f32() * 1000.0);
+        }
+    }
+
+    pub fn draw(&self, shapes: &mut ShapeRenderer, width: u32, streaming: Option<&StreamingStats>) {
+        if !self.enabled {
+            return;
+        }
+        let left = width as f32 - MARGIN - PANEL_SIZE[0];
+        let top = MARGIN;
+        let bottom = top + PANEL_SIZE[1];
+        let panel_bottom = bottom
+            + LATENCY_HEIGHT
+            + if streaming.is_some() {
+                STREAMING_HEIGHT
+            } else {
+                0.0
+            };
+        shapes.rect(
+            Point2::new(left, top),
+            Point2::new(left + PANEL_SIZE[0], panel_bottom),
+            8.0,
+            ShapeStyle::Fill,
+            PANEL_COLOR,
+        );
+        shapes.rect(
+            Point2::new(left, top),
+            Point2::new(left + PANEL_SIZE[0], panel_bottom),
+            8.0,
+            ShapeStyle::Stroke(1.0),
+            BORDER_COLOR,
+        );
+
+        // Frame time graph, the newest frame on the right
+        let graph_left = left + PADDING;
+        let graph_right = left + PANEL_SIZE[0] - PANEL_SIZE[1];
+        let graph_height = PANEL_SIZE[1] - 2.0 * PADDING;
+        let graph_y = |milliseconds: f32| {
+            bottom - PADDING - (milliseconds / GRAPH_MAX_MILLISECONDS).min(1.0) * graph_height
+        };
+        let step = (graph_right - graph_left) / (HISTORY - 1) as f32;
+        let first_x = graph_right - (self.frame_times.len() as f32 - 1.0) * step;
+        shapes.line(
+            Point2::new(graph_left, graph_y(BUDGET_MILLISECONDS)),
+            Point2::new(graph_right, graph_y(BUDGET_MILLISECONDS)),
+            1.0,
+            BUDGET_COLOR,
+        );
+        let points = self
+            .frame_times
+            .iter()
+            .enumerate()
+            .map(|(index, &milliseconds)| {
+                (
+                    Point2::new(first_x + index as f32 * step, graph_y(milliseconds)),
+                    milliseconds,
+                )
+            })
+            .collect::<Vec<_>>();
+        for pair in points.windows(2) {
+            let [(from, _), (to, milliseconds)] = [pair[0], pair[1]];
+            shapes.line(from, to, 1.5, frame_color(milliseconds));
+        }
+
+        // Latency bar, between the graph and the bottom of the panel
+        let latency_y = bottom + (LATENCY_HEIGHT - PADDING) / 2.0;
+        let latency_x = |milliseconds: f32| {
+            graph_left
+                + (milliseconds / (LATENCY_MAX_FRAMES as f32 * BUDGET_MILLISECONDS)).min(1.0)
+                    * (graph_right - graph_left)
+        };
+        shapes.line(
+            Point2::new(graph_left, latency_y),
+            Point2::new(graph_right, latency_y),
+            4.0,
+            BORDER_COLOR,
+        );
+        if let Some(latency) = self.latency {
+            shapes.line(
+                Point2::new(graph_left, latency_y),
+                Point2::new(latency_x(latency), latency_y),
+                4.0,
+                LATENCY_COLOR,
+            );
+        }
+        for frames in 1..LATENCY_MAX_FRAMES {
+            let x = latency_x(frames as f32 * BUDGET_MILLISECONDS);
+            shapes.line(
+                Point2::new(x, latency_y - 4.0),
+                Point2::new(x, latency_y + 4.0),
+                1.0,
+                BUDGET_COLOR,
+            );
+        }
+
+        if let Some(stats) = streaming {
+            // Video memory of the streamed textures, below the latency bar
+            let streaming_y = latency_y + STREAMING_HEIGHT;
+            let memory_x = |bytes: u64| {
+                graph_left
+                    + (bytes as f32 / stats.budget as f32).min(1.0) * (graph_right - graph_left)
+            };
+            shapes.line(
+                Point2::new(graph_left, streaming_y),
+                Point2::new(graph_right, streaming_y),
+                4.0,
+                BORDER_COLOR,
+            );
+            shapes.line(
+                Point2::new(graph_left, streaming_y),
+                Point2::new(memory_x(stats.resident), streaming_y),
+                4.0,
+                RESIDENT_COLOR,
+            );
+            let needed_x = memory_x(stats.needed);
+            shapes.line(
+                Point2::new(needed_x, streaming_y - 5.0),
+                Point2::new(needed_x, streaming_y + 5.0),
+                1.5,
+                if stats.needed > stats.budget {
+                    BAD_COLOR
+                } else {
+                    BUDGET_COLOR
+                },
+            );
+
+            // A cell per texture, below the gauge
+            let cells_left = graph_right + PADDING;
+            let cells_width = left + PANEL_SIZE[0] - PADDING - cells_left;
+            let cell_width =
+                TEXTURE_CELL_WIDTH.min(cells_width / stats.textures.len().max(1) as f32);
+            let cell_bottom = streaming_y + 5.0;
+            let cell_height = 10.0;
+            for (index, texture) in stats.textures.iter().enumerate() {
+                let cell_left = cells_left + index as f32 * cell_width;
+                let cell_right = cell_left + cell_width - 2.0;
+                let resident = (texture.level_count - texture.resident_level) as f32
+                    / texture.level_count as f32;
+                let color = match texture.needed_level {
+                    None => BUDGET_COLOR,
+                    Some(level) if texture.resident_level <= level => GOOD_COLOR,
+                    Some(_) => STREAMING_COLOR,
+                };
+                shapes.rect(
+                    Point2::new(cell_left, cell_bottom - cell_height),
+                    Point2::new(cell_right, cell_bottom),
+                    1.0,
+                    ShapeStyle::Fill,
+                    BORDER_COLOR,
+                );
+                shapes.rect(
+                    Point2::new(cell_left, cell_bottom - resident * cell_height),
+                    Point2::new(cell_right, cell_bottom),
+                    1.0,
+                    ShapeStyle::Fill,
+                    color,
+                );
+            }
+        }
+
+        // Gauge of the average frame time, starting at the top
+        let average = self.frame_times.iter().sum::<f32>() / self.frame_times.len().max(1) as f32;
+        let center = Point2::new(
+            left + PANEL_SIZE[0] - PANEL_SIZE[1] / 2.0,
+            top + PANEL_SIZE[1] / 2.0,
+        );
+        let radius = PANEL_SIZE[1] / 2.0 - PADDING - 3.0;
+        let start = -TAU / 4.0;
+        let fraction = (average / GRAPH_MAX_MILLISECONDS).min(1.0);
+        shapes.circle(center, radius, ShapeStyle::Stroke(6.0), BORDER_COLOR);
+        shapes.arc(
+            center,
+            radius,
+            Rad(start),
+            Rad(start + fraction * TAU),
+            ShapeStyle::Stroke(6.0),
+            frame_color(average),
+        );
+        shapes.arc(
+            center,
+            radius * 0.5,
+            Rad(start),
+            Rad(start + fraction * TAU),
+            ShapeStyle::Fill,
+            frame_color(average),
+        );
+    }
+}
+
+/// Green within the frame budget, red beyond it.
+fn frame_color(milliseconds: f32) -> [f32; 4] {
+    if milliseconds <= BUDGET_MILLISECONDS * 1.05 {
+        GOOD_COLOR
+    } else {
+        BAD_COLOR
+    }
+}
@@ -0,0 +1,399 @@
+use std::{
+    collections::VecDeque,
+    fmt,
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::Path,
+    str::FromStr,
+};
+
+use color_eyre::{
+    eyre::{bail, eyre, Context, OptionExt},
+    Result,
+};
+use winit::{
+    event::{ElementState, MouseButton, MouseScrollDelta, WindowEvent},
+    keyboard::{KeyCode, PhysicalKey},
+};
+
+const RECORDING_HEADER: &str = "# rustlab2024-wgpu input recording v1";
+
+// Pixel based scroll deltas (touchpads) are converted to lines using this factor.
+const PIXELS_PER_LINE: f64 = 20.0;
+
+/// Input event as consumed by the simulation.
+///
+/// Only contains what the application reacts to, so it can be recorded to and
+/// replayed from a file.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InputEvent {
+    Key { code: KeyCode, pressed: bool },
+    MouseButton { button: MouseButton, pressed: bool },
+    CursorMoved { x: f64, y: f64 },
+    Scroll { x: f32, y: f32 },
+}
+
+impl InputEvent {
+    pub fn from_window_event(event: &WindowEvent) -> Option<Self> {
+        match event {
+            WindowEvent::KeyboardInput { event, .. } if !event.repeat => {
+                let PhysicalKey::Code(code) = event.physical_key else {
+                    return None;
+                };
+                // Keys without a name cannot be recorded, so they are ignored altogether
+                // to keep live and replayed sessions identical.
+                key_name(code)?;
+                Some(Self::Key {
+                    code,
+                    pressed: event.state == ElementState::Pressed,
+                })
+            }
+            WindowEvent::MouseInput { state, button, .. } => Some(Self::MouseButton {
+                button: *button,
+                pressed: *state == ElementState::Pressed,
+            }),
+            WindowEvent::CursorMoved { position, .. } => Some(Self::CursorMoved {
+                x: position.x,
+                y: position.y,
+            }),
+            WindowEvent::MouseWheel { delta, .. } => Some(match delta {
+                MouseScrollDelta::LineDelta(x, y) => Self::Scroll { x: *x, y: *y },
+                MouseScrollDelta::PixelDelta(position) => Self::Scroll {
+                    x: (position.x / PIXELS_PER_LINE) as f32,
+                    y: (position.y / PIXELS_PER_LINE) as f32,
+                },
+            }),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for InputEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = |pressed: bool| if pressed { "down" } else { "up" };
+        match self {
+            Self::Key { code, pressed } => write!(
+                f,
+                "key {} {}",
+                key_name(*code).unwrap_or("Unidentified"),
+                state(*pressed)
+            ),
+            Self::MouseButton { button, pressed } => {
+                let button = match button {
+                    MouseButton::Left => "left".to_owned(),
+                    MouseButton::Right => "right".to_owned(),
+                    MouseButton::Middle => "middle".to_owned(),
+                    MouseButton::Back => "back".to_owned(),
+                    MouseButton::Forward => "forward".to_owned(),
+                    MouseButton::Other(id) => id.to_string(),
+                };
+                write!(f, "button {button} {}", state(*pressed))
+            }
+            Self::CursorMoved { x, y } => write!(f, "cursor {x} {y}"),
+            Self::Scroll { x, y } => write!(f, "scroll {x} {y}"),
+        }
+    }
+}
+
+impl FromStr for InputEvent {
+    type Err = color_eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let fields: Vec<&str> = s.split_whitespace().collect();
+        let pressed = |state: &str| match state {
+            "down" => Ok(true),
+            "up" => Ok(false),
+            _ => Err(eyre!("invalid state {state:?}")),
+        };
+        Ok(match fields.as_slice() {
+            ["key", name, state] => Self::Key {
+                code: key_code(name).ok_or_else(|| eyre!("unknown key {name:?}"))?,
+                pressed: pressed(state)?,
+            },
+            ["button", button, state] => Self::MouseButton {
+                button: match *button {
+                    "left" => MouseButton::Left,
+                    "right" => MouseButton::Right,
+                    "middle" => MouseButton::Middle,
+                    "back" => MouseButton::Back,
+                    "forward" => MouseButton::Forward,
+                    id => MouseButton::Other(
+                        id.parse()
+                            .wrap_err_with(|| format!("invalid mouse button {id:?}"))?,
+                    ),
+                },
+                pressed: pressed(state)?,
+            },
+            ["cursor", x, y] => Self::CursorMoved {
+                x: x.parse()?,
+                y: y.parse()?,
+            },
+            ["scroll", x, y] => Self::Scroll {
+                x: x.parse()?,
+                y: y.parse()?,
+            },
+            _ => bail!("invalid input event {s:?}"),
+        })
+    }
+}
+
+/// Writes input events along with the tick they were applied in to a file.
+pub struct InputRecorder {
+    writer: BufWriter<File>,
+}
+
+impl InputRecorder {
+    pub fn create(path: &Path) -> Result<Self> {
+        let file = File::create(path)
+            .wrap_err_with(|| format!("failed to create recording {}", path.display()))?;
+        let mut writer = BufWriter::new(file);
+        writeln!(writer, "{RECORDING_HEADER}")?;
+        Ok(Self { writer })
+    }
+
+    pub fn record(&mut self, tick: u64, event: &InputEvent) {
+        if let Err(e) = writeln!(self.writer, "{tick} {event}") {
+            log::error!("Failed to record input event: {e}");
+        }
+    }
+}
+
+impl Drop for InputRecorder {
+    fn drop(&mut self) {
+        if let Err(e) = self.writer.flush() {
+            log::error!("Failed to write input recording: {e}");
+        }
+    }
+}
+
+/// Input events loaded from a recording, handed out tick by tick.
+pub struct InputReplay {
+    events: VecDeque<(u64, InputEvent)>,
+}
+
+impl InputReplay {
+    pub fn load(path: &Path) -> Result<Self> {
+        let file = File::open(path)
+            .wrap_err_with(|| format!("failed to open recording {}", path.display()))?;
+        let mut lines = BufReader::new(file).lines();
+        if lines.next().transpose()?.as_deref() != Some(RECORDING_HEADER) {
+            bail!("{} is not an input recording", path.display());
+        }
+
+        let mut events = VecDeque::new();
+        for (line_number, line) in lines.enumerate() {
+            let line = line?;
+            let parse = || -> Result<(u64, InputEvent)> {
+                let (tick, event) = line.split_once(' ').ok_or_eyre("missing tick")?;
+                Ok((tick.parse()?, event.parse()?))
+            };
+            let (tick, event) = parse().wrap_err_with(|| {
+                format!("{}:{}: invalid line", path.display(), line_number + 2)
+            })?;
+            if events
+                .back()
+                .is_some_and(|&(last_tick, _)| tick < last_tick)
+            {
+                bail!(
+                    "{}:{}: ticks are not in order",
+                    path.display(),
+                    line_number + 2
+                );
+            }
+            events.push_back((tick, event));
+        }
+        Ok(Self { events })
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// Removes and returns the events recorded for `tick`.
+    /// Ticks must be requested in increasing order.
+    pub fn events_for(&mut self, tick: u64) -> Vec<InputEvent> {
+        let mut events = Vec::new();
+        while let Some(&(event_tick, event)) = self.events.front() {
+            if event_tick > tick {
+                break;
+            }
+            events.push(event);
+            self.events.pop_front();
+        }
+        events
+    }
+}
+
+macro_rules! key_names {
+    ($($code:ident),* $(,)?) => {
+        fn key_name(code: KeyCode) -> Option<&'static str> {
+            match code {
+                $(KeyCode::$code => Some(stringify!($code)),)*
+                _ => None,
+            }
+        }
+
+        fn key_code(name: &str) -> Option<KeyCode> {
+            match name {
+                $(stringify!($code) => Some(KeyCode::$code),)*
+                _ => None,
+            }
+        }
+    };
+}
+
+key_names! {
+    KeyA, KeyB, KeyC, KeyD, KeyE, KeyF, KeyG, KeyH, KeyI, KeyJ, KeyK, KeyL, KeyM,
+    KeyN, KeyO, KeyP, KeyQ, KeyR, KeyS, KeyT, KeyU, KeyV, KeyW, KeyX, KeyY, KeyZ,
+    Digit0, Digit1, Digit2, Digit3, Digit4, Digit5, Digit6, Digit7, Digit8, Digit9,
+    F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12,
+    ArrowUp, ArrowDown, ArrowLeft, ArrowRight,
+    PageUp, PageDown, Home, End, Insert, Delete,
+    Escape, Enter, Tab, Space, Backspace,
+    ShiftLeft, ShiftRight, ControlLeft, ControlRight, AltLeft, AltRight,
+    Minus, Equal, BracketLeft, BracketRight, Backslash, Semicolon, Quote,
+    Backquote, Comma, Period, Slash,
+    Numpad0, Numpad1, Numpad2, Numpad3, Numpad4, Numpad5, Numpad6, Numpad7,
+    Numpad8, Numpad9, NumpadAdd, NumpadSubtract, NumpadMultiply, NumpadDivide,
+    NumpadDecimal, NumpadEnter,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    fn round_trip(event: InputEvent) {
+        assert_eq!(event.to_string().parse::<InputEvent>().unwrap(), event);
+    }
+
+    // Writes a recording into a file of its own, as tests run in parallel.
+    fn write_recording(name: &str, contents: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "rustlab2024-wgpu-{}-{name}.txt",
+            std::process::id()
+        ));
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    fn load(name: &str, contents: &str) -> Result<InputReplay> {
+        let path = write_recording(name, contents);
+        let replay = InputReplay::load(&path);
+        std::fs::remove_file(path).unwrap();
+        replay
+    }
+
+    #[test]
+    fn events_round_trip() {
+        for pressed in [true, false] {
+            round_trip(InputEvent::Key {
+                code: KeyCode::KeyW,
+                pressed,
+            });
+            round_trip(InputEvent::Key {
+                code: KeyCode::NumpadEnter,
+                pressed,
+            });
+            for button in [
+                MouseButton::Left,
+                MouseButton::Right,
+                MouseButton::Middle,
+                MouseButton::Back,
+                MouseButton::Forward,
+                MouseButton::Other(0),
+                MouseButton::Other(u16::MAX),
+            ] {
+                round_trip(InputEvent::MouseButton { button, pressed });
+            }
+        }
+        round_trip(InputEvent::CursorMoved {
+            x: 0.1 + 0.2,
+            y: -1_234.567_890_123_4,
+        });
+        round_trip(InputEvent::CursorMoved {
+            x: f64::MIN_POSITIVE,
+            y: 1e300,
+        });
+        round_trip(InputEvent::Scroll { x: 0.0, y: -1.5 });
+        round_trip(InputEvent::Scroll {
+            x: 1.0 / 3.0,
+            y: 0.05,
+        });
+    }
+
+    #[test]
+    fn rejects_invalid_events() {
+        for line in [
+            "",
+            "key KeyW",
+            "key KeyW pressed",
+            "key Unidentified down",
+            "button wheel down",
+            "button -1 down",
+            "cursor 1",
+            "cursor x 1",
+            "scroll 1 2 3",
+            "touch 1 2",
+        ] {
+            assert!(line.parse::<InputEvent>().is_err(), "{line:?}");
+        }
+    }
+
+    #[test]
+    fn load_rejects_missing_header() {
+        assert!(load("empty", "").is_err());
+        assert!(load("no-header", "0 key KeyW down\n").is_err());
+    }
+
+    #[test]
+    fn load_rejects_ticks_out_of_order() {
+        let recording = format!("{RECORDING_HEADER}\n5 key KeyW down\n4 key KeyW up\n");
+        assert!(load("out-of-order", &recording).is_err());
+    }
+
+    #[test]
+    fn load_rejects_invalid_lines() {
+        let recording = format!("{RECORDING_HEADER}\nkey KeyW down\n");
+        assert!(load("invalid-line", &recording).is_err());
+    }
+
+    #[test]
+    fn events_for_drains_ticks_up_to_requested_one() {
+        let recording = format!(
+            "{RECORDING_HEADER}\n\
+             1 key KeyW down\n\
+             1 cursor 10 20\n\
+             3 key KeyW up\n\
+             7 scroll 0 1\n"
+        );
+        let mut replay = load("events-for", &recording).unwrap();
+        assert!(replay.events_for(0).is_empty());
+        assert_eq!(
+            replay.events_for(1),
+            [
+                InputEvent::Key {
+                    code: KeyCode::KeyW,
+                    pressed: true
+                },
+                InputEvent::CursorMoved { x: 10.0, y: 20.0 },
+            ]
+        );
+        assert!(replay.events_for(2).is_empty());
+        // Skipped ticks are drained along with the requested one
+        assert_eq!(
+            replay.events_for(5),
+            [InputEvent::Key {
+                code: KeyCode::KeyW,
+                pressed: false
+            }]
+        );
+        assert!(!replay.is_finished());
+        assert_eq!(
+            replay.events_for(7),
+            [InputEvent::Scroll { x: 0.0, y: 1.0 }]
+        );
+        assert!(replay.is_finished());
+    }
+}
@@ -0,0 +1,275 @@
+use std::borrow::Cow;
+
+use wgpu::{
+    BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
+    BindGroupLayoutEntry, BindingResource, BindingType, Color, ColorTargetState, ColorWrites,
+    CommandEncoder, ComputePassDescriptor, ComputePipeline, ComputePipelineDescriptor, Device,
+    Extent3d, FragmentState, ImageCopyTexture, ImageDataLayout, LoadOp, MultisampleState,
+    Operations, Origin3d, PipelineCompilationOptions, PipelineLayoutDescriptor, PrimitiveState,
+    Queue, RenderPassColorAttachment, RenderPassDescriptor, RenderPipeline,
+    RenderPipelineDescriptor, ShaderModuleDescriptor, ShaderStages, StorageTextureAccess, StoreOp,
+    TextureAspect, TextureDescriptor, TextureDimension, TextureFormat, TextureSampleType,
+    TextureUsages, TextureView, TextureViewDimension, VertexState,
+};
+
+use crate::ping_pong::PingPongTarget;
+
+/// Cells of the grid along X and Y.
+const GRID_SIZE: [u32; 2] = [256, 144];
+/// Workgroup size of `life_step.wgsl` along both axes.
+const WORKGROUP_SIZE: u32 = 8;
+/// Value of a living cell, see `life_step.wgsl`.
+const ALIVE: u32 = 255;
+/// Fraction of the cells that live after seeding the grid.
+const SEED_DENSITY: f32 = 0.3;
+
+/// Conway's Game of Life, simulated and drawn entirely on the GPU.
+///
+/// The grid is kept in a pair of storage textures. Each generation is computed from the texture
+/// holding the current one into the other texture, after which the two swap roles.
+/// One generation is computed per tick of the simulation, see [`GameOfLife::tick`].
+pub struct GameOfLife {
+    cells: PingPongTarget,
+    step_pipeline: ComputePipeline,
+    draw_pipeline: RenderPipeline,
+    /// Generations to compute before the next frame is drawn.
+    pending_steps: u32,
+    /// Seed of the random grid, which changes on every reset.
+    seed: u32,
+}
+
+impl GameOfLife {
+    pub fn new(
+        device: &Device,
+        queue: &Queue,
+        camera_bind_group_layout: &BindGroupLayout,
+        color_format: TextureFormat,
+    ) -> Self {
+        // Computing a generation reads the current cells and writes the next ones,
+        // drawing only reads the current cells
+        let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Game of Life bind group layout"),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::COMPUTE | ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Uint,
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: BindingType::StorageTexture {
+                        access: StorageTextureAccess::WriteOnly,
+                        format: TextureFormat::R32Uint,
+                        view_dimension: TextureViewDimension::D2,
+                    },
+                    count: None,
+                },
+            ],
+        });
+        let cells = PingPongTarget::new(
+            device,
+            &TextureDescriptor {
+                label: Some("Game of Life texture"),
+                size: grid_extent(),
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: TextureDimension::D2,
+                format: TextureFormat::R32Uint,
+                usage: TextureUsages::TEXTURE_BINDING
+                    | TextureUsages::STORAGE_BINDING
+                    | TextureUsages::COPY_DST,
+                view_formats: &[],
+            },
+            |current: &TextureView, next: &TextureView| {
+                device.create_bind_group(&BindGroupDescriptor {
+                    label: Some("Game of Life bind group"),
+                    layout: &bind_group_layout,
+                    entries: &[
+                        BindGroupEntry {
+                            binding: 0,
+                            resource: BindingResource::TextureView(current),
+                        },
+                        BindGroupEntry {
+                            binding: 1,
+                            resource: BindingResource::TextureView(next),
+                        },
+                    ],
+                })
+            },
+        );
+
+        let step_shader_module = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("Game of Life step shader module"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("./life_step.wgsl"))),
+        });
+        let step_pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Game of Life step pipeline layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let step_pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
+            label: Some("Game of Life step pipeline"),
+            layout: Some(&step_pipeline_layout),
+            module: &step_shader_module,
+            entry_point: Some("cs_step"),
+            compilation_options: PipelineCompilationOptions::default(),
+            cache: None,
+        });
+
+        let draw_shader_module = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("Game of Life draw shader module"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(concat!(
+                include_str!("./camera.wgsl"),
+                include_str!("./life.wgsl")
+            ))),
+        });
+        let draw_pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Game of Life draw pipeline layout"),
+            bind_group_layouts: &[camera_bind_group_layout, &bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let draw_pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("Game of Life draw pipeline"),
+            layout: Some(&draw_pipeline_layout),
+            vertex: VertexState {
+                module: &draw_shader_module,
+                entry_point: Some("vs_main"),
+                compilation_options: PipelineCompilationOptions::default(),
+                buffers: &[],
+            },
+            primitive: PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+            fragment: Some(FragmentState {
+                module: &draw_shader_module,
+                entry_point: Some("fs_main"),
+                compilation_options: PipelineCompilationOptions::default(),
+                targets: &[Some(ColorTargetState {
+                    format: color_format,
+                    blend: None,
+                    write_mask: ColorWrites::default(),
+                })],
+            }),
+            multiview: None,
+            cache: None,
+        });
+
+        let life = Self {
+            cells,
+            step_pipeline,
+            draw_pipeline,
+            pending_steps: 0,
+            seed: 0,
+        };
+        life.write_cells(queue);
+        life
+    }
+
+    /// Replaces the current generation with a new random grid.
+    pub fn reset(&mut self, queue: &Queue) {
+        self.seed += 1;
+        self.pending_steps = 0;
+        self.write_cells(queue);
+    }
+
+    /// Writes a random grid depending on the seed into the current generation.
+    fn write_cells(&self, queue: &Queue) {
+        queue.write_texture(
+            ImageCopyTexture {
+                texture: self.cells.current(),
+                mip_level: 0,
+                origin: Origin3d::ZERO,
+                aspect: TextureAspect::All,
+            },
+            bytemuck::cast_slice(&random_cells(self.seed)),
+            ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(GRID_SIZE[0] * size_of::<u32>() as u32),
+                rows_per_image: None,
+            },
+            grid_extent(),
+        );
+    }
+
+    /// Advances the simulation by one generation, which is computed in [`GameOfLife::render`].
+    pub fn tick(&mut self) {
+        self.pending_steps += 1;
+    }
+
+    /// Computes the pending generations, then draws the current one into the whole target.
+    pub fn render(
+        &mut self,
+        command_encoder: &mut CommandEncoder,
+        camera_bind_group: &BindGroup,
+        target: &TextureView,
+    ) {
+        if self.pending_steps > 0 {
+            let mut compute_pass = command_encoder.begin_compute_pass(&ComputePassDescriptor {
+                label: Some("Game of Life step pass"),
+                timestamp_writes: None,
+            });
+            compute_pass.set_pipeline(&self.step_pipeline);
+            for _ in 0..self.pending_steps {
+                compute_pass.set_bind_group(0, self.cells.bind_group(), &[]);
+                compute_pass.dispatch_workgroups(
+                    GRID_SIZE[0].div_ceil(WORKGROUP_SIZE),
+                    GRID_SIZE[1].div_ceil(WORKGROUP_SIZE),
+                    1,
+                );
+                self.cells.swap();
+            }
+            self.pending_steps = 0;
+        }
+
+        let mut render_pass = command_encoder.begin_render_pass(&RenderPassDescriptor {
+            label: Some("Game of Life draw pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: Operations {
+                    load: LoadOp::Clear(Color::BLACK),
+                    store: StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        render_pass.set_pipeline(&self.draw_pipeline);
+        render_pass.set_bind_group(0, camera_bind_group, &[]);
+        render_pass.set_bind_group(1, self.cells.bind_group(), &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
+
+fn grid_extent() -> Extent3d {
+    Extent3d {
+        width: GRID_SIZE[0],
+        height: GRID_SIZE[1],
+        depth_or_array_layers: 1,
+    }
+}
+
+/// Cells that are alive with a probability of [`SEED_DENSITY`], depending on the seed.
+fn random_cells(seed: u32) -> Vec<u32> {
+    (0..GRID_SIZE[0] * GRID_SIZE[1])
+        .map(|index| {
+            let mut hash = index.wrapping_mul(0x9e37_79b9) ^ seed.wrapping_mul(0x85eb_ca6b);
+            hash ^= hash >> 16;
+            hash = hash.wrapping_mul(0x7feb_352d);
+            hash ^= hash >> 15;
+            let random = (hash & 0xffff) as f32 / 0xffff as f32;
+            if random < SEED_DENSITY {
+                ALIVE
+            } else {
+                0
+            }
+        })
+        .collect()
+}
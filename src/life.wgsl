@@ -0,0 +1,39 @@
+// Draws the cells of the Game of Life, see `life_step.wgsl`, into the whole surface.
+// Expects `camera.wgsl` to be prepended, for the size of the surface.
+
+// Value of a living cell, see `life_step.wgsl`
+const alive: u32 = 255u;
+
+const alive_color: vec3<f32> = vec3<f32>(1.0, 0.85, 0.4);
+const trail_color: vec3<f32> = vec3<f32>(0.1, 0.3, 0.9);
+const background_color: vec3<f32> = vec3<f32>(0.01, 0.01, 0.02);
+const border_color: vec3<f32> = vec3<f32>(0.0);
+
+@group(1) @binding(0)
+var state: texture_2d<u32>;
+
+// One triangle covering the whole surface
+@vertex
+fn vs_main(@builtin(vertex_index) in_vertex_index: u32) -> @builtin(position) vec4<f32> {
+    let uv = vec2<f32>(f32((in_vertex_index << 1u) & 2u), f32(in_vertex_index & 2u));
+    return vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+}
+
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    // Square cells, as large as fit into the surface, with the grid centered
+    let size = vec2<f32>(textureDimensions(state));
+    let cell_size = min(camera.viewport.x / size.x, camera.viewport.y / size.y);
+    let origin = (camera.viewport.xy - size * cell_size) / 2.0;
+    let cell = (position.xy - origin) / cell_size;
+    if any(cell < vec2<f32>(0.0)) || any(cell >= size) {
+        return vec4<f32>(border_color, 1.0);
+    }
+
+    let value = textureLoad(state, vec2<i32>(cell), 0).r;
+    if value == alive {
+        return vec4<f32>(alive_color, 1.0);
+    }
+    let trail = f32(value) / f32(alive);
+    return vec4<f32>(mix(background_color, trail_color, trail * trail), 1.0);
+}
@@ -0,0 +1,46 @@
+// Computes the next generation of Conway's Game of Life on a wrapping grid, one texel per cell.
+//
+// Reads the current generation from one texture and writes the next one into the other,
+// which swap roles every generation ("ping-pong").
+//
+// A cell's value is `alive` while it lives. Dead cells fade out from there by `fade` per
+// generation, which leaves a trail behind moving patterns.
+
+const alive: u32 = 255u;
+const fade: u32 = 8u;
+
+@group(0) @binding(0)
+var state: texture_2d<u32>;
+@group(0) @binding(1)
+var next_state: texture_storage_2d<r32uint, write>;
+
+fn is_alive(cell: vec2<i32>, size: vec2<i32>) -> u32 {
+    // The grid wraps around at its edges
+    let wrapped = (cell + size) % size;
+    return u32(textureLoad(state, wrapped, 0).r == alive);
+}
+
+@compute @workgroup_size(8, 8, 1)
+fn cs_step(@builtin(global_invocation_id) id: vec3<u32>) {
+    let size = vec2<i32>(textureDimensions(state));
+    let cell = vec2<i32>(id.xy);
+    if any(cell >= size) {
+        return;
+    }
+
+    var neighbors = 0u;
+    for (var y = -1; y <= 1; y++) {
+        for (var x = -1; x <= 1; x++) {
+            if x != 0 || y != 0 {
+                neighbors += is_alive(cell + vec2<i32>(x, y), size);
+            }
+        }
+    }
+
+    let value = textureLoad(state, cell, 0).r;
+    var next = max(value, fade) - fade;
+    if neighbors == 3u || (neighbors == 2u && value == alive) {
+        next = alive;
+    }
+    textureStore(next_state, cell, vec4<u32>(next, 0u, 0u, 0u));
+}
@@ -0,0 +1,198 @@
+use std::borrow::Cow;
+
+use wgpu::{
+    BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
+    BindGroupLayoutEntry, BindingResource, BindingType, BlendState, ColorTargetState, ColorWrites,
+    Device, FragmentState, MultisampleState, PipelineCompilationOptions, PipelineLayout,
+    PipelineLayoutDescriptor, PrimitiveState, RenderPass, RenderPipeline, RenderPipelineDescriptor,
+    ShaderModule, ShaderModuleDescriptor, ShaderStages, TextureFormat, TextureSampleType,
+    TextureView, TextureViewDimension, VertexState,
+};
+
+use crate::{clusters::LightClusters, deferred::RenderPath};
+
+/// What the lighting debug visualization shows on top of the scene.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LightDebugView {
+    Off,
+    /// Heatmap of the point lights shaded per pixel: those binned into its cluster on the
+    /// Forward+ path, those whose radius reaches it on the other paths.
+    LightCount,
+    /// The clusters of the Forward+ path, tinted in a color each, and the outlines of their
+    /// screen space tiles, on every render path.
+    Clusters,
+}
+
+impl LightDebugView {
+    pub fn next(self) -> Self {
+        match self {
+            LightDebugView::Off => LightDebugView::LightCount,
+            LightDebugView::LightCount => LightDebugView::Clusters,
+            LightDebugView::Clusters => LightDebugView::Off,
+        }
+    }
+}
+
+/// Visualizations of the lighting, to diagnose the light culling.
+///
+/// Like the fog, they are drawn as a full-screen triangle reading the depth buffer, so that
+/// they cover the meshes and the ground alike, whichever render path shaded them.
+pub struct LightDebug {
+    pub view: LightDebugView,
+    light_count_pipeline: RenderPipeline,
+    cluster_light_count_pipeline: RenderPipeline,
+    cluster_pipeline: RenderPipeline,
+    depth_bind_group_layout: BindGroupLayout,
+    depth_bind_group: BindGroup,
+}
+
+impl LightDebug {
+    pub fn new(
+        device: &Device,
+        camera_bind_group_layout: &BindGroupLayout,
+        lights_bind_group_layout: &BindGroupLayout,
+        clusters_bind_group_layout: &BindGroupLayout,
+        color_format: TextureFormat,
+        depth_view: &TextureView,
+    ) -> Self {
+        let depth_bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Light debug depth bind group layout"),
+            entries: &[BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Texture {
+                    sample_type: TextureSampleType::Float { filterable: false },
+                    view_dimension: TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            }],
+        });
+
+        let shader_module = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("Light debug shader module"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(concat!(
+                include_str!("./camera.wgsl"),
+                include_str!("./lighting.wgsl"),
+                include_str!("./clusters.wgsl"),
+                include_str!("./light_debug.wgsl")
+            ))),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Light debug pipeline layout"),
+            bind_group_layouts: &[
+                camera_bind_group_layout,
+                lights_bind_group_layout,
+                &depth_bind_group_layout,
+                clusters_bind_group_layout,
+            ],
+            push_constant_ranges: &[],
+        });
+        let create_pipeline = |label, fragment_entry_point| {
+            create_pipeline(
+                device,
+                label,
+                &pipeline_layout,
+                &shader_module,
+                fragment_entry_point,
+                color_format,
+            )
+        };
+
+        Self {
+            view: LightDebugView::Off,
+            light_count_pipeline: create_pipeline("Light count pipeline", "fs_light_count"),
+            cluster_light_count_pipeline: create_pipeline(
+                "Cluster light count pipeline",
+                "fs_cluster_light_count",
+            ),
+            cluster_pipeline: create_pipeline("Cluster pipeline", "fs_clusters"),
+            depth_bind_group: create_bind_group(device, &depth_bind_group_layout, depth_view),
+            depth_bind_group_layout,
+        }
+    }
+
+    /// Binds the new depth buffer after the surface was resized.
+    pub fn resize(&mut self, device: &Device, depth_view: &TextureView) {
+        self.depth_bind_group =
+            create_bind_group(device, &self.depth_bind_group_layout, depth_view);
+    }
+
+    /// Draws the current view, if any, on top of the scene rendered along the render path.
+    ///
+    /// The pass must not use the depth buffer as attachment, as it is read here.
+    pub fn render(
+        &self,
+        render_pass: &mut RenderPass,
+        camera_bind_group: &BindGroup,
+        lights_bind_group: &BindGroup,
+        light_clusters: &LightClusters,
+        render_path: RenderPath,
+    ) {
+        let pipeline = match self.view {
+            LightDebugView::Off => return,
+            LightDebugView::LightCount if render_path == RenderPath::ForwardClustered => {
+                &self.cluster_light_count_pipeline
+            }
+            LightDebugView::LightCount => &self.light_count_pipeline,
+            LightDebugView::Clusters => &self.cluster_pipeline,
+        };
+        render_pass.set_pipeline(pipeline);
+        render_pass.set_bind_group(0, camera_bind_group, &[]);
+        render_pass.set_bind_group(1, lights_bind_group, &[]);
+        render_pass.set_bind_group(2, &self.depth_bind_group, &[]);
+        render_pass.set_bind_group(3, light_clusters.bind_group(), &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
+
+fn create_bind_group(
+    device: &Device,
+    layout: &BindGroupLayout,
+    depth_view: &TextureView,
+) -> BindGroup {
+    device.create_bind_group(&BindGroupDescriptor {
+        label: Some("Light debug depth bind group"),
+        layout,
+        entries: &[BindGroupEntry {
+            binding: 0,
+            resource: BindingResource::TextureView(depth_view),
+        }],
+    })
+}
+
+// Blends over the scene without depth state, as the depth buffer is read instead.
+fn create_pipeline(
+    device: &Device,
+    label: &str,
+    layout: &PipelineLayout,
+    module: &ShaderModule,
+    fragment_entry_point: &str,
+    color_format: TextureFormat,
+) -> RenderPipeline {
+    device.create_render_pipeline(&RenderPipelineDescriptor {
+        label: Some(label),
+        layout: Some(layout),
+        vertex: VertexState {
+            module,
+            entry_point: Some("vs_main"),
+            compilation_options: PipelineCompilationOptions::default(),
+            buffers: &[],
+        },
+        primitive: PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: MultisampleState::default(),
+        fragment: Some(FragmentState {
+            module,
+            entry_point: Some(fragment_entry_point),
+            compilation_options: PipelineCompilationOptions::default(),
+            targets: &[Some(ColorTargetState {
+                format: color_format,
+                blend: Some(BlendState::ALPHA_BLENDING),
+                write_mask: ColorWrites::default(),
+            })],
+        }),
+        multiview: None,
+        cache: None,
+    })
+}
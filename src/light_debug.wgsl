@@ -0,0 +1,105 @@
+// Visualizes the lighting of the scene on top of it with a single full-screen triangle,
+// see `LightDebug`.
+// Expects `camera.wgsl`, `lighting.wgsl` and `clusters.wgsl` to be prepended.
+
+@group(2) @binding(0)
+var depth_texture: texture_2d<f32>;
+@group(3) @binding(0)
+var<storage, read> clusters: Clusters;
+
+// Light count shown in red, fewer lights range from blue over green to yellow
+const heatmap_max: f32 = 32.0;
+// Opacity of the visualization over the scene
+const opacity: f32 = 0.6;
+
+@vertex
+fn vs_main(@builtin(vertex_index) in_vertex_index: u32) -> @builtin(position) vec4<f32> {
+    // A triangle covering the whole screen, its excess is clipped
+    let uv = vec2<f32>(f32((in_vertex_index << 1u) & 2u), f32(in_vertex_index & 2u));
+    return vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+}
+
+// Reconstructs the world space position of the opaque scene at a pixel,
+// returns false for the background, which has no position.
+fn scene_position(position: vec2<f32>, world_position: ptr<function, vec3<f32>>) -> bool {
+    let depth = textureLoad(depth_texture, vec2<i32>(position), 0).r;
+    if depth <= 0.0 {
+        return false;
+    }
+    let ndc = vec2<f32>(position.x, camera.viewport.y - position.y) * camera.viewport.zw * 2.0 - 1.0;
+    let world = camera.inverse_view_projection * vec4<f32>(ndc, depth, 1.0);
+    *world_position = world.xyz / world.w;
+    return true;
+}
+
+fn scene_cluster(position: vec2<f32>, world_position: vec3<f32>) -> u32 {
+    let forward = -vec3<f32>(camera.view[0].z, camera.view[1].z, camera.view[2].z);
+    return cluster_index(position, dot(world_position - camera.position.xyz, forward));
+}
+
+// Blue for no lights, over cyan, green and yellow to red for `heatmap_max` lights and more.
+fn heatmap(count: u32) -> vec4<f32> {
+    let t = clamp(f32(count) / heatmap_max, 0.0, 1.0);
+    let color = clamp(1.5 - abs(t * 4.0 - vec3<f32>(3.0, 2.0, 1.0)), vec3<f32>(0.0), vec3<f32>(1.0));
+    return vec4<f32>(color, opacity);
+}
+
+// Number of point lights whose radius reaches the pixel, which the forward and deferred
+// paths evaluate, skipping all others after a distance check.
+@fragment
+fn fs_light_count(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    var world_position: vec3<f32>;
+    if !scene_position(position.xy, &world_position) {
+        discard;
+    }
+    var count = 0u;
+    for (var i = 0u; i < light_list.count; i++) {
+        let light = light_list.lights[i];
+        if distance(light.position, world_position) < light.radius {
+            count++;
+        }
+    }
+    return heatmap(count);
+}
+
+// Number of point lights binned into the cluster of the pixel, which the Forward+ path
+// evaluates. Full clusters, which may have dropped lights, are shown in white.
+@fragment
+fn fs_cluster_light_count(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    var world_position: vec3<f32>;
+    if !scene_position(position.xy, &world_position) {
+        discard;
+    }
+    let cluster = scene_cluster(position.xy, world_position);
+    let count = clusters.data[cluster_start(cluster, clusters.capacity)];
+    if count >= clusters.capacity {
+        return vec4<f32>(1.0, 1.0, 1.0, opacity);
+    }
+    return heatmap(count);
+}
+
+// Tints every cluster in a color of its own and outlines the screen space tiles.
+@fragment
+fn fs_clusters(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let tile_size = camera.viewport.xy / vec2<f32>(cluster_grid.xy);
+    let in_tile = position.xy % tile_size;
+    if any(in_tile < vec2<f32>(1.0)) {
+        return vec4<f32>(0.0, 0.0, 0.0, opacity);
+    }
+    var world_position: vec3<f32>;
+    if !scene_position(position.xy, &world_position) {
+        discard;
+    }
+
+    // Neighboring clusters get unrelated hues, which makes the depth slices stand out
+    var hash = scene_cluster(position.xy, world_position) * 2654435761u;
+    hash = (hash ^ (hash >> 15u)) * 2246822519u;
+    hash ^= hash >> 13u;
+    let hue = f32(hash & 0xffffu) / 65535.0;
+    let color = clamp(
+        abs(fract(vec3<f32>(hue) + vec3<f32>(0.0, 2.0, 1.0) / 3.0) * 6.0 - 3.0) - 1.0,
+        vec3<f32>(0.0),
+        vec3<f32>(1.0),
+    );
+    return vec4<f32>(color, opacity * 0.6);
+}
@@ -0,0 +1,80 @@
+// Dynamic point lights and the shading model shared by the forward and deferred paths.
+// Expects `camera.wgsl` to be prepended.
+
+struct PointLight {
+    position: vec3<f32>,
+    // Distance at which the light's contribution has faded out completely
+    radius: f32,
+    color: vec3<f32>,
+    intensity: f32,
+}
+
+struct LightList {
+    count: u32,
+    lights: array<PointLight>,
+}
+
+@group(1) @binding(0)
+var<storage, read> light_list: LightList;
+
+// Matches the sun of the sky in `sky.wgsl`
+const sun_direction: vec3<f32> = vec3<f32>(0.48, 0.6, -0.64);
+const sun_color: vec3<f32> = vec3<f32>(1.0, 0.95, 0.85);
+const sun_intensity: f32 = 0.6;
+const ambient: vec3<f32> = vec3<f32>(0.08, 0.1, 0.13);
+
+struct Surface {
+    position: vec3<f32>,
+    normal: vec3<f32>,
+    albedo: vec3<f32>,
+    roughness: f32,
+    metallic: f32,
+}
+
+// Inverse square falloff, windowed to reach zero at the light's radius,
+// as in "Real Shading in Unreal Engine 4" by Brian Karis.
+fn attenuation(distance: f32, radius: f32) -> f32 {
+    let ratio = distance / radius;
+    let window = clamp(1.0 - ratio * ratio * ratio * ratio, 0.0, 1.0);
+    return window * window / (distance * distance + 1.0);
+}
+
+// Blinn-Phong shading, with the specular exponent derived from the roughness
+// and the specular color tinted by the albedo of metals.
+fn shade_light(surface: Surface, to_light: vec3<f32>, radiance: vec3<f32>) -> vec3<f32> {
+    let view = normalize(camera.position.xyz - surface.position);
+    let half_vector = normalize(to_light + view);
+    let n_dot_l = max(dot(surface.normal, to_light), 0.0);
+
+    let shininess = exp2(12.0 * (1.0 - surface.roughness) + 1.0);
+    let specular_color = mix(vec3<f32>(0.04), surface.albedo, surface.metallic);
+    let specular = specular_color * pow(max(dot(surface.normal, half_vector), 0.0), shininess)
+        * (shininess + 8.0) / 8.0;
+    let diffuse = surface.albedo * (1.0 - surface.metallic);
+    return (diffuse + specular) * radiance * n_dot_l;
+}
+
+fn shade_point_light(surface: Surface, light: PointLight) -> vec3<f32> {
+    let offset = light.position - surface.position;
+    let distance = length(offset);
+    if distance >= light.radius {
+        return vec3<f32>(0.0);
+    }
+    let radiance = light.color * light.intensity * attenuation(distance, light.radius);
+    return shade_light(surface, offset / distance, radiance);
+}
+
+// Shades a surface with the sun and ambient light.
+fn shade_environment(surface: Surface) -> vec3<f32> {
+    return ambient * surface.albedo
+        + shade_light(surface, normalize(sun_direction), sun_color * sun_intensity);
+}
+
+// Shades a surface with all point lights.
+fn shade_point_lights(surface: Surface) -> vec3<f32> {
+    var color = vec3<f32>(0.0);
+    for (var i = 0u; i < light_list.count; i++) {
+        color += shade_point_light(surface, light_list.lights[i]);
+    }
+    return color;
+}
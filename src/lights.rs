@@ -0,0 +1,142 @@
+use cgmath::Point3;
+use wgpu::{
+    BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
+    BindGroupLayoutEntry, BindingType, Buffer, BufferAddress, BufferBindingType, BufferDescriptor,
+    BufferUsages, Device, Queue, ShaderStages,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PointLight {
+    pub position: Point3<f32>,
+    /// Linear RGB color, multiplied with the intensity.
+    pub color: [f32; 3],
+    pub intensity: f32,
+    /// Distance at which the light's contribution has faded out completely.
+    pub radius: f32,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct GpuPointLight {
+    position: [f32; 3],
+    radius: f32,
+    color: [f32; 3],
+    intensity: f32,
+}
+
+// Matches the alignment of the light array in `LightList`, see `lighting.wgsl`
+const HEADER_SIZE: BufferAddress = 16;
+
+/// The dynamic point lights of a frame, in a storage buffer bound as `light_list` of `lighting.wgsl`.
+pub struct Lights {
+    bind_group_layout: BindGroupLayout,
+    bind_group: BindGroup,
+    buffer: Buffer,
+    /// Most lights the buffer can hold within the storage buffer binding size of the device.
+    max_capacity: BufferAddress,
+    lights: Vec<PointLight>,
+}
+
+impl Lights {
+    const INITIAL_CAPACITY: BufferAddress = 64;
+
+    pub fn new(device: &Device) -> Self {
+        let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Lights bind group layout"),
+            entries: &[BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStages::FRAGMENT | ShaderStages::COMPUTE,
+                ty: BindingType::Buffer {
+                    ty: BufferBindingType::Storage { read_only: true },
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+        let buffer = create_buffer(device, Self::INITIAL_CAPACITY);
+        let bind_group = create_bind_group(device, &bind_group_layout, &buffer);
+        let max_capacity = (BufferAddress::from(device.limits().max_storage_buffer_binding_size)
+            - HEADER_SIZE)
+            / size_of::<GpuPointLight>() as BufferAddress;
+        Self {
+            bind_group_layout,
+            bind_group,
+            buffer,
+            max_capacity,
+            lights: Vec::new(),
+        }
+    }
+
+    pub fn bind_group_layout(&self) -> &BindGroupLayout {
+        &self.bind_group_layout
+    }
+
+    pub fn bind_group(&self) -> &BindGroup {
+        &self.bind_group
+    }
+
+    pub fn add(&mut self, light: PointLight) {
+        self.lights.push(light);
+    }
+
+    /// Uploads the lights added since the previous call.
+    ///
+    /// Lights beyond what fits into a storage buffer binding of the device are dropped.
+    pub fn prepare(&mut self, device: &Device, queue: &Queue) {
+        if self.lights.len() as u64 > self.max_capacity {
+            log::warn!(
+                "Dropping {} lights exceeding the storage buffer binding size",
+                self.lights.len() as u64 - self.max_capacity
+            );
+            self.lights.truncate(self.max_capacity as usize);
+        }
+        let capacity = (self.buffer.size() - HEADER_SIZE) / size_of::<GpuPointLight>() as u64;
+        if self.lights.len() as u64 > capacity {
+            let capacity = (self.lights.len() as u64)
+                .next_power_of_two()
+                .min(self.max_capacity);
+            self.buffer = create_buffer(device, capacity);
+            self.bind_group = create_bind_group(device, &self.bind_group_layout, &self.buffer);
+        }
+
+        let gpu_lights: Vec<GpuPointLight> = self
+            .lights
+            .drain(..)
+            .map(|light| GpuPointLight {
+                position: light.position.into(),
+                radius: light.radius,
+                color: light.color,
+                intensity: light.intensity,
+            })
+            .collect();
+        queue.write_buffer(
+            &self.buffer,
+            0,
+            bytemuck::cast_slice(&[gpu_lights.len() as u32, 0, 0, 0]),
+        );
+        if !gpu_lights.is_empty() {
+            queue.write_buffer(&self.buffer, HEADER_SIZE, bytemuck::cast_slice(&gpu_lights));
+        }
+    }
+}
+
+fn create_buffer(device: &Device, capacity: BufferAddress) -> Buffer {
+    device.create_buffer(&BufferDescriptor {
+        label: Some("Light buffer"),
+        size: HEADER_SIZE + capacity * size_of::<GpuPointLight>() as BufferAddress,
+        usage: BufferUsages::STORAGE | BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+fn create_bind_group(device: &Device, layout: &BindGroupLayout, buffer: &Buffer) -> BindGroup {
+    device.create_bind_group(&BindGroupDescriptor {
+        label: Some("Lights bind group"),
+        layout,
+        entries: &[BindGroupEntry {
+            binding: 0,
+            resource: buffer.as_entire_binding(),
+        }],
+    })
+}
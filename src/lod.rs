@@ -0,0 +1,95 @@
+use cgmath::{InnerSpace, Matrix4, MetricSpace, Point3, Rad};
+use wgpu::Device;
+
+use crate::{
+    camera::Camera,
+    meshes::{MeshData, MeshId, MeshRenderer},
+};
+
+/// Screen coverage below which each level of detail but the coarsest gives way to the next one.
+///
+/// Coverage is the diameter of the bounding sphere as a fraction of the screen's height.
+const COVERAGE_THRESHOLDS: [f32; 2] = [0.3, 0.12];
+/// Width of the band of coverage above each threshold over which two levels are cross-faded,
+/// relative to the threshold.
+const CROSS_FADE_BAND: f32 = 0.3;
+/// Cell size of the vertex clustering of the first decimated level, relative to the bounding
+/// radius. Each further level doubles it.
+const BASE_CELL_SIZE: f32 = 0.15;
+
+/// A mesh uploaded in decreasing levels of detail, generated by decimating the original.
+///
+/// The level drawn for an object is chosen by how much of the screen its bounding sphere covers.
+/// Near the switch between two levels, both can be drawn with complementary dither patterns,
+/// cross-fading them instead of popping from one to the other.
+pub struct LodMesh {
+    /// From finest to coarsest.
+    levels: Vec<MeshId>,
+    /// Radius of the bounding sphere around the mesh's origin.
+    radius: f32,
+}
+
+impl LodMesh {
+    pub fn new(device: &Device, meshes: &mut MeshRenderer, data: &MeshData) -> Self {
+        let radius = data
+            .vertices
+            .iter()
+            .map(|vertex| Point3::from(vertex.position).distance(Point3::new(0.0, 0.0, 0.0)))
+            .fold(0.0, f32::max);
+
+        let mut levels = vec![meshes.add_mesh(device, data)];
+        let mut triangles = vec![data.indices.len() / 3];
+        let mut cell_size = BASE_CELL_SIZE * radius;
+        for _ in COVERAGE_THRESHOLDS {
+            let level = data.decimate(cell_size);
+            levels.push(meshes.add_mesh(device, &level));
+            triangles.push(level.indices.len() / 3);
+            cell_size *= 2.0;
+        }
+        log::info!("Levels of detail with {triangles:?} triangles");
+
+        Self { levels, radius }
+    }
+
+    /// The most detailed level.
+    pub fn finest(&self) -> MeshId {
+        self.levels[0]
+    }
+
+    /// Returns the levels to draw with the given transform, consisting of translation,
+    /// rotation and uniform scale, along with the range of dither thresholds kept for each.
+    pub fn select(
+        &self,
+        transform: Matrix4<f32>,
+        camera: &Camera,
+        cross_fade: bool,
+    ) -> impl Iterator<Item = (MeshId, [f32; 2])> {
+        let radius = self.radius * transform.x.truncate().magnitude();
+        let distance = Point3::from_homogeneous(transform.w).distance(camera.eye);
+        let half_height = distance * (Rad::from(camera.fovy) / 2.0).0.tan();
+        let coverage = if distance > radius {
+            radius / half_height
+        } else {
+            f32::INFINITY
+        };
+
+        let level = COVERAGE_THRESHOLDS
+            .iter()
+            .take_while(|&&threshold| coverage < threshold)
+            .count();
+        // Just above the threshold to the next level, the next level fades in as this one fades out
+        let fade = COVERAGE_THRESHOLDS
+            .get(level)
+            .map(|&threshold| (coverage - threshold) / (threshold * CROSS_FADE_BAND))
+            .filter(|&fade| cross_fade && fade < 1.0);
+        match fade {
+            Some(fade) => [
+                Some((self.levels[level], [0.0, fade])),
+                Some((self.levels[level + 1], [fade, 1.0])),
+            ],
+            None => [Some((self.levels[level], [0.0, 1.0])), None],
+        }
+        .into_iter()
+        .flatten()
+    }
+}
@@ -2,6 +2,7 @@ use std::sync::Arc;
 
 use application::Application;
 use color_eyre::Result;
+use options::Options;
 use winit::{
     application::ApplicationHandler,
     dpi::LogicalSize,
@@ -11,6 +12,52 @@ use winit::{
 };
 
 mod application;
+mod benchmark;
+mod billboards;
+mod camera;
+mod camera_controller;
+mod clock;
+mod clusters;
+mod color_grading;
+mod decals;
+mod deferred;
+mod environment;
+mod fog;
+mod fractal;
+mod frame_pacing;
+mod globals;
+mod gpu_timer;
+mod ground;
+#[cfg(not(target_arch = "wasm32"))]
+mod hot_reload;
+mod hud;
+mod input;
+mod life;
+mod light_debug;
+mod lights;
+mod lod;
+mod meshes;
+mod oit;
+mod options;
+mod outline;
+mod overlay;
+mod paintings;
+mod ping_pong;
+mod primitives;
+mod probes;
+mod raymarch;
+mod reflections;
+mod render_scale;
+mod scene;
+mod shadertoy;
+mod shapes;
+mod skinning;
+mod ssr;
+mod streaming;
+mod surface;
+mod terrain;
+mod text;
+mod water;
 
 fn main() -> Result<()> {
     #[cfg(not(target_arch = "wasm32"))]
@@ -21,9 +68,11 @@ fn main() -> Result<()> {
         std::panic::set_hook(Box::new(console_error_panic_hook::hook));
     }
 
+    let options = Options::from_args()?;
+
     let event_loop = EventLoop::with_user_event().build()?;
 
-    let mut app = ApplicationWindow::new(&event_loop);
+    let mut app = ApplicationWindow::new(&event_loop, options);
     event_loop.run_app(&mut app)?;
 
     Ok(())
@@ -38,15 +87,17 @@ pub struct ApplicationWindow {
     window: Option<Arc<Window>>,
     close_requested: bool,
     event_proxy: EventLoopProxy<UserEvent>,
+    options: Options,
 }
 
 impl ApplicationWindow {
-    pub fn new(event_loop: &EventLoop<UserEvent>) -> Self {
+    pub fn new(event_loop: &EventLoop<UserEvent>, options: Options) -> Self {
         Self {
             window: None,
             app: None,
             close_requested: false,
             event_proxy: event_loop.create_proxy(),
+            options,
         }
     }
 }
@@ -55,10 +106,11 @@ async fn create_application(
     window: Arc<Window>,
     size: LogicalSize<u32>,
     event_proxy: EventLoopProxy<UserEvent>,
+    options: Options,
 ) {
     let size = size.to_physical(window.scale_factor());
     log::info!("Initial size: {}x{}", size.width, size.height);
-    let app = Application::new(window, size)
+    let app = Application::new(window, size, &options)
         .await
         .expect("creation of application failed");
     event_proxy
@@ -122,10 +174,11 @@ impl ApplicationHandler<UserEvent> for ApplicationWindow {
         };
 
         let event_proxy = self.event_proxy.clone();
+        let options = self.options.clone();
         #[cfg(not(target_arch = "wasm32"))]
-        futures::executor::block_on(create_application(window, size, event_proxy));
+        futures::executor::block_on(create_application(window, size, event_proxy, options));
         #[cfg(target_arch = "wasm32")]
-        wasm_bindgen_futures::spawn_local(create_application(window, size, event_proxy));
+        wasm_bindgen_futures::spawn_local(create_application(window, size, event_proxy, options));
     }
 
     fn user_event(&mut self, _event_loop: &winit::event_loop::ActiveEventLoop, event: UserEvent) {
@@ -145,7 +198,7 @@ impl ApplicationHandler<UserEvent> for ApplicationWindow {
         let (Some(app), Some(window)) = (&mut self.app, &self.window) else {
             return;
         };
-        if app.handle_event(&window, &event) {
+        if app.handle_event(window, &event) {
             return;
         }
 
@@ -155,7 +208,7 @@ impl ApplicationHandler<UserEvent> for ApplicationWindow {
                 self.close_requested = true;
             }
             WindowEvent::RedrawRequested => {
-                if let Err(e) = app.render(&window) {
+                if let Err(e) = app.render(window) {
                     if e == wgpu::SurfaceError::Outdated {
                         let size = window.inner_size();
                         app.resize(size.width, size.height);
@@ -173,6 +226,11 @@ impl ApplicationHandler<UserEvent> for ApplicationWindow {
             event_loop.exit();
             return;
         }
+        if let Some(report) = self.app.as_mut().and_then(Application::benchmark_report) {
+            println!("{}", report.to_json());
+            event_loop.exit();
+            return;
+        }
         if let Some(window) = &self.window {
             window.request_redraw();
         }
@@ -0,0 +1,922 @@
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    f32::consts::{PI, TAU},
+    ops::Range,
+};
+
+use cgmath::{InnerSpace, Matrix, Matrix3, Matrix4, Point3, SquareMatrix, Vector3, Zero};
+use wgpu::{
+    util::{BufferInitDescriptor, DeviceExt},
+    BindGroup, BindGroupLayout, BlendState, Buffer, BufferAddress, BufferDescriptor, BufferUsages,
+    ColorTargetState, ColorWrites, CompareFunction, DepthStencilState, Device, Face, FragmentState,
+    IndexFormat, MultisampleState, PipelineCompilationOptions, PipelineLayout,
+    PipelineLayoutDescriptor, PrimitiveState, Queue, RenderPass, RenderPipeline,
+    RenderPipelineDescriptor, ShaderModule, ShaderModuleDescriptor, TextureFormat,
+    VertexBufferLayout, VertexState, VertexStepMode,
+};
+
+use crate::{
+    camera::Camera,
+    clusters::LightClusters,
+    deferred::DeferredLighting,
+    lod::LodMesh,
+    oit::{Transparency, WeightedBlendedOit},
+    outline::SelectionOutline,
+};
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct MeshVertex {
+    pub position: [f32; 3],
+    pub normal: [f32; 3],
+}
+
+impl MeshVertex {
+    const ATTRIBUTES: [wgpu::VertexAttribute; 2] =
+        wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x3];
+
+    pub fn layout() -> VertexBufferLayout<'static> {
+        VertexBufferLayout {
+            array_stride: size_of::<Self>() as BufferAddress,
+            step_mode: VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+}
+
+/// Indexed triangle list geometry on the CPU, see [`MeshRenderer::add_mesh`].
+#[derive(Debug, Clone, Default)]
+pub struct MeshData {
+    pub vertices: Vec<MeshVertex>,
+    pub indices: Vec<u32>,
+}
+
+impl MeshData {
+    /// A cube with side length 1 around the origin, with flat shaded faces.
+    pub fn cube() -> Self {
+        let mut mesh = Self::default();
+        for axis in 0..3 {
+            for sign in [-1.0, 1.0] {
+                let mut normal = [0.0; 3];
+                normal[axis] = sign;
+                let normal = Vector3::from(normal);
+                // Two axes spanning the face, ordered for counter-clockwise winding
+                let u = Vector3::new(normal.y, normal.z, normal.x);
+                let v = normal.cross(u);
+
+                let first = mesh.vertices.len() as u32;
+                for (a, b) in [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)] {
+                    let position = (normal + u * a + v * b) * 0.5;
+                    mesh.vertices.push(MeshVertex {
+                        position: position.into(),
+                        normal: normal.into(),
+                    });
+                }
+                mesh.indices
+                    .extend([0, 1, 2, 0, 2, 3].map(|index| first + index));
+            }
+        }
+        mesh
+    }
+
+    /// A sphere with radius 1 around the origin, tessellated along latitude and longitude.
+    pub fn sphere(segments: u32, rings: u32) -> Self {
+        let mut mesh = Self::default();
+        for ring in 0..=rings {
+            let polar = PI * ring as f32 / rings as f32;
+            for segment in 0..=segments {
+                let azimuth = TAU * segment as f32 / segments as f32;
+                let normal = [
+                    polar.sin() * azimuth.cos(),
+                    polar.cos(),
+                    -polar.sin() * azimuth.sin(),
+                ];
+                mesh.vertices.push(MeshVertex {
+                    position: normal,
+                    normal,
+                });
+            }
+        }
+        let stride = segments + 1;
+        for ring in 0..rings {
+            for segment in 0..segments {
+                let top = ring * stride + segment;
+                let bottom = top + stride;
+                mesh.indices
+                    .extend([top, bottom, top + 1, top + 1, bottom, bottom + 1]);
+            }
+        }
+        mesh
+    }
+
+    /// Merges copies of meshes into a single mesh, baking the transform of each copy into its
+    /// vertices ("static batching").
+    ///
+    /// Static objects sharing a material are then drawn with a single draw call, without
+    /// submitting an instance for each of them every frame. The copies must share a material,
+    /// [`MeshData::batch_by_material`] groups them by it first.
+    ///
+    /// Transforms that mirror a copy reverse the winding of its triangles, so the triangles of
+    /// such copies are flipped back to stay front facing.
+    pub fn batch<'a>(copies: impl IntoIterator<Item = (&'a MeshData, Matrix4<f32>)>) -> Self {
+        let mut batch = Self::default();
+        for (mesh, transform) in copies {
+            // Normals are transformed by the inverse transpose, which keeps them perpendicular
+            // to the surface under non-uniform scale
+            let linear = Matrix3::from_cols(
+                transform.x.truncate(),
+                transform.y.truncate(),
+                transform.z.truncate(),
+            );
+            let normal_matrix = linear.invert().unwrap_or(linear).transpose();
+            let first = batch.vertices.len() as u32;
+            batch.vertices.extend(mesh.vertices.iter().map(|vertex| {
+                MeshVertex {
+                    position: (transform * Vector3::from(vertex.position).extend(1.0))
+                        .truncate()
+                        .into(),
+                    normal: (normal_matrix * Vector3::from(vertex.normal))
+                        .normalize()
+                        .into(),
+                }
+            }));
+            let mirrored = linear.determinant() < 0.0;
+            batch.indices.extend(
+                mesh.indices
+                    .chunks_exact(3)
+                    .flat_map(|triangle| {
+                        if mirrored {
+                            [triangle[0], triangle[2], triangle[1]]
+                        } else {
+                            [triangle[0], triangle[1], triangle[2]]
+                        }
+                    })
+                    .map(|index| first + index),
+            );
+        }
+        batch
+    }
+
+    /// Merges static objects into a mesh per material, see [`MeshData::batch`], in the order
+    /// each material first appears.
+    pub fn batch_by_material<'a>(
+        objects: impl IntoIterator<Item = (&'a MeshData, Matrix4<f32>, Material)>,
+    ) -> Vec<(Material, Self)> {
+        let mut groups: Vec<(Material, Vec<_>)> = Vec::new();
+        for (mesh, transform, material) in objects {
+            match groups.iter_mut().find(|(other, _)| *other == material) {
+                Some((_, copies)) => copies.push((mesh, transform)),
+                None => groups.push((material, vec![(mesh, transform)])),
+            }
+        }
+        groups
+            .into_iter()
+            .map(|(material, copies)| (material, Self::batch(copies)))
+            .collect()
+    }
+
+    /// Simplifies the mesh by clustering its vertices: all vertices within the same cell of a grid
+    /// are merged into one at their average position, and triangles that collapse are dropped.
+    ///
+    /// Normals are averaged as well, so hard edges are smoothed once they are merged.
+    pub fn decimate(&self, cell_size: f32) -> Self {
+        let mut cells = HashMap::new();
+        let mut sums: Vec<(Vector3<f32>, Vector3<f32>, f32)> = Vec::new();
+        let remap: Vec<u32> = self
+            .vertices
+            .iter()
+            .map(|vertex| {
+                let cell = vertex.position.map(|x| (x / cell_size).floor() as i32);
+                let index = *cells.entry(cell).or_insert_with(|| {
+                    sums.push((Vector3::zero(), Vector3::zero(), 0.0));
+                    sums.len() as u32 - 1
+                });
+                let sum = &mut sums[index as usize];
+                sum.0 += Vector3::from(vertex.position);
+                sum.1 += Vector3::from(vertex.normal);
+                sum.2 += 1.0;
+                index
+            })
+            .collect();
+
+        let vertices = sums
+            .into_iter()
+            .map(|(position, normal, count)| MeshVertex {
+                position: (position / count).into(),
+                normal: normal.normalize().into(),
+            })
+            .collect();
+        let indices = self
+            .indices
+            .chunks_exact(3)
+            .map(|triangle| [0, 1, 2].map(|corner| remap[triangle[corner] as usize]))
+            .filter(|[a, b, c]| a != b && b != c && c != a)
+            .flatten()
+            .collect();
+        Self { vertices, indices }
+    }
+}
+
+/// Surface parameters of a lit mesh.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Material {
+    /// Linear RGB color.
+    pub albedo: [f32; 3],
+    /// From 0 (mirror like) to 1 (completely diffuse).
+    pub roughness: f32,
+    /// From 0 (dielectric) to 1 (metal).
+    pub metallic: f32,
+    /// From 0 (invisible) to 1 (opaque).
+    pub opacity: f32,
+    /// How the mesh is blended with what is behind it, unless it is opaque.
+    pub transparency: Transparency,
+}
+
+/// Identifies a mesh added to a [`MeshRenderer`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MeshId(usize);
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct MeshInstance {
+    model: [[f32; 4]; 4],
+    albedo: [f32; 3],
+    roughness: f32,
+    metallic: f32,
+    dither: [f32; 2],
+    opacity: f32,
+}
+
+impl MeshInstance {
+    const ATTRIBUTES: [wgpu::VertexAttribute; 9] = wgpu::vertex_attr_array![
+        2 => Float32x4,
+        3 => Float32x4,
+        4 => Float32x4,
+        5 => Float32x4,
+        6 => Float32x3,
+        7 => Float32,
+        8 => Float32,
+        9 => Float32x2,
+        10 => Float32,
+    ];
+
+    fn layout() -> VertexBufferLayout<'static> {
+        VertexBufferLayout {
+            array_stride: size_of::<Self>() as BufferAddress,
+            step_mode: VertexStepMode::Instance,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+}
+
+struct Mesh {
+    vertex_buffer: Buffer,
+    index_buffer: Buffer,
+    index_count: u32,
+    /// Instances submitted for the current frame.
+    instances: Vec<MeshInstance>,
+    /// Range of the instances in the instance buffer once prepared.
+    instance_range: Range<u32>,
+    /// Transparent instances submitted for the current frame, blended without sorting.
+    oit_instances: Vec<MeshInstance>,
+    /// Range of the order-independent instances in the instance buffer once prepared.
+    oit_range: Range<u32>,
+    /// Selected instances submitted for the current frame, drawn into the selection mask only.
+    selected: Vec<MeshInstance>,
+    /// Range of the selected instances in the instance buffer once prepared.
+    selected_range: Range<u32>,
+}
+
+/// Draws instances of lit triangle meshes.
+///
+/// The forward path shades opaque instances directly with all lights, the Forward+ path only
+/// with the lights binned into their cluster by [`LightClusters`], and the deferred path writes
+/// their surface attributes into the G-buffer of [`DeferredLighting`].
+/// Selected instances are also drawn into the mask of [`SelectionOutline`].
+///
+/// Instances whose material isn't opaque are shaded with all lights in any render path, but
+/// after the opaque scene, depending on their [`Transparency`]: either sorted back to front and
+/// blended onto the scene by [`MeshRenderer::render_sorted`], or drawn into the accumulation
+/// pass of [`WeightedBlendedOit`] by [`MeshRenderer::render_oit`]. They are not reflected.
+///
+/// The forward paths may draw the meshes' depth in a prepass first, so that their shading
+/// pipelines only test for equal depth and shade every pixel once, the nearest fragment.
+pub struct MeshRenderer {
+    /// Whether [`MeshRenderer::draw_lod`] cross-fades between levels of detail
+    /// rather than switching them abruptly.
+    pub lod_cross_fade: bool,
+    depth_pipeline: RenderPipeline,
+    forward_pipeline: RenderPipeline,
+    forward_equal_pipeline: RenderPipeline,
+    clustered_pipeline: RenderPipeline,
+    clustered_equal_pipeline: RenderPipeline,
+    gbuffer_pipeline: RenderPipeline,
+    mask_pipeline: RenderPipeline,
+    sorted_pipeline: RenderPipeline,
+    oit_pipeline: RenderPipeline,
+    meshes: Vec<Mesh>,
+    /// Sorted transparent instances submitted for the current frame, with their mesh.
+    sorted: Vec<(MeshId, MeshInstance)>,
+    /// Meshes of the sorted transparent instances once prepared, in the order of their instances.
+    sorted_meshes: Vec<MeshId>,
+    /// Index of the first sorted transparent instance in the instance buffer once prepared.
+    sorted_start: u32,
+    instance_buffer: Buffer,
+}
+
+impl MeshRenderer {
+    const INITIAL_CAPACITY: BufferAddress = 64 * size_of::<MeshInstance>() as BufferAddress;
+
+    pub fn new(
+        device: &Device,
+        camera_bind_group_layout: &BindGroupLayout,
+        lights_bind_group_layout: &BindGroupLayout,
+        probes_bind_group_layout: &BindGroupLayout,
+        clusters_bind_group_layout: &BindGroupLayout,
+        color_format: TextureFormat,
+        depth_format: TextureFormat,
+    ) -> Self {
+        let shader_module = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("Mesh shader module"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(concat!(
+                include_str!("./camera.wgsl"),
+                include_str!("./lighting.wgsl"),
+                include_str!("./probes.wgsl"),
+                include_str!("./clusters.wgsl"),
+                include_str!("./oit.wgsl"),
+                include_str!("./meshes.wgsl")
+            ))),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Mesh pipeline layout"),
+            bind_group_layouts: &[
+                camera_bind_group_layout,
+                lights_bind_group_layout,
+                probes_bind_group_layout,
+            ],
+            push_constant_ranges: &[],
+        });
+        let gbuffer_pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Mesh G-buffer pipeline layout"),
+            bind_group_layouts: &[camera_bind_group_layout, lights_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let camera_pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Mesh camera pipeline layout"),
+            bind_group_layouts: &[camera_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let clustered_pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Mesh clustered pipeline layout"),
+            bind_group_layouts: &[
+                camera_bind_group_layout,
+                lights_bind_group_layout,
+                probes_bind_group_layout,
+                clusters_bind_group_layout,
+            ],
+            push_constant_ranges: &[],
+        });
+        let color_targets = [Some(ColorTargetState {
+            format: color_format,
+            blend: None,
+            write_mask: ColorWrites::default(),
+        })];
+        let depth_pipeline = create_pipeline(
+            device,
+            "Mesh depth pipeline",
+            &camera_pipeline_layout,
+            &shader_module,
+            "fs_depth",
+            &[],
+            Some(depth_stencil(depth_format, CompareFunction::GreaterEqual)),
+        );
+        let forward_pipeline = create_pipeline(
+            device,
+            "Mesh forward pipeline",
+            &pipeline_layout,
+            &shader_module,
+            "fs_forward",
+            &color_targets,
+            Some(depth_stencil(depth_format, CompareFunction::GreaterEqual)),
+        );
+        let forward_equal_pipeline = create_pipeline(
+            device,
+            "Mesh forward depth equal pipeline",
+            &pipeline_layout,
+            &shader_module,
+            "fs_forward",
+            &color_targets,
+            Some(depth_stencil(depth_format, CompareFunction::Equal)),
+        );
+        let clustered_pipeline = create_pipeline(
+            device,
+            "Mesh clustered pipeline",
+            &clustered_pipeline_layout,
+            &shader_module,
+            "fs_forward_clustered",
+            &color_targets,
+            Some(depth_stencil(depth_format, CompareFunction::GreaterEqual)),
+        );
+        let clustered_equal_pipeline = create_pipeline(
+            device,
+            "Mesh clustered depth equal pipeline",
+            &clustered_pipeline_layout,
+            &shader_module,
+            "fs_forward_clustered",
+            &color_targets,
+            Some(depth_stencil(depth_format, CompareFunction::Equal)),
+        );
+        let gbuffer_pipeline = create_pipeline(
+            device,
+            "Mesh G-buffer pipeline",
+            &gbuffer_pipeline_layout,
+            &shader_module,
+            "fs_gbuffer",
+            &DeferredLighting::color_targets(),
+            Some(depth_stencil(depth_format, CompareFunction::GreaterEqual)),
+        );
+        // Selected meshes are outlined even where they are hidden
+        let mask_pipeline = create_pipeline(
+            device,
+            "Mesh mask pipeline",
+            &camera_pipeline_layout,
+            &shader_module,
+            "fs_mask",
+            &[Some(ColorTargetState {
+                format: SelectionOutline::MASK_FORMAT,
+                blend: None,
+                write_mask: ColorWrites::default(),
+            })],
+            None,
+        );
+        // Transparent meshes are tested against the opaque scene's depth, but don't write it
+        let transparent_depth_stencil = DepthStencilState {
+            depth_write_enabled: false,
+            ..depth_stencil(depth_format, CompareFunction::GreaterEqual)
+        };
+        let sorted_pipeline = create_pipeline(
+            device,
+            "Mesh sorted transparency pipeline",
+            &pipeline_layout,
+            &shader_module,
+            "fs_transparent",
+            &[Some(ColorTargetState {
+                format: color_format,
+                blend: Some(BlendState::ALPHA_BLENDING),
+                write_mask: ColorWrites::default(),
+            })],
+            Some(transparent_depth_stencil.clone()),
+        );
+        let oit_pipeline = create_pipeline(
+            device,
+            "Mesh OIT pipeline",
+            &pipeline_layout,
+            &shader_module,
+            "fs_oit",
+            &WeightedBlendedOit::color_targets(),
+            Some(transparent_depth_stencil),
+        );
+
+        Self {
+            lod_cross_fade: true,
+            depth_pipeline,
+            forward_pipeline,
+            forward_equal_pipeline,
+            clustered_pipeline,
+            clustered_equal_pipeline,
+            gbuffer_pipeline,
+            mask_pipeline,
+            sorted_pipeline,
+            oit_pipeline,
+            meshes: Vec::new(),
+            sorted: Vec::new(),
+            sorted_meshes: Vec::new(),
+            sorted_start: 0,
+            instance_buffer: create_instance_buffer(device, Self::INITIAL_CAPACITY),
+        }
+    }
+
+    /// Uploads a mesh, which can then be drawn any number of times per frame.
+    pub fn add_mesh(&mut self, device: &Device, data: &MeshData) -> MeshId {
+        let vertex_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Mesh vertex buffer"),
+            contents: bytemuck::cast_slice(&data.vertices),
+            usage: BufferUsages::VERTEX,
+        });
+        let index_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Mesh index buffer"),
+            contents: bytemuck::cast_slice(&data.indices),
+            usage: BufferUsages::INDEX,
+        });
+        self.meshes.push(Mesh {
+            vertex_buffer,
+            index_buffer,
+            index_count: data.indices.len() as u32,
+            instances: Vec::new(),
+            instance_range: 0..0,
+            oit_instances: Vec::new(),
+            oit_range: 0..0,
+            selected: Vec::new(),
+            selected_range: 0..0,
+        });
+        MeshId(self.meshes.len() - 1)
+    }
+
+    /// Creates a mesh whose vertices are written on the GPU, by a compute shader binding
+    /// [`MeshRenderer::vertex_buffer`] as storage buffer of [`MeshVertex`] values.
+    pub fn add_dynamic_mesh(
+        &mut self,
+        device: &Device,
+        vertex_count: u32,
+        indices: &[u32],
+    ) -> MeshId {
+        let vertex_buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("Dynamic mesh vertex buffer"),
+            size: vertex_count as BufferAddress * size_of::<MeshVertex>() as BufferAddress,
+            usage: BufferUsages::VERTEX | BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+        let index_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Dynamic mesh index buffer"),
+            contents: bytemuck::cast_slice(indices),
+            usage: BufferUsages::INDEX,
+        });
+        self.meshes.push(Mesh {
+            vertex_buffer,
+            index_buffer,
+            index_count: indices.len() as u32,
+            instances: Vec::new(),
+            instance_range: 0..0,
+            oit_instances: Vec::new(),
+            oit_range: 0..0,
+            selected: Vec::new(),
+            selected_range: 0..0,
+        });
+        MeshId(self.meshes.len() - 1)
+    }
+
+    pub fn vertex_buffer(&self, mesh: MeshId) -> &Buffer {
+        &self.meshes[mesh.0].vertex_buffer
+    }
+
+    /// Draws a mesh with a transform consisting of translation, rotation and uniform scale.
+    pub fn draw(&mut self, mesh: MeshId, transform: Matrix4<f32>, material: Material) {
+        self.draw_dithered(mesh, transform, material, [0.0, 1.0]);
+    }
+
+    /// Draws the level of detail of a mesh that suits its size on the screen of `camera`,
+    /// see [`MeshRenderer::draw`].
+    pub fn draw_lod(
+        &mut self,
+        lod: &LodMesh,
+        transform: Matrix4<f32>,
+        material: Material,
+        camera: &Camera,
+    ) {
+        for (mesh, dither) in lod.select(transform, camera, self.lod_cross_fade) {
+            self.draw_dithered(mesh, transform, material, dither);
+        }
+    }
+
+    fn draw_dithered(
+        &mut self,
+        mesh: MeshId,
+        transform: Matrix4<f32>,
+        material: Material,
+        dither: [f32; 2],
+    ) {
+        let instance = MeshInstance {
+            model: transform.into(),
+            albedo: material.albedo,
+            roughness: material.roughness,
+            metallic: material.metallic,
+            dither,
+            opacity: material.opacity,
+        };
+        if material.opacity >= 1.0 {
+            self.meshes[mesh.0].instances.push(instance);
+            return;
+        }
+        match material.transparency {
+            Transparency::Sorted => self.sorted.push((mesh, instance)),
+            Transparency::WeightedBlended => self.meshes[mesh.0].oit_instances.push(instance),
+        }
+    }
+
+    /// Marks a mesh drawn with [`MeshRenderer::draw`] as selected, drawing it into the
+    /// selection mask with the same transform.
+    pub fn draw_selected(&mut self, mesh: MeshId, transform: Matrix4<f32>) {
+        self.meshes[mesh.0].selected.push(MeshInstance {
+            model: transform.into(),
+            albedo: [1.0; 3],
+            roughness: 1.0,
+            metallic: 0.0,
+            dither: [0.0, 1.0],
+            opacity: 1.0,
+        });
+    }
+
+    /// Sorts and uploads the instances drawn since the previous call.
+    pub fn prepare(&mut self, device: &Device, queue: &Queue, camera: &Camera) {
+        let mut instances = Vec::new();
+        for mesh in &mut self.meshes {
+            let start = instances.len() as u32;
+            instances.append(&mut mesh.instances);
+            mesh.instance_range = start..instances.len() as u32;
+        }
+        for mesh in &mut self.meshes {
+            let start = instances.len() as u32;
+            instances.append(&mut mesh.oit_instances);
+            mesh.oit_range = start..instances.len() as u32;
+        }
+        // Sorted back to front by the depth of their origin
+        let forward = camera.forward();
+        let depth = |(_, instance): &(MeshId, MeshInstance)| {
+            let [x, y, z, _] = instance.model[3];
+            (Point3::new(x, y, z) - camera.eye).dot(forward)
+        };
+        self.sorted.sort_by(|a, b| depth(b).total_cmp(&depth(a)));
+        self.sorted_meshes.clear();
+        self.sorted_start = instances.len() as u32;
+        for (mesh, instance) in self.sorted.drain(..) {
+            self.sorted_meshes.push(mesh);
+            instances.push(instance);
+        }
+        for mesh in &mut self.meshes {
+            let start = instances.len() as u32;
+            instances.append(&mut mesh.selected);
+            mesh.selected_range = start..instances.len() as u32;
+        }
+
+        let size = (instances.len() * size_of::<MeshInstance>()) as BufferAddress;
+        if size > self.instance_buffer.size() {
+            self.instance_buffer = create_instance_buffer(device, size.next_power_of_two());
+        }
+        if size > 0 {
+            queue.write_buffer(&self.instance_buffer, 0, bytemuck::cast_slice(&instances));
+        }
+    }
+
+    /// Draws the meshes' depth only, for the depth prepass of the forward paths.
+    pub fn render_depth(&self, render_pass: &mut RenderPass, camera_bind_group: &BindGroup) {
+        render_pass.set_pipeline(&self.depth_pipeline);
+        self.draw_meshes(render_pass, camera_bind_group);
+    }
+
+    /// Draws the meshes shaded by all lights and the reflection probes, for the forward path.
+    ///
+    /// After [`MeshRenderer::render_depth`], only the fragments matching its depth are shaded.
+    pub fn render_forward(
+        &self,
+        render_pass: &mut RenderPass,
+        camera_bind_group: &BindGroup,
+        lights_bind_group: &BindGroup,
+        probes_bind_group: &BindGroup,
+        after_depth_prepass: bool,
+    ) {
+        render_pass.set_pipeline(if after_depth_prepass {
+            &self.forward_equal_pipeline
+        } else {
+            &self.forward_pipeline
+        });
+        render_pass.set_bind_group(1, lights_bind_group, &[]);
+        render_pass.set_bind_group(2, probes_bind_group, &[]);
+        self.draw_meshes(render_pass, camera_bind_group);
+    }
+
+    /// Draws the meshes shaded by the lights of their clusters and the reflection probes,
+    /// for the Forward+ path.
+    ///
+    /// After [`MeshRenderer::render_depth`], only the fragments matching its depth are shaded.
+    pub fn render_forward_clustered(
+        &self,
+        render_pass: &mut RenderPass,
+        camera_bind_group: &BindGroup,
+        lights_bind_group: &BindGroup,
+        probes_bind_group: &BindGroup,
+        clusters: &LightClusters,
+        after_depth_prepass: bool,
+    ) {
+        render_pass.set_pipeline(if after_depth_prepass {
+            &self.clustered_equal_pipeline
+        } else {
+            &self.clustered_pipeline
+        });
+        render_pass.set_bind_group(1, lights_bind_group, &[]);
+        render_pass.set_bind_group(2, probes_bind_group, &[]);
+        render_pass.set_bind_group(3, clusters.bind_group(), &[]);
+        self.draw_meshes(render_pass, camera_bind_group);
+    }
+
+    /// Draws the meshes into the geometry pass of [`DeferredLighting`].
+    pub fn render_gbuffer(
+        &self,
+        render_pass: &mut RenderPass,
+        camera_bind_group: &BindGroup,
+        lights_bind_group: &BindGroup,
+    ) {
+        render_pass.set_pipeline(&self.gbuffer_pipeline);
+        // Not used by the G-buffer shader, but part of the shared pipeline layout
+        render_pass.set_bind_group(1, lights_bind_group, &[]);
+        self.draw_meshes(render_pass, camera_bind_group);
+    }
+
+    /// Draws the sorted transparent meshes back to front, shaded by all lights and the
+    /// reflection probes, blending them onto the scene.
+    pub fn render_sorted(
+        &self,
+        render_pass: &mut RenderPass,
+        camera_bind_group: &BindGroup,
+        lights_bind_group: &BindGroup,
+        probes_bind_group: &BindGroup,
+    ) {
+        if self.sorted_meshes.is_empty() {
+            return;
+        }
+        render_pass.set_pipeline(&self.sorted_pipeline);
+        render_pass.set_bind_group(0, camera_bind_group, &[]);
+        render_pass.set_bind_group(1, lights_bind_group, &[]);
+        render_pass.set_bind_group(2, probes_bind_group, &[]);
+        render_pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
+        for (&mesh, instance) in self.sorted_meshes.iter().zip(self.sorted_start..) {
+            self.meshes[mesh.0].draw(render_pass, instance..instance + 1);
+        }
+    }
+
+    /// Draws the order-independent transparent meshes into the accumulation pass of
+    /// [`WeightedBlendedOit`], shaded by all lights and the reflection probes.
+    pub fn render_oit(
+        &self,
+        render_pass: &mut RenderPass,
+        camera_bind_group: &BindGroup,
+        lights_bind_group: &BindGroup,
+        probes_bind_group: &BindGroup,
+    ) {
+        render_pass.set_pipeline(&self.oit_pipeline);
+        render_pass.set_bind_group(0, camera_bind_group, &[]);
+        render_pass.set_bind_group(1, lights_bind_group, &[]);
+        render_pass.set_bind_group(2, probes_bind_group, &[]);
+        render_pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
+        for mesh in &self.meshes {
+            mesh.draw(render_pass, mesh.oit_range.clone());
+        }
+    }
+
+    /// Draws the selected meshes into the mask pass of [`SelectionOutline`].
+    pub fn render_selection_mask(
+        &self,
+        render_pass: &mut RenderPass,
+        camera_bind_group: &BindGroup,
+    ) {
+        render_pass.set_pipeline(&self.mask_pipeline);
+        render_pass.set_bind_group(0, camera_bind_group, &[]);
+        render_pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
+        for mesh in &self.meshes {
+            mesh.draw(render_pass, mesh.selected_range.clone());
+        }
+    }
+
+    fn draw_meshes(&self, render_pass: &mut RenderPass, camera_bind_group: &BindGroup) {
+        render_pass.set_bind_group(0, camera_bind_group, &[]);
+        render_pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
+        for mesh in &self.meshes {
+            mesh.draw(render_pass, mesh.instance_range.clone());
+        }
+    }
+}
+
+impl Mesh {
+    fn draw(&self, render_pass: &mut RenderPass, instances: Range<u32>) {
+        if instances.is_empty() {
+            return;
+        }
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.set_index_buffer(self.index_buffer.slice(..), IndexFormat::Uint32);
+        render_pass.draw_indexed(0..self.index_count, 0, instances);
+    }
+}
+
+fn create_instance_buffer(device: &Device, size: BufferAddress) -> Buffer {
+    device.create_buffer(&BufferDescriptor {
+        label: Some("Mesh instance buffer"),
+        size,
+        usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+fn create_pipeline(
+    device: &Device,
+    label: &str,
+    layout: &PipelineLayout,
+    module: &ShaderModule,
+    fragment_entry_point: &str,
+    targets: &[Option<ColorTargetState>],
+    depth_stencil: Option<DepthStencilState>,
+) -> RenderPipeline {
+    device.create_render_pipeline(&RenderPipelineDescriptor {
+        label: Some(label),
+        layout: Some(layout),
+        vertex: VertexState {
+            module,
+            entry_point: Some("vs_main"),
+            compilation_options: PipelineCompilationOptions::default(),
+            buffers: &[MeshVertex::layout(), MeshInstance::layout()],
+        },
+        primitive: PrimitiveState {
+            cull_mode: Some(Face::Back),
+            ..Default::default()
+        },
+        depth_stencil,
+        multisample: MultisampleState::default(),
+        fragment: Some(FragmentState {
+            module,
+            entry_point: Some(fragment_entry_point),
+            compilation_options: PipelineCompilationOptions::default(),
+            targets,
+        }),
+        multiview: None,
+        cache: None,
+    })
+}
+
+// Testing for equal depth, after the depth prepass wrote it, leaves nothing to write.
+fn depth_stencil(format: TextureFormat, compare: CompareFunction) -> DepthStencilState {
+    DepthStencilState {
+        format,
+        depth_write_enabled: compare != CompareFunction::Equal,
+        depth_compare: compare,
+        stencil: Default::default(),
+        bias: Default::default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::Point3;
+
+    use super::*;
+
+    const STONE: Material = Material {
+        albedo: [0.5, 0.5, 0.5],
+        roughness: 1.0,
+        metallic: 0.0,
+        opacity: 1.0,
+        transparency: Transparency::Sorted,
+    };
+    const METAL: Material = Material {
+        albedo: [0.9, 0.9, 0.9],
+        roughness: 0.2,
+        metallic: 1.0,
+        opacity: 1.0,
+        transparency: Transparency::Sorted,
+    };
+
+    /// Whether every triangle winds counter-clockwise seen from the side its normals point to.
+    fn is_front_facing(mesh: &MeshData) -> bool {
+        mesh.indices.chunks_exact(3).all(|triangle| {
+            let [a, b, c] = [0, 1, 2].map(|corner| {
+                let vertex = mesh.vertices[triangle[corner] as usize];
+                (Point3::from(vertex.position), Vector3::from(vertex.normal))
+            });
+            (b.0 - a.0).cross(c.0 - a.0).dot(a.1) > 0.0
+        })
+    }
+
+    #[test]
+    fn batch_keeps_mirrored_copies_front_facing() {
+        let cube = MeshData::cube();
+        assert!(is_front_facing(&cube));
+        let batch = MeshData::batch([
+            (&cube, Matrix4::from_translation(Vector3::unit_x() * 2.0)),
+            (&cube, Matrix4::from_nonuniform_scale(-1.0, 1.0, 1.0)),
+            (&cube, Matrix4::from_nonuniform_scale(-1.0, -2.0, 1.0)),
+        ]);
+        assert_eq!(batch.vertices.len(), 3 * cube.vertices.len());
+        assert!(is_front_facing(&batch));
+    }
+
+    #[test]
+    fn batch_by_material_groups_in_order_of_appearance() {
+        let cube = MeshData::cube();
+        let sphere = MeshData::sphere(8, 4);
+        let identity = Matrix4::identity();
+        let batches = MeshData::batch_by_material([
+            (&cube, identity, METAL),
+            (&sphere, identity, STONE),
+            (&cube, identity, METAL),
+            (&cube, identity, STONE),
+        ]);
+        let materials = batches
+            .iter()
+            .map(|(material, _)| *material)
+            .collect::<Vec<_>>();
+        assert_eq!(materials, [METAL, STONE]);
+        assert_eq!(batches[0].1.indices.len(), 2 * cube.indices.len());
+        assert_eq!(
+            batches[1].1.indices.len(),
+            sphere.indices.len() + cube.indices.len()
+        );
+    }
+}
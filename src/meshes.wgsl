@@ -0,0 +1,156 @@
+// Draws lit triangle meshes, one instance per object, either shaded directly (forward paths)
+// or writing their surface attributes into the G-buffer (deferred path).
+// Transparent meshes are shaded like the forward path, and then either blended onto the scene
+// or accumulated for order-independent transparency.
+// Expects `camera.wgsl`, `lighting.wgsl`, `probes.wgsl`, `clusters.wgsl` and `oit.wgsl` to be
+// prepended.
+
+// Only used by the Forward+ path, see `LightClusters`
+@group(3) @binding(0)
+var<storage, read> clusters: Clusters;
+
+struct Vertex {
+    @location(0) position: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+}
+
+struct Instance {
+    // Model matrix, rotation and uniform scale only so that it also transforms normals
+    @location(2) model_0: vec4<f32>,
+    @location(3) model_1: vec4<f32>,
+    @location(4) model_2: vec4<f32>,
+    @location(5) model_3: vec4<f32>,
+    @location(6) albedo: vec3<f32>,
+    @location(7) roughness: f32,
+    @location(8) metallic: f32,
+    // Range of dither thresholds kept while cross-fading between levels of detail, see `LodMesh`
+    @location(9) dither: vec2<f32>,
+    @location(10) opacity: f32,
+}
+
+struct VertexOutput {
+    // Invariant, so that the depth prepass and the shading passes compute the exact same
+    // depth from the same vertex shader, which testing for equal depth relies on
+    @builtin(position) @invariant position: vec4<f32>,
+    @location(0) world_position: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) albedo: vec3<f32>,
+    @location(3) roughness: f32,
+    @location(4) metallic: f32,
+    @location(5) @interpolate(flat) dither: vec2<f32>,
+    @location(6) @interpolate(flat) opacity: f32,
+}
+
+// 4x4 ordered dithering pattern
+const bayer: array<u32, 16> = array<u32, 16>(0u, 8u, 2u, 10u, 12u, 4u, 14u, 6u, 3u, 11u, 1u, 9u, 15u, 7u, 13u, 5u);
+
+@vertex
+fn vs_main(vertex: Vertex, instance: Instance) -> VertexOutput {
+    let model = mat4x4<f32>(instance.model_0, instance.model_1, instance.model_2, instance.model_3);
+    let world_position = model * vec4<f32>(vertex.position, 1.0);
+
+    var out: VertexOutput;
+    out.position = camera.view_projection * world_position;
+    out.world_position = world_position.xyz;
+    out.normal = (model * vec4<f32>(vertex.normal, 0.0)).xyz;
+    out.albedo = instance.albedo;
+    out.roughness = instance.roughness;
+    out.metallic = instance.metallic;
+    out.dither = instance.dither;
+    out.opacity = instance.opacity;
+    return out;
+}
+
+// Whether a fragment is left out by the screen-door cross-fade between levels of detail,
+// as the dither threshold of its pixel lies outside of the instance's range.
+fn dithered_out(in: VertexOutput) -> bool {
+    let pixel = vec2<u32>(in.position.xy) % 4u;
+    let threshold = (f32(bayer[pixel.y * 4u + pixel.x]) + 0.5) / 16.0;
+    return threshold < in.dither.x || threshold >= in.dither.y;
+}
+
+// Writes depth only, for the depth prepass of the forward paths
+@fragment
+fn fs_depth(in: VertexOutput) {
+    if dithered_out(in) {
+        discard;
+    }
+}
+
+// Shades a fragment with the reflection probes and all lights
+fn shade_forward(in: VertexOutput) -> vec3<f32> {
+    let surface = Surface(in.world_position, normalize(in.normal), in.albedo, in.roughness, in.metallic);
+    return shade_probes(surface) + shade_point_lights(surface);
+}
+
+@fragment
+fn fs_forward(in: VertexOutput) -> @location(0) vec4<f32> {
+    if dithered_out(in) {
+        discard;
+    }
+    return vec4<f32>(shade_forward(in), 1.0);
+}
+
+// Blends a sorted transparent mesh onto the scene
+@fragment
+fn fs_transparent(in: VertexOutput) -> @location(0) vec4<f32> {
+    if dithered_out(in) {
+        discard;
+    }
+    return vec4<f32>(shade_forward(in), in.opacity);
+}
+
+// Accumulates a transparent mesh that needs no sorting, see `WeightedBlendedOit`
+@fragment
+fn fs_oit(in: VertexOutput) -> OitOutput {
+    if dithered_out(in) {
+        discard;
+    }
+    let forward = -vec3<f32>(camera.view[0].z, camera.view[1].z, camera.view[2].z);
+    let view_depth = dot(in.world_position - camera.position.xyz, forward);
+    return oit_output(vec4<f32>(shade_forward(in), in.opacity), view_depth);
+}
+
+// Only evaluates the lights binned into the fragment's cluster
+@fragment
+fn fs_forward_clustered(in: VertexOutput) -> @location(0) vec4<f32> {
+    if dithered_out(in) {
+        discard;
+    }
+    let surface = Surface(in.world_position, normalize(in.normal), in.albedo, in.roughness, in.metallic);
+    let forward = -vec3<f32>(camera.view[0].z, camera.view[1].z, camera.view[2].z);
+    let cluster = cluster_index(in.position.xy, dot(in.world_position - camera.position.xyz, forward));
+
+    var color = shade_probes(surface);
+    let start = cluster_start(cluster, clusters.capacity);
+    let count = clusters.data[start];
+    for (var i = 0u; i < count; i++) {
+        color += shade_point_light(surface, light_list.lights[clusters.data[start + 1u + i]]);
+    }
+    return vec4<f32>(color, 1.0);
+}
+
+// Layout of the G-buffer, see `DeferredLighting`
+struct GBufferOutput {
+    @location(0) albedo: vec4<f32>,
+    @location(1) normal: vec4<f32>,
+    @location(2) material: vec4<f32>,
+}
+
+@fragment
+fn fs_gbuffer(in: VertexOutput) -> GBufferOutput {
+    if dithered_out(in) {
+        discard;
+    }
+    var out: GBufferOutput;
+    out.albedo = vec4<f32>(in.albedo, 1.0);
+    out.normal = vec4<f32>(normalize(in.normal), 0.0);
+    out.material = vec4<f32>(in.roughness, in.metallic, 0.0, 0.0);
+    return out;
+}
+
+// Marks the covered pixels in the selection mask, see `SelectionOutline`
+@fragment
+fn fs_mask() -> @location(0) vec4<f32> {
+    return vec4<f32>(1.0);
+}
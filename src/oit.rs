@@ -0,0 +1,262 @@
+use std::borrow::Cow;
+
+use wgpu::{
+    BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
+    BindGroupLayoutEntry, BindingResource, BindingType, BlendComponent, BlendFactor,
+    BlendOperation, BlendState, Color, ColorTargetState, ColorWrites, CommandEncoder,
+    CompareFunction, DepthStencilState, Device, Extent3d, FragmentState, LoadOp, MultisampleState,
+    Operations, PipelineCompilationOptions, PipelineLayoutDescriptor, PrimitiveState, RenderPass,
+    RenderPassColorAttachment, RenderPassDepthStencilAttachment, RenderPassDescriptor,
+    RenderPipeline, RenderPipelineDescriptor, ShaderModuleDescriptor, ShaderStages, StoreOp,
+    TextureDescriptor, TextureDimension, TextureFormat, TextureSampleType, TextureUsages,
+    TextureView, TextureViewDescriptor, TextureViewDimension, VertexState,
+};
+
+/// How a transparent surface is blended with what is behind it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transparency {
+    /// Sorted back to front on the CPU and alpha blended onto the scene.
+    ///
+    /// Exact, but only as long as surfaces don't intersect.
+    Sorted,
+    /// Accumulated in any order by [`WeightedBlendedOit`] and resolved afterwards.
+    ///
+    /// Approximate, but handles intersecting surfaces and requires no sorting.
+    WeightedBlended,
+}
+
+/// Weighted blended order-independent transparency.
+///
+/// Transparent surfaces are drawn into an accumulation and a revealage target
+/// in a separate pass, testing against the scene's depth, and then composited
+/// onto the scene by [`WeightedBlendedOit::resolve`].
+/// Their shaders prepend `oit.wgsl` and return its `oit_output`.
+pub struct WeightedBlendedOit {
+    accumulation_view: TextureView,
+    revealage_view: TextureView,
+    resolve_bind_group_layout: BindGroupLayout,
+    resolve_bind_group: BindGroup,
+    resolve_pipeline: RenderPipeline,
+}
+
+impl WeightedBlendedOit {
+    pub const ACCUMULATION_FORMAT: TextureFormat = TextureFormat::Rgba16Float;
+    pub const REVEALAGE_FORMAT: TextureFormat = TextureFormat::R8Unorm;
+
+    pub fn new(
+        device: &Device,
+        color_format: TextureFormat,
+        depth_format: TextureFormat,
+        width: u32,
+        height: u32,
+    ) -> Self {
+        let texture_entry = |binding| BindGroupLayoutEntry {
+            binding,
+            visibility: ShaderStages::FRAGMENT,
+            ty: BindingType::Texture {
+                sample_type: TextureSampleType::Float { filterable: false },
+                view_dimension: TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        };
+        let resolve_bind_group_layout =
+            device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+                label: Some("OIT resolve bind group layout"),
+                entries: &[texture_entry(0), texture_entry(1)],
+            });
+
+        let shader_module = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("OIT resolve shader module"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("./oit_resolve.wgsl"))),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("OIT resolve pipeline layout"),
+            bind_group_layouts: &[&resolve_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let resolve_pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("OIT resolve pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: VertexState {
+                module: &shader_module,
+                entry_point: None,
+                compilation_options: PipelineCompilationOptions::default(),
+                buffers: &[],
+            },
+            primitive: PrimitiveState::default(),
+            // The resolve runs in a pass with the scene's depth attachment, but ignores it
+            depth_stencil: Some(DepthStencilState {
+                format: depth_format,
+                depth_write_enabled: false,
+                depth_compare: CompareFunction::Always,
+                stencil: Default::default(),
+                bias: Default::default(),
+            }),
+            multisample: MultisampleState::default(),
+            fragment: Some(FragmentState {
+                module: &shader_module,
+                entry_point: None,
+                compilation_options: PipelineCompilationOptions::default(),
+                targets: &[Some(ColorTargetState {
+                    format: color_format,
+                    blend: Some(BlendState::ALPHA_BLENDING),
+                    write_mask: ColorWrites::default(),
+                })],
+            }),
+            multiview: None,
+            cache: None,
+        });
+
+        let (accumulation_view, revealage_view, resolve_bind_group) =
+            create_targets(device, &resolve_bind_group_layout, width, height);
+        Self {
+            accumulation_view,
+            revealage_view,
+            resolve_bind_group_layout,
+            resolve_bind_group,
+            resolve_pipeline,
+        }
+    }
+
+    /// Recreates the targets to match the size of the surface.
+    pub fn resize(&mut self, device: &Device, width: u32, height: u32) {
+        (
+            self.accumulation_view,
+            self.revealage_view,
+            self.resolve_bind_group,
+        ) = create_targets(device, &self.resolve_bind_group_layout, width, height);
+    }
+
+    /// Color targets of pipelines drawing transparent surfaces into the accumulation pass.
+    pub fn color_targets() -> [Option<ColorTargetState>; 2] {
+        [
+            // Sum of the weighted, premultiplied colors and of the weighted alphas
+            Some(ColorTargetState {
+                format: Self::ACCUMULATION_FORMAT,
+                blend: Some(BlendState {
+                    color: BlendComponent {
+                        src_factor: BlendFactor::One,
+                        dst_factor: BlendFactor::One,
+                        operation: BlendOperation::Add,
+                    },
+                    alpha: BlendComponent {
+                        src_factor: BlendFactor::One,
+                        dst_factor: BlendFactor::One,
+                        operation: BlendOperation::Add,
+                    },
+                }),
+                write_mask: ColorWrites::ALL,
+            }),
+            // Product of (1 - alpha), the fraction of the scene that remains visible
+            Some(ColorTargetState {
+                format: Self::REVEALAGE_FORMAT,
+                blend: Some(BlendState {
+                    color: BlendComponent {
+                        src_factor: BlendFactor::Zero,
+                        dst_factor: BlendFactor::OneMinusSrc,
+                        operation: BlendOperation::Add,
+                    },
+                    alpha: BlendComponent::REPLACE,
+                }),
+                write_mask: ColorWrites::RED,
+            }),
+        ]
+    }
+
+    /// Begins the pass accumulating transparent surfaces, which are depth tested against `depth_view`.
+    pub fn begin_accumulation_pass<'encoder>(
+        &self,
+        command_encoder: &'encoder mut CommandEncoder,
+        depth_view: &TextureView,
+    ) -> RenderPass<'encoder> {
+        command_encoder.begin_render_pass(&RenderPassDescriptor {
+            label: Some("OIT accumulation pass"),
+            color_attachments: &[
+                Some(RenderPassColorAttachment {
+                    view: &self.accumulation_view,
+                    resolve_target: None,
+                    ops: Operations {
+                        load: LoadOp::Clear(Color::TRANSPARENT),
+                        store: StoreOp::Store,
+                    },
+                }),
+                Some(RenderPassColorAttachment {
+                    view: &self.revealage_view,
+                    resolve_target: None,
+                    ops: Operations {
+                        load: LoadOp::Clear(Color::WHITE),
+                        store: StoreOp::Store,
+                    },
+                }),
+            ],
+            depth_stencil_attachment: Some(RenderPassDepthStencilAttachment {
+                view: depth_view,
+                depth_ops: Some(Operations {
+                    load: LoadOp::Load,
+                    store: StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        })
+    }
+
+    /// Composites the accumulated surfaces onto the render pass' color attachment.
+    pub fn resolve(&self, render_pass: &mut RenderPass) {
+        render_pass.set_pipeline(&self.resolve_pipeline);
+        render_pass.set_bind_group(0, &self.resolve_bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
+
+fn create_targets(
+    device: &Device,
+    layout: &BindGroupLayout,
+    width: u32,
+    height: u32,
+) -> (TextureView, TextureView, BindGroup) {
+    let create_view = |label, format| {
+        device
+            .create_texture(&TextureDescriptor {
+                label: Some(label),
+                size: Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: TextureDimension::D2,
+                format,
+                usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            })
+            .create_view(&TextureViewDescriptor::default())
+    };
+    let accumulation_view = create_view(
+        "OIT accumulation texture",
+        WeightedBlendedOit::ACCUMULATION_FORMAT,
+    );
+    let revealage_view = create_view(
+        "OIT revealage texture",
+        WeightedBlendedOit::REVEALAGE_FORMAT,
+    );
+
+    let bind_group = device.create_bind_group(&BindGroupDescriptor {
+        label: Some("OIT resolve bind group"),
+        layout,
+        entries: &[
+            BindGroupEntry {
+                binding: 0,
+                resource: BindingResource::TextureView(&accumulation_view),
+            },
+            BindGroupEntry {
+                binding: 1,
+                resource: BindingResource::TextureView(&revealage_view),
+            },
+        ],
+    });
+    (accumulation_view, revealage_view, bind_group)
+}
@@ -0,0 +1,20 @@
+// Weighted blended order-independent transparency, see https://jcgt.org/published/0002/02/09/
+// Prepended to the shaders of transparent surfaces which are drawn into the accumulation
+// and revealage targets of `WeightedBlendedOit`, instead of blending onto the scene.
+
+struct OitOutput {
+    @location(0) accumulation: vec4<f32>,
+    @location(1) revealage: f32,
+}
+
+// Weights a fragment with straight (not premultiplied) alpha by its distance to the camera
+// along the view direction, so that closer surfaces dominate the resolved color.
+fn oit_output(color: vec4<f32>, view_depth: f32) -> OitOutput {
+    let z = abs(view_depth);
+    let weight = clamp(10.0 / (1e-5 + pow(z / 5.0, 2.0) + pow(z / 200.0, 6.0)), 1e-2, 3e3);
+
+    var out: OitOutput;
+    out.accumulation = vec4<f32>(color.rgb * color.a, color.a) * weight;
+    out.revealage = color.a;
+    return out;
+}
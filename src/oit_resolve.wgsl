@@ -0,0 +1,28 @@
+// Composites the accumulated transparent surfaces onto the scene with a single full-screen triangle.
+
+@group(0) @binding(0)
+var accumulation_texture: texture_2d<f32>;
+@group(0) @binding(1)
+var revealage_texture: texture_2d<f32>;
+
+@vertex
+fn vs_main(@builtin(vertex_index) in_vertex_index: u32) -> @builtin(position) vec4<f32> {
+    // A triangle covering the whole screen, its excess is clipped
+    let uv = vec2<f32>(f32((in_vertex_index << 1u) & 2u), f32(in_vertex_index & 2u));
+    return vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+}
+
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let coords = vec2<i32>(position.xy);
+
+    // Revealage is the product of (1 - alpha) of all surfaces, 1 where there are none
+    let revealage = textureLoad(revealage_texture, coords, 0).r;
+    if revealage >= 1.0 {
+        discard;
+    }
+
+    let accumulation = textureLoad(accumulation_texture, coords, 0);
+    let average_color = accumulation.rgb / clamp(accumulation.a, 1e-4, 5e4);
+    return vec4<f32>(average_color, 1.0 - revealage);
+}
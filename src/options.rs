@@ -0,0 +1,287 @@
+use std::path::PathBuf;
+
+use color_eyre::{
+    eyre::{bail, Context, OptionExt},
+    Result,
+};
+
+use crate::{
+    fog::FogQuality, frame_pacing::FramePacer, render_scale::RenderScale, ssr::SsrSettings,
+};
+
+const USAGE: &str = "usage: rustlab2024-wgpu [--benchmark <frames>] [--record <file> | --replay <file>] \
+[--fog <low|medium|high>] [--ssr-steps <count>] [--ssr-refinement <count>] [--ssr-roughness <cutoff>] \
+[--shadertoy <file>] [--render-scale <factor>] [--frames-in-flight <count>] [--lut <file>]... \
+[--texture-budget <MiB>]";
+
+/// Command line options.
+#[derive(Debug, Clone, Default)]
+pub struct Options {
+    /// Render this many frames along a fixed camera path with vsync off,
+    /// print a JSON report of the frame timings and exit.
+    pub benchmark_frames: Option<u32>,
+    /// Record all input events to this file.
+    pub record: Option<PathBuf>,
+    /// Replay the input events recorded to this file instead of using live input.
+    pub replay: Option<PathBuf>,
+    /// Resolution of the volumetric fog.
+    pub fog_quality: FogQuality,
+    /// Step counts and roughness cutoff of the screen-space reflections.
+    pub ssr: SsrSettings,
+    /// Start in the ShaderToy mode, running the image shader in this file, or the GLSL copied from
+    /// ShaderToy in it if it ends in `.glsl` or `.frag`.
+    pub shadertoy: Option<PathBuf>,
+    /// Render the scene at this multiple of the window size, see [`RenderScale`].
+    pub render_scale: Option<f32>,
+    /// Let the CPU run at most this many frames ahead of the GPU, see [`FramePacer`].
+    pub frames_in_flight: Option<u32>,
+    /// Grade the image through the 3D LUTs in these `.cube` files or PNG strips, starting with the
+    /// first.
+    pub luts: Vec<PathBuf>,
+    /// Video memory in MiB the streamed textures may occupy, see [`TextureStreamer`].
+    ///
+    /// [`TextureStreamer`]: crate::streaming::TextureStreamer
+    pub texture_budget: Option<u32>,
+}
+
+impl Options {
+    pub fn from_args() -> Result<Self> {
+        Self::parse(std::env::args().skip(1))
+    }
+
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self> {
+        let mut options = Self::default();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--benchmark" => {
+                    let frames = args
+                        .next()
+                        .ok_or_eyre("--benchmark requires a frame count")?;
+                    let frames = frames
+                        .parse::<u32>()
+                        .wrap_err_with(|| format!("invalid frame count {frames:?}"))?;
+                    if frames == 0 {
+                        bail!("--benchmark requires at least one frame");
+                    }
+                    options.benchmark_frames = Some(frames);
+                }
+                "--record" => {
+                    let path = args.next().ok_or_eyre("--record requires a file")?;
+                    options.record = Some(path.into());
+                }
+                "--replay" => {
+                    let path = args.next().ok_or_eyre("--replay requires a file")?;
+                    options.replay = Some(path.into());
+                }
+                "--fog" => {
+                    let quality = args.next().ok_or_eyre("--fog requires a quality")?;
+                    options.fog_quality = quality.parse()?;
+                }
+                "--ssr-steps" => {
+                    options.ssr.steps = parse_count(&mut args, "--ssr-steps")?;
+                }
+                "--ssr-refinement" => {
+                    options.ssr.refinement_steps = parse_count(&mut args, "--ssr-refinement")?;
+                }
+                "--ssr-roughness" => {
+                    let cutoff = args
+                        .next()
+                        .ok_or_eyre("--ssr-roughness requires a roughness cutoff")?;
+                    let cutoff = cutoff
+                        .parse::<f32>()
+                        .wrap_err_with(|| format!("invalid roughness cutoff {cutoff:?}"))?;
+                    if !(cutoff > 0.0 && cutoff <= 1.0) {
+                        bail!("--ssr-roughness requires a cutoff above 0 and at most 1");
+                    }
+                    options.ssr.roughness_cutoff = cutoff;
+                }
+                "--shadertoy" => {
+                    let path = args.next().ok_or_eyre("--shadertoy requires a file")?;
+                    options.shadertoy = Some(path.into());
+                }
+                "--render-scale" => {
+                    let scale = args.next().ok_or_eyre("--render-scale requires a factor")?;
+                    let scale = scale
+                        .parse::<f32>()
+                        .wrap_err_with(|| format!("invalid render scale {scale:?}"))?;
+                    if !(RenderScale::MIN..=RenderScale::MAX).contains(&scale) {
+                        bail!(
+                            "--render-scale requires a factor from {} to {}",
+                            RenderScale::MIN,
+                            RenderScale::MAX
+                        );
+                    }
+                    options.render_scale = Some(scale);
+                }
+                "--frames-in-flight" => {
+                    let frames = args
+                        .next()
+                        .ok_or_eyre("--frames-in-flight requires a frame count")?;
+                    let frames = frames
+                        .parse::<u32>()
+                        .wrap_err_with(|| format!("invalid frame count {frames:?}"))?;
+                    if !(FramePacer::MIN_FRAMES_IN_FLIGHT..=FramePacer::MAX_FRAMES_IN_FLIGHT)
+                        .contains(&frames)
+                    {
+                        bail!(
+                            "--frames-in-flight requires a frame count from {} to {}",
+                            FramePacer::MIN_FRAMES_IN_FLIGHT,
+                            FramePacer::MAX_FRAMES_IN_FLIGHT
+                        );
+                    }
+                    options.frames_in_flight = Some(frames);
+                }
+                "--lut" => {
+                    let path = args.next().ok_or_eyre("--lut requires a file")?;
+                    options.luts.push(path.into());
+                }
+                "--texture-budget" => {
+                    let budget = args
+                        .next()
+                        .ok_or_eyre("--texture-budget requires a size in MiB")?;
+                    let budget = budget
+                        .parse::<u32>()
+                        .wrap_err_with(|| format!("invalid texture budget {budget:?}"))?;
+                    if budget == 0 {
+                        bail!("--texture-budget requires at least 1 MiB");
+                    }
+                    options.texture_budget = Some(budget);
+                }
+                "-h" | "--help" => {
+                    println!("{USAGE}");
+                    std::process::exit(0);
+                }
+                _ => bail!("unknown argument {arg:?}\n{USAGE}"),
+            }
+        }
+        if options.record.is_some() && options.replay.is_some() {
+            bail!("--record and --replay cannot be used together");
+        }
+        Ok(options)
+    }
+}
+
+/// Parses the step count following `flag`, which must be at least one.
+fn parse_count(args: &mut impl Iterator<Item = String>, flag: &str) -> Result<u32> {
+    let count = args
+        .next()
+        .ok_or_eyre(format!("{flag} requires a step count"))?;
+    let count = count
+        .parse::<u32>()
+        .wrap_err_with(|| format!("invalid step count {count:?}"))?;
+    if count == 0 {
+        bail!("{flag} requires at least one step");
+    }
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Options> {
+        Options::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn no_arguments_give_defaults() {
+        let options = parse(&[]).unwrap();
+        assert_eq!(options.benchmark_frames, None);
+        assert_eq!(options.fog_quality, FogQuality::Medium);
+        assert_eq!(options.ssr, SsrSettings::default());
+        assert!(options.luts.is_empty());
+        assert_eq!(options.texture_budget, None);
+    }
+
+    #[test]
+    fn parses_flag_values() {
+        let options = parse(&[
+            "--benchmark",
+            "120",
+            "--record",
+            "input.txt",
+            "--fog",
+            "high",
+            "--ssr-steps",
+            "16",
+            "--ssr-roughness",
+            "0.5",
+            "--render-scale",
+            "1.5",
+            "--frames-in-flight",
+            "3",
+            "--lut",
+            "a.cube",
+            "--lut",
+            "b.cube",
+            "--texture-budget",
+            "256",
+        ])
+        .unwrap();
+        assert_eq!(options.benchmark_frames, Some(120));
+        assert_eq!(options.record, Some(PathBuf::from("input.txt")));
+        assert_eq!(options.fog_quality, FogQuality::High);
+        assert_eq!(options.ssr.steps, 16);
+        assert_eq!(options.ssr.roughness_cutoff, 0.5);
+        assert_eq!(options.render_scale, Some(1.5));
+        assert_eq!(options.frames_in_flight, Some(3));
+        assert_eq!(
+            options.luts,
+            [PathBuf::from("a.cube"), PathBuf::from("b.cube")]
+        );
+        assert_eq!(options.texture_budget, Some(256));
+    }
+
+    #[test]
+    fn rejects_missing_values() {
+        for flag in [
+            "--benchmark",
+            "--record",
+            "--replay",
+            "--fog",
+            "--ssr-steps",
+            "--ssr-refinement",
+            "--ssr-roughness",
+            "--shadertoy",
+            "--render-scale",
+            "--frames-in-flight",
+            "--lut",
+            "--texture-budget",
+        ] {
+            assert!(parse(&[flag]).is_err(), "{flag} without a value");
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_values() {
+        for args in [
+            ["--benchmark", "many"],
+            ["--benchmark", "0"],
+            ["--benchmark", "-1"],
+            ["--fog", "ultra"],
+            ["--ssr-steps", "0"],
+            ["--ssr-refinement", "x"],
+            ["--ssr-roughness", "0"],
+            ["--ssr-roughness", "1.5"],
+            ["--ssr-roughness", "NaN"],
+            ["--render-scale", "4"],
+            ["--frames-in-flight", "0"],
+            ["--frames-in-flight", "9"],
+            ["--texture-budget", "0"],
+            ["--texture-budget", "1.5"],
+        ] {
+            assert!(parse(&args).is_err(), "{args:?}");
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_arguments() {
+        assert!(parse(&["--vsync"]).is_err());
+        assert!(parse(&["scene.gltf"]).is_err());
+    }
+
+    #[test]
+    fn rejects_recording_while_replaying() {
+        assert!(parse(&["--record", "a.txt", "--replay", "b.txt"]).is_err());
+    }
+}
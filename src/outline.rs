@@ -0,0 +1,159 @@
+use std::borrow::Cow;
+
+use wgpu::{
+    BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
+    BindGroupLayoutEntry, BindingResource, BindingType, BlendState, Color, ColorTargetState,
+    ColorWrites, CommandEncoder, Device, Extent3d, FragmentState, LoadOp, MultisampleState,
+    Operations, PipelineCompilationOptions, PipelineLayoutDescriptor, PrimitiveState, RenderPass,
+    RenderPassColorAttachment, RenderPassDescriptor, RenderPipeline, RenderPipelineDescriptor,
+    ShaderModuleDescriptor, ShaderStages, StoreOp, TextureDescriptor, TextureDimension,
+    TextureFormat, TextureSampleType, TextureUsages, TextureView, TextureViewDescriptor,
+    TextureViewDimension, VertexState,
+};
+
+/// A colored outline around the selected objects, drawn over the final image.
+///
+/// The selected meshes are drawn into a mask first, without depth testing so that hidden
+/// parts are outlined as well, see [`MeshRenderer::render_selection_mask`]. The outline
+/// then covers the pixels around the mask, found by dilating it.
+///
+/// [`MeshRenderer::render_selection_mask`]: crate::meshes::MeshRenderer::render_selection_mask
+pub struct SelectionOutline {
+    mask_view: TextureView,
+    bind_group_layout: BindGroupLayout,
+    bind_group: BindGroup,
+    pipeline: RenderPipeline,
+}
+
+impl SelectionOutline {
+    /// Selected pixels are 1, all others 0.
+    pub const MASK_FORMAT: TextureFormat = TextureFormat::R8Unorm;
+
+    pub fn new(device: &Device, color_format: TextureFormat, width: u32, height: u32) -> Self {
+        let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Outline bind group layout"),
+            entries: &[BindGroupLayoutEntry {
+                binding: 0,
+                visibility: ShaderStages::FRAGMENT,
+                ty: BindingType::Texture {
+                    sample_type: TextureSampleType::Float { filterable: false },
+                    view_dimension: TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            }],
+        });
+        let mask_view = create_mask_view(device, width, height);
+        let bind_group = create_bind_group(device, &bind_group_layout, &mask_view);
+
+        let shader_module = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("Outline shader module"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("./outline.wgsl"))),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Outline pipeline layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("Outline pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: VertexState {
+                module: &shader_module,
+                entry_point: None,
+                compilation_options: PipelineCompilationOptions::default(),
+                buffers: &[],
+            },
+            primitive: PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+            fragment: Some(FragmentState {
+                module: &shader_module,
+                entry_point: None,
+                compilation_options: PipelineCompilationOptions::default(),
+                targets: &[Some(ColorTargetState {
+                    format: color_format,
+                    blend: Some(BlendState::ALPHA_BLENDING),
+                    write_mask: ColorWrites::default(),
+                })],
+            }),
+            multiview: None,
+            cache: None,
+        });
+
+        Self {
+            mask_view,
+            bind_group_layout,
+            bind_group,
+            pipeline,
+        }
+    }
+
+    /// Recreates the mask to match the size of the surface.
+    pub fn resize(&mut self, device: &Device, width: u32, height: u32) {
+        self.mask_view = create_mask_view(device, width, height);
+        self.bind_group = create_bind_group(device, &self.bind_group_layout, &self.mask_view);
+    }
+
+    /// Begins the pass drawing the selected objects into the mask, clearing it.
+    pub fn begin_mask_pass<'encoder>(
+        &self,
+        command_encoder: &'encoder mut CommandEncoder,
+    ) -> RenderPass<'encoder> {
+        command_encoder.begin_render_pass(&RenderPassDescriptor {
+            label: Some("Selection mask pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: &self.mask_view,
+                resolve_target: None,
+                ops: Operations {
+                    load: LoadOp::Clear(Color::TRANSPARENT),
+                    store: StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        })
+    }
+
+    /// Draws the outline onto a pass without depth attachment.
+    pub fn render(&self, render_pass: &mut RenderPass) {
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
+
+fn create_mask_view(device: &Device, width: u32, height: u32) -> TextureView {
+    device
+        .create_texture(&TextureDescriptor {
+            label: Some("Selection mask texture"),
+            size: Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: SelectionOutline::MASK_FORMAT,
+            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        })
+        .create_view(&TextureViewDescriptor::default())
+}
+
+fn create_bind_group(
+    device: &Device,
+    layout: &BindGroupLayout,
+    mask_view: &TextureView,
+) -> BindGroup {
+    device.create_bind_group(&BindGroupDescriptor {
+        label: Some("Outline bind group"),
+        layout,
+        entries: &[BindGroupEntry {
+            binding: 0,
+            resource: BindingResource::TextureView(mask_view),
+        }],
+    })
+}
@@ -0,0 +1,44 @@
+// Draws an outline around the selected objects with a single full-screen triangle.
+//
+// The selection mask is dilated: pixels outside the mask are covered by the outline
+// depending on their distance to the closest pixel inside it.
+
+// Outline width in pixels
+const radius: i32 = 3;
+// Linear RGB color of the outline
+const outline_color: vec3<f32> = vec3<f32>(1.0, 0.45, 0.05);
+
+@group(0) @binding(0)
+var mask_texture: texture_2d<f32>;
+
+@vertex
+fn vs_main(@builtin(vertex_index) in_vertex_index: u32) -> @builtin(position) vec4<f32> {
+    // A triangle covering the whole screen, its excess is clipped
+    let uv = vec2<f32>(f32((in_vertex_index << 1u) & 2u), f32(in_vertex_index & 2u));
+    return vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+}
+
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let coords = vec2<i32>(position.xy);
+    let size = vec2<i32>(textureDimensions(mask_texture));
+    if textureLoad(mask_texture, coords, 0).r > 0.5 {
+        discard;
+    }
+
+    var closest = f32(radius) + 1.0;
+    for (var y = -radius; y <= radius; y++) {
+        for (var x = -radius; x <= radius; x++) {
+            let neighbor = clamp(coords + vec2<i32>(x, y), vec2<i32>(0), size - 1);
+            if textureLoad(mask_texture, neighbor, 0).r > 0.5 {
+                closest = min(closest, length(vec2<f32>(f32(x), f32(y))));
+            }
+        }
+    }
+    // Antialiased towards the outer edge
+    let coverage = saturate(f32(radius) + 0.5 - closest);
+    if coverage <= 0.0 {
+        discard;
+    }
+    return vec4<f32>(outline_color, coverage);
+}
@@ -0,0 +1,294 @@
+use cgmath::{EuclideanSpace, InnerSpace, Matrix4, Point3, SquareMatrix, Vector3};
+use wgpu::{
+    util::{BufferInitDescriptor, DeviceExt},
+    BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, Buffer, BufferUsages, Device,
+    Queue, RenderPass, TextureFormat,
+};
+
+use crate::{
+    camera::{orthographic, Camera, CameraUniform},
+    primitives::{DepthMode, PrimitiveRenderer},
+};
+
+const GRID_SPACING: f32 = 1.0;
+const GRID_MAJOR_EVERY: i32 = 10;
+// Number of grid lines on each side of the grid's center
+const GRID_HALF_LINES: i32 = 50;
+// Each grid line is split into segments so its alpha can fade out with the distance
+const GRID_SEGMENTS: u32 = 20;
+const GRID_MINOR_COLOR: [f32; 4] = [0.5, 0.5, 0.5, 0.35];
+const GRID_MAJOR_COLOR: [f32; 4] = [0.7, 0.7, 0.7, 0.6];
+
+const AXIS_COLORS: [[f32; 4]; 3] = [
+    [0.95, 0.25, 0.25, 1.0],
+    [0.3, 0.9, 0.3, 1.0],
+    [0.3, 0.45, 1.0, 1.0],
+];
+// Alpha factor of the negative half of an axis
+const NEGATIVE_AXIS_ALPHA: f32 = 0.4;
+
+// Fraction of the smaller window dimension used by the orientation gizmo
+const GIZMO_SCALE: f32 = 0.15;
+const GIZMO_MARGIN: f32 = 8.0;
+
+/// Reference geometry drawn on top of the scene: a ground grid with the world axes,
+/// and a gizmo in the bottom left corner showing the camera's orientation.
+///
+/// The grid and axes are depth tested against the scene, but don't write depth
+/// themselves, and fade out with increasing distance from the camera's target.
+pub struct Overlay {
+    pub show_grid: bool,
+    renderer: PrimitiveRenderer,
+    gizmo_renderer: PrimitiveRenderer,
+    gizmo_camera_buffer: Buffer,
+    gizmo_camera_bind_group: BindGroup,
+    gizmo_viewport: [f32; 4],
+    /// Size of the render target, whose full viewport is restored after drawing the gizmo.
+    target_size: [f32; 2],
+}
+
+impl Overlay {
+    pub fn new(
+        device: &Device,
+        camera_bind_group_layout: &BindGroupLayout,
+        color_format: TextureFormat,
+        depth_format: TextureFormat,
+    ) -> Self {
+        let gizmo_camera_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Gizmo camera buffer"),
+            contents: bytemuck::bytes_of(&CameraUniform::from_matrices(
+                Matrix4::identity(),
+                Matrix4::identity(),
+                Point3::origin(),
+                1,
+                1,
+            )),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+        let gizmo_camera_bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Gizmo camera bind group"),
+            layout: camera_bind_group_layout,
+            entries: &[BindGroupEntry {
+                binding: 0,
+                resource: gizmo_camera_buffer.as_entire_binding(),
+            }],
+        });
+
+        Self {
+            show_grid: true,
+            renderer: PrimitiveRenderer::new(
+                device,
+                camera_bind_group_layout,
+                color_format,
+                depth_format,
+                DepthMode::Test,
+            ),
+            gizmo_renderer: PrimitiveRenderer::new(
+                device,
+                camera_bind_group_layout,
+                color_format,
+                depth_format,
+                DepthMode::Ignore,
+            ),
+            gizmo_camera_buffer,
+            gizmo_camera_bind_group,
+            gizmo_viewport: [0.0; 4],
+            target_size: [1.0; 2],
+        }
+    }
+
+    pub fn prepare(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        camera: &Camera,
+        width: u32,
+        height: u32,
+    ) {
+        if self.show_grid {
+            self.draw_grid(camera);
+        }
+        self.renderer.prepare(device, queue);
+
+        self.draw_gizmo(camera);
+        self.gizmo_renderer.prepare(device, queue);
+        let size = (width.min(height) as f32 * GIZMO_SCALE).round().max(1.0);
+        self.gizmo_viewport = [
+            GIZMO_MARGIN,
+            (height as f32 - size - GIZMO_MARGIN).max(0.0),
+            size,
+            size,
+        ];
+        self.target_size = [width as f32, height as f32];
+        // Look at the gizmo from the same direction as the camera looks at the scene
+        let eye = Point3::from_vec(-camera.forward() * 3.0);
+        let view = Matrix4::look_at_rh(eye, Point3::origin(), camera.up);
+        let projection = orthographic(-1.3, 1.3, -1.3, 1.3, 0.1, 10.0);
+        queue.write_buffer(
+            &self.gizmo_camera_buffer,
+            0,
+            bytemuck::bytes_of(&CameraUniform::from_matrices(
+                view,
+                projection,
+                eye,
+                size as u32,
+                size as u32,
+            )),
+        );
+    }
+
+    /// Draws the overlay, which must happen after the scene was drawn to the render pass.
+    ///
+    /// The viewport covers the whole render target again afterwards, so that the pass can
+    /// go on drawing after the overlay.
+    pub fn render(&self, render_pass: &mut RenderPass, camera_bind_group: &BindGroup) {
+        self.renderer.render(render_pass, camera_bind_group);
+
+        let [x, y, width, height] = self.gizmo_viewport;
+        render_pass.set_viewport(x, y, width, height, 0.0, 1.0);
+        self.gizmo_renderer
+            .render(render_pass, &self.gizmo_camera_bind_group);
+        let [width, height] = self.target_size;
+        render_pass.set_viewport(0.0, 0.0, width, height, 0.0, 1.0);
+    }
+
+    fn draw_grid(&mut self, camera: &Camera) {
+        // The grid moves along with the camera's target in steps of major lines,
+        // so it appears to be infinite while the lines stay fixed in world space.
+        let major_spacing = GRID_SPACING * GRID_MAJOR_EVERY as f32;
+        let center = Point3::new(
+            (camera.target.x / major_spacing).round() * major_spacing,
+            0.0,
+            (camera.target.z / major_spacing).round() * major_spacing,
+        );
+        let radius = GRID_HALF_LINES as f32 * GRID_SPACING;
+        let first_x = (center.x / GRID_SPACING).round() as i32 - GRID_HALF_LINES;
+        let first_z = (center.z / GRID_SPACING).round() as i32 - GRID_HALF_LINES;
+
+        for i in 0..=2 * GRID_HALF_LINES {
+            // Line parallel to the Z axis, the Z axis itself at x = 0
+            let x = first_x + i;
+            let (color, axis) = grid_line_style(x, 2);
+            self.draw_faded_line(
+                Point3::new(x as f32 * GRID_SPACING, 0.0, center.z - radius),
+                Point3::new(x as f32 * GRID_SPACING, 0.0, center.z + radius),
+                color,
+                axis,
+                center,
+                radius,
+            );
+
+            // Line parallel to the X axis, the X axis itself at z = 0
+            let z = first_z + i;
+            let (color, axis) = grid_line_style(z, 0);
+            self.draw_faded_line(
+                Point3::new(center.x - radius, 0.0, z as f32 * GRID_SPACING),
+                Point3::new(center.x + radius, 0.0, z as f32 * GRID_SPACING),
+                color,
+                axis,
+                center,
+                radius,
+            );
+        }
+
+        // The Y axis, fading out upwards
+        let top = Point3::new(0.0, radius * 0.5, 0.0);
+        let bottom = Point3::new(0.0, -radius * 0.5, 0.0);
+        let fade = horizontal_fade(Point3::origin(), center, radius);
+        let [r, g, b, a] = AXIS_COLORS[1];
+        self.renderer
+            .line_gradient(Point3::origin(), [r, g, b, a * fade], top, [r, g, b, 0.0]);
+        self.renderer.line_gradient(
+            Point3::origin(),
+            [r, g, b, a * fade * NEGATIVE_AXIS_ALPHA],
+            bottom,
+            [r, g, b, 0.0],
+        );
+    }
+
+    /// Draws a line on the ground plane whose alpha fades out towards the edge of the grid.
+    /// Lines along an axis are dimmed on the axis' negative side.
+    fn draw_faded_line(
+        &mut self,
+        from: Point3<f32>,
+        to: Point3<f32>,
+        color: [f32; 4],
+        axis: Option<usize>,
+        center: Point3<f32>,
+        radius: f32,
+    ) {
+        let color_at = |point: Point3<f32>| {
+            let [r, g, b, a] = color;
+            let negative = axis.is_some_and(|axis| point[axis] < 0.0);
+            let a = a
+                * horizontal_fade(point, center, radius)
+                * if negative { NEGATIVE_AXIS_ALPHA } else { 1.0 };
+            [r, g, b, a]
+        };
+
+        let step = (to - from) / GRID_SEGMENTS as f32;
+        for segment in 0..GRID_SEGMENTS {
+            let start = from + step * segment as f32;
+            let end = start + step;
+            if let Some(axis) = axis {
+                // Split the segment at the origin, so the dimming starts exactly there
+                let (start_coordinate, end_coordinate) = (start[axis], end[axis]);
+                if start_coordinate < 0.0 && end_coordinate > 0.0 {
+                    let mut origin = start;
+                    origin[axis] = 0.0;
+                    let mut negative_color = color_at(origin);
+                    negative_color[3] *= NEGATIVE_AXIS_ALPHA;
+                    self.renderer
+                        .line_gradient(start, color_at(start), origin, negative_color);
+                    self.renderer
+                        .line_gradient(origin, color_at(origin), end, color_at(end));
+                    continue;
+                }
+            }
+            self.renderer
+                .line_gradient(start, color_at(start), end, color_at(end));
+        }
+    }
+
+    fn draw_gizmo(&mut self, camera: &Camera) {
+        let towards_camera = -camera.forward();
+        let mut axes = [Vector3::unit_x(), Vector3::unit_y(), Vector3::unit_z()]
+            .into_iter()
+            .zip(AXIS_COLORS)
+            .collect::<Vec<_>>();
+        // Without depth testing, axes closer to the camera must be drawn last
+        axes.sort_by(|(a, _), (b, _)| a.dot(towards_camera).total_cmp(&b.dot(towards_camera)));
+
+        for (axis, color) in axes {
+            let [r, g, b, a] = color;
+            self.gizmo_renderer.line(
+                Point3::origin(),
+                Point3::from_vec(-axis * 0.6),
+                [r, g, b, a * NEGATIVE_AXIS_ALPHA],
+            );
+            self.gizmo_renderer
+                .line(Point3::origin(), Point3::from_vec(axis), color);
+            self.gizmo_renderer.point(Point3::from_vec(axis), color);
+        }
+    }
+}
+
+/// Returns the color of the grid line with the given index, and the world axis
+/// (by component index) it lies on, if any.
+/// `axis` is the component the line runs along.
+fn grid_line_style(index: i32, axis: usize) -> ([f32; 4], Option<usize>) {
+    if index == 0 {
+        (AXIS_COLORS[axis], Some(axis))
+    } else if index % GRID_MAJOR_EVERY == 0 {
+        (GRID_MAJOR_COLOR, None)
+    } else {
+        (GRID_MINOR_COLOR, None)
+    }
+}
+
+/// Alpha factor fading out between half the radius and the full radius around the center.
+fn horizontal_fade(point: Point3<f32>, center: Point3<f32>, radius: f32) -> f32 {
+    let distance = Vector3::new(point.x - center.x, 0.0, point.z - center.z).magnitude();
+    let t = ((distance - 0.5 * radius) / (0.5 * radius)).clamp(0.0, 1.0);
+    1.0 - t * t * (3.0 - 2.0 * t)
+}
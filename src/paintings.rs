@@ -0,0 +1,280 @@
+use std::{
+    borrow::Cow,
+    f32::consts::{FRAC_1_SQRT_2, TAU},
+};
+
+use cgmath::{Angle, InnerSpace, Point3, Rad, Vector3};
+use wgpu::{
+    BindGroup, BindGroupLayout, Buffer, BufferAddress, BufferDescriptor, BufferUsages,
+    ColorTargetState, ColorWrites, CompareFunction, DepthStencilState, Device, Face, FragmentState,
+    MultisampleState, PipelineCompilationOptions, PipelineLayoutDescriptor, PrimitiveState,
+    PrimitiveTopology, Queue, RenderPass, RenderPipeline, RenderPipelineDescriptor,
+    ShaderModuleDescriptor, TextureFormat, VertexBufferLayout, VertexState, VertexStepMode,
+};
+
+use crate::{
+    camera::Camera,
+    streaming::{mip_level, LevelSource, StreamedTextureId, TextureStreamer},
+};
+
+/// Number of sine waves summed up for a canvas, each with twice the frequency of the previous.
+const CANVAS_OCTAVES: u32 = 10;
+/// Frequency of the first octave, in periods across the canvas.
+const CANVAS_BASE_FREQUENCY: f32 = 2.0;
+/// Width of the frame painted around a canvas, relative to its side length.
+const CANVAS_FRAME: f32 = 0.025;
+
+/// A square, unlit picture standing upright in the scene, textured with a streamed texture.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Painting {
+    pub center: Point3<f32>,
+    /// Horizontal direction the picture faces, it is invisible from behind.
+    pub facing: Vector3<f32>,
+    /// Side length in world units.
+    pub size: f32,
+    pub texture: StreamedTextureId,
+    /// Side length of the texture's finest level.
+    pub texture_size: u32,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct PaintingInstance {
+    center: [f32; 3],
+    right: [f32; 3],
+    up: [f32; 3],
+}
+
+impl PaintingInstance {
+    const ATTRIBUTES: [wgpu::VertexAttribute; 3] = wgpu::vertex_attr_array![
+        0 => Float32x3,
+        1 => Float32x3,
+        2 => Float32x3,
+    ];
+
+    fn layout() -> VertexBufferLayout<'static> {
+        VertexBufferLayout {
+            array_stride: size_of::<Self>() as BufferAddress,
+            step_mode: VertexStepMode::Instance,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+
+    fn new(painting: &Painting) -> Self {
+        let facing = painting.facing.normalize();
+        let right = Vector3::unit_y().cross(facing) * painting.size / 2.0;
+        Self {
+            center: painting.center.into(),
+            right: right.into(),
+            up: (Vector3::unit_y() * painting.size / 2.0).into(),
+        }
+    }
+}
+
+/// Draws the paintings submitted during a frame, one instance each, and requests the mip level
+/// of their textures their size on screen needs from the [`TextureStreamer`].
+pub struct PaintingRenderer {
+    pipeline: RenderPipeline,
+    instance_buffer: Buffer,
+    paintings: Vec<Painting>,
+    /// Textures of the uploaded paintings, in the order of their instances.
+    textures: Vec<StreamedTextureId>,
+}
+
+impl PaintingRenderer {
+    const INITIAL_CAPACITY: BufferAddress = 16 * size_of::<PaintingInstance>() as BufferAddress;
+
+    pub fn new(
+        device: &Device,
+        camera_bind_group_layout: &BindGroupLayout,
+        streamer: &TextureStreamer,
+        color_format: TextureFormat,
+        depth_format: TextureFormat,
+    ) -> Self {
+        let shader_module = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("Painting shader module"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(concat!(
+                include_str!("./camera.wgsl"),
+                include_str!("./paintings.wgsl")
+            ))),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Painting pipeline layout"),
+            bind_group_layouts: &[camera_bind_group_layout, streamer.bind_group_layout()],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("Painting pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: VertexState {
+                module: &shader_module,
+                entry_point: Some("vs_main"),
+                compilation_options: PipelineCompilationOptions::default(),
+                buffers: &[PaintingInstance::layout()],
+            },
+            primitive: PrimitiveState {
+                topology: PrimitiveTopology::TriangleStrip,
+                cull_mode: Some(Face::Back),
+                ..Default::default()
+            },
+            depth_stencil: Some(DepthStencilState {
+                format: depth_format,
+                depth_write_enabled: true,
+                depth_compare: CompareFunction::GreaterEqual,
+                stencil: Default::default(),
+                bias: Default::default(),
+            }),
+            multisample: MultisampleState::default(),
+            fragment: Some(FragmentState {
+                module: &shader_module,
+                entry_point: Some("fs_main"),
+                compilation_options: PipelineCompilationOptions::default(),
+                targets: &[Some(ColorTargetState {
+                    format: color_format,
+                    blend: None,
+                    write_mask: ColorWrites::default(),
+                })],
+            }),
+            multiview: None,
+            cache: None,
+        });
+
+        Self {
+            pipeline,
+            instance_buffer: create_instance_buffer(device, Self::INITIAL_CAPACITY),
+            paintings: Vec::new(),
+            textures: Vec::new(),
+        }
+    }
+
+    pub fn draw(&mut self, painting: Painting) {
+        self.paintings.push(painting);
+    }
+
+    /// Requests the textures of the visible paintings submitted since the previous call, and
+    /// uploads the paintings.
+    ///
+    /// The level requested keeps a texel per pixel at the point of the painting closest to the
+    /// camera, with the camera rendering `height` pixels.
+    pub fn prepare(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        camera: &Camera,
+        height: u32,
+        streamer: &mut TextureStreamer,
+    ) {
+        let half_height = (Rad::from(camera.fovy) / 2.0).tan();
+        // Half of the opening angle of a cone around the view direction holding the frustum
+        let half_diagonal = Rad::atan(half_height * (1.0 + camera.aspect * camera.aspect).sqrt());
+        for painting in &self.paintings {
+            let to_center = painting.center - camera.eye;
+            let distance = to_center.magnitude();
+            let radius = painting.size * FRAC_1_SQRT_2;
+            if to_center.dot(painting.facing) >= 0.0 {
+                continue;
+            }
+            if distance > radius
+                && camera.forward().angle(to_center) > half_diagonal + Rad::asin(radius / distance)
+            {
+                continue;
+            }
+            let nearest = (distance - radius).max(camera.znear);
+            let pixels = painting.size / (2.0 * nearest * half_height) * height as f32;
+            streamer.request(painting.texture, mip_level(painting.texture_size, pixels));
+        }
+
+        let instances = self
+            .paintings
+            .iter()
+            .map(PaintingInstance::new)
+            .collect::<Vec<_>>();
+        self.textures.clear();
+        self.textures
+            .extend(self.paintings.drain(..).map(|painting| painting.texture));
+        let size = (instances.len() * size_of::<PaintingInstance>()) as BufferAddress;
+        if size > self.instance_buffer.size() {
+            self.instance_buffer = create_instance_buffer(device, size.next_power_of_two());
+        }
+        if size > 0 {
+            queue.write_buffer(&self.instance_buffer, 0, bytemuck::cast_slice(&instances));
+        }
+    }
+
+    /// Draws the paintings uploaded by the last [`PaintingRenderer::prepare`], with the levels of
+    /// their textures that are resident.
+    pub fn render(
+        &self,
+        render_pass: &mut RenderPass,
+        camera_bind_group: &BindGroup,
+        streamer: &TextureStreamer,
+    ) {
+        if self.textures.is_empty() {
+            return;
+        }
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, camera_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.instance_buffer.slice(..));
+        for (index, &texture) in self.textures.iter().enumerate() {
+            let index = index as u32;
+            render_pass.set_bind_group(1, streamer.bind_group(texture), &[]);
+            render_pass.draw(0..4, index..index + 1);
+        }
+    }
+}
+
+fn create_instance_buffer(device: &Device, size: BufferAddress) -> Buffer {
+    device.create_buffer(&BufferDescriptor {
+        label: Some("Painting instance buffer"),
+        size,
+        usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+/// Paints the levels of a canvas, which differs with the seed, from domain warped sine waves.
+///
+/// Each octave fades out as its frequency approaches the level's resolution, so a level shows
+/// the detail it can hold without aliasing, without having to be filtered from the finer ones.
+pub fn canvas(seed: u32) -> LevelSource {
+    // Cosine palette, see https://iquilezles.org/articles/palettes/
+    let phase = [0.0, 0.1, 0.2].map(|channel| channel + seed as f32 * 0.23);
+    Box::new(move |side| {
+        let texel = 1.0 / side as f32;
+        let mut texels = Vec::with_capacity((side * side * 4) as usize);
+        for y in 0..side {
+            for x in 0..side {
+                let (u, v) = ((x as f32 + 0.5) * texel, (y as f32 + 0.5) * texel);
+                let edge = u.min(v).min(1.0 - u).min(1.0 - v);
+                if edge < CANVAS_FRAME {
+                    texels.extend([60, 40, 25, 255]);
+                    continue;
+                }
+
+                let mut value = 0.0;
+                let mut frequency = CANVAS_BASE_FREQUENCY;
+                let mut amplitude = 1.0;
+                for octave in 0..CANVAS_OCTAVES {
+                    // Full strength up to a quarter of the texels, gone at half of them
+                    let fade = (2.0 - 4.0 * frequency * texel).clamp(0.0, 1.0);
+                    if fade == 0.0 {
+                        break;
+                    }
+                    let angle = seed as f32 * 2.4 + octave as f32 * 1.7;
+                    let along = u * angle.cos() + v * angle.sin();
+                    value += amplitude * fade * (TAU * frequency * along + 2.0 * value).sin();
+                    frequency *= 2.0;
+                    amplitude *= 0.7;
+                }
+
+                let t = 0.6 * (0.5 + 0.25 * value);
+                let [r, g, b] = phase.map(|phase| {
+                    let channel = 0.55 + 0.35 * (TAU * (t + phase)).cos();
+                    (channel * 255.0).round() as u8
+                });
+                texels.extend([r, g, b, 255]);
+            }
+        }
+        texels
+    })
+}
@@ -0,0 +1,38 @@
+// Draws unlit, textured quads standing in the scene, one instance per painting.
+// Expects `camera.wgsl` to be prepended.
+
+@group(1) @binding(0)
+var canvas_texture: texture_2d<f32>;
+@group(1) @binding(1)
+var canvas_sampler: sampler;
+
+struct Instance {
+    @location(0) center: vec3<f32>,
+    // Half of the width, along the painting's horizontal axis
+    @location(1) right: vec3<f32>,
+    // Half of the height, along the painting's vertical axis
+    @location(2) up: vec3<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) in_vertex_index: u32, instance: Instance) -> VertexOutput {
+    // Two triangles forming a quad, as a triangle strip, counter-clockwise seen from the front
+    let corner = vec2<f32>(f32(in_vertex_index & 1u), f32((in_vertex_index >> 1u) & 1u));
+    let offset = corner * 2.0 - 1.0;
+    let world_position = instance.center + instance.right * offset.x + instance.up * offset.y;
+
+    var out: VertexOutput;
+    out.position = camera.view_projection * vec4<f32>(world_position, 1.0);
+    out.uv = vec2<f32>(corner.x, 1.0 - corner.y);
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(textureSample(canvas_texture, canvas_sampler, in.uv).rgb, 1.0);
+}
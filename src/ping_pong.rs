@@ -0,0 +1,65 @@
+use wgpu::{BindGroup, Device, Texture, TextureDescriptor, TextureView, TextureViewDescriptor};
+
+/// Two textures of the same size and format, one holding the current state of a simulation or
+/// image, and one the next state is written to, after which they swap roles.
+///
+/// Feedback effects, blur chains and simulations on the GPU all read the previous result while
+/// writing the next one, which cannot be the same texture. Each texture has its own bind group,
+/// which reads it as the current state and writes the other one as the next state, so passes only
+/// need to bind [`PingPongTarget::bind_group`] and call [`PingPongTarget::swap`] afterwards.
+pub struct PingPongTarget {
+    textures: [Texture; 2],
+    views: [TextureView; 2],
+    /// Bind groups used while the first and the second texture is the current one, respectively.
+    bind_groups: [BindGroup; 2],
+    /// Index of the texture holding the current state.
+    current: usize,
+}
+
+impl PingPongTarget {
+    /// Creates both textures, with bind groups created from views of the current and the next
+    /// texture, in that order.
+    pub fn new(
+        device: &Device,
+        descriptor: &TextureDescriptor,
+        create_bind_group: impl Fn(&TextureView, &TextureView) -> BindGroup,
+    ) -> Self {
+        let textures = [0, 1].map(|_| device.create_texture(descriptor));
+        let views = textures
+            .each_ref()
+            .map(|texture| texture.create_view(&TextureViewDescriptor::default()));
+        let bind_groups =
+            [0, 1].map(|current| create_bind_group(&views[current], &views[1 - current]));
+        Self {
+            textures,
+            views,
+            bind_groups,
+            current: 0,
+        }
+    }
+
+    /// The texture holding the current state.
+    pub fn current(&self) -> &Texture {
+        &self.textures[self.current]
+    }
+
+    /// View of the texture holding the current state.
+    pub fn current_view(&self) -> &TextureView {
+        &self.views[self.current]
+    }
+
+    /// View of the texture the next state is written to, e.g. to render into it.
+    pub fn next_view(&self) -> &TextureView {
+        &self.views[1 - self.current]
+    }
+
+    /// Bind group reading the current state and writing the next one.
+    pub fn bind_group(&self) -> &BindGroup {
+        &self.bind_groups[self.current]
+    }
+
+    /// Makes the next state the current one, once it has been written.
+    pub fn swap(&mut self) {
+        self.current = 1 - self.current;
+    }
+}
@@ -0,0 +1,289 @@
+use std::{borrow::Cow, ops::Range};
+
+use cgmath::Point3;
+use wgpu::{
+    BindGroup, BindGroupLayout, BlendState, Buffer, BufferAddress, BufferDescriptor, BufferUsages,
+    ColorTargetState, ColorWrites, CompareFunction, DepthStencilState, Device, FragmentState,
+    MultisampleState, PipelineCompilationOptions, PipelineLayoutDescriptor, PrimitiveState,
+    PrimitiveTopology, Queue, RenderPass, RenderPipeline, RenderPipelineDescriptor, ShaderModule,
+    ShaderModuleDescriptor, TextureFormat, VertexBufferLayout, VertexState, VertexStepMode,
+};
+
+/// Vertex of a line or point, in world space.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct PrimitiveVertex {
+    pub position: [f32; 3],
+    pub color: [f32; 4],
+}
+
+impl PrimitiveVertex {
+    const ATTRIBUTES: [wgpu::VertexAttribute; 2] =
+        wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x4];
+
+    fn layout() -> VertexBufferLayout<'static> {
+        VertexBufferLayout {
+            array_stride: size_of::<Self>() as BufferAddress,
+            step_mode: VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+}
+
+/// How primitives interact with the depth buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DepthMode {
+    /// Depth tested and written, for opaque geometry.
+    Write,
+    /// Depth tested but not written, for transparent overlays that must not hide each other.
+    Test,
+    /// Drawn on top of everything, in submission order.
+    Ignore,
+}
+
+/// Vertices of one kind of primitive, collected while a frame is built.
+#[derive(Default)]
+struct Batch {
+    vertices: Vec<PrimitiveVertex>,
+    // Separate draws within `vertices`, only used for line strips
+    draws: Vec<Range<u32>>,
+}
+
+/// Draws lines and points submitted during a frame.
+///
+/// Geometry is submitted through [`PrimitiveRenderer::line`], [`PrimitiveRenderer::line_strip`]
+/// and [`PrimitiveRenderer::point`], uploaded in [`PrimitiveRenderer::prepare`] and drawn
+/// (once) in [`PrimitiveRenderer::render`].
+/// Points are always rasterized as a single pixel, as WebGPU has no point size.
+pub struct PrimitiveRenderer {
+    line_list_pipeline: RenderPipeline,
+    line_strip_pipeline: RenderPipeline,
+    point_list_pipeline: RenderPipeline,
+    vertex_buffer: Buffer,
+    lines: Batch,
+    line_strips: Batch,
+    points: Batch,
+    // Vertex ranges of the uploaded batches, in the order lines, line strips, points
+    uploaded: [Vec<Range<u32>>; 3],
+}
+
+impl PrimitiveRenderer {
+    const INITIAL_CAPACITY: BufferAddress = 4096;
+
+    pub fn new(
+        device: &Device,
+        camera_bind_group_layout: &BindGroupLayout,
+        color_format: TextureFormat,
+        depth_format: TextureFormat,
+        depth_mode: DepthMode,
+    ) -> Self {
+        let shader_module = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("Primitives shader module"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(concat!(
+                include_str!("./camera.wgsl"),
+                include_str!("./primitives.wgsl")
+            ))),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Primitives pipeline layout"),
+            bind_group_layouts: &[camera_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let depth_stencil = DepthStencilState {
+            format: depth_format,
+            depth_write_enabled: depth_mode == DepthMode::Write,
+            depth_compare: match depth_mode {
+                DepthMode::Write | DepthMode::Test => CompareFunction::GreaterEqual,
+                DepthMode::Ignore => CompareFunction::Always,
+            },
+            stencil: Default::default(),
+            bias: Default::default(),
+        };
+        let create_pipeline = |label, topology| {
+            create_pipeline(
+                device,
+                label,
+                &pipeline_layout,
+                &shader_module,
+                topology,
+                color_format,
+                depth_stencil.clone(),
+            )
+        };
+
+        Self {
+            line_list_pipeline: create_pipeline("Line list pipeline", PrimitiveTopology::LineList),
+            line_strip_pipeline: create_pipeline(
+                "Line strip pipeline",
+                PrimitiveTopology::LineStrip,
+            ),
+            point_list_pipeline: create_pipeline(
+                "Point list pipeline",
+                PrimitiveTopology::PointList,
+            ),
+            vertex_buffer: create_vertex_buffer(device, Self::INITIAL_CAPACITY),
+            lines: Batch::default(),
+            line_strips: Batch::default(),
+            points: Batch::default(),
+            uploaded: Default::default(),
+        }
+    }
+
+    pub fn line(&mut self, from: Point3<f32>, to: Point3<f32>, color: [f32; 4]) {
+        self.line_gradient(from, color, to, color);
+    }
+
+    /// Draws a line whose color is interpolated between its end points.
+    pub fn line_gradient(
+        &mut self,
+        from: Point3<f32>,
+        from_color: [f32; 4],
+        to: Point3<f32>,
+        to_color: [f32; 4],
+    ) {
+        self.lines.vertices.extend([
+            PrimitiveVertex {
+                position: from.into(),
+                color: from_color,
+            },
+            PrimitiveVertex {
+                position: to.into(),
+                color: to_color,
+            },
+        ]);
+    }
+
+    /// Connects all points with lines, in order.
+    pub fn line_strip(&mut self, points: impl IntoIterator<Item = Point3<f32>>, color: [f32; 4]) {
+        let start = self.line_strips.vertices.len() as u32;
+        self.line_strips
+            .vertices
+            .extend(points.into_iter().map(|point| PrimitiveVertex {
+                position: point.into(),
+                color,
+            }));
+        let end = self.line_strips.vertices.len() as u32;
+        if end - start >= 2 {
+            self.line_strips.draws.push(start..end);
+        } else {
+            self.line_strips.vertices.truncate(start as usize);
+        }
+    }
+
+    pub fn point(&mut self, position: Point3<f32>, color: [f32; 4]) {
+        self.points.vertices.push(PrimitiveVertex {
+            position: position.into(),
+            color,
+        });
+    }
+
+    /// Uploads the geometry submitted since the previous call.
+    pub fn prepare(&mut self, device: &Device, queue: &Queue) {
+        let vertex_count = self.lines.vertices.len()
+            + self.line_strips.vertices.len()
+            + self.points.vertices.len();
+        let size = (vertex_count * size_of::<PrimitiveVertex>()) as BufferAddress;
+        if size > self.vertex_buffer.size() {
+            self.vertex_buffer = create_vertex_buffer(device, size.next_power_of_two());
+        }
+
+        let mut offset = 0;
+        for (batch, uploaded) in [&mut self.lines, &mut self.line_strips, &mut self.points]
+            .into_iter()
+            .zip(&mut self.uploaded)
+        {
+            let len = batch.vertices.len() as u32;
+            uploaded.clear();
+            if batch.draws.is_empty() {
+                uploaded.push(offset..offset + len);
+            } else {
+                uploaded.extend(
+                    batch
+                        .draws
+                        .drain(..)
+                        .map(|draw| draw.start + offset..draw.end + offset),
+                );
+            }
+            if len > 0 {
+                queue.write_buffer(
+                    &self.vertex_buffer,
+                    offset as BufferAddress * size_of::<PrimitiveVertex>() as BufferAddress,
+                    bytemuck::cast_slice(&batch.vertices),
+                );
+            }
+            batch.vertices.clear();
+            offset += len;
+        }
+    }
+
+    pub fn render(&self, render_pass: &mut RenderPass, camera_bind_group: &BindGroup) {
+        if self.uploaded.iter().flatten().all(Range::is_empty) {
+            return;
+        }
+        render_pass.set_bind_group(0, camera_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+
+        let pipelines = [
+            &self.line_list_pipeline,
+            &self.line_strip_pipeline,
+            &self.point_list_pipeline,
+        ];
+        for (pipeline, draws) in pipelines.into_iter().zip(&self.uploaded) {
+            if draws.iter().all(Range::is_empty) {
+                continue;
+            }
+            render_pass.set_pipeline(pipeline);
+            for draw in draws.iter().filter(|draw| !draw.is_empty()) {
+                render_pass.draw(draw.clone(), 0..1);
+            }
+        }
+    }
+}
+
+fn create_vertex_buffer(device: &Device, size: BufferAddress) -> Buffer {
+    device.create_buffer(&BufferDescriptor {
+        label: Some("Primitives vertex buffer"),
+        size,
+        usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+fn create_pipeline(
+    device: &Device,
+    label: &str,
+    layout: &wgpu::PipelineLayout,
+    shader_module: &ShaderModule,
+    topology: PrimitiveTopology,
+    color_format: TextureFormat,
+    depth_stencil: DepthStencilState,
+) -> RenderPipeline {
+    device.create_render_pipeline(&RenderPipelineDescriptor {
+        label: Some(label),
+        layout: Some(layout),
+        vertex: VertexState {
+            module: shader_module,
+            entry_point: None,
+            compilation_options: PipelineCompilationOptions::default(),
+            buffers: &[PrimitiveVertex::layout()],
+        },
+        primitive: PrimitiveState {
+            topology,
+            ..Default::default()
+        },
+        depth_stencil: Some(depth_stencil),
+        multisample: MultisampleState::default(),
+        fragment: Some(FragmentState {
+            module: shader_module,
+            entry_point: None,
+            compilation_options: PipelineCompilationOptions::default(),
+            targets: &[Some(ColorTargetState {
+                format: color_format,
+                blend: Some(BlendState::ALPHA_BLENDING),
+                write_mask: ColorWrites::default(),
+            })],
+        }),
+        multiview: None,
+        cache: None,
+    })
+}
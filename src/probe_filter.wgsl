@@ -0,0 +1,61 @@
+// Convolves the surroundings captured by a reflection probe, see `ReflectionProbes`.
+// Expects `cube_face.wgsl` to be prepended.
+//
+// Each mip level of the prefiltered cube map, selected by the instance index, holds the
+// reflection seen by surfaces of increasing roughness: the capture averaged over the specular
+// lobe of `shade_light` in `lighting.wgsl`. The irradiance cube map holds the light received
+// by diffuse surfaces, the capture averaged over the hemisphere weighted by the cosine.
+
+@group(0) @binding(0)
+var capture_texture: texture_cube<f32>;
+@group(0) @binding(1)
+var capture_sampler: sampler;
+
+// Must match `PREFILTERED_LEVELS` of `probes.rs`
+const prefiltered_levels: u32 = 5u;
+const pi: f32 = 3.14159265;
+
+// Low-discrepancy points in the unit square, which cover it more evenly than random points.
+fn hammersley(i: u32, count: u32) -> vec2<f32> {
+    return vec2<f32>(f32(i) / f32(count), f32(reverseBits(i)) * 2.3283064e-10);
+}
+
+// Averages the capture around a direction, weighted by the cosine of the angle to it raised to
+// an exponent, by sampling directions with a density proportional to that weight.
+fn convolve(direction: vec3<f32>, exponent: f32, sample_count: u32) -> vec3<f32> {
+    let up = select(vec3<f32>(0.0, 1.0, 0.0), vec3<f32>(1.0, 0.0, 0.0), abs(direction.y) > 0.99);
+    let tangent = normalize(cross(up, direction));
+    let bitangent = cross(direction, tangent);
+
+    var sum = vec3<f32>(0.0);
+    for (var i = 0u; i < sample_count; i++) {
+        let xi = hammersley(i, sample_count);
+        let cos_theta = pow(xi.x, 1.0 / (exponent + 1.0));
+        let sin_theta = sqrt(1.0 - cos_theta * cos_theta);
+        let phi = 2.0 * pi * xi.y;
+        let sample_direction = tangent * sin_theta * cos(phi) + bitangent * sin_theta * sin(phi)
+            + direction * cos_theta;
+        sum += textureSampleLevel(capture_texture, capture_sampler, sample_direction, 0.0).rgb;
+    }
+    return sum / f32(sample_count);
+}
+
+@fragment
+fn fs_prefilter(in: VertexOutput) -> @location(0) vec4<f32> {
+    let direction = normalize(face_direction(in.face, in.uv));
+    if in.instance == 0u {
+        return vec4<f32>(textureSampleLevel(capture_texture, capture_sampler, direction, 0.0).rgb, 1.0);
+    }
+
+    // The roughness maps to the shininess like in `shade_light`. Its lobe around the half vector
+    // is approximately as wide as a lobe with a quarter of the exponent around the reflection.
+    let roughness = f32(in.instance) / f32(prefiltered_levels - 1u);
+    let shininess = exp2(12.0 * (1.0 - roughness) + 1.0);
+    return vec4<f32>(convolve(direction, shininess / 4.0, 128u), 1.0);
+}
+
+@fragment
+fn fs_irradiance(in: VertexOutput) -> @location(0) vec4<f32> {
+    let direction = normalize(face_direction(in.face, in.uv));
+    return vec4<f32>(convolve(direction, 1.0, 256u), 1.0);
+}
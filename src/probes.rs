@@ -0,0 +1,541 @@
+use std::{borrow::Cow, num::NonZeroU64};
+
+use cgmath::{Deg, Point3, Vector3};
+use wgpu::{
+    util::{BufferInitDescriptor, DeviceExt},
+    AddressMode, BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout,
+    BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingResource, BindingType, Buffer,
+    BufferAddress, BufferBinding, BufferBindingType, BufferDescriptor, BufferUsages, Color,
+    ColorTargetState, ColorWrites, CommandEncoder, Device, Extent3d, FilterMode, FragmentState,
+    LoadOp, MultisampleState, Operations, PipelineCompilationOptions, PipelineLayoutDescriptor,
+    PrimitiveState, Queue, RenderPass, RenderPassColorAttachment, RenderPassDepthStencilAttachment,
+    RenderPassDescriptor, RenderPipeline, RenderPipelineDescriptor, SamplerBindingType,
+    SamplerDescriptor, ShaderModuleDescriptor, ShaderStages, StoreOp, Texture, TextureDescriptor,
+    TextureDimension, TextureFormat, TextureSampleType, TextureUsages, TextureView,
+    TextureViewDescriptor, TextureViewDimension, VertexState,
+};
+
+use crate::camera::{Camera, CameraUniform};
+
+/// Side length of the captured faces, and of the largest mip level of the prefiltered faces.
+const CAPTURE_SIZE: u32 = 64;
+/// Must match `prefiltered_levels` of `probe_filter.wgsl`.
+const PREFILTERED_LEVELS: u32 = 5;
+/// Diffuse light changes slowly with the direction, so a few texels per face suffice.
+const IRRADIANCE_SIZE: u32 = 8;
+const FORMAT: TextureFormat = TextureFormat::Rgba16Float;
+
+/// Direction and up vector of the camera capturing each face, in the order of the faces of a
+/// cube map.
+///
+/// Cameras see a mirror image of how cube maps are addressed, so rather than mirroring each
+/// face, which would reverse the winding order of the triangles drawn, the scene is captured
+/// mirrored along Z, see `probe_direction` of `probes.wgsl`.
+const FACE_CAMERAS: [(Vector3<f32>, Vector3<f32>); 6] = [
+    (Vector3::new(1.0, 0.0, 0.0), Vector3::new(0.0, 1.0, 0.0)),
+    (Vector3::new(-1.0, 0.0, 0.0), Vector3::new(0.0, 1.0, 0.0)),
+    (Vector3::new(0.0, 1.0, 0.0), Vector3::new(0.0, 0.0, 1.0)),
+    (Vector3::new(0.0, -1.0, 0.0), Vector3::new(0.0, 0.0, -1.0)),
+    (Vector3::new(0.0, 0.0, -1.0), Vector3::new(0.0, 1.0, 0.0)),
+    (Vector3::new(0.0, 0.0, 1.0), Vector3::new(0.0, 1.0, 0.0)),
+];
+
+/// A point from which the surroundings are captured, for the reflections and the diffuse
+/// light of the surfaces within its box.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReflectionProbe {
+    /// Where the surroundings are captured from, the center of the box.
+    pub position: Point3<f32>,
+    /// Half of the size of the box, which should roughly match the surroundings,
+    /// as reflections are projected onto it.
+    pub half_extent: Vector3<f32>,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct GpuReflectionProbe {
+    position: [f32; 4],
+    half_extent: [f32; 4],
+}
+
+/// The probes as seen by `probes.wgsl`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct ProbeListUniform {
+    count: u32,
+    padding: [u32; 3],
+    probes: [GpuReflectionProbe; ReflectionProbes::MAX_PROBES],
+}
+
+/// Reflection probes, baked by rendering the scene into a cube map from the position of each
+/// probe, which is then convolved for the reflections of rough surfaces and for diffuse light.
+///
+/// Lit surfaces shade themselves with the nearest probe whose box contains them, bound as
+/// `@group(2)` of `probes.wgsl`. Probes are baked when they are placed or moved, and when
+/// [`ReflectionProbes::invalidate`] is called after the scene changed.
+pub struct ReflectionProbes {
+    /// Probes submitted for the current frame.
+    probes: Vec<ReflectionProbe>,
+    /// Slots of the cube map arrays, fewer than [`ReflectionProbes::MAX_PROBES`] if the device's
+    /// texture arrays cannot hold the faces of all of them.
+    capacity: usize,
+    /// Side length of the captured faces, at most [`CAPTURE_SIZE`].
+    capture_size: u32,
+    /// The probe baked into each slot of the cube map arrays, if any.
+    baked: [Option<ReflectionProbe>; Self::MAX_PROBES],
+    /// Slots to bake in the current frame.
+    pending: Vec<usize>,
+    list_buffer: Buffer,
+    bind_group_layout: BindGroupLayout,
+    bind_group: BindGroup,
+    /// Binds no probes at all.
+    empty_bind_group: BindGroup,
+    /// A camera per face of each slot.
+    camera_buffer: Buffer,
+    camera_bind_groups: Vec<BindGroup>,
+    capture_texture: Texture,
+    capture_depth_view: TextureView,
+    filter_bind_group: BindGroup,
+    prefilter_pipeline: RenderPipeline,
+    irradiance_pipeline: RenderPipeline,
+    prefiltered_texture: Texture,
+    irradiance_texture: Texture,
+}
+
+impl ReflectionProbes {
+    /// Must match `max_probes` of `probes.wgsl`.
+    pub const MAX_PROBES: usize = 4;
+
+    pub fn new(
+        device: &Device,
+        camera_bind_group_layout: &BindGroupLayout,
+        color_format: TextureFormat,
+        depth_format: TextureFormat,
+    ) -> Self {
+        let limits = device.limits();
+        let capacity = Self::MAX_PROBES.min(limits.max_texture_array_layers as usize / 6);
+        let capture_size = CAPTURE_SIZE.min(limits.max_texture_dimension_2d);
+        let cube_array_texture = |label, size, mip_level_count| {
+            device.create_texture(&TextureDescriptor {
+                label: Some(label),
+                size: Extent3d {
+                    width: size,
+                    height: size,
+                    depth_or_array_layers: 6 * capacity as u32,
+                },
+                mip_level_count,
+                sample_count: 1,
+                dimension: TextureDimension::D2,
+                format: FORMAT,
+                usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            })
+        };
+        // Smaller faces have fewer mip levels, the roughest ones are then missing
+        let prefiltered_texture = cube_array_texture(
+            "Probe prefiltered texture",
+            capture_size,
+            PREFILTERED_LEVELS.min(capture_size.ilog2() + 1),
+        );
+        let irradiance_texture = cube_array_texture(
+            "Probe irradiance texture",
+            IRRADIANCE_SIZE.min(capture_size),
+            1,
+        );
+        let sampler = device.create_sampler(&SamplerDescriptor {
+            label: Some("Probe sampler"),
+            address_mode_u: AddressMode::ClampToEdge,
+            address_mode_v: AddressMode::ClampToEdge,
+            address_mode_w: AddressMode::ClampToEdge,
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            mipmap_filter: FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let cube_entry = |binding, view_dimension| BindGroupLayoutEntry {
+            binding,
+            visibility: ShaderStages::FRAGMENT,
+            ty: BindingType::Texture {
+                sample_type: TextureSampleType::Float { filterable: true },
+                view_dimension,
+                multisampled: false,
+            },
+            count: None,
+        };
+        let sampler_entry = |binding| BindGroupLayoutEntry {
+            binding,
+            visibility: ShaderStages::FRAGMENT,
+            ty: BindingType::Sampler(SamplerBindingType::Filtering),
+            count: None,
+        };
+        let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Probe bind group layout"),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                cube_entry(1, TextureViewDimension::CubeArray),
+                cube_entry(2, TextureViewDimension::CubeArray),
+                sampler_entry(3),
+            ],
+        });
+        let cube_array_view = |texture: &Texture| {
+            texture.create_view(&TextureViewDescriptor {
+                label: Some("Probe cube array view"),
+                dimension: Some(TextureViewDimension::CubeArray),
+                ..Default::default()
+            })
+        };
+        let prefiltered_view = cube_array_view(&prefiltered_texture);
+        let irradiance_view = cube_array_view(&irradiance_texture);
+        let create_bind_group = |label, buffer: &Buffer| {
+            device.create_bind_group(&BindGroupDescriptor {
+                label: Some(label),
+                layout: &bind_group_layout,
+                entries: &[
+                    BindGroupEntry {
+                        binding: 0,
+                        resource: buffer.as_entire_binding(),
+                    },
+                    BindGroupEntry {
+                        binding: 1,
+                        resource: BindingResource::TextureView(&prefiltered_view),
+                    },
+                    BindGroupEntry {
+                        binding: 2,
+                        resource: BindingResource::TextureView(&irradiance_view),
+                    },
+                    BindGroupEntry {
+                        binding: 3,
+                        resource: BindingResource::Sampler(&sampler),
+                    },
+                ],
+            })
+        };
+        let list_buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("Probe list buffer"),
+            size: size_of::<ProbeListUniform>() as BufferAddress,
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group = create_bind_group("Probe bind group", &list_buffer);
+        // Probes are captured without the light of other probes, so that a bake doesn't
+        // depend on earlier bakes, nor reads the textures it writes.
+        let empty_list_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Empty probe list buffer"),
+            contents: bytemuck::bytes_of(&<ProbeListUniform as bytemuck::Zeroable>::zeroed()),
+            usage: BufferUsages::UNIFORM,
+        });
+        let empty_bind_group = create_bind_group("Empty probe bind group", &empty_list_buffer);
+
+        // A camera per face of every slot, as all of them may be captured in the same frame
+        let camera_size = size_of::<CameraUniform>() as BufferAddress;
+        let camera_stride = camera_size.next_multiple_of(BufferAddress::from(
+            device.limits().min_uniform_buffer_offset_alignment,
+        ));
+        let camera_buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("Probe camera buffer"),
+            size: camera_stride * 6 * capacity as BufferAddress,
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let camera_bind_groups = (0..6 * capacity as BufferAddress)
+            .map(|index| {
+                device.create_bind_group(&BindGroupDescriptor {
+                    label: Some("Probe camera bind group"),
+                    layout: camera_bind_group_layout,
+                    entries: &[BindGroupEntry {
+                        binding: 0,
+                        resource: BindingResource::Buffer(BufferBinding {
+                            buffer: &camera_buffer,
+                            offset: index * camera_stride,
+                            size: NonZeroU64::new(camera_size),
+                        }),
+                    }],
+                })
+            })
+            .collect();
+
+        // The capture is drawn by the pipelines of the scene
+        let capture_texture = device.create_texture(&TextureDescriptor {
+            label: Some("Probe capture texture"),
+            size: Extent3d {
+                width: capture_size,
+                height: capture_size,
+                depth_or_array_layers: 6,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: color_format,
+            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        let capture_depth_view = device
+            .create_texture(&TextureDescriptor {
+                label: Some("Probe capture depth texture"),
+                size: Extent3d {
+                    width: capture_size,
+                    height: capture_size,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: TextureDimension::D2,
+                format: depth_format,
+                usage: TextureUsages::RENDER_ATTACHMENT,
+                view_formats: &[],
+            })
+            .create_view(&TextureViewDescriptor::default());
+
+        let filter_bind_group_layout =
+            device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+                label: Some("Probe filter bind group layout"),
+                entries: &[cube_entry(0, TextureViewDimension::Cube), sampler_entry(1)],
+            });
+        let filter_bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Probe filter bind group"),
+            layout: &filter_bind_group_layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(&capture_texture.create_view(
+                        &TextureViewDescriptor {
+                            label: Some("Probe capture view"),
+                            dimension: Some(TextureViewDimension::Cube),
+                            ..Default::default()
+                        },
+                    )),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::Sampler(&sampler),
+                },
+            ],
+        });
+        let shader_module = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("Probe filter shader module"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(concat!(
+                include_str!("./cube_face.wgsl"),
+                include_str!("./probe_filter.wgsl")
+            ))),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Probe filter pipeline layout"),
+            bind_group_layouts: &[&filter_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let create_pipeline = |label, entry_point| {
+            device.create_render_pipeline(&RenderPipelineDescriptor {
+                label: Some(label),
+                layout: Some(&pipeline_layout),
+                vertex: VertexState {
+                    module: &shader_module,
+                    entry_point: None,
+                    compilation_options: PipelineCompilationOptions::default(),
+                    buffers: &[],
+                },
+                primitive: PrimitiveState::default(),
+                depth_stencil: None,
+                multisample: MultisampleState::default(),
+                fragment: Some(FragmentState {
+                    module: &shader_module,
+                    entry_point: Some(entry_point),
+                    compilation_options: PipelineCompilationOptions::default(),
+                    targets: &[Some(ColorTargetState {
+                        format: FORMAT,
+                        blend: None,
+                        write_mask: ColorWrites::default(),
+                    })],
+                }),
+                multiview: None,
+                cache: None,
+            })
+        };
+        let prefilter_pipeline = create_pipeline("Probe prefilter pipeline", "fs_prefilter");
+        let irradiance_pipeline = create_pipeline("Probe irradiance pipeline", "fs_irradiance");
+
+        Self {
+            probes: Vec::new(),
+            capacity,
+            capture_size,
+            baked: [None; Self::MAX_PROBES],
+            pending: Vec::new(),
+            list_buffer,
+            bind_group_layout,
+            bind_group,
+            empty_bind_group,
+            camera_buffer,
+            camera_bind_groups,
+            capture_texture,
+            capture_depth_view,
+            filter_bind_group,
+            prefilter_pipeline,
+            irradiance_pipeline,
+            prefiltered_texture,
+            irradiance_texture,
+        }
+    }
+
+    pub fn bind_group_layout(&self) -> &BindGroupLayout {
+        &self.bind_group_layout
+    }
+
+    /// Binds the probes submitted for the current frame.
+    pub fn bind_group(&self) -> &BindGroup {
+        &self.bind_group
+    }
+
+    /// Binds no probes, for the passes capturing them.
+    pub fn empty_bind_group(&self) -> &BindGroup {
+        &self.empty_bind_group
+    }
+
+    /// Submits a probe for the current frame, probes beyond [`ReflectionProbes::MAX_PROBES`],
+    /// or the fewer slots the device has room for, are ignored.
+    pub fn draw(&mut self, probe: ReflectionProbe) {
+        if self.probes.len() < self.capacity {
+            self.probes.push(probe);
+        }
+    }
+
+    /// Bakes all probes again, e.g. after the scene changed.
+    pub fn invalidate(&mut self) {
+        self.baked = [None; Self::MAX_PROBES];
+    }
+
+    /// Uploads the probes submitted since the previous call, and the cameras capturing the
+    /// probes that are new or have changed, see [`ReflectionProbes::pending`].
+    pub fn prepare(&mut self, queue: &Queue) {
+        let camera_stride = self.camera_buffer.size() / self.camera_bind_groups.len() as u64;
+        let mut list = <ProbeListUniform as bytemuck::Zeroable>::zeroed();
+        list.count = self.probes.len() as u32;
+        self.pending.clear();
+        for (slot, probe) in self.probes.drain(..).enumerate() {
+            list.probes[slot] = GpuReflectionProbe {
+                position: probe.position.to_homogeneous().into(),
+                half_extent: probe.half_extent.extend(0.0).into(),
+            };
+            if self.baked[slot] == Some(probe) {
+                continue;
+            }
+            self.baked[slot] = Some(probe);
+            self.pending.push(slot);
+            for (face, (forward, up)) in FACE_CAMERAS.into_iter().enumerate() {
+                let camera = Camera {
+                    eye: probe.position,
+                    target: probe.position + forward,
+                    up,
+                    fovy: Deg(90.0),
+                    aspect: 1.0,
+                    znear: 0.05,
+                    zfar: 1000.0,
+                };
+                queue.write_buffer(
+                    &self.camera_buffer,
+                    (slot * 6 + face) as BufferAddress * camera_stride,
+                    bytemuck::bytes_of(&CameraUniform::new(
+                        &camera,
+                        self.capture_size,
+                        self.capture_size,
+                    )),
+                );
+            }
+        }
+        queue.write_buffer(&self.list_buffer, 0, bytemuck::bytes_of(&list));
+    }
+
+    /// Slots of the probes to bake in the current frame: for each of them, every face is
+    /// captured with [`ReflectionProbes::begin_capture_pass`], then
+    /// [`ReflectionProbes::filter`] is called.
+    pub fn pending(&self) -> &[usize] {
+        &self.pending
+    }
+
+    /// Camera bind group capturing a face of the probe in a slot.
+    pub fn camera_bind_group(&self, slot: usize, face: u32) -> &BindGroup {
+        &self.camera_bind_groups[slot * 6 + face as usize]
+    }
+
+    /// Begins the pass capturing a face, clearing the capture's targets.
+    pub fn begin_capture_pass<'encoder>(
+        &self,
+        command_encoder: &'encoder mut CommandEncoder,
+        face: u32,
+    ) -> RenderPass<'encoder> {
+        let face_view = self.capture_texture.create_view(&TextureViewDescriptor {
+            label: Some("Probe capture face view"),
+            dimension: Some(TextureViewDimension::D2),
+            base_array_layer: face,
+            array_layer_count: Some(1),
+            ..Default::default()
+        });
+        command_encoder.begin_render_pass(&RenderPassDescriptor {
+            label: Some("Probe capture pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: &face_view,
+                resolve_target: None,
+                ops: Operations {
+                    load: LoadOp::Clear(Color::BLACK),
+                    store: StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: Some(RenderPassDepthStencilAttachment {
+                view: &self.capture_depth_view,
+                depth_ops: Some(Operations {
+                    load: LoadOp::Clear(0.0),
+                    store: StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        })
+    }
+
+    /// Convolves the captured faces into the slot of a probe.
+    pub fn filter(&self, command_encoder: &mut CommandEncoder, slot: usize) {
+        let targets = (0..self.prefiltered_texture.mip_level_count())
+            .map(|level| (&self.prefiltered_texture, &self.prefilter_pipeline, level))
+            .chain([(&self.irradiance_texture, &self.irradiance_pipeline, 0)]);
+        for (texture, pipeline, level) in targets {
+            for face in 0..6 {
+                let face_view = texture.create_view(&TextureViewDescriptor {
+                    label: Some("Probe face view"),
+                    dimension: Some(TextureViewDimension::D2),
+                    base_mip_level: level,
+                    mip_level_count: Some(1),
+                    base_array_layer: slot as u32 * 6 + face,
+                    array_layer_count: Some(1),
+                    ..Default::default()
+                });
+                let mut render_pass = command_encoder.begin_render_pass(&RenderPassDescriptor {
+                    label: Some("Probe filter pass"),
+                    color_attachments: &[Some(RenderPassColorAttachment {
+                        view: &face_view,
+                        resolve_target: None,
+                        ops: Operations {
+                            load: LoadOp::Clear(Color::BLACK),
+                            store: StoreOp::Store,
+                        },
+                    })],
+                    depth_stencil_attachment: None,
+                    timestamp_writes: None,
+                    occlusion_query_set: None,
+                });
+                render_pass.set_pipeline(pipeline);
+                render_pass.set_bind_group(0, &self.filter_bind_group, &[]);
+                // The instance index selects the mip level, see `probe_filter.wgsl`
+                render_pass.draw(face * 3..face * 3 + 3, level..level + 1);
+            }
+        }
+    }
+}
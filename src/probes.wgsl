@@ -0,0 +1,99 @@
+// Light captured by the reflection probes placed in the scene, see `ReflectionProbes`.
+// Expects `camera.wgsl` and `lighting.wgsl` to be prepended.
+//
+// A surface uses the nearest of the probes whose box contains it. Its reflection is looked up
+// in the direction from the probe to where the reflected ray leaves the box, which stands in
+// for the surroundings of the probe ("box projection"), so that nearby objects are reflected
+// in the right place rather than as if they were infinitely far away.
+
+struct ReflectionProbe {
+    // Where the probe was captured, the center of its box, w is unused
+    position: vec4<f32>,
+    // Half of the size of the box, w is unused
+    half_extent: vec4<f32>,
+}
+
+// Must match `ReflectionProbes::MAX_PROBES` of `probes.rs`
+const max_probes: u32 = 4u;
+
+struct ProbeList {
+    count: u32,
+    probes: array<ReflectionProbe, max_probes>,
+}
+
+@group(2) @binding(0)
+var<uniform> probe_list: ProbeList;
+// One cube per probe, each mip level reflecting a rougher surface
+@group(2) @binding(1)
+var prefiltered_texture: texture_cube_array<f32>;
+@group(2) @binding(2)
+var irradiance_texture: texture_cube_array<f32>;
+@group(2) @binding(3)
+var probe_sampler: sampler;
+
+// Returns the index of the nearest probe whose box contains a position, -1 if there is none.
+fn nearest_probe(position: vec3<f32>) -> i32 {
+    var nearest = -1;
+    var nearest_distance = 0.0;
+    for (var i = 0u; i < probe_list.count; i++) {
+        let probe = probe_list.probes[i];
+        let offset = position - probe.position.xyz;
+        let distance = dot(offset, offset);
+        if all(abs(offset) <= probe.half_extent.xyz) && (nearest < 0 || distance < nearest_distance) {
+            nearest = i32(i);
+            nearest_distance = distance;
+        }
+    }
+    return nearest;
+}
+
+// Intersects a ray starting inside of the box of a probe with the box,
+// and returns the direction from the probe to the intersection.
+fn box_project(position: vec3<f32>, direction: vec3<f32>, probe: ReflectionProbe) -> vec3<f32> {
+    let to_max = (probe.position.xyz + probe.half_extent.xyz - position) / direction;
+    let to_min = (probe.position.xyz - probe.half_extent.xyz - position) / direction;
+    let exit = max(to_max, to_min);
+    let distance = min(min(exit.x, exit.y), exit.z);
+    return position + direction * distance - probe.position.xyz;
+}
+
+// The probes are captured mirrored along Z, see `face_camera` of `probes.rs`.
+fn probe_direction(direction: vec3<f32>) -> vec3<f32> {
+    return direction * vec3<f32>(1.0, 1.0, -1.0);
+}
+
+// Shades a surface with the sun and the light captured by its nearest probe,
+// or with the sun and ambient light like `shade_environment` outside of all probes.
+fn shade_probes(surface: Surface) -> vec3<f32> {
+    let index = nearest_probe(surface.position);
+    if index < 0 {
+        return shade_environment(surface);
+    }
+    let probe = probe_list.probes[index];
+    let view = normalize(camera.position.xyz - surface.position);
+    let reflected = box_project(surface.position, reflect(-view, surface.normal), probe);
+    let levels = f32(textureNumLevels(prefiltered_texture));
+    let reflection = textureSampleLevel(
+        prefiltered_texture,
+        probe_sampler,
+        probe_direction(reflected),
+        index,
+        surface.roughness * (levels - 1.0),
+    ).rgb;
+    let irradiance = textureSampleLevel(
+        irradiance_texture,
+        probe_sampler,
+        probe_direction(surface.normal),
+        index,
+        0.0,
+    ).rgb;
+
+    // Schlick's approximation of the Fresnel term, which rough surfaces reflect less of
+    let specular_color = mix(vec3<f32>(0.04), surface.albedo, surface.metallic);
+    let n_dot_v = max(dot(surface.normal, view), 0.0);
+    let fresnel = specular_color
+        + (max(vec3<f32>(1.0 - surface.roughness), specular_color) - specular_color) * pow(1.0 - n_dot_v, 5.0);
+    let diffuse = surface.albedo * (1.0 - surface.metallic);
+    return diffuse * irradiance + fresnel * reflection
+        + shade_light(surface, normalize(sun_direction), sun_color * sun_intensity);
+}
@@ -0,0 +1,162 @@
+use std::borrow::Cow;
+
+use wgpu::{
+    BindGroup, BindGroupLayout, Color, ColorTargetState, ColorWrites, CommandEncoder, Device,
+    FragmentState, LoadOp, MultisampleState, Operations, PipelineCompilationOptions,
+    PipelineLayout, PipelineLayoutDescriptor, PrimitiveState, RenderPassColorAttachment,
+    RenderPassDescriptor, RenderPipeline, RenderPipelineDescriptor, ShaderModuleDescriptor,
+    StoreOp, TextureFormat, TextureView, VertexState,
+};
+
+#[cfg(not(target_arch = "wasm32"))]
+use crate::hot_reload::ShaderWatcher;
+
+/// Shaders prepended to `raymarch.wgsl`, which are not reloaded.
+#[cfg(not(target_arch = "wasm32"))]
+const PRELUDE: &str = concat!(
+    include_str!("./camera.wgsl"),
+    include_str!("./sky.wgsl"),
+    include_str!("./globals.wgsl")
+);
+
+/// Location of `raymarch.wgsl` in the source tree, which is watched for changes.
+#[cfg(not(target_arch = "wasm32"))]
+const SHADER_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/src/raymarch.wgsl");
+
+/// Draws a scene described by a signed distance function, ray marched per pixel.
+///
+/// The scene lives entirely in `raymarch.wgsl`. On native targets, the shader is reloaded
+/// from the source tree whenever it is saved, see [`RayMarching::reload_if_modified`].
+pub struct RayMarching {
+    pipeline: RenderPipeline,
+    #[cfg(not(target_arch = "wasm32"))]
+    hot_reload: HotReload,
+}
+
+/// What is needed to rebuild the pipeline from a changed shader.
+#[cfg(not(target_arch = "wasm32"))]
+struct HotReload {
+    pipeline_layout: PipelineLayout,
+    color_format: TextureFormat,
+    watcher: ShaderWatcher,
+}
+
+impl RayMarching {
+    pub fn new(
+        device: &Device,
+        camera_bind_group_layout: &BindGroupLayout,
+        globals_bind_group_layout: &BindGroupLayout,
+        color_format: TextureFormat,
+    ) -> Self {
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Ray marching pipeline layout"),
+            bind_group_layouts: &[camera_bind_group_layout, globals_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = create_pipeline(
+            device,
+            &pipeline_layout,
+            color_format,
+            concat!(
+                include_str!("./camera.wgsl"),
+                include_str!("./sky.wgsl"),
+                include_str!("./globals.wgsl"),
+                include_str!("./raymarch.wgsl")
+            ),
+        );
+
+        Self {
+            pipeline,
+            #[cfg(not(target_arch = "wasm32"))]
+            hot_reload: HotReload {
+                pipeline_layout,
+                color_format,
+                watcher: ShaderWatcher::new(SHADER_PATH),
+            },
+        }
+    }
+
+    /// Rebuilds the pipeline if `raymarch.wgsl` was saved since it was last built.
+    ///
+    /// A shader that fails to compile is logged, and the previous pipeline is kept,
+    /// so mistakes can be fixed without restarting the application.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn reload_if_modified(&mut self, device: &Device) {
+        let hot_reload = &mut self.hot_reload;
+        if let Some(pipeline) = hot_reload.watcher.reload(device, |source| {
+            create_pipeline(
+                device,
+                &hot_reload.pipeline_layout,
+                hot_reload.color_format,
+                &format!("{PRELUDE}{source}"),
+            )
+        }) {
+            self.pipeline = pipeline;
+        }
+    }
+
+    /// Draws the scene into the whole target.
+    pub fn render(
+        &self,
+        command_encoder: &mut CommandEncoder,
+        camera_bind_group: &BindGroup,
+        globals_bind_group: &BindGroup,
+        target: &TextureView,
+    ) {
+        let mut render_pass = command_encoder.begin_render_pass(&RenderPassDescriptor {
+            label: Some("Ray marching pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: Operations {
+                    load: LoadOp::Clear(Color::BLACK),
+                    store: StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, camera_bind_group, &[]);
+        render_pass.set_bind_group(1, globals_bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
+
+fn create_pipeline(
+    device: &Device,
+    layout: &PipelineLayout,
+    color_format: TextureFormat,
+    source: &str,
+) -> RenderPipeline {
+    let shader_module = device.create_shader_module(ShaderModuleDescriptor {
+        label: Some("Ray marching shader module"),
+        source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(source)),
+    });
+    device.create_render_pipeline(&RenderPipelineDescriptor {
+        label: Some("Ray marching pipeline"),
+        layout: Some(layout),
+        vertex: VertexState {
+            module: &shader_module,
+            entry_point: Some("vs_main"),
+            compilation_options: PipelineCompilationOptions::default(),
+            buffers: &[],
+        },
+        primitive: PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: MultisampleState::default(),
+        fragment: Some(FragmentState {
+            module: &shader_module,
+            entry_point: Some("fs_main"),
+            compilation_options: PipelineCompilationOptions::default(),
+            targets: &[Some(ColorTargetState {
+                format: color_format,
+                blend: None,
+                write_mask: ColorWrites::default(),
+            })],
+        }),
+        multiview: None,
+        cache: None,
+    })
+}
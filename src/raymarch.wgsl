@@ -0,0 +1,120 @@
+// Ray marches a scene described by a signed distance function, for every pixel of the surface.
+// Expects `camera.wgsl`, `sky.wgsl` and `globals.wgsl` to be prepended.
+//
+// Edit this file while the application runs to see the changes in the next frame.
+// The distance functions follow https://iquilezles.org/articles/distfunctions/
+
+const max_steps: i32 = 128;
+const max_distance: f32 = 100.0;
+const surface_epsilon: f32 = 0.001;
+
+// One triangle covering the whole surface
+@vertex
+fn vs_main(@builtin(vertex_index) in_vertex_index: u32) -> @builtin(position) vec4<f32> {
+    let uv = vec2<f32>(f32((in_vertex_index << 1u) & 2u), f32(in_vertex_index & 2u));
+    return vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+}
+
+fn sphere_distance(p: vec3<f32>, radius: f32) -> f32 {
+    return length(p) - radius;
+}
+
+fn torus_distance(p: vec3<f32>, radii: vec2<f32>) -> f32 {
+    let q = vec2<f32>(length(p.xz) - radii.x, p.y);
+    return length(q) - radii.y;
+}
+
+fn smooth_union(a: f32, b: f32, k: f32) -> f32 {
+    let h = clamp(0.5 + 0.5 * (b - a) / k, 0.0, 1.0);
+    return mix(b, a, h) - k * h * (1.0 - h);
+}
+
+// Distance to the closest surface of the scene: the ground, and blobs orbiting a torus
+fn scene_distance(p: vec3<f32>) -> f32 {
+    let t = globals.time;
+    var blob = torus_distance(p - vec3<f32>(0.0, 1.0, 0.0), vec2<f32>(1.2, 0.25));
+    for (var i = 0; i < 3; i++) {
+        let angle = t * (0.6 + 0.25 * f32(i)) + f32(i) * 2.094;
+        let center = vec3<f32>(cos(angle) * 1.2, 1.0 + sin(t * 1.3 + f32(i)) * 0.6, sin(angle) * 1.2);
+        blob = smooth_union(blob, sphere_distance(p - center, 0.45), 0.5);
+    }
+    return min(blob, p.y);
+}
+
+fn scene_normal(p: vec3<f32>) -> vec3<f32> {
+    // Tetrahedron technique, four samples instead of six central differences
+    let k = vec2<f32>(1.0, -1.0) * 0.0005;
+    return normalize(
+        k.xyy * scene_distance(p + k.xyy) + k.yyx * scene_distance(p + k.yyx)
+            + k.yxy * scene_distance(p + k.yxy) + k.xxx * scene_distance(p + k.xxx)
+    );
+}
+
+// Returns the distance along the ray to the closest surface, or a negative value if it hits none
+fn march(origin: vec3<f32>, direction: vec3<f32>) -> f32 {
+    var t = 0.0;
+    for (var i = 0; i < max_steps; i++) {
+        let distance = scene_distance(origin + direction * t);
+        if distance < surface_epsilon * t {
+            return t;
+        }
+        t += distance;
+        if t > max_distance {
+            break;
+        }
+    }
+    return -1.0;
+}
+
+// Soft shadows from the closest the shadow ray passes by the scene
+fn soft_shadow(origin: vec3<f32>, direction: vec3<f32>) -> f32 {
+    var shadow = 1.0;
+    var t = 0.02;
+    for (var i = 0; i < 64; i++) {
+        let distance = scene_distance(origin + direction * t);
+        shadow = min(shadow, 8.0 * distance / t);
+        t += clamp(distance, 0.01, 0.5);
+        if shadow < 0.001 || t > 20.0 {
+            break;
+        }
+    }
+    return clamp(shadow, 0.0, 1.0);
+}
+
+// Ambient occlusion from how far the scene is along the normal, compared to an open space
+fn ambient_occlusion(p: vec3<f32>, normal: vec3<f32>) -> f32 {
+    var occlusion = 0.0;
+    var weight = 1.0;
+    for (var i = 1; i <= 5; i++) {
+        let h = 0.04 * f32(i);
+        occlusion += (h - scene_distance(p + normal * h)) * weight;
+        weight *= 0.7;
+    }
+    return clamp(1.0 - 3.0 * occlusion, 0.0, 1.0);
+}
+
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let origin = camera.position.xyz;
+    let direction = view_ray(position.xy);
+    let t = march(origin, direction);
+    if t < 0.0 {
+        return vec4<f32>(sky(direction), 1.0);
+    }
+
+    let p = origin + direction * t;
+    let normal = scene_normal(p);
+    let light = normalize(sun_direction);
+    var albedo = vec3<f32>(0.9, 0.35, 0.2);
+    if p.y < surface_epsilon * t * 2.0 {
+        // Checkered ground
+        let checker = (i32(floor(p.x)) + i32(floor(p.z))) & 1;
+        albedo = vec3<f32>(0.3 + 0.2 * f32(checker));
+    }
+    let diffuse = max(dot(normal, light), 0.0) * soft_shadow(p + normal * 0.01, light);
+    let ambient = sky(normal) * 0.4 * ambient_occlusion(p, normal);
+    var color = albedo * (vec3<f32>(1.0, 0.95, 0.85) * diffuse + ambient);
+    // Fade into the sky with distance
+    color = mix(color, sky(direction), 1.0 - exp(-0.002 * t * t));
+    return vec4<f32>(color, 1.0);
+}
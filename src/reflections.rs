@@ -0,0 +1,217 @@
+use cgmath::{InnerSpace, Matrix, Matrix4, SquareMatrix, Transform, Vector3, Vector4};
+use wgpu::{
+    BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, Buffer, BufferAddress,
+    BufferDescriptor, BufferUsages, Color, CommandEncoder, Device, Extent3d, LoadOp, Operations,
+    Queue, RenderPass, RenderPassColorAttachment, RenderPassDepthStencilAttachment,
+    RenderPassDescriptor, StoreOp, TextureDescriptor, TextureDimension, TextureFormat,
+    TextureUsages, TextureView, TextureViewDescriptor,
+};
+
+use crate::camera::{Camera, CameraUniform};
+
+/// Reflections are rendered at a fraction of the surface's resolution,
+/// they are blurred by the distortion of the water anyway.
+const RESOLUTION_DIVISOR: u32 = 2;
+
+/// The scene as reflected by a horizontal plane, rendered into an offscreen target.
+///
+/// The camera is mirrored about the plane, and its projection is given an oblique near plane
+/// lying in the reflecting plane, so that nothing below it ends up in the reflection.
+/// As mirroring reverses the winding order of triangles, the image is also flipped
+/// horizontally, which restores the winding order so that the regular pipelines with
+/// back-face culling can render into it. Surfaces sampling the reflection must flip it back.
+pub struct PlanarReflection {
+    /// World space height of the reflecting plane.
+    plane_height: f32,
+    camera_buffer: Buffer,
+    camera_bind_group: BindGroup,
+    color_format: TextureFormat,
+    depth_format: TextureFormat,
+    color_view: TextureView,
+    depth_view: TextureView,
+    width: u32,
+    height: u32,
+}
+
+impl PlanarReflection {
+    pub fn new(
+        device: &Device,
+        camera_bind_group_layout: &BindGroupLayout,
+        color_format: TextureFormat,
+        depth_format: TextureFormat,
+        plane_height: f32,
+        width: u32,
+        height: u32,
+    ) -> Self {
+        let camera_buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("Reflection camera buffer"),
+            size: size_of::<CameraUniform>() as BufferAddress,
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let camera_bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Reflection camera bind group"),
+            layout: camera_bind_group_layout,
+            entries: &[BindGroupEntry {
+                binding: 0,
+                resource: camera_buffer.as_entire_binding(),
+            }],
+        });
+        let (width, height) = reflection_size(width, height);
+        let (color_view, depth_view) =
+            create_targets(device, color_format, depth_format, width, height);
+
+        Self {
+            plane_height,
+            camera_buffer,
+            camera_bind_group,
+            color_format,
+            depth_format,
+            color_view,
+            depth_view,
+            width,
+            height,
+        }
+    }
+
+    /// Recreates the targets to match the size of the surface.
+    pub fn resize(&mut self, device: &Device, width: u32, height: u32) {
+        (self.width, self.height) = reflection_size(width, height);
+        (self.color_view, self.depth_view) = create_targets(
+            device,
+            self.color_format,
+            self.depth_format,
+            self.width,
+            self.height,
+        );
+    }
+
+    /// Mirrors the camera about the reflecting plane.
+    pub fn prepare(&self, queue: &Queue, camera: &Camera) {
+        let mirror = Matrix4::from_translation(Vector3::new(0.0, self.plane_height, 0.0))
+            * Matrix4::from_nonuniform_scale(1.0, -1.0, 1.0)
+            * Matrix4::from_translation(Vector3::new(0.0, -self.plane_height, 0.0));
+        let view = camera.view() * mirror;
+
+        // Plane equation of the reflecting plane, facing the side that is kept
+        let plane = Vector4::new(0.0, 1.0, 0.0, -self.plane_height);
+        let view_plane = view.invert().unwrap_or(Matrix4::identity()).transpose() * plane;
+        let projection = Matrix4::from_nonuniform_scale(-1.0, 1.0, 1.0)
+            * oblique_projection(camera.projection(), view_plane);
+
+        queue.write_buffer(
+            &self.camera_buffer,
+            0,
+            bytemuck::bytes_of(&CameraUniform::from_matrices(
+                view,
+                projection,
+                mirror.transform_point(camera.eye),
+                self.width,
+                self.height,
+            )),
+        );
+    }
+
+    /// Camera bind group of the mirrored camera, to render into [`PlanarReflection::begin_pass`].
+    pub fn camera_bind_group(&self) -> &BindGroup {
+        &self.camera_bind_group
+    }
+
+    /// The reflected scene, flipped horizontally.
+    pub fn color_view(&self) -> &TextureView {
+        &self.color_view
+    }
+
+    /// Begins the pass rendering the reflected scene, clearing the reflection's targets.
+    pub fn begin_pass<'encoder>(
+        &self,
+        command_encoder: &'encoder mut CommandEncoder,
+    ) -> RenderPass<'encoder> {
+        command_encoder.begin_render_pass(&RenderPassDescriptor {
+            label: Some("Reflection pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: &self.color_view,
+                resolve_target: None,
+                ops: Operations {
+                    load: LoadOp::Clear(Color::BLACK),
+                    store: StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: Some(RenderPassDepthStencilAttachment {
+                view: &self.depth_view,
+                depth_ops: Some(Operations {
+                    load: LoadOp::Clear(0.0),
+                    store: StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        })
+    }
+}
+
+/// Replaces the near plane of a reverse-Z projection, which maps the near plane to a depth
+/// of 1 and the far plane to 0, by a view space clip plane, see Eric Lengyel, "Oblique View
+/// Frustum Depth Projection and Clipping".
+///
+/// Points with a positive distance to the plane are kept.
+fn oblique_projection(projection: Matrix4<f32>, plane: Vector4<f32>) -> Matrix4<f32> {
+    // Corner of the view frustum opposite to the plane, which stays on the far plane
+    let clip_plane = projection
+        .invert()
+        .unwrap_or(Matrix4::identity())
+        .transpose()
+        * plane;
+    let corner = projection.invert().unwrap_or(Matrix4::identity())
+        * Vector4::new(clip_plane.x.signum(), clip_plane.y.signum(), 0.0, 1.0);
+    // The near plane is where z equals w, so w - z becomes the scaled clip plane, scaled for
+    // z to remain 0 at the corner
+    let row_w = projection.row(3);
+    let row_z = row_w - plane * (row_w.dot(corner) / plane.dot(corner));
+
+    let mut oblique = projection;
+    oblique.x.z = row_z.x;
+    oblique.y.z = row_z.y;
+    oblique.z.z = row_z.z;
+    oblique.w.z = row_z.w;
+    oblique
+}
+
+fn reflection_size(width: u32, height: u32) -> (u32, u32) {
+    (
+        (width / RESOLUTION_DIVISOR).max(1),
+        (height / RESOLUTION_DIVISOR).max(1),
+    )
+}
+
+fn create_targets(
+    device: &Device,
+    color_format: TextureFormat,
+    depth_format: TextureFormat,
+    width: u32,
+    height: u32,
+) -> (TextureView, TextureView) {
+    let create_view = |label, format| {
+        device
+            .create_texture(&TextureDescriptor {
+                label: Some(label),
+                size: Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: TextureDimension::D2,
+                format,
+                usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            })
+            .create_view(&TextureViewDescriptor::default())
+    };
+    (
+        create_view("Reflection color texture", color_format),
+        create_view("Reflection depth texture", depth_format),
+    )
+}
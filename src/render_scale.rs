@@ -0,0 +1,219 @@
+use std::borrow::Cow;
+
+use wgpu::{
+    AddressMode, BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout,
+    BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingResource, BindingType, Color,
+    ColorTargetState, ColorWrites, CommandEncoder, Device, Extent3d, FilterMode, FragmentState,
+    LoadOp, MultisampleState, Operations, PipelineCompilationOptions, PipelineLayoutDescriptor,
+    PrimitiveState, RenderPassColorAttachment, RenderPassDescriptor, RenderPipeline,
+    RenderPipelineDescriptor, Sampler, SamplerBindingType, SamplerDescriptor,
+    ShaderModuleDescriptor, ShaderStages, StoreOp, TextureDescriptor, TextureDimension,
+    TextureFormat, TextureSampleType, TextureUsages, TextureView, TextureViewDescriptor,
+    TextureViewDimension, VertexState,
+};
+
+/// Renders at a multiple of the surface size, scaling the image to the surface afterwards.
+///
+/// Scales below 1 trade sharpness for speed on weak GPUs, scales above 1 supersample the image,
+/// smoothing its edges. At a scale of 1, the image is rendered into the surface directly.
+pub struct RenderScale {
+    scale: f32,
+    /// Largest width and height of a texture, which the scaled image must not exceed.
+    max_dimension: u32,
+    color_format: TextureFormat,
+    surface_width: u32,
+    surface_height: u32,
+    sampler: Sampler,
+    bind_group_layout: BindGroupLayout,
+    pipeline: RenderPipeline,
+    /// The scaled image and the bind group sampling it, unless the scale is 1.
+    image: Option<(TextureView, BindGroup)>,
+}
+
+impl RenderScale {
+    pub const MIN: f32 = 0.5;
+    pub const MAX: f32 = 2.0;
+    /// Change of the scale per key press.
+    pub const STEP: f32 = 0.25;
+
+    pub fn new(
+        device: &Device,
+        color_format: TextureFormat,
+        scale: f32,
+        max_dimension: u32,
+        width: u32,
+        height: u32,
+    ) -> Self {
+        let sampler = device.create_sampler(&SamplerDescriptor {
+            label: Some("Render scale sampler"),
+            address_mode_u: AddressMode::ClampToEdge,
+            address_mode_v: AddressMode::ClampToEdge,
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            ..Default::default()
+        });
+        let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Render scale bind group layout"),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Float { filterable: true },
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+
+        let shader_module = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("Render scale shader module"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("./render_scale.wgsl"))),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Render scale pipeline layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("Render scale pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: VertexState {
+                module: &shader_module,
+                entry_point: None,
+                compilation_options: PipelineCompilationOptions::default(),
+                buffers: &[],
+            },
+            primitive: PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+            fragment: Some(FragmentState {
+                module: &shader_module,
+                entry_point: None,
+                compilation_options: PipelineCompilationOptions::default(),
+                targets: &[Some(ColorTargetState {
+                    format: color_format,
+                    blend: None,
+                    write_mask: ColorWrites::default(),
+                })],
+            }),
+            multiview: None,
+            cache: None,
+        });
+
+        let mut render_scale = Self {
+            scale: scale.clamp(Self::MIN, Self::MAX),
+            max_dimension,
+            color_format,
+            surface_width: width,
+            surface_height: height,
+            sampler,
+            bind_group_layout,
+            pipeline,
+            image: None,
+        };
+        render_scale.create_image(device);
+        render_scale
+    }
+
+    pub fn scale(&self) -> f32 {
+        self.scale
+    }
+
+    /// Changes the scale, clamped to [`RenderScale::MIN`] and [`RenderScale::MAX`].
+    /// Everything rendered at the scaled size must be resized to [`RenderScale::size`].
+    pub fn set_scale(&mut self, device: &Device, scale: f32) {
+        self.scale = scale.clamp(Self::MIN, Self::MAX);
+        self.create_image(device);
+    }
+
+    /// Recreates the scaled image to match the size of the surface.
+    pub fn resize(&mut self, device: &Device, width: u32, height: u32) {
+        self.surface_width = width;
+        self.surface_height = height;
+        self.create_image(device);
+    }
+
+    /// Width and height to render at.
+    pub fn size(&self) -> (u32, u32) {
+        let scale =
+            |size: u32| ((size as f32 * self.scale).round() as u32).clamp(1, self.max_dimension);
+        (scale(self.surface_width), scale(self.surface_height))
+    }
+
+    /// The image to render into instead of the surface, or `None` at a scale of 1.
+    pub fn view(&self) -> Option<&TextureView> {
+        self.image.as_ref().map(|(view, _)| view)
+    }
+
+    /// Scales the image rendered into [`RenderScale::view`] to the surface, if there is one.
+    pub fn blit(&self, command_encoder: &mut CommandEncoder, texture_view: &TextureView) {
+        let Some((_, bind_group)) = &self.image else {
+            return;
+        };
+        let mut render_pass = command_encoder.begin_render_pass(&RenderPassDescriptor {
+            label: Some("Render scale pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: texture_view,
+                resolve_target: None,
+                ops: Operations {
+                    load: LoadOp::Clear(Color::BLACK),
+                    store: StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+
+    fn create_image(&mut self, device: &Device) {
+        if self.scale == 1.0 {
+            self.image = None;
+            return;
+        }
+        let (width, height) = self.size();
+        let view = device
+            .create_texture(&TextureDescriptor {
+                label: Some("Render scale texture"),
+                size: Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: TextureDimension::D2,
+                format: self.color_format,
+                usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            })
+            .create_view(&TextureViewDescriptor::default());
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Render scale bind group"),
+            layout: &self.bind_group_layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(&view),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::Sampler(&self.sampler),
+                },
+            ],
+        });
+        self.image = Some((view, bind_group));
+    }
+}
@@ -0,0 +1,32 @@
+// Scales the image rendered at the render scale to the size of the surface with a single
+// full-screen triangle.
+//
+// Bilinear filtering interpolates between the pixels when upscaling. When downscaling by a factor
+// of two, each pixel of the surface lies exactly between four pixels of the image, which are
+// averaged, so the image is supersampled.
+
+@group(0) @binding(0)
+var image_texture: texture_2d<f32>;
+@group(0) @binding(1)
+var image_sampler: sampler;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) in_vertex_index: u32) -> VertexOutput {
+    // A triangle covering the whole screen, its excess is clipped
+    let uv = vec2<f32>(f32((in_vertex_index << 1u) & 2u), f32(in_vertex_index & 2u));
+    var out: VertexOutput;
+    out.position = vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+    // Texture rows start at the top
+    out.uv = vec2<f32>(uv.x, 1.0 - uv.y);
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return textureSampleLevel(image_texture, image_sampler, in.uv, 0.0);
+}
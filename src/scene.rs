@@ -0,0 +1,767 @@
+use std::{
+    collections::VecDeque,
+    f32::consts::{PI, TAU},
+};
+
+use cgmath::{
+    Deg, EuclideanSpace, InnerSpace, Matrix3, Matrix4, Point3, Rad, SquareMatrix, Vector3,
+};
+use wgpu::{Device, Queue};
+
+use crate::{
+    billboards::{Billboard, BillboardRenderer, BillboardSize, Orientation, Sprite},
+    camera::Camera,
+    decals::{Decal, DecalRenderer},
+    lights::{Lights, PointLight},
+    lod::LodMesh,
+    meshes::{Material, MeshData, MeshId, MeshRenderer},
+    oit::Transparency,
+    paintings::{self, Painting, PaintingRenderer},
+    primitives::PrimitiveRenderer,
+    probes::{ReflectionProbe, ReflectionProbes},
+    skinning::SkinnedMesh,
+    streaming::TextureStreamer,
+    text::{Label, TextRenderer, TextSize},
+};
+
+const FOUNTAIN_POSITION: Point3<f32> = Point3::new(0.0, 0.0, 3.0);
+const PARTICLES_PER_SECOND: f32 = 120.0;
+const PARTICLE_LIFETIME: f32 = 2.5;
+const GRAVITY: f32 = 9.81;
+const RING_RADIUS: f32 = 6.0;
+const RING_OBJECTS: u32 = 12;
+const RING_LIGHTS: u32 = 256;
+/// Spawning more decals replaces the oldest ones.
+const MAX_DECALS: usize = 32;
+/// Half of the size of the box of probes placed with [`Scene::place_probe`].
+const PROBE_HALF_EXTENT: Vector3<f32> = Vector3::new(4.0, 3.0, 4.0);
+
+/// Where the tentacle stands, behind the ring of objects.
+const TENTACLE_POSITION: Point3<f32> = Point3::new(0.0, 0.0, -7.5);
+const TENTACLE_MATERIAL: Material = Material {
+    albedo: [0.55, 0.2, 0.45],
+    roughness: 0.4,
+    metallic: 0.0,
+    opacity: 1.0,
+    transparency: Transparency::Sorted,
+};
+/// Joints of the tentacle's skeleton, see [`SkinnedMeshData::tentacle`].
+///
+/// [`SkinnedMeshData::tentacle`]: crate::skinning::SkinnedMeshData::tentacle
+pub const TENTACLE_JOINTS: u32 = 8;
+pub const TENTACLE_LENGTH: f32 = 3.0;
+pub const TENTACLE_RADIUS: f32 = 0.35;
+
+/// Small stones lining the pond, merged into a mesh per material, see
+/// [`MeshData::batch_by_material`].
+const POND_STONES: u32 = 200;
+const STONE_MATERIALS: [Material; 2] = [
+    Material {
+        albedo: [0.45, 0.43, 0.4],
+        roughness: 0.85,
+        metallic: 0.0,
+        opacity: 1.0,
+        transparency: Transparency::Sorted,
+    },
+    Material {
+        albedo: [0.25, 0.24, 0.23],
+        roughness: 0.6,
+        metallic: 0.0,
+        opacity: 1.0,
+        transparency: Transparency::Sorted,
+    },
+];
+
+/// Paintings stand on a circle around the ring of objects, facing its center, at these angles
+/// from the X axis. The terrain takes up the gap behind the tentacle.
+const PAINTING_ANGLES: [Deg<f32>; 6] = [
+    Deg(-22.5),
+    Deg(22.5),
+    Deg(67.5),
+    Deg(112.5),
+    Deg(157.5),
+    Deg(202.5),
+];
+const PAINTING_RADIUS: f32 = 11.0;
+const PAINTING_SIZE: f32 = 3.0;
+/// Side length of the paintings' textures, which are streamed, see [`TextureStreamer`].
+const PAINTING_TEXTURE_SIZE: u32 = 2048;
+
+/// Center of the pond, slightly above the ground so that the two don't fight over depth.
+pub const WATER_CENTER: Point3<f32> = Point3::new(0.0, 0.05, 0.0);
+/// Half of the pond's side length, inside the ring of objects.
+pub const WATER_EXTENT: f32 = 4.5;
+
+/// The demo content shown by the application.
+pub struct Scene {
+    cube: MeshId,
+    sphere: LodMesh,
+    /// The stones lining the pond, with their transforms baked into a mesh per material.
+    pond_stones: Vec<(MeshId, Material)>,
+    objects: Vec<Object>,
+    decals: VecDeque<Decal>,
+    probes: VecDeque<ReflectionProbe>,
+    paintings: Vec<Painting>,
+    /// Index of the selected object, see [`Scene::select`].
+    selected: Option<usize>,
+    /// Seconds simulated so far.
+    time: f32,
+    /// Duration of the last tick in seconds.
+    dt: f32,
+    /// How far the drawn state lies between the states before and after the last tick,
+    /// see [`Scene::interpolate`].
+    alpha: f32,
+    point_cloud: Vec<Point3<f32>>,
+    particles: Vec<Particle>,
+    spawn_accumulator: f32,
+    random: XorShift,
+}
+
+/// Where a ray hit the scene, see [`Scene::pick`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Hit {
+    pub position: Point3<f32>,
+    pub normal: Vector3<f32>,
+    /// Distance along the ray, in multiples of its direction.
+    pub distance: f32,
+    /// Index of the object that was hit, `None` for the ground.
+    object: Option<usize>,
+}
+
+enum Shape {
+    /// A cube with side length 1.
+    Cube,
+    /// A sphere with radius 1.
+    Sphere,
+}
+
+impl Shape {
+    fn name(&self) -> &'static str {
+        match self {
+            Shape::Cube => "Cube",
+            Shape::Sphere => "Sphere",
+        }
+    }
+}
+
+/// A lit mesh of the scene, which can be picked.
+struct Object {
+    shape: Shape,
+    position: Point3<f32>,
+    /// Rotation around the Y axis.
+    rotation: Deg<f32>,
+    scale: f32,
+    material: Material,
+}
+
+impl Object {
+    fn transform(&self) -> Matrix4<f32> {
+        Matrix4::from_translation(self.position.to_vec())
+            * Matrix4::from_angle_y(self.rotation)
+            * Matrix4::from_scale(self.scale)
+    }
+
+    fn intersect(&self, origin: Point3<f32>, direction: Vector3<f32>) -> Option<Hit> {
+        match self.shape {
+            Shape::Sphere => {
+                let offset = origin - self.position;
+                let b = offset.dot(direction) / direction.magnitude2();
+                let c = (offset.magnitude2() - self.scale * self.scale) / direction.magnitude2();
+                let discriminant = b * b - c;
+                if discriminant < 0.0 {
+                    return None;
+                }
+                // The far intersection is hit from inside of the sphere
+                let distance = [-b - discriminant.sqrt(), -b + discriminant.sqrt()]
+                    .into_iter()
+                    .find(|&distance| distance >= 0.0)?;
+                let position = origin + direction * distance;
+                Some(Hit {
+                    position,
+                    normal: (position - self.position) / self.scale,
+                    distance,
+                    object: None,
+                })
+            }
+            Shape::Cube => {
+                // Slab test in the cube's local space, where it is axis aligned
+                let to_world = Matrix3::from_angle_y(self.rotation);
+                let to_local = Matrix3::from_angle_y(-self.rotation) / self.scale;
+                let local_origin = to_local * (origin - self.position);
+                let local_direction = to_local * direction;
+
+                let mut near = (f32::NEG_INFINITY, 0);
+                let mut far = f32::INFINITY;
+                for axis in 0..3 {
+                    let a = (-0.5 - local_origin[axis]) / local_direction[axis];
+                    let b = (0.5 - local_origin[axis]) / local_direction[axis];
+                    if a.min(b) > near.0 {
+                        near = (a.min(b), axis);
+                    }
+                    far = far.min(a.max(b));
+                }
+                if far < near.0.max(0.0) {
+                    return None;
+                }
+                let distance = if near.0 >= 0.0 { near.0 } else { far };
+                let mut normal = Vector3::new(0.0, 0.0, 0.0);
+                normal[near.1] = -local_direction[near.1].signum();
+                Some(Hit {
+                    position: origin + direction * distance,
+                    normal: to_world * normal,
+                    distance,
+                    object: None,
+                })
+            }
+        }
+    }
+}
+
+struct Particle {
+    position: Point3<f32>,
+    /// Position before the last tick.
+    previous_position: Point3<f32>,
+    velocity: Vector3<f32>,
+    age: f32,
+}
+
+impl Scene {
+    pub fn new(
+        device: &Device,
+        queue: &Queue,
+        meshes: &mut MeshRenderer,
+        streamer: &mut TextureStreamer,
+    ) -> Self {
+        // Polished sphere inside the wireframe cube
+        let mut objects = vec![Object {
+            shape: Shape::Sphere,
+            position: Point3::new(0.0, 1.0, 0.0),
+            rotation: Deg(0.0),
+            scale: 0.7,
+            material: Material {
+                albedo: [1.0, 0.75, 0.3],
+                roughness: 0.25,
+                metallic: 1.0,
+                opacity: 1.0,
+                transparency: Transparency::Sorted,
+            },
+        }];
+
+        // Ring of alternating cubes and spheres around the center
+        objects.extend((0..RING_OBJECTS).map(|i| {
+            let angle = TAU * i as f32 / RING_OBJECTS as f32;
+            let (shape, rotation, scale) = if i % 2 == 0 {
+                (Shape::Cube, Deg(30.0 * i as f32), 1.0)
+            } else {
+                (Shape::Sphere, Deg(0.0), 0.5)
+            };
+            Object {
+                shape,
+                position: Point3::new(RING_RADIUS * angle.cos(), 0.5, RING_RADIUS * angle.sin()),
+                rotation,
+                scale,
+                material: Material {
+                    albedo: [0.8, 0.8, 0.8],
+                    roughness: i as f32 / RING_OBJECTS as f32,
+                    metallic: 0.0,
+                    opacity: 1.0,
+                    transparency: Transparency::Sorted,
+                },
+            }
+        }));
+
+        // Glass inside the ring: an intersecting cube and sphere, which need no sorting, and a
+        // sphere sorted back to front
+        let glass = |albedo, transparency| Material {
+            albedo,
+            roughness: 0.1,
+            metallic: 0.0,
+            opacity: 0.35,
+            transparency,
+        };
+        objects.extend([
+            Object {
+                shape: Shape::Cube,
+                position: Point3::new(2.8, 0.8, 2.0),
+                rotation: Deg(20.0),
+                scale: 0.9,
+                material: glass([0.3, 0.9, 0.5], Transparency::WeightedBlended),
+            },
+            Object {
+                shape: Shape::Sphere,
+                position: Point3::new(3.2, 1.1, 2.4),
+                rotation: Deg(0.0),
+                scale: 0.55,
+                material: glass([0.9, 0.4, 0.3], Transparency::WeightedBlended),
+            },
+            Object {
+                shape: Shape::Sphere,
+                position: Point3::new(-2.5, 0.8, 2.5),
+                rotation: Deg(0.0),
+                scale: 0.6,
+                material: glass([0.3, 0.5, 1.0], Transparency::Sorted),
+            },
+        ]);
+
+        let paintings = PAINTING_ANGLES
+            .iter()
+            .zip(0..)
+            .map(|(&angle, seed)| {
+                let (sin, cos) = Rad::from(angle).0.sin_cos();
+                let name = format!("Painting {seed}");
+                Painting {
+                    center: Point3::new(
+                        PAINTING_RADIUS * cos,
+                        0.5 + PAINTING_SIZE / 2.0,
+                        PAINTING_RADIUS * sin,
+                    ),
+                    facing: Vector3::new(-cos, 0.0, -sin),
+                    size: PAINTING_SIZE,
+                    texture: streamer.add(
+                        device,
+                        queue,
+                        &name,
+                        PAINTING_TEXTURE_SIZE,
+                        paintings::canvas(seed),
+                    ),
+                    texture_size: PAINTING_TEXTURE_SIZE,
+                }
+            })
+            .collect();
+
+        Self {
+            cube: meshes.add_mesh(device, &MeshData::cube()),
+            sphere: LodMesh::new(device, meshes, &MeshData::sphere(64, 32)),
+            pond_stones: pond_stones(&mut XorShift(0x1b87_3593))
+                .into_iter()
+                .map(|(material, mesh)| (meshes.add_mesh(device, &mesh), material))
+                .collect(),
+            objects,
+            decals: VecDeque::new(),
+            // A probe on either side of the polished sphere, each covering its half of the ring
+            probes: [-2.5, 2.5]
+                .map(|x| ReflectionProbe {
+                    position: Point3::new(x, 1.0, 0.0),
+                    half_extent: Vector3::new(5.5, 3.0, 8.0),
+                })
+                .into(),
+            paintings,
+            selected: None,
+            time: 0.0,
+            dt: 0.0,
+            alpha: 1.0,
+            point_cloud: fibonacci_sphere(1024, 1.0),
+            particles: Vec::new(),
+            spawn_accumulator: 0.0,
+            random: XorShift(0x2545_f491),
+        }
+    }
+
+    /// Advances the scene's simulation by one fixed tick.
+    ///
+    /// Only depends on the tick duration and a fixed random seed,
+    /// so replayed sessions look identical.
+    pub fn update(&mut self, dt: f32) {
+        self.time += dt;
+        self.dt = dt;
+
+        for particle in &mut self.particles {
+            particle.previous_position = particle.position;
+            particle.velocity.y -= GRAVITY * dt;
+            particle.position += particle.velocity * dt;
+            particle.age += dt;
+        }
+        self.particles
+            .retain(|particle| particle.age < PARTICLE_LIFETIME && particle.position.y >= 0.0);
+
+        self.spawn_accumulator += PARTICLES_PER_SECOND * dt;
+        while self.spawn_accumulator >= 1.0 {
+            self.spawn_accumulator -= 1.0;
+            let angle = self.random.next_f32() * TAU;
+            let spread = 0.6 + 0.4 * self.random.next_f32();
+            self.particles.push(Particle {
+                position: FOUNTAIN_POSITION,
+                previous_position: FOUNTAIN_POSITION,
+                velocity: Vector3::new(
+                    spread * angle.cos(),
+                    5.0 + self.random.next_f32(),
+                    spread * angle.sin(),
+                ),
+                age: 0.0,
+            });
+        }
+    }
+
+    /// Draws the state a fraction `alpha` of the last tick after the state before it,
+    /// see [`FixedClock::alpha`].
+    ///
+    /// [`FixedClock::alpha`]: crate::clock::FixedClock::alpha
+    pub fn interpolate(&mut self, alpha: f32) {
+        self.alpha = alpha;
+    }
+
+    /// Seconds simulated up to the drawn state.
+    pub fn time(&self) -> f32 {
+        self.time - (1.0 - self.alpha) * self.dt
+    }
+
+    /// Submits the scene's line and point geometry for the current frame.
+    pub fn draw_primitives(&self, primitives: &mut PrimitiveRenderer) {
+        // Wireframe cube
+        let corner = |i: u32| {
+            Point3::new(
+                if i & 1 == 0 { -1.0 } else { 1.0 },
+                if i & 2 == 0 { 0.0 } else { 2.0 },
+                if i & 4 == 0 { -1.0 } else { 1.0 },
+            )
+        };
+        for i in 0..8 {
+            for axis in [1, 2, 4] {
+                if i & axis == 0 {
+                    primitives.line(corner(i), corner(i | axis), [1.0, 0.8, 0.2, 1.0]);
+                }
+            }
+        }
+
+        // Helix
+        primitives.line_strip(
+            (0..=256).map(|i| {
+                let t = i as f32 / 256.0;
+                let angle = t * 4.0 * TAU;
+                Point3::new(3.0 + 0.5 * angle.cos(), 2.0 * t, 0.5 * angle.sin())
+            }),
+            [0.3, 0.9, 1.0, 1.0],
+        );
+
+        // Point cloud
+        for point in &self.point_cloud {
+            primitives.point(
+                Point3::new(point.x - 3.0, point.y + 1.0, point.z),
+                [1.0, 0.4, 0.6, 1.0],
+            );
+        }
+    }
+
+    /// Poses the tentacle, swaying in a wave running up from its base, and submits it for the
+    /// current frame.
+    pub fn draw_tentacle(&self, tentacle: &mut SkinnedMesh, meshes: &mut MeshRenderer) {
+        let time = self.time();
+        // The plane the tentacle bends in turns slowly around it
+        let axis = Vector3::new((0.4 * time).cos(), 0.0, (0.4 * time).sin());
+        let mut joint = Matrix4::identity();
+        let joints: Vec<_> = (0..TENTACLE_JOINTS)
+            .map(|i| {
+                if i > 0 {
+                    let spacing = TENTACLE_LENGTH / TENTACLE_JOINTS as f32;
+                    joint = joint * Matrix4::from_translation(Vector3::unit_y() * spacing);
+                }
+                let bend = Rad(0.25 * (1.7 * time - 0.7 * i as f32).sin());
+                joint = joint * Matrix4::from_axis_angle(axis, bend);
+                joint
+            })
+            .collect();
+        tentacle.pose(&joints);
+        tentacle.draw(
+            meshes,
+            Matrix4::from_translation(TENTACLE_POSITION.to_vec()),
+            TENTACLE_MATERIAL,
+        );
+    }
+
+    /// Submits the scene's lit meshes for the current frame, including the selected one.
+    pub fn draw_meshes(&self, meshes: &mut MeshRenderer, camera: &Camera) {
+        for &(stones, material) in &self.pond_stones {
+            meshes.draw(stones, Matrix4::identity(), material);
+        }
+        for object in &self.objects {
+            match object.shape {
+                Shape::Cube => meshes.draw(self.cube, object.transform(), object.material),
+                Shape::Sphere => {
+                    meshes.draw_lod(&self.sphere, object.transform(), object.material, camera)
+                }
+            }
+        }
+        if let Some(object) = self.selected.map(|index| &self.objects[index]) {
+            meshes.draw_selected(self.mesh(object), object.transform());
+        }
+    }
+
+    fn mesh(&self, object: &Object) -> MeshId {
+        match object.shape {
+            Shape::Cube => self.cube,
+            Shape::Sphere => self.sphere.finest(),
+        }
+    }
+
+    /// Returns the closest intersection of a ray with the scene's meshes,
+    /// and with the ground at a height of 0 if `ground` is set.
+    pub fn pick(&self, origin: Point3<f32>, direction: Vector3<f32>, ground: bool) -> Option<Hit> {
+        let ground_hit = (ground && origin.y > 0.0 && direction.y < 0.0).then(|| {
+            let distance = -origin.y / direction.y;
+            Hit {
+                position: origin + direction * distance,
+                normal: Vector3::unit_y(),
+                distance,
+                object: None,
+            }
+        });
+        self.objects
+            .iter()
+            .enumerate()
+            .filter_map(|(index, object)| {
+                let hit = object.intersect(origin, direction)?;
+                Some(Hit {
+                    object: Some(index),
+                    ..hit
+                })
+            })
+            .chain(ground_hit)
+            .min_by(|a, b| a.distance.total_cmp(&b.distance))
+    }
+
+    /// Selects the object that was hit, or clears the selection if nothing or the ground was hit.
+    pub fn select(&mut self, hit: Option<&Hit>) {
+        self.selected = hit.and_then(|hit| hit.object);
+    }
+
+    pub fn has_selection(&self) -> bool {
+        self.selected.is_some()
+    }
+
+    /// Projects a decal with a random color and rotation onto the surface that was hit.
+    pub fn spawn_decal(&mut self, hit: &Hit) {
+        if self.decals.len() == MAX_DECALS {
+            self.decals.pop_front();
+        }
+        let [r, g, b] = hue(self.random.next_f32());
+        self.decals.push_back(Decal {
+            position: hit.position,
+            normal: hit.normal,
+            size: 0.8,
+            angle: Rad(self.random.next_f32() * TAU),
+            color: [r, g, b, 0.9],
+        });
+    }
+
+    /// Submits the scene's decals for the current frame.
+    pub fn draw_decals(&self, decals: &mut DecalRenderer) {
+        for decal in &self.decals {
+            decals.draw(decal);
+        }
+    }
+
+    /// Places a reflection probe, replacing the oldest one if there are as many as fit.
+    pub fn place_probe(&mut self, position: Point3<f32>) {
+        if self.probes.len() == ReflectionProbes::MAX_PROBES {
+            self.probes.pop_front();
+        }
+        self.probes.push_back(ReflectionProbe {
+            position,
+            half_extent: PROBE_HALF_EXTENT,
+        });
+    }
+
+    /// Submits the scene's reflection probes for the current frame.
+    pub fn draw_probes(&self, probes: &mut ReflectionProbes) {
+        for &probe in &self.probes {
+            probes.draw(probe);
+        }
+    }
+
+    /// Submits the scene's paintings for the current frame.
+    pub fn draw_paintings(&self, paintings: &mut PaintingRenderer) {
+        for &painting in &self.paintings {
+            paintings.draw(painting);
+        }
+    }
+
+    /// Submits the scene's dynamic lights for the current frame.
+    pub fn draw_lights(&self, lights: &mut Lights) {
+        for light in self.ring_lights() {
+            lights.add(light);
+        }
+    }
+
+    /// Colored lights circling between the objects of the ring.
+    fn ring_lights(&self) -> impl Iterator<Item = PointLight> + '_ {
+        (0..RING_LIGHTS).map(|i| {
+            let offset = i as f32 / RING_LIGHTS as f32;
+            let time = self.time();
+            let angle = TAU * offset + 0.3 * time;
+            let radius = RING_RADIUS + 1.2 * (3.0 * angle + time).sin();
+            PointLight {
+                position: Point3::new(
+                    radius * angle.cos(),
+                    0.6 + 0.4 * (2.0 * time + TAU * offset).sin(),
+                    radius * angle.sin(),
+                ),
+                color: hue(offset),
+                intensity: 1.5,
+                radius: 2.5,
+            }
+        })
+    }
+
+    /// Submits the scene's labels for the current frame.
+    pub fn draw_labels(&self, text: &mut TextRenderer) {
+        // Roughness of the ring's objects, floating above them
+        for object in &self.objects[1..=RING_OBJECTS as usize] {
+            text.draw(&Label {
+                position: object.position + Vector3::new(0.0, 1.0, 0.0),
+                text: &format!("Roughness {:.2}", object.material.roughness),
+                size: TextSize::World(0.2),
+                color: [1.0, 1.0, 1.0, 1.0],
+            });
+        }
+
+        // Name of the selected object, staying readable from afar
+        if let Some(object) = self.selected.map(|index| &self.objects[index]) {
+            text.draw(&Label {
+                position: object.position + Vector3::new(0.0, 1.4 * object.scale + 0.5, 0.0),
+                text: object.shape.name(),
+                size: TextSize::Screen(18.0),
+                color: [1.0, 0.6, 0.1, 1.0],
+            });
+        }
+    }
+
+    /// Submits the scene's billboards for the current frame.
+    pub fn draw_billboards(&self, billboards: &mut BillboardRenderer) {
+        // Fountain particles, fading out with age
+        for particle in &self.particles {
+            let life = 1.0 - particle.age / PARTICLE_LIFETIME;
+            billboards.draw(Billboard {
+                position: particle.previous_position
+                    + (particle.position - particle.previous_position) * self.alpha,
+                size: BillboardSize::World(0.15, 0.15),
+                orientation: Orientation::Full,
+                sprite: Sprite::Glow,
+                color: [0.4, 0.7, 1.0, life],
+                // Hundreds of overlapping particles, which need not be sorted
+                transparency: Transparency::WeightedBlended,
+            });
+        }
+
+        // Glowing markers at the dynamic lights
+        for light in self.ring_lights() {
+            let [r, g, b] = light.color;
+            billboards.draw(Billboard {
+                position: light.position,
+                size: BillboardSize::World(0.15, 0.15),
+                orientation: Orientation::Full,
+                sprite: Sprite::Glow,
+                color: [r, g, b, 1.0],
+                transparency: Transparency::Sorted,
+            });
+        }
+
+        // Light icon, keeping its size on screen like an editor gizmo
+        billboards.draw(Billboard {
+            position: Point3::new(0.0, 4.0, 0.0),
+            size: BillboardSize::Screen(32.0, 32.0),
+            orientation: Orientation::Full,
+            sprite: Sprite::Ring,
+            color: [1.0, 0.9, 0.5, 1.0],
+            transparency: Transparency::Sorted,
+        });
+
+        // Torch flames on the cube's top corners, staying upright
+        for (x, z) in [(-1.0, -1.0), (1.0, -1.0), (-1.0, 1.0), (1.0, 1.0)] {
+            billboards.draw(Billboard {
+                position: Point3::new(x, 2.3, z),
+                size: BillboardSize::World(0.3, 0.6),
+                orientation: Orientation::AxisLocked(Vector3::unit_y()),
+                sprite: Sprite::Flame,
+                color: [1.0, 0.55, 0.15, 0.9],
+                transparency: Transparency::Sorted,
+            });
+        }
+
+        // Translucent halo behind the point cloud
+        billboards.draw(Billboard {
+            position: Point3::new(-3.0, 1.0, 0.0),
+            size: BillboardSize::World(3.0, 3.0),
+            orientation: Orientation::Full,
+            sprite: Sprite::Disc,
+            color: [1.0, 0.4, 0.6, 0.15],
+            transparency: Transparency::Sorted,
+        });
+
+        // Overlapping panes of colored glass
+        for (offset, color) in [
+            (-0.5, [1.0, 0.2, 0.2, 0.5]),
+            (0.0, [0.2, 1.0, 0.2, 0.5]),
+            (0.5, [0.2, 0.4, 1.0, 0.5]),
+        ] {
+            billboards.draw(Billboard {
+                position: Point3::new(offset, 3.0 + offset.abs(), -3.0 + offset),
+                size: BillboardSize::World(1.2, 1.2),
+                orientation: Orientation::Full,
+                sprite: Sprite::Disc,
+                color,
+                transparency: Transparency::WeightedBlended,
+            });
+        }
+    }
+}
+
+/// Fully saturated color of the given hue, from 0 to 1.
+fn hue(hue: f32) -> [f32; 3] {
+    [0.0, 2.0 / 3.0, 1.0 / 3.0]
+        .map(|offset| (((hue + offset) * TAU).cos() * 0.5 + 0.5).clamp(0.0, 1.0))
+}
+
+/// A small, deterministic pseudo random number generator.
+struct XorShift(u32);
+
+impl XorShift {
+    /// Returns a number in the range 0 to 1.
+    fn next_f32(&mut self) -> f32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 17;
+        self.0 ^= self.0 << 5;
+        (self.0 >> 8) as f32 / (1 << 24) as f32
+    }
+}
+
+/// Flattened, randomly turned cubes along the edges of the pond, merged into a single mesh.
+fn pond_stones(random: &mut XorShift) -> Vec<(Material, MeshData)> {
+    let cube = MeshData::cube();
+    let perimeter = 8.0 * WATER_EXTENT;
+    let edge = WATER_EXTENT + 0.15;
+    MeshData::batch_by_material((0..POND_STONES).map(|i| {
+        // Position along the perimeter, going around the pond from a corner
+        let along = perimeter * (i as f32 + random.next_f32() * 0.5) / POND_STONES as f32;
+        let offset = along % (2.0 * WATER_EXTENT) - WATER_EXTENT;
+        let (x, z) = match (along / (2.0 * WATER_EXTENT)) as u32 % 4 {
+            0 => (offset, -edge),
+            1 => (edge, offset),
+            2 => (-offset, edge),
+            _ => (-edge, -offset),
+        };
+        let size = 0.15 + 0.15 * random.next_f32();
+        let transform = Matrix4::from_translation(Vector3::new(x, 0.3 * size, z))
+            * Matrix4::from_angle_y(Rad(random.next_f32() * TAU))
+            * Matrix4::from_nonuniform_scale(size * 1.4, size * 0.6, size);
+        // About a third of the stones are darker
+        let material = STONE_MATERIALS[(random.next_f32() < 0.3) as usize];
+        (&cube, transform, material)
+    }))
+}
+
+/// Evenly distributes `count` points on a sphere.
+fn fibonacci_sphere(count: u32, radius: f32) -> Vec<Point3<f32>> {
+    let golden_angle = PI * (3.0 - 5.0f32.sqrt());
+    (0..count)
+        .map(|i| {
+            let y = 1.0 - 2.0 * (i as f32 + 0.5) / count as f32;
+            let ring_radius = (1.0 - y * y).sqrt();
+            let angle = golden_angle * i as f32;
+            Point3::new(
+                radius * ring_radius * angle.cos(),
+                radius * y,
+                radius * ring_radius * angle.sin(),
+            )
+        })
+        .collect()
+}
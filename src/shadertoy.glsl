@@ -0,0 +1,38 @@
+#version 450
+
+// Provides the inputs of ShaderToy (https://www.shadertoy.com) to a pass of a ShaderToy shader
+// imported as GLSL, which is appended to this file, see `ShaderToy`.
+//
+// The pass defines `void mainImage(out vec4 fragColor, in vec2 fragCoord)` like on ShaderToy,
+// which `main` in `shadertoy_main.glsl`, appended after the pass, calls for every pixel.
+// `SHADERTOY_IMAGE` is defined for the image pass, which is shown on the surface, and
+// `SHADERTOY_SRGB_SURFACE` if the surface encodes colors as sRGB.
+// Like in ShaderToy, the origin of `fragCoord` and of texture coordinates is the bottom left.
+
+// Must match `ShaderToyUniform`
+layout(set = 0, binding = 0) uniform ShaderToy {
+    // Width and height of the surface in pixels, z is the pixel aspect ratio, w is unused
+    vec4 shadertoy_resolution;
+    // Position of the cursor while the left mouse button is held in xy, and where it was pressed
+    // in zw. z is negative while the button is released, w is only positive right after pressing it.
+    vec4 iMouse;
+    // Seconds since the shader started
+    float iTime;
+    // Seconds since the previous frame
+    float iTimeDelta;
+    // Frames since the shader started, zero in the first frame
+    int iFrame;
+    uint shadertoy_padding;
+};
+layout(set = 0, binding = 1) uniform sampler shadertoy_sampler;
+// Buffers A to C, as of the last time they were written, or black without the buffer
+layout(set = 1, binding = 0) uniform texture2D shadertoy_channel0;
+layout(set = 2, binding = 0) uniform texture2D shadertoy_channel1;
+layout(set = 3, binding = 0) uniform texture2D shadertoy_channel2;
+
+#define iResolution shadertoy_resolution.xyz
+#define iChannel0 sampler2D(shadertoy_channel0, shadertoy_sampler)
+#define iChannel1 sampler2D(shadertoy_channel1, shadertoy_sampler)
+#define iChannel2 sampler2D(shadertoy_channel2, shadertoy_sampler)
+
+layout(location = 0) out vec4 shadertoy_color;
@@ -0,0 +1,839 @@
+use std::{borrow::Cow, collections::HashMap, path::Path};
+
+#[cfg(not(target_arch = "wasm32"))]
+use color_eyre::{eyre::bail, Result};
+use wgpu::{
+    util::{BufferInitDescriptor, DeviceExt},
+    AddressMode, BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout,
+    BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingResource, BindingType, Buffer,
+    BufferBindingType, BufferUsages, Color, ColorTargetState, ColorWrites, CommandEncoder, Device,
+    Extent3d, FilterMode, FragmentState, LoadOp, MultisampleState, Operations,
+    PipelineCompilationOptions, PipelineLayout, PipelineLayoutDescriptor, PrimitiveState, Queue,
+    RenderPass, RenderPassColorAttachment, RenderPassDescriptor, RenderPipeline,
+    RenderPipelineDescriptor, SamplerBindingType, SamplerDescriptor, ShaderModule,
+    ShaderModuleDescriptor, ShaderStages, StoreOp, TextureDescriptor, TextureDimension,
+    TextureFormat, TextureSampleType, TextureUsages, TextureView, TextureViewDimension,
+    VertexState,
+};
+use winit::event::MouseButton;
+
+#[cfg(not(target_arch = "wasm32"))]
+use crate::hot_reload::ShaderWatcher;
+use crate::{input::InputEvent, ping_pong::PingPongTarget};
+
+/// Format of the images kept for `channel0` and of the buffers of GLSL shaders, which hold
+/// colors beyond 1 like ShaderToy's buffers.
+const HISTORY_FORMAT: TextureFormat = TextureFormat::Rgba16Float;
+
+/// Buffers a GLSL shader may have, Buffer A to C, read through the channels of the same index.
+/// With one bind group for the inputs and one per channel, this uses the four bind groups
+/// every device supports.
+#[cfg(not(target_arch = "wasm32"))]
+const MAX_BUFFERS: usize = 3;
+
+/// Location of `shadertoy_image.wgsl` in the source tree, which is watched for changes.
+#[cfg(not(target_arch = "wasm32"))]
+const IMAGE_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/src/shadertoy_image.wgsl");
+
+/// ShaderToy's inputs as seen by the shaders, see `shadertoy.wgsl` and `shadertoy.glsl`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct ShaderToyUniform {
+    resolution: [f32; 4],
+    mouse: [f32; 4],
+    time: f32,
+    time_delta: f32,
+    frame: i32,
+    padding: u32,
+}
+
+/// Runs fullscreen image shaders written against ShaderToy's inputs, see `shadertoy.wgsl`.
+///
+/// Besides the time, resolution and mouse, the image of the previous frame is available to the
+/// shader, for feedback effects that ShaderToy builds with buffers.
+///
+/// The image shader is `shadertoy_image.wgsl`, unless another file is given. On native targets,
+/// it is reloaded whenever it is saved, which restarts it.
+///
+/// Files ending in `.glsl` or `.frag` are imported as shaders copied from ShaderToy, whose GLSL
+/// naga translates after `shadertoy.glsl` and `shadertoy_main.glsl` wrapped it. Their passes
+/// are separated by lines holding only the name of ShaderToy's tab as a comment, e.g.
+/// `// Buffer A`, see [`GlslPasses::split`]. The buffers run before the image, each into a
+/// [`PingPongTarget`], which every pass reads through the channel of the buffer's index.
+pub struct ShaderToy {
+    uniform: ShaderToyUniform,
+    uniform_buffer: Buffer,
+    /// Simulation time at which the shader started, set by the first frame after a restart.
+    start_time: Option<f32>,
+    /// Last cursor position, with the origin in the top left like window coordinates.
+    cursor: Option<(f64, f64)>,
+    mouse_pressed: bool,
+    /// Whether the left mouse button was pressed since the previous frame.
+    mouse_clicked: bool,
+    /// The uniform buffer and the sampler of the channels.
+    inputs_bind_group: BindGroup,
+    /// Layout of the bind groups of the channels, holding a texture each.
+    channel_bind_group_layout: BindGroupLayout,
+    /// Bound to the channels of GLSL shaders without a buffer, reading black.
+    #[cfg(not(target_arch = "wasm32"))]
+    empty_channel_bind_group: BindGroup,
+    /// The image of the previous frame, and the one the current frame is written to.
+    history: PingPongTarget,
+    program: Program,
+    #[cfg(not(target_arch = "wasm32"))]
+    hot_reload: HotReload,
+}
+
+/// The pipelines of the shader being run.
+enum Program {
+    /// An image shader written in WGSL, reading the previous frame through `channel0`.
+    Wgsl(RenderPipeline),
+    /// A shader imported from ShaderToy, whose buffers run in order before the image.
+    #[cfg(not(target_arch = "wasm32"))]
+    Glsl {
+        /// Buffers A to C, by channel.
+        buffers: Vec<Option<GlslBuffer>>,
+        image: RenderPipeline,
+    },
+}
+
+/// A buffer pass of a GLSL shader, writing the next image of the buffer from the current one.
+#[cfg(not(target_arch = "wasm32"))]
+struct GlslBuffer {
+    pipeline: RenderPipeline,
+    target: PingPongTarget,
+}
+
+/// What is needed to rebuild the pipelines from a changed shader.
+#[cfg(not(target_arch = "wasm32"))]
+struct HotReload {
+    wgsl_pipeline_layout: PipelineLayout,
+    glsl_pipeline_layout: PipelineLayout,
+    /// Module with the vertex shader of the GLSL pipelines, drawing a full-screen triangle.
+    vertex_module: ShaderModule,
+    color_format: TextureFormat,
+    /// Whether the file is GLSL copied from ShaderToy rather than WGSL.
+    glsl: bool,
+    watcher: ShaderWatcher,
+}
+
+impl ShaderToy {
+    /// Creates the mode with the image shader in `image_path`, which is loaded when the mode is
+    /// first shown, or `shadertoy_image.wgsl` if there is none.
+    pub fn new(
+        device: &Device,
+        color_format: TextureFormat,
+        width: u32,
+        height: u32,
+        image_path: Option<&Path>,
+    ) -> Self {
+        let uniform = ShaderToyUniform {
+            resolution: [width as f32, height as f32, 1.0, 0.0],
+            mouse: [0.0; 4],
+            time: 0.0,
+            time_delta: 0.0,
+            frame: 0,
+            padding: 0,
+        };
+        let uniform_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("ShaderToy uniform buffer"),
+            contents: bytemuck::bytes_of(&uniform),
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+        });
+        let sampler = device.create_sampler(&SamplerDescriptor {
+            label: Some("ShaderToy sampler"),
+            address_mode_u: AddressMode::ClampToEdge,
+            address_mode_v: AddressMode::ClampToEdge,
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            ..Default::default()
+        });
+        let inputs_bind_group_layout =
+            device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+                label: Some("ShaderToy inputs bind group layout"),
+                entries: &[
+                    BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: ShaderStages::FRAGMENT,
+                        ty: BindingType::Buffer {
+                            ty: BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: ShaderStages::FRAGMENT,
+                        ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                ],
+            });
+        let inputs_bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("ShaderToy inputs bind group"),
+            layout: &inputs_bind_group_layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::Sampler(&sampler),
+                },
+            ],
+        });
+        let channel_bind_group_layout =
+            device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+                label: Some("ShaderToy channel bind group layout"),
+                entries: &[BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Float { filterable: true },
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                }],
+            });
+        let history = create_channel_target(
+            device,
+            &channel_bind_group_layout,
+            "ShaderToy history texture",
+            width,
+            height,
+        );
+
+        let wgsl_pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("ShaderToy pipeline layout"),
+            bind_group_layouts: &[&inputs_bind_group_layout, &channel_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let module = create_wgsl_module(
+            device,
+            concat!(
+                include_str!("./shadertoy.wgsl"),
+                include_str!("./shadertoy_image.wgsl")
+            ),
+        );
+        let pipeline = create_wgsl_pipeline(device, &wgsl_pipeline_layout, color_format, &module);
+        #[cfg(target_arch = "wasm32")]
+        if let Some(image_path) = image_path {
+            log::warn!(
+                "Cannot load {} on the web, showing the default image",
+                image_path.display()
+            );
+        }
+
+        Self {
+            uniform,
+            uniform_buffer,
+            start_time: None,
+            cursor: None,
+            mouse_pressed: false,
+            mouse_clicked: false,
+            inputs_bind_group,
+            #[cfg(not(target_arch = "wasm32"))]
+            empty_channel_bind_group: create_channel_bind_group(
+                device,
+                &channel_bind_group_layout,
+                &device
+                    .create_texture(&TextureDescriptor {
+                        label: Some("ShaderToy empty channel texture"),
+                        size: Extent3d::default(),
+                        mip_level_count: 1,
+                        sample_count: 1,
+                        dimension: TextureDimension::D2,
+                        format: HISTORY_FORMAT,
+                        usage: TextureUsages::TEXTURE_BINDING,
+                        view_formats: &[],
+                    })
+                    .create_view(&Default::default()),
+            ),
+            history,
+            program: Program::Wgsl(pipeline),
+            #[cfg(not(target_arch = "wasm32"))]
+            hot_reload: HotReload {
+                wgsl_pipeline_layout,
+                glsl_pipeline_layout: device.create_pipeline_layout(&PipelineLayoutDescriptor {
+                    label: Some("ShaderToy GLSL pipeline layout"),
+                    bind_group_layouts: &[
+                        &inputs_bind_group_layout,
+                        &channel_bind_group_layout,
+                        &channel_bind_group_layout,
+                        &channel_bind_group_layout,
+                    ],
+                    push_constant_ranges: &[],
+                }),
+                vertex_module: module,
+                color_format,
+                glsl: image_path.is_some_and(is_glsl),
+                watcher: match image_path {
+                    Some(image_path) => ShaderWatcher::unloaded(image_path),
+                    None => ShaderWatcher::new(IMAGE_PATH),
+                },
+            },
+            channel_bind_group_layout,
+        }
+    }
+
+    pub fn resize(&mut self, device: &Device, width: u32, height: u32) {
+        self.history = create_channel_target(
+            device,
+            &self.channel_bind_group_layout,
+            "ShaderToy history texture",
+            width,
+            height,
+        );
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Program::Glsl { buffers, .. } = &mut self.program {
+            for buffer in buffers.iter_mut().flatten() {
+                buffer.target = create_channel_target(
+                    device,
+                    &self.channel_bind_group_layout,
+                    "ShaderToy buffer texture",
+                    width,
+                    height,
+                );
+            }
+        }
+        self.uniform.resolution = [width as f32, height as f32, 1.0, 0.0];
+        // Shaders accumulating images would otherwise continue from a black one
+        self.restart();
+    }
+
+    /// Starts the shader over, from the first frame at time zero.
+    pub fn restart(&mut self) {
+        self.start_time = None;
+        self.uniform.frame = 0;
+    }
+
+    pub fn handle_input(&mut self, event: &InputEvent) {
+        match *event {
+            InputEvent::MouseButton {
+                button: MouseButton::Left,
+                pressed,
+            } => {
+                self.mouse_pressed = pressed;
+                self.mouse_clicked |= pressed;
+                if let (true, Some(position)) = (pressed, self.mouse_position()) {
+                    self.uniform.mouse = [position[0], position[1], position[0], position[1]];
+                }
+            }
+            InputEvent::CursorMoved { x, y } => {
+                self.cursor = Some((x, y));
+                if let (true, Some(position)) = (self.mouse_pressed, self.mouse_position()) {
+                    self.uniform.mouse[..2].copy_from_slice(&position);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Cursor position with the origin in the bottom left, like ShaderToy's.
+    fn mouse_position(&self) -> Option<[f32; 2]> {
+        let (x, y) = self.cursor?;
+        Some([x as f32, self.uniform.resolution[1] - y as f32])
+    }
+
+    /// Rebuilds the pipelines if the shader was saved since it was last built.
+    ///
+    /// A shader that fails to compile is logged, and the previous pipelines are kept,
+    /// so mistakes can be fixed without restarting the application.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn reload_if_modified(&mut self, device: &Device) {
+        let HotReload {
+            wgsl_pipeline_layout,
+            glsl_pipeline_layout,
+            vertex_module,
+            color_format,
+            glsl,
+            watcher,
+        } = &mut self.hot_reload;
+        let [width, height] = [0, 1].map(|axis| self.uniform.resolution[axis] as u32);
+        let program = watcher.reload(device, |source| {
+            if !*glsl {
+                let module = create_wgsl_module(
+                    device,
+                    &format!("{}{source}", include_str!("./shadertoy.wgsl")),
+                );
+                return Some(Program::Wgsl(create_wgsl_pipeline(
+                    device,
+                    wgsl_pipeline_layout,
+                    *color_format,
+                    &module,
+                )));
+            }
+            let passes = GlslPasses::split(source)
+                .inspect_err(|error| log::error!("Could not import the shader: {error}"))
+                .ok()?;
+            let buffers = passes
+                .buffers
+                .iter()
+                .map(|buffer| {
+                    Some(GlslBuffer {
+                        pipeline: create_glsl_pipeline(
+                            device,
+                            glsl_pipeline_layout,
+                            vertex_module,
+                            *color_format,
+                            &passes.common,
+                            buffer.as_deref()?,
+                            false,
+                        ),
+                        target: create_channel_target(
+                            device,
+                            &self.channel_bind_group_layout,
+                            "ShaderToy buffer texture",
+                            width,
+                            height,
+                        ),
+                    })
+                })
+                .collect();
+            let image = create_glsl_pipeline(
+                device,
+                glsl_pipeline_layout,
+                vertex_module,
+                *color_format,
+                &passes.common,
+                &passes.image,
+                true,
+            );
+            Some(Program::Glsl { buffers, image })
+        });
+        if let Some(program) = program.flatten() {
+            self.program = program;
+            self.restart();
+        }
+    }
+
+    /// Uploads the inputs of a new frame, given the current simulation time in seconds.
+    pub fn prepare(&mut self, queue: &Queue, time: f32) {
+        let start_time = *self.start_time.get_or_insert(time);
+        let time = time - start_time;
+        self.uniform.time_delta = time - self.uniform.time;
+        self.uniform.time = time;
+        // ShaderToy's signs: z while the button is held, w only in the frame it was pressed
+        let [x, y, click_x, click_y] = self.uniform.mouse;
+        let sign = |positive: bool| if positive { 1.0 } else { -1.0 };
+        self.uniform.mouse = [
+            x,
+            y,
+            click_x.abs() * sign(self.mouse_pressed),
+            click_y.abs() * sign(std::mem::take(&mut self.mouse_clicked)),
+        ];
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::bytes_of(&self.uniform));
+    }
+
+    /// Draws the image into the whole target, after running the buffers of GLSL shaders.
+    pub fn render(&mut self, command_encoder: &mut CommandEncoder, target: &TextureView) {
+        match &mut self.program {
+            Program::Wgsl(pipeline) => {
+                // The first frame has no previous one
+                if self.uniform.frame == 0 {
+                    clear(command_encoder, self.history.current_view());
+                }
+
+                let mut render_pass = command_encoder.begin_render_pass(&RenderPassDescriptor {
+                    label: Some("ShaderToy pass"),
+                    color_attachments: &[
+                        Some(surface_attachment(target)),
+                        Some(RenderPassColorAttachment {
+                            view: self.history.next_view(),
+                            resolve_target: None,
+                            ops: Operations {
+                                load: LoadOp::Clear(Color::TRANSPARENT),
+                                store: StoreOp::Store,
+                            },
+                        }),
+                    ],
+                    depth_stencil_attachment: None,
+                    timestamp_writes: None,
+                    occlusion_query_set: None,
+                });
+                render_pass.set_pipeline(pipeline);
+                render_pass.set_bind_group(0, &self.inputs_bind_group, &[]);
+                render_pass.set_bind_group(1, self.history.bind_group(), &[]);
+                render_pass.draw(0..3, 0..1);
+                drop(render_pass);
+
+                self.history.swap();
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            Program::Glsl { buffers, image } => {
+                // The buffers start out black, like ShaderToy's
+                if self.uniform.frame == 0 {
+                    for buffer in buffers.iter().flatten() {
+                        clear(command_encoder, buffer.target.current_view());
+                    }
+                }
+
+                // Every pass reads each buffer as it was last written, which is in the current
+                // frame for the buffers before it, and in the previous frame for the others
+                for index in 0..buffers.len() {
+                    let Some(buffer) = &buffers[index] else {
+                        continue;
+                    };
+                    let mut render_pass =
+                        command_encoder.begin_render_pass(&RenderPassDescriptor {
+                            label: Some("ShaderToy buffer pass"),
+                            color_attachments: &[Some(RenderPassColorAttachment {
+                                view: buffer.target.next_view(),
+                                resolve_target: None,
+                                ops: Operations {
+                                    load: LoadOp::Clear(Color::TRANSPARENT),
+                                    store: StoreOp::Store,
+                                },
+                            })],
+                            depth_stencil_attachment: None,
+                            timestamp_writes: None,
+                            occlusion_query_set: None,
+                        });
+                    render_pass.set_pipeline(&buffer.pipeline);
+                    bind_channels(
+                        &mut render_pass,
+                        &self.inputs_bind_group,
+                        &self.empty_channel_bind_group,
+                        buffers,
+                    );
+                    render_pass.draw(0..3, 0..1);
+                    drop(render_pass);
+
+                    if let Some(buffer) = &mut buffers[index] {
+                        buffer.target.swap();
+                    }
+                }
+
+                let mut render_pass = command_encoder.begin_render_pass(&RenderPassDescriptor {
+                    label: Some("ShaderToy pass"),
+                    color_attachments: &[Some(surface_attachment(target))],
+                    depth_stencil_attachment: None,
+                    timestamp_writes: None,
+                    occlusion_query_set: None,
+                });
+                render_pass.set_pipeline(image);
+                bind_channels(
+                    &mut render_pass,
+                    &self.inputs_bind_group,
+                    &self.empty_channel_bind_group,
+                    buffers,
+                );
+                render_pass.draw(0..3, 0..1);
+            }
+        }
+        self.uniform.frame += 1;
+    }
+}
+
+/// Binds the inputs and the current image of every buffer to its channel.
+#[cfg(not(target_arch = "wasm32"))]
+fn bind_channels(
+    render_pass: &mut RenderPass,
+    inputs_bind_group: &BindGroup,
+    empty_channel_bind_group: &BindGroup,
+    buffers: &[Option<GlslBuffer>],
+) {
+    render_pass.set_bind_group(0, inputs_bind_group, &[]);
+    for channel in 0..MAX_BUFFERS {
+        let bind_group = match buffers.get(channel) {
+            Some(Some(buffer)) => buffer.target.bind_group(),
+            _ => empty_channel_bind_group,
+        };
+        render_pass.set_bind_group(1 + channel as u32, bind_group, &[]);
+    }
+}
+
+/// The passes of a shader copied from ShaderToy, whose tabs are separated by a line holding
+/// only a comment with the tab's name: `// Common`, `// Buffer A` to `// Buffer C` or
+/// `// Image`. Code before the first such line belongs to the image, so a shader with a single
+/// pass can be pasted as it is.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Default, PartialEq)]
+struct GlslPasses {
+    /// Code shared by all passes, prepended to each of them.
+    common: String,
+    /// Buffers A to C, by channel.
+    buffers: [Option<String>; MAX_BUFFERS],
+    image: String,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl GlslPasses {
+    fn split(source: &str) -> Result<Self> {
+        let mut passes = Self::default();
+        let mut pass = &mut passes.image;
+        for line in source.lines() {
+            if let Some(name) = line.trim().strip_prefix("//").map(str::trim) {
+                match name {
+                    "Common" => {
+                        pass = &mut passes.common;
+                        continue;
+                    }
+                    "Image" => {
+                        pass = &mut passes.image;
+                        continue;
+                    }
+                    "Buffer A" | "Buffer B" | "Buffer C" => {
+                        let index = (name.as_bytes()[7] - b'A') as usize;
+                        pass = passes.buffers[index].get_or_insert_with(String::new);
+                        continue;
+                    }
+                    "Buffer D" | "Cube A" | "Sound" => {
+                        bail!("{name} is not supported, only Common, Buffer A to C and Image")
+                    }
+                    _ => {}
+                }
+            }
+            pass.push_str(line);
+            pass.push('\n');
+        }
+        Ok(passes)
+    }
+}
+
+/// Whether the shader in `path` is GLSL copied from ShaderToy rather than WGSL.
+#[cfg(not(target_arch = "wasm32"))]
+fn is_glsl(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension == "glsl" || extension == "frag")
+}
+
+fn surface_attachment(target: &TextureView) -> RenderPassColorAttachment<'_> {
+    RenderPassColorAttachment {
+        view: target,
+        resolve_target: None,
+        ops: Operations {
+            load: LoadOp::Clear(Color::BLACK),
+            store: StoreOp::Store,
+        },
+    }
+}
+
+fn clear(command_encoder: &mut CommandEncoder, view: &TextureView) {
+    command_encoder.begin_render_pass(&RenderPassDescriptor {
+        label: Some("ShaderToy clear pass"),
+        color_attachments: &[Some(RenderPassColorAttachment {
+            view,
+            resolve_target: None,
+            ops: Operations {
+                load: LoadOp::Clear(Color::TRANSPARENT),
+                store: StoreOp::Store,
+            },
+        })],
+        depth_stencil_attachment: None,
+        timestamp_writes: None,
+        occlusion_query_set: None,
+    });
+}
+
+/// Creates the images of a buffer, or of the previous frame, and the bind groups reading them
+/// through a channel.
+fn create_channel_target(
+    device: &Device,
+    layout: &BindGroupLayout,
+    label: &str,
+    width: u32,
+    height: u32,
+) -> PingPongTarget {
+    PingPongTarget::new(
+        device,
+        &TextureDescriptor {
+            label: Some(label),
+            size: Extent3d {
+                width: width.max(1),
+                height: height.max(1),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: HISTORY_FORMAT,
+            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        },
+        // The next image is a render attachment rather than bound
+        |current: &TextureView, _| create_channel_bind_group(device, layout, current),
+    )
+}
+
+fn create_channel_bind_group(
+    device: &Device,
+    layout: &BindGroupLayout,
+    view: &TextureView,
+) -> BindGroup {
+    device.create_bind_group(&BindGroupDescriptor {
+        label: Some("ShaderToy channel bind group"),
+        layout,
+        entries: &[BindGroupEntry {
+            binding: 0,
+            resource: BindingResource::TextureView(view),
+        }],
+    })
+}
+
+fn create_wgsl_module(device: &Device, source: &str) -> ShaderModule {
+    device.create_shader_module(ShaderModuleDescriptor {
+        label: Some("ShaderToy shader module"),
+        source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(source)),
+    })
+}
+
+/// Creates the pipeline of a WGSL image shader, which also writes the image to the history.
+fn create_wgsl_pipeline(
+    device: &Device,
+    layout: &PipelineLayout,
+    color_format: TextureFormat,
+    module: &ShaderModule,
+) -> RenderPipeline {
+    let constants = HashMap::from([(
+        "srgb_surface".to_owned(),
+        color_format.is_srgb() as u32 as f64,
+    )]);
+    create_pipeline(
+        device,
+        layout,
+        module,
+        FragmentState {
+            module,
+            entry_point: Some("fs_main"),
+            compilation_options: PipelineCompilationOptions {
+                constants: &constants,
+                ..Default::default()
+            },
+            targets: &[
+                Some(ColorTargetState {
+                    format: color_format,
+                    blend: None,
+                    write_mask: ColorWrites::default(),
+                }),
+                Some(ColorTargetState {
+                    format: HISTORY_FORMAT,
+                    blend: None,
+                    write_mask: ColorWrites::default(),
+                }),
+            ],
+        },
+    )
+}
+
+/// Creates the pipeline of a pass of a GLSL shader, writing the surface for the image and the
+/// buffer's next image otherwise.
+#[cfg(not(target_arch = "wasm32"))]
+fn create_glsl_pipeline(
+    device: &Device,
+    layout: &PipelineLayout,
+    vertex_module: &ShaderModule,
+    color_format: TextureFormat,
+    common: &str,
+    pass: &str,
+    image: bool,
+) -> RenderPipeline {
+    let mut defines = wgpu::naga::FastHashMap::default();
+    if image {
+        defines.insert("SHADERTOY_IMAGE".to_owned(), "1".to_owned());
+    }
+    if image && color_format.is_srgb() {
+        defines.insert("SHADERTOY_SRGB_SURFACE".to_owned(), "1".to_owned());
+    }
+    let module = device.create_shader_module(ShaderModuleDescriptor {
+        label: Some("ShaderToy GLSL shader module"),
+        source: wgpu::ShaderSource::Glsl {
+            shader: Cow::Owned(format!(
+                "{}{common}{pass}{}",
+                include_str!("./shadertoy.glsl"),
+                include_str!("./shadertoy_main.glsl")
+            )),
+            stage: wgpu::naga::ShaderStage::Fragment,
+            defines,
+        },
+    });
+    let format = if image { color_format } else { HISTORY_FORMAT };
+    create_pipeline(
+        device,
+        layout,
+        vertex_module,
+        FragmentState {
+            module: &module,
+            entry_point: Some("main"),
+            compilation_options: PipelineCompilationOptions::default(),
+            targets: &[Some(ColorTargetState {
+                format,
+                blend: None,
+                write_mask: ColorWrites::default(),
+            })],
+        },
+    )
+}
+
+/// Creates a pipeline drawing the full-screen triangle of `vertex_module` with `fragment`.
+fn create_pipeline(
+    device: &Device,
+    layout: &PipelineLayout,
+    vertex_module: &ShaderModule,
+    fragment: FragmentState,
+) -> RenderPipeline {
+    device.create_render_pipeline(&RenderPipelineDescriptor {
+        label: Some("ShaderToy pipeline"),
+        layout: Some(layout),
+        vertex: VertexState {
+            module: vertex_module,
+            entry_point: Some("vs_main"),
+            compilation_options: PipelineCompilationOptions::default(),
+            buffers: &[],
+        },
+        primitive: PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: MultisampleState::default(),
+        fragment: Some(fragment),
+        multiview: None,
+        cache: None,
+    })
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn code_without_markers_is_the_image() {
+        let passes = GlslPasses::split("void mainImage() {}\n").unwrap();
+        assert_eq!(
+            passes,
+            GlslPasses {
+                image: "void mainImage() {}\n".to_owned(),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn splits_passes_at_markers() {
+        let passes = GlslPasses::split(
+            "// Common\nfloat f;\n  //   Buffer B  \nb\n// Image\ni\n// a comment\n// Buffer A\na\n",
+        )
+        .unwrap();
+        assert_eq!(passes.common, "float f;\n");
+        assert_eq!(
+            passes.buffers,
+            [Some("a\n".to_owned()), Some("b\n".to_owned()), None]
+        );
+        assert_eq!(passes.image, "i\n// a comment\n");
+    }
+
+    #[test]
+    fn rejects_unsupported_passes() {
+        assert!(GlslPasses::split("// Buffer D\n").is_err());
+        assert!(GlslPasses::split("// Sound\n").is_err());
+    }
+
+    #[test]
+    fn detects_glsl_by_extension() {
+        assert!(is_glsl(Path::new("shader.glsl")));
+        assert!(is_glsl(Path::new("shader.frag")));
+        assert!(!is_glsl(Path::new("shader.wgsl")));
+        assert!(!is_glsl(Path::new("shader")));
+    }
+}
@@ -0,0 +1,80 @@
+// Provides the inputs of ShaderToy (https://www.shadertoy.com) to an image shader appended to
+// this file, which makes porting its shaders from GLSL mostly a matter of syntax.
+//
+// Instead of GLSL's `void mainImage(out vec4 fragColor, in vec2 fragCoord)`, the image shader
+// defines `fn mainImage(fragCoord: vec2<f32>) -> vec4<f32>`, see `shadertoy_image.wgsl`.
+// The inputs are private variables named like ShaderToy's, set before `mainImage` is called.
+// Like in ShaderToy, the origin of `fragCoord` and of texture coordinates is the bottom left.
+
+struct ShaderToy {
+    // Width and height of the surface in pixels, w is unused
+    resolution: vec4<f32>,
+    // See `iMouse`
+    mouse: vec4<f32>,
+    time: f32,
+    time_delta: f32,
+    frame: i32,
+    padding: u32,
+}
+
+@group(0) @binding(0)
+var<uniform> shadertoy: ShaderToy;
+@group(0) @binding(1)
+var channel0_sampler: sampler;
+// What `mainImage` returned in the previous frame, see `channel0`
+@group(1) @binding(0)
+var channel0_texture: texture_2d<f32>;
+
+// Whether the surface encodes colors as sRGB. ShaderToy displays the colors returned by
+// `mainImage` as they are, so they are decoded to cancel out the encoding.
+override srgb_surface: bool = false;
+
+// Width, height and pixel aspect ratio of the surface
+var<private> iResolution: vec3<f32>;
+// Seconds since the shader started
+var<private> iTime: f32;
+// Seconds since the previous frame
+var<private> iTimeDelta: f32;
+// Frames since the shader started, zero in the first frame
+var<private> iFrame: i32;
+// Position of the cursor while the left mouse button is held in xy, and where it was pressed
+// in zw. z is negative while the button is released, w is only positive right after pressing it.
+var<private> iMouse: vec4<f32>;
+
+// Samples the image of the previous frame, like `texture(iChannel0, uv)` in ShaderToy
+// with the previous frame as buffer
+fn channel0(uv: vec2<f32>) -> vec4<f32> {
+    // Texture rows start at the top
+    return textureSampleLevel(channel0_texture, channel0_sampler, vec2<f32>(uv.x, 1.0 - uv.y), 0.0);
+}
+
+// One triangle covering the whole surface
+@vertex
+fn vs_main(@builtin(vertex_index) in_vertex_index: u32) -> @builtin(position) vec4<f32> {
+    let uv = vec2<f32>(f32((in_vertex_index << 1u) & 2u), f32(in_vertex_index & 2u));
+    return vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+}
+
+struct FragmentOutput {
+    @location(0) surface: vec4<f32>,
+    // Kept for `channel0` in the next frame
+    @location(1) history: vec4<f32>,
+}
+
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> FragmentOutput {
+    iResolution = vec3<f32>(shadertoy.resolution.xy, 1.0);
+    iTime = shadertoy.time;
+    iTimeDelta = shadertoy.time_delta;
+    iFrame = shadertoy.frame;
+    iMouse = shadertoy.mouse;
+
+    let color = mainImage(vec2<f32>(position.x, iResolution.y - position.y));
+    var out: FragmentOutput;
+    out.history = color;
+    out.surface = vec4<f32>(color.rgb, 1.0);
+    if srgb_surface {
+        out.surface = vec4<f32>(pow(clamp(color.rgb, vec3<f32>(0.0), vec3<f32>(1.0)), vec3<f32>(2.2)), 1.0);
+    }
+    return out;
+}
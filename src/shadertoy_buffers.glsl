@@ -0,0 +1,24 @@
+// A ShaderToy shader with a buffer, as copied from its tabs: Buffer A paints a trail behind a
+// moving dot, or behind the cursor while the mouse button is held, and fades out the previous
+// frame, which the image colors.
+
+// Common
+float circle(vec2 p, vec2 center, float radius) {
+    return smoothstep(radius, radius - 2.0, length(p - center));
+}
+
+// Buffer A
+void mainImage(out vec4 fragColor, in vec2 fragCoord) {
+    vec2 center = iMouse.z > 0.0
+        ? iMouse.xy
+        : iResolution.xy * (0.5 + 0.35 * vec2(cos(iTime), sin(2.0 * iTime)));
+    float previous = texture(iChannel0, fragCoord / iResolution.xy).r;
+    fragColor = vec4(max(previous * 0.98, circle(fragCoord, center, 20.0)), 0.0, 0.0, 1.0);
+}
+
+// Image
+void mainImage(out vec4 fragColor, in vec2 fragCoord) {
+    float trail = texture(iChannel0, fragCoord / iResolution.xy).r;
+    vec3 color = 0.5 + 0.5 * cos(6.2831 * (trail + vec3(0.0, 0.33, 0.67)));
+    fragColor = vec4(color * trail, 1.0);
+}
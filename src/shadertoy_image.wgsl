@@ -0,0 +1,27 @@
+// The default image of the ShaderToy mode: glowing points circling the center of the surface,
+// or the cursor while the left mouse button is held, drawn over the fading previous frame.
+// Expects `shadertoy.wgsl` to be prepended.
+//
+// Edit this file while the application runs to see the changes in the next frame.
+
+fn mainImage(fragCoord: vec2<f32>) -> vec4<f32> {
+    if iFrame == 0 {
+        return vec4<f32>(0.0, 0.0, 0.0, 1.0);
+    }
+    let uv = fragCoord / iResolution.xy;
+    // Trails, fading out at the same speed at any frame rate
+    var color = channel0(uv).rgb * exp(-2.0 * iTimeDelta);
+
+    var center = iResolution.xy / 2.0;
+    if iMouse.z > 0.0 {
+        center = iMouse.xy;
+    }
+    let radius = iResolution.y * 0.25;
+    for (var i = 0; i < 5; i++) {
+        let angle = iTime * (1.0 + 0.3 * f32(i)) + f32(i) * 1.2566;
+        let point = center + vec2<f32>(cos(angle), sin(angle * 1.3)) * radius;
+        let glow = pow(3.0 / max(length(fragCoord - point), 1.0), 2.0);
+        color += glow * (0.5 + 0.5 * cos(f32(i) + vec3<f32>(0.0, 2.0, 4.0)));
+    }
+    return vec4<f32>(color, 1.0);
+}
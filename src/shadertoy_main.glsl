@@ -0,0 +1,18 @@
+// Appended to a pass of a ShaderToy shader imported as GLSL, after `shadertoy.glsl` and the pass.
+
+void main() {
+    vec4 color = vec4(0.0);
+#ifdef SHADERTOY_IMAGE
+    mainImage(color, vec2(gl_FragCoord.x, iResolution.y - gl_FragCoord.y));
+    shadertoy_color = vec4(color.rgb, 1.0);
+#ifdef SHADERTOY_SRGB_SURFACE
+    // ShaderToy displays the colors as they are, so they are decoded to cancel out the encoding
+    shadertoy_color = vec4(pow(clamp(color.rgb, 0.0, 1.0), vec3(2.2)), 1.0);
+#endif
+#else
+    // Buffers are stored upside down compared to the surface, with the first row at the bottom
+    // like in OpenGL, so that sampling them at `fragCoord / iResolution.xy` reads the same pixel.
+    mainImage(color, gl_FragCoord.xy);
+    shadertoy_color = color;
+#endif
+}
@@ -0,0 +1,284 @@
+use std::borrow::Cow;
+
+use cgmath::{Point2, Rad};
+use wgpu::{
+    BindGroup, BindGroupLayout, BlendState, Buffer, BufferAddress, BufferDescriptor, BufferUsages,
+    ColorTargetState, ColorWrites, CompareFunction, DepthStencilState, Device, FragmentState,
+    MultisampleState, PipelineCompilationOptions, PipelineLayoutDescriptor, PrimitiveState,
+    PrimitiveTopology, Queue, RenderPass, RenderPipeline, RenderPipelineDescriptor,
+    ShaderModuleDescriptor, TextureFormat, VertexBufferLayout, VertexState, VertexStepMode,
+};
+
+/// Pixels added around each shape's bounds, so its antialiased edge is not cut off.
+const EDGE_MARGIN: f32 = 1.0;
+
+/// Whether a shape is filled or only its outline is drawn.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ShapeStyle {
+    Fill,
+    /// Outline of the given width in pixels, centered on the shape's edge.
+    Stroke(f32),
+}
+
+impl ShapeStyle {
+    fn stroke_width(self) -> f32 {
+        match self {
+            ShapeStyle::Fill => 0.0,
+            ShapeStyle::Stroke(width) => width,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct ShapeInstance {
+    /// Top left and bottom right corners of the quad covering the shape, in pixels.
+    bounds: [f32; 4],
+    /// Meaning depends on the kind, see `shapes.wgsl`.
+    parameters: [f32; 4],
+    extra: [f32; 2],
+    kind: u32,
+    stroke_width: f32,
+    color: [f32; 4],
+}
+
+impl ShapeInstance {
+    const KIND_RECT: u32 = 0;
+    const KIND_CIRCLE: u32 = 1;
+    const KIND_LINE: u32 = 2;
+    const KIND_ARC: u32 = 3;
+
+    const ATTRIBUTES: [wgpu::VertexAttribute; 6] = wgpu::vertex_attr_array![
+        0 => Float32x4,
+        1 => Float32x4,
+        2 => Float32x2,
+        3 => Uint32,
+        4 => Float32,
+        5 => Float32x4,
+    ];
+
+    fn layout() -> VertexBufferLayout<'static> {
+        VertexBufferLayout {
+            array_stride: size_of::<Self>() as BufferAddress,
+            step_mode: VertexStepMode::Instance,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+}
+
+/// Draws antialiased 2D shapes on top of the scene, e.g. for HUDs and editor handles.
+///
+/// Shapes are given in pixels with the origin in the top left corner of the surface, and drawn
+/// in submission order. Each shape is a quad whose fragment shader evaluates the shape's signed
+/// distance function, so edges stay smooth at any size without tessellating curves.
+pub struct ShapeRenderer {
+    pipeline: RenderPipeline,
+    instance_buffer: Buffer,
+    instances: Vec<ShapeInstance>,
+    instance_count: u32,
+}
+
+impl ShapeRenderer {
+    const INITIAL_CAPACITY: BufferAddress = 256 * size_of::<ShapeInstance>() as BufferAddress;
+
+    pub fn new(
+        device: &Device,
+        camera_bind_group_layout: &BindGroupLayout,
+        color_format: TextureFormat,
+        depth_format: TextureFormat,
+    ) -> Self {
+        let shader_module = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("Shapes shader module"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(concat!(
+                include_str!("./camera.wgsl"),
+                include_str!("./shapes.wgsl")
+            ))),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Shapes pipeline layout"),
+            bind_group_layouts: &[camera_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("Shapes pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: VertexState {
+                module: &shader_module,
+                entry_point: Some("vs_main"),
+                compilation_options: PipelineCompilationOptions::default(),
+                buffers: &[ShapeInstance::layout()],
+            },
+            primitive: PrimitiveState {
+                topology: PrimitiveTopology::TriangleStrip,
+                ..Default::default()
+            },
+            // Drawn on top of everything, in passes that have the scene's depth attached
+            depth_stencil: Some(DepthStencilState {
+                format: depth_format,
+                depth_write_enabled: false,
+                depth_compare: CompareFunction::Always,
+                stencil: Default::default(),
+                bias: Default::default(),
+            }),
+            multisample: MultisampleState::default(),
+            fragment: Some(FragmentState {
+                module: &shader_module,
+                entry_point: Some("fs_main"),
+                compilation_options: PipelineCompilationOptions::default(),
+                targets: &[Some(ColorTargetState {
+                    format: color_format,
+                    blend: Some(BlendState::ALPHA_BLENDING),
+                    write_mask: ColorWrites::default(),
+                })],
+            }),
+            multiview: None,
+            cache: None,
+        });
+
+        Self {
+            pipeline,
+            instance_buffer: create_instance_buffer(device, Self::INITIAL_CAPACITY),
+            instances: Vec::new(),
+            instance_count: 0,
+        }
+    }
+
+    /// Draws a rectangle between two corners, with corners rounded by `radius`.
+    pub fn rect(
+        &mut self,
+        min: Point2<f32>,
+        max: Point2<f32>,
+        radius: f32,
+        style: ShapeStyle,
+        color: [f32; 4],
+    ) {
+        let center = [(min.x + max.x) / 2.0, (min.y + max.y) / 2.0];
+        let half_size = [(max.x - min.x) / 2.0, (max.y - min.y) / 2.0];
+        self.push(
+            ShapeInstance::KIND_RECT,
+            [min.x, min.y, max.x, max.y],
+            [center[0], center[1], half_size[0], half_size[1]],
+            [radius.min(half_size[0]).min(half_size[1]), 0.0],
+            style,
+            color,
+        );
+    }
+
+    pub fn circle(&mut self, center: Point2<f32>, radius: f32, style: ShapeStyle, color: [f32; 4]) {
+        self.push(
+            ShapeInstance::KIND_CIRCLE,
+            [
+                center.x - radius,
+                center.y - radius,
+                center.x + radius,
+                center.y + radius,
+            ],
+            [center.x, center.y, radius, 0.0],
+            [0.0; 2],
+            style,
+            color,
+        );
+    }
+
+    /// Draws a line with round caps.
+    pub fn line(&mut self, from: Point2<f32>, to: Point2<f32>, width: f32, color: [f32; 4]) {
+        let half_width = width / 2.0;
+        self.push(
+            ShapeInstance::KIND_LINE,
+            [
+                from.x.min(to.x) - half_width,
+                from.y.min(to.y) - half_width,
+                from.x.max(to.x) + half_width,
+                from.y.max(to.y) + half_width,
+            ],
+            [from.x, from.y, to.x, to.y],
+            [half_width, 0.0],
+            ShapeStyle::Fill,
+            color,
+        );
+    }
+
+    /// Draws the part of a circle between two angles, clockwise on screen starting to the right.
+    ///
+    /// Filling it draws a pie slice, stroking it draws the arc with round caps.
+    pub fn arc(
+        &mut self,
+        center: Point2<f32>,
+        radius: f32,
+        start: Rad<f32>,
+        end: Rad<f32>,
+        style: ShapeStyle,
+        color: [f32; 4],
+    ) {
+        self.push(
+            ShapeInstance::KIND_ARC,
+            [
+                center.x - radius,
+                center.y - radius,
+                center.x + radius,
+                center.y + radius,
+            ],
+            [center.x, center.y, radius, 0.0],
+            [start.0, end.0],
+            style,
+            color,
+        );
+    }
+
+    fn push(
+        &mut self,
+        kind: u32,
+        bounds: [f32; 4],
+        parameters: [f32; 4],
+        extra: [f32; 2],
+        style: ShapeStyle,
+        color: [f32; 4],
+    ) {
+        let margin = style.stroke_width() / 2.0 + EDGE_MARGIN;
+        let [left, top, right, bottom] = bounds;
+        self.instances.push(ShapeInstance {
+            bounds: [left - margin, top - margin, right + margin, bottom + margin],
+            parameters,
+            extra,
+            kind,
+            stroke_width: style.stroke_width(),
+            color,
+        });
+    }
+
+    /// Uploads the shapes submitted since the previous call.
+    pub fn prepare(&mut self, device: &Device, queue: &Queue) {
+        self.instance_count = self.instances.len() as u32;
+        let size = (self.instances.len() * size_of::<ShapeInstance>()) as BufferAddress;
+        if size > self.instance_buffer.size() {
+            self.instance_buffer = create_instance_buffer(device, size.next_power_of_two());
+        }
+        if size > 0 {
+            queue.write_buffer(
+                &self.instance_buffer,
+                0,
+                bytemuck::cast_slice(&self.instances),
+            );
+        }
+        self.instances.clear();
+    }
+
+    pub fn render(&self, render_pass: &mut RenderPass, camera_bind_group: &BindGroup) {
+        if self.instance_count == 0 {
+            return;
+        }
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, camera_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.instance_buffer.slice(..));
+        render_pass.draw(0..4, 0..self.instance_count);
+    }
+}
+
+fn create_instance_buffer(device: &Device, size: BufferAddress) -> Buffer {
+    device.create_buffer(&BufferDescriptor {
+        label: Some("Shape instance buffer"),
+        size,
+        usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
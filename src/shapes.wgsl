@@ -0,0 +1,135 @@
+// Draws 2D shapes in pixel coordinates, one quad per shape, evaluating the shape's signed
+// distance function per pixel for smooth edges.
+// Expects `camera.wgsl` to be prepended, whose viewport maps pixels to clip space.
+//
+// The signed distance functions follow https://iquilezles.org/articles/distfunctions2d/
+
+// Kinds of shapes, see `ShapeInstance`
+const kind_rect: u32 = 0u;
+const kind_circle: u32 = 1u;
+const kind_line: u32 = 2u;
+const kind_arc: u32 = 3u;
+
+const pi: f32 = 3.14159265;
+
+struct Instance {
+    // Top left and bottom right corners of the quad, in pixels
+    @location(0) bounds: vec4<f32>,
+    // Rect: center and half size, circle and arc: center and radius, line: both end points
+    @location(1) parameters: vec4<f32>,
+    // Rect: corner radius, line: half width, arc: start and end angle
+    @location(2) extra: vec2<f32>,
+    @location(3) kind: u32,
+    // Zero for filled shapes
+    @location(4) stroke_width: f32,
+    @location(5) color: vec4<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) parameters: vec4<f32>,
+    @location(1) extra: vec2<f32>,
+    @location(2) @interpolate(flat) kind: u32,
+    @location(3) stroke_width: f32,
+    @location(4) color: vec4<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) in_vertex_index: u32, instance: Instance) -> VertexOutput {
+    // Two triangles forming a quad, as a triangle strip
+    let corner = vec2<f32>(f32(in_vertex_index & 1u), f32((in_vertex_index >> 1u) & 1u));
+    let pixel = mix(instance.bounds.xy, instance.bounds.zw, corner);
+    // Pixels to normalized device coordinates, flipping Y as it points down in pixels
+    let ndc = pixel * camera.viewport.zw * 2.0 - 1.0;
+
+    var out: VertexOutput;
+    out.position = vec4<f32>(ndc.x, -ndc.y, 0.0, 1.0);
+    out.parameters = instance.parameters;
+    out.extra = instance.extra;
+    out.kind = instance.kind;
+    out.stroke_width = instance.stroke_width;
+    out.color = instance.color;
+    return out;
+}
+
+fn rounded_rect_distance(p: vec2<f32>, half_size: vec2<f32>, radius: f32) -> f32 {
+    let q = abs(p) - half_size + radius;
+    return length(max(q, vec2<f32>(0.0))) + min(max(q.x, q.y), 0.0) - radius;
+}
+
+fn line_distance(p: vec2<f32>, a: vec2<f32>, b: vec2<f32>) -> f32 {
+    let pa = p - a;
+    let ba = b - a;
+    let h = clamp(dot(pa, ba) / max(dot(ba, ba), 1e-6), 0.0, 1.0);
+    return length(pa - ba * h);
+}
+
+// Turns a point around the arc's center so that the arc is symmetric about the Y axis,
+// mirrored onto positive X. Returns the point and the sine and cosine of half the arc's angle.
+fn arc_space(p: vec2<f32>, angles: vec2<f32>) -> array<vec2<f32>, 2> {
+    let half_angle = clamp((angles.y - angles.x) / 2.0, 0.0, pi);
+    let rotation = pi / 2.0 - (angles.x + angles.y) / 2.0;
+    let q = vec2<f32>(
+        p.x * cos(rotation) - p.y * sin(rotation),
+        p.x * sin(rotation) + p.y * cos(rotation),
+    );
+    return array<vec2<f32>, 2>(vec2<f32>(abs(q.x), q.y), vec2<f32>(sin(half_angle), cos(half_angle)));
+}
+
+// Distance to the arc's center line, whose caps are rounded by the stroke
+fn arc_distance(p: vec2<f32>, radius: f32, angles: vec2<f32>) -> f32 {
+    let space = arc_space(p, angles);
+    let q = space[0];
+    let sc = space[1];
+    if sc.y * q.x > sc.x * q.y {
+        return length(q - sc * radius);
+    }
+    return abs(length(q) - radius);
+}
+
+fn pie_distance(p: vec2<f32>, radius: f32, angles: vec2<f32>) -> f32 {
+    let space = arc_space(p, angles);
+    let q = space[0];
+    let sc = space[1];
+    let to_circle = length(q) - radius;
+    let to_sides = length(q - sc * clamp(dot(q, sc), 0.0, radius));
+    return max(to_circle, to_sides * sign(sc.y * q.x - sc.x * q.y));
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // The fragment position is in pixels, like the shape's parameters
+    let p = in.position.xy;
+    let stroke = in.stroke_width / 2.0;
+
+    var distance: f32;
+    switch in.kind {
+        case kind_rect: {
+            distance = rounded_rect_distance(p - in.parameters.xy, in.parameters.zw, in.extra.x);
+        }
+        case kind_circle: {
+            distance = length(p - in.parameters.xy) - in.parameters.z;
+        }
+        case kind_line: {
+            distance = line_distance(p, in.parameters.xy, in.parameters.zw) - in.extra.x;
+        }
+        case kind_arc, default: {
+            if stroke > 0.0 {
+                // An arc has no inside, the stroke below widens its center line
+                distance = arc_distance(p - in.parameters.xy, in.parameters.z, in.extra);
+            } else {
+                distance = pie_distance(p - in.parameters.xy, in.parameters.z, in.extra);
+            }
+        }
+    }
+    if stroke > 0.0 {
+        distance = abs(distance) - stroke;
+    }
+
+    // Antialiasing over one pixel around the edge
+    let coverage = clamp(0.5 - distance, 0.0, 1.0);
+    if coverage <= 0.0 {
+        discard;
+    }
+    return vec4<f32>(in.color.rgb, in.color.a * coverage);
+}
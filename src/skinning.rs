@@ -0,0 +1,255 @@
+use std::{borrow::Cow, f32::consts::TAU};
+
+use cgmath::{InnerSpace, Matrix4, SquareMatrix, Vector3};
+use wgpu::{
+    util::{BufferInitDescriptor, DeviceExt},
+    BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayoutDescriptor,
+    BindGroupLayoutEntry, BindingType, Buffer, BufferBindingType, BufferUsages, CommandEncoder,
+    ComputePassDescriptor, ComputePipeline, ComputePipelineDescriptor, Device,
+    PipelineCompilationOptions, PipelineLayoutDescriptor, Queue, ShaderModuleDescriptor,
+    ShaderStages,
+};
+
+use crate::meshes::{Material, MeshId, MeshRenderer};
+
+/// Workgroup size of `skinning.wgsl`.
+const WORKGROUP_SIZE: u32 = 64;
+
+/// A vertex bound to up to four joints of a skeleton, see `skinning.wgsl`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct SkinnedVertex {
+    /// Position in the bind pose.
+    pub position: [f32; 3],
+    /// Indices of four joints, one per byte.
+    pub joints: u32,
+    /// Normal in the bind pose.
+    pub normal: [f32; 3],
+    padding: f32,
+    /// Weights of the four joints, summing up to 1.
+    pub weights: [f32; 4],
+}
+
+/// Indexed triangle list geometry bound to a skeleton on the CPU, see [`SkinnedMesh`].
+#[derive(Debug, Clone, Default)]
+pub struct SkinnedMeshData {
+    pub vertices: Vec<SkinnedVertex>,
+    pub indices: Vec<u32>,
+    /// Transform from model space into the space of every joint in the bind pose.
+    pub inverse_bind_matrices: Vec<Matrix4<f32>>,
+}
+
+impl SkinnedMeshData {
+    /// A cone standing on the origin, tapering to a fifth of its radius at its flat top, with a
+    /// chain of joints along the Y axis spaced evenly from the bottom.
+    pub fn tentacle(joints: u32, length: f32, radius: f32) -> Self {
+        const SEGMENTS: u32 = 16;
+        let rings = joints * 4;
+        let joint_spacing = length / joints as f32;
+        let taper = 0.8 * radius / length;
+
+        let mut mesh = Self::default();
+        for ring in 0..=rings {
+            let y = length * ring as f32 / rings as f32;
+            let ring_radius = radius - taper * y;
+            // Each vertex blends the two joints whose segments' centers it lies between
+            let along = (y / joint_spacing - 0.5).clamp(0.0, (joints - 1) as f32);
+            let first = (along as u32).min(joints.saturating_sub(2));
+            let weight = along - first as f32;
+            for segment in 0..=SEGMENTS {
+                let angle = TAU * segment as f32 / SEGMENTS as f32;
+                let (sin, cos) = angle.sin_cos();
+                let normal = Vector3::new(cos, taper, -sin).normalize();
+                mesh.vertices.push(SkinnedVertex {
+                    position: [ring_radius * cos, y, -ring_radius * sin],
+                    joints: first | ((first + 1).min(joints - 1) << 8),
+                    normal: normal.into(),
+                    padding: 0.0,
+                    weights: [1.0 - weight, weight, 0.0, 0.0],
+                });
+            }
+        }
+        let stride = SEGMENTS + 1;
+        for ring in 0..rings {
+            for segment in 0..SEGMENTS {
+                let bottom = ring * stride + segment;
+                let top = bottom + stride;
+                mesh.indices
+                    .extend([bottom, bottom + 1, top, top, bottom + 1, top + 1]);
+            }
+        }
+
+        // The top is closed by a fan around its center, moving with the last joint
+        let center = mesh.vertices.len() as u32;
+        let top_ring = rings * stride;
+        for segment in 0..=SEGMENTS {
+            let vertex = mesh.vertices[(top_ring + segment) as usize];
+            mesh.vertices.push(SkinnedVertex {
+                normal: [0.0, 1.0, 0.0],
+                joints: joints - 1,
+                weights: [1.0, 0.0, 0.0, 0.0],
+                ..vertex
+            });
+        }
+        mesh.vertices.push(SkinnedVertex {
+            position: [0.0, length, 0.0],
+            joints: joints - 1,
+            normal: [0.0, 1.0, 0.0],
+            padding: 0.0,
+            weights: [1.0, 0.0, 0.0, 0.0],
+        });
+        let cap_center = mesh.vertices.len() as u32 - 1;
+        for segment in 0..SEGMENTS {
+            mesh.indices
+                .extend([center + segment, center + segment + 1, cap_center]);
+        }
+
+        mesh.inverse_bind_matrices = (0..joints)
+            .map(|joint| {
+                Matrix4::from_translation(Vector3::unit_y() * -(joint as f32 * joint_spacing))
+            })
+            .collect();
+        mesh
+    }
+}
+
+/// A mesh deformed by a skeleton, skinned by a compute pass every frame.
+///
+/// The skinned vertices are written into the vertex buffer of a mesh of the [`MeshRenderer`],
+/// like the vertices of the terrain, so that every pass drawing the meshes reuses them rather
+/// than skinning the mesh again in its vertex shader.
+pub struct SkinnedMesh {
+    mesh: MeshId,
+    vertex_count: u32,
+    inverse_bind_matrices: Vec<Matrix4<f32>>,
+    skinning_matrices: Vec<[[f32; 4]; 4]>,
+    skinning_buffer: Buffer,
+    bind_group: BindGroup,
+    pipeline: ComputePipeline,
+}
+
+impl SkinnedMesh {
+    pub fn new(device: &Device, meshes: &mut MeshRenderer, data: &SkinnedMeshData) -> Self {
+        let vertex_count = data.vertices.len() as u32;
+        let mesh = meshes.add_dynamic_mesh(device, vertex_count, &data.indices);
+
+        let bind_pose_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Bind pose buffer"),
+            contents: bytemuck::cast_slice(&data.vertices),
+            usage: BufferUsages::STORAGE,
+        });
+        // Starts out in the bind pose
+        let skinning_matrices: Vec<[[f32; 4]; 4]> = data
+            .inverse_bind_matrices
+            .iter()
+            .map(|_| Matrix4::identity().into())
+            .collect();
+        let skinning_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Skinning matrix buffer"),
+            contents: bytemuck::cast_slice(&skinning_matrices),
+            usage: BufferUsages::STORAGE | BufferUsages::COPY_DST,
+        });
+
+        let storage_entry = |binding, read_only| BindGroupLayoutEntry {
+            binding,
+            visibility: ShaderStages::COMPUTE,
+            ty: BindingType::Buffer {
+                ty: BufferBindingType::Storage { read_only },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Skinning bind group layout"),
+            entries: &[
+                storage_entry(0, true),
+                storage_entry(1, true),
+                storage_entry(2, false),
+            ],
+        });
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Skinning bind group"),
+            layout: &bind_group_layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: bind_pose_buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: skinning_buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: meshes.vertex_buffer(mesh).as_entire_binding(),
+                },
+            ],
+        });
+
+        let shader_module = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("Skinning shader module"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("./skinning.wgsl"))),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Skinning pipeline layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
+            label: Some("Skinning pipeline"),
+            layout: Some(&pipeline_layout),
+            module: &shader_module,
+            entry_point: None,
+            compilation_options: PipelineCompilationOptions::default(),
+            cache: None,
+        });
+
+        Self {
+            mesh,
+            vertex_count,
+            inverse_bind_matrices: data.inverse_bind_matrices.clone(),
+            skinning_matrices,
+            skinning_buffer,
+            bind_group,
+            pipeline,
+        }
+    }
+
+    /// Poses the skeleton with the transform of every joint into model space.
+    pub fn pose(&mut self, joints: &[Matrix4<f32>]) {
+        for ((skinning, joint), inverse_bind) in self
+            .skinning_matrices
+            .iter_mut()
+            .zip(joints)
+            .zip(&self.inverse_bind_matrices)
+        {
+            *skinning = (joint * inverse_bind).into();
+        }
+    }
+
+    /// Uploads the pose set since the previous call.
+    pub fn prepare(&self, queue: &Queue) {
+        queue.write_buffer(
+            &self.skinning_buffer,
+            0,
+            bytemuck::cast_slice(&self.skinning_matrices),
+        );
+    }
+
+    /// Skins the vertices in the current pose, once for all passes of the frame.
+    pub fn skin(&self, command_encoder: &mut CommandEncoder) {
+        let mut compute_pass = command_encoder.begin_compute_pass(&ComputePassDescriptor {
+            label: Some("Skinning pass"),
+            timestamp_writes: None,
+        });
+        compute_pass.set_pipeline(&self.pipeline);
+        compute_pass.set_bind_group(0, &self.bind_group, &[]);
+        compute_pass.dispatch_workgroups(self.vertex_count.div_ceil(WORKGROUP_SIZE), 1, 1);
+    }
+
+    /// Submits the skinned mesh for the current frame.
+    pub fn draw(&self, meshes: &mut MeshRenderer, transform: Matrix4<f32>, material: Material) {
+        meshes.draw(self.mesh, transform, material);
+    }
+}
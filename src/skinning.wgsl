@@ -0,0 +1,48 @@
+// Skins the vertices of a mesh bound to a skeleton, one invocation per vertex, writing the
+// vertex buffer drawn as mesh afterwards, see `SkinnedMesh`.
+//
+// Every vertex is transformed by the blend of the skinning matrices of up to four joints,
+// weighted by the vertex's weights (linear blend skinning).
+
+struct SkinnedVertex {
+    // In the bind pose
+    position: vec3<f32>,
+    // Indices of four joints, one per byte
+    joints: u32,
+    normal: vec3<f32>,
+    // Weights of the four joints, summing up to 1
+    weights: vec4<f32>,
+}
+
+@group(0) @binding(0)
+var<storage, read> bind_pose: array<SkinnedVertex>;
+// Transform from the bind pose to the current pose of every joint
+@group(0) @binding(1)
+var<storage, read> skinning_matrices: array<mat4x4<f32>>;
+// Position and normal of every vertex, see `MeshVertex`
+@group(0) @binding(2)
+var<storage, read_write> vertices: array<f32>;
+
+@compute @workgroup_size(64)
+fn cs_main(@builtin(global_invocation_id) id: vec3<u32>) {
+    if id.x >= arrayLength(&bind_pose) {
+        return;
+    }
+    let vertex = bind_pose[id.x];
+    var skinning = mat4x4<f32>(vec4<f32>(0.0), vec4<f32>(0.0), vec4<f32>(0.0), vec4<f32>(0.0));
+    for (var i = 0u; i < 4u; i++) {
+        let joint = (vertex.joints >> (8u * i)) & 0xffu;
+        skinning += skinning_matrices[joint] * vertex.weights[i];
+    }
+    let position = (skinning * vec4<f32>(vertex.position, 1.0)).xyz;
+    // Blending rotations scales slightly, which the normalization undoes
+    let normal = normalize((skinning * vec4<f32>(vertex.normal, 0.0)).xyz);
+
+    let index = id.x * 6u;
+    vertices[index] = position.x;
+    vertices[index + 1u] = position.y;
+    vertices[index + 2u] = position.z;
+    vertices[index + 3u] = normal.x;
+    vertices[index + 4u] = normal.y;
+    vertices[index + 5u] = normal.z;
+}
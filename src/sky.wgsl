@@ -0,0 +1,18 @@
+// The colors of the sky, shared by the background and the baked environment map.
+
+const sun_direction: vec3<f32> = vec3<f32>(0.48, 0.6, -0.64);
+
+// Returns the color of the sky seen in a normalized world space direction.
+fn sky(direction: vec3<f32>) -> vec3<f32> {
+    let horizon = vec3<f32>(0.75, 0.82, 0.9);
+    let zenith = vec3<f32>(0.22, 0.42, 0.75);
+    let ground = vec3<f32>(0.3, 0.28, 0.26);
+    var color: vec3<f32>;
+    if direction.y >= 0.0 {
+        color = mix(horizon, zenith, pow(direction.y, 0.5));
+    } else {
+        color = mix(horizon, ground, pow(-direction.y, 0.3));
+    }
+    let sun = pow(max(dot(direction, normalize(sun_direction)), 0.0), 512.0);
+    return color + vec3<f32>(1.0, 0.9, 0.7) * sun;
+}
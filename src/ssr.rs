@@ -0,0 +1,322 @@
+use std::borrow::Cow;
+
+use wgpu::{
+    AddressMode, BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout,
+    BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingResource, BindingType, Buffer,
+    BufferAddress, BufferBindingType, BufferDescriptor, BufferUsages, ColorTargetState,
+    ColorWrites, Device, Extent3d, FilterMode, FragmentState, MultisampleState,
+    PipelineCompilationOptions, PipelineLayoutDescriptor, PrimitiveState, Queue, RenderPass,
+    RenderPipeline, RenderPipelineDescriptor, Sampler, SamplerBindingType, SamplerDescriptor,
+    ShaderModuleDescriptor, ShaderStages, TextureDescriptor, TextureDimension, TextureFormat,
+    TextureSampleType, TextureUsages, TextureView, TextureViewDescriptor, TextureViewDimension,
+    VertexState,
+};
+
+use crate::{deferred::DeferredLighting, environment::EnvironmentMap};
+
+/// Distance in world space after which reflection rays give up.
+const MAX_DISTANCE: f32 = 12.0;
+/// Distance in world space by which a ray may pass behind the depth buffer and still hit it.
+const THICKNESS: f32 = 0.5;
+/// Fraction of the screen towards its edges over which reflections fade out.
+const EDGE_FADE: f32 = 0.1;
+
+/// Trade-offs between the quality and cost of the screen-space reflections.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SsrSettings {
+    /// Steps marched along each reflection ray.
+    pub steps: u32,
+    /// Steps of the binary search refining where a ray hit the scene.
+    pub refinement_steps: u32,
+    /// Surfaces at least this rough reflect nothing, which skips their rays entirely.
+    pub roughness_cutoff: f32,
+}
+
+impl Default for SsrSettings {
+    fn default() -> Self {
+        Self {
+            steps: 32,
+            refinement_steps: 6,
+            roughness_cutoff: 0.6,
+        }
+    }
+}
+
+/// Reflection parameters as seen by the shader, see `ssr.wgsl`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct SsrUniform {
+    steps: u32,
+    refinement_steps: u32,
+    roughness_cutoff: f32,
+    max_distance: f32,
+    thickness: f32,
+    edge_fade: f32,
+    environment_levels: f32,
+    padding: f32,
+}
+
+/// Glossy reflections of the deferred path, traced against the depth buffer.
+///
+/// The lighting pass shades the G-buffer into the scene texture of this pass instead of
+/// the surface, and [`ScreenSpaceReflections::resolve`] then copies it onto the surface
+/// with the reflections added. Only what the G-buffer holds is reflected, rays missing
+/// it fall back to the [`EnvironmentMap`].
+pub struct ScreenSpaceReflections {
+    pub enabled: bool,
+    pub settings: SsrSettings,
+    color_format: TextureFormat,
+    uniform_buffer: Buffer,
+    sampler: Sampler,
+    scene_view: TextureView,
+    bind_group_layout: BindGroupLayout,
+    bind_group: BindGroup,
+    pipeline: RenderPipeline,
+}
+
+impl ScreenSpaceReflections {
+    pub fn new(
+        device: &Device,
+        camera_bind_group_layout: &BindGroupLayout,
+        deferred_lighting: &DeferredLighting,
+        environment: &EnvironmentMap,
+        color_format: TextureFormat,
+        width: u32,
+        height: u32,
+    ) -> Self {
+        let uniform_buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("SSR uniform buffer"),
+            size: size_of::<SsrUniform>() as BufferAddress,
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let sampler = device.create_sampler(&SamplerDescriptor {
+            label: Some("Environment sampler"),
+            address_mode_u: AddressMode::ClampToEdge,
+            address_mode_v: AddressMode::ClampToEdge,
+            address_mode_w: AddressMode::ClampToEdge,
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            mipmap_filter: FilterMode::Linear,
+            ..Default::default()
+        });
+        let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("SSR bind group layout"),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Float { filterable: false },
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Float { filterable: true },
+                        view_dimension: TextureViewDimension::Cube,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+        let scene_view = create_scene_view(device, color_format, width, height);
+        let bind_group = create_bind_group(
+            device,
+            &bind_group_layout,
+            &uniform_buffer,
+            &scene_view,
+            environment,
+            &sampler,
+        );
+
+        let shader_module = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("SSR shader module"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(concat!(
+                include_str!("./camera.wgsl"),
+                include_str!("./ssr.wgsl")
+            ))),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("SSR pipeline layout"),
+            bind_group_layouts: &[
+                camera_bind_group_layout,
+                deferred_lighting.gbuffer_bind_group_layout(),
+                &bind_group_layout,
+            ],
+            push_constant_ranges: &[],
+        });
+        // The pass reads the depth buffer, so it has no depth attachment
+        let pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("SSR pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: VertexState {
+                module: &shader_module,
+                entry_point: None,
+                compilation_options: PipelineCompilationOptions::default(),
+                buffers: &[],
+            },
+            primitive: PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+            fragment: Some(FragmentState {
+                module: &shader_module,
+                entry_point: None,
+                compilation_options: PipelineCompilationOptions::default(),
+                targets: &[Some(ColorTargetState {
+                    format: color_format,
+                    blend: None,
+                    write_mask: ColorWrites::default(),
+                })],
+            }),
+            multiview: None,
+            cache: None,
+        });
+
+        Self {
+            enabled: true,
+            settings: SsrSettings::default(),
+            color_format,
+            uniform_buffer,
+            sampler,
+            scene_view,
+            bind_group_layout,
+            bind_group,
+            pipeline,
+        }
+    }
+
+    /// Recreates the scene texture to match the size of the surface.
+    pub fn resize(
+        &mut self,
+        device: &Device,
+        environment: &EnvironmentMap,
+        width: u32,
+        height: u32,
+    ) {
+        self.scene_view = create_scene_view(device, self.color_format, width, height);
+        self.bind_group = create_bind_group(
+            device,
+            &self.bind_group_layout,
+            &self.uniform_buffer,
+            &self.scene_view,
+            environment,
+            &self.sampler,
+        );
+    }
+
+    pub fn prepare(&self, queue: &Queue) {
+        queue.write_buffer(
+            &self.uniform_buffer,
+            0,
+            bytemuck::bytes_of(&SsrUniform {
+                steps: self.settings.steps,
+                refinement_steps: self.settings.refinement_steps,
+                roughness_cutoff: self.settings.roughness_cutoff,
+                max_distance: MAX_DISTANCE,
+                thickness: THICKNESS,
+                edge_fade: EDGE_FADE,
+                environment_levels: EnvironmentMap::MIP_LEVEL_COUNT as f32,
+                padding: 0.0,
+            }),
+        );
+    }
+
+    /// The texture the deferred lighting pass shades into while reflections are enabled.
+    pub fn scene_view(&self) -> &TextureView {
+        &self.scene_view
+    }
+
+    /// Draws the scene texture with reflections onto the render pass' color attachment.
+    ///
+    /// The pass must not use the depth buffer as attachment, as it is read here.
+    pub fn resolve(
+        &self,
+        render_pass: &mut RenderPass,
+        camera_bind_group: &BindGroup,
+        deferred_lighting: &DeferredLighting,
+    ) {
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, camera_bind_group, &[]);
+        render_pass.set_bind_group(1, deferred_lighting.gbuffer_bind_group(), &[]);
+        render_pass.set_bind_group(2, &self.bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
+
+fn create_scene_view(
+    device: &Device,
+    color_format: TextureFormat,
+    width: u32,
+    height: u32,
+) -> TextureView {
+    device
+        .create_texture(&TextureDescriptor {
+            label: Some("SSR scene texture"),
+            size: Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: color_format,
+            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        })
+        .create_view(&TextureViewDescriptor::default())
+}
+
+fn create_bind_group(
+    device: &Device,
+    layout: &BindGroupLayout,
+    uniform_buffer: &Buffer,
+    scene_view: &TextureView,
+    environment: &EnvironmentMap,
+    sampler: &Sampler,
+) -> BindGroup {
+    device.create_bind_group(&BindGroupDescriptor {
+        label: Some("SSR bind group"),
+        layout,
+        entries: &[
+            BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            },
+            BindGroupEntry {
+                binding: 1,
+                resource: BindingResource::TextureView(scene_view),
+            },
+            BindGroupEntry {
+                binding: 2,
+                resource: BindingResource::TextureView(environment.view()),
+            },
+            BindGroupEntry {
+                binding: 3,
+                resource: BindingResource::Sampler(sampler),
+            },
+        ],
+    })
+}
@@ -0,0 +1,160 @@
+// Adds screen-space reflections to the shaded G-buffer with a single full-screen triangle.
+// Expects `camera.wgsl` to be prepended.
+//
+// A ray is marched from every pixel's surface along the reflected view direction in world space,
+// testing each step against the depth buffer. When it passes behind the depth buffer by less than
+// the assumed thickness of the surfaces, the hit is refined by a binary search and the shaded scene
+// is read at that point. Rays leaving the screen or the traced distance fall back to the
+// environment map, which is sampled blurrier the rougher the surface is.
+
+struct Ssr {
+    // Steps marched along each ray
+    steps: u32,
+    // Steps of the binary search refining a hit
+    refinement_steps: u32,
+    // Surfaces at least this rough reflect nothing
+    roughness_cutoff: f32,
+    // Distance in world space at which rays give up
+    max_distance: f32,
+    // Distance in world space by which a ray may pass behind the depth buffer and still hit it
+    thickness: f32,
+    // Fraction of the screen towards its edges over which reflections fade out
+    edge_fade: f32,
+    // Number of mip levels of the environment map
+    environment_levels: f32,
+    // Unused
+    padding: f32,
+}
+
+@group(1) @binding(0)
+var albedo_texture: texture_2d<f32>;
+@group(1) @binding(1)
+var normal_texture: texture_2d<f32>;
+@group(1) @binding(2)
+var material_texture: texture_2d<f32>;
+@group(1) @binding(3)
+var depth_texture: texture_2d<f32>;
+
+@group(2) @binding(0)
+var<uniform> ssr: Ssr;
+@group(2) @binding(1)
+var scene_texture: texture_2d<f32>;
+@group(2) @binding(2)
+var environment_texture: texture_cube<f32>;
+@group(2) @binding(3)
+var environment_sampler: sampler;
+
+@vertex
+fn vs_main(@builtin(vertex_index) in_vertex_index: u32) -> @builtin(position) vec4<f32> {
+    // A triangle covering the whole screen, its excess is clipped
+    let uv = vec2<f32>(f32((in_vertex_index << 1u) & 2u), f32(in_vertex_index & 2u));
+    return vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+}
+
+// Distance along the view direction of a point with the given depth.
+fn linear_depth(depth: f32) -> f32 {
+    return camera.projection[3][2] / (depth + camera.projection[2][2]);
+}
+
+// Projects a world space point to screen space pixels and depth.
+fn project(position: vec3<f32>) -> vec3<f32> {
+    let clip = camera.view_projection * vec4<f32>(position, 1.0);
+    let ndc = clip.xyz / clip.w;
+    return vec3<f32>(
+        (ndc.x * 0.5 + 0.5) * camera.viewport.x,
+        (0.5 - ndc.y * 0.5) * camera.viewport.y,
+        ndc.z,
+    );
+}
+
+// Whether the projected point is in front of the camera and on the screen.
+fn on_screen(point: vec3<f32>) -> bool {
+    return all(point.xy >= vec2<f32>(0.0)) && all(point.xy < camera.viewport.xy)
+        && point.z >= 0.0 && point.z <= 1.0;
+}
+
+// How far behind the depth buffer a projected point lies, negative if in front of it.
+fn depth_difference(point: vec3<f32>) -> f32 {
+    let depth = textureLoad(depth_texture, vec2<i32>(point.xy), 0).r;
+    return linear_depth(point.z) - linear_depth(depth);
+}
+
+// Returns the color of the scene hit by the ray, with an alpha of how much to trust it.
+fn trace(origin: vec3<f32>, direction: vec3<f32>) -> vec4<f32> {
+    let step = ssr.max_distance / f32(ssr.steps);
+    var previous = 0.0;
+    for (var i = 1u; i <= ssr.steps; i++) {
+        let distance = f32(i) * step;
+        let point = project(origin + direction * distance);
+        if !on_screen(point) {
+            break;
+        }
+        let difference = depth_difference(point);
+        if difference <= 0.0 {
+            previous = distance;
+            continue;
+        }
+        if difference > ssr.thickness {
+            // Passed behind an object, what lies behind it is unknown
+            break;
+        }
+
+        var near = previous;
+        var far = distance;
+        for (var j = 0u; j < ssr.refinement_steps; j++) {
+            let middle = (near + far) * 0.5;
+            if depth_difference(project(origin + direction * middle)) > 0.0 {
+                far = middle;
+            } else {
+                near = middle;
+            }
+        }
+        let hit = project(origin + direction * far);
+        let uv = hit.xy * camera.viewport.zw;
+        let edge = min(min(uv.x, 1.0 - uv.x), min(uv.y, 1.0 - uv.y));
+        let confidence = saturate(edge / ssr.edge_fade) * (1.0 - far / ssr.max_distance);
+        return vec4<f32>(textureLoad(scene_texture, vec2<i32>(hit.xy), 0).rgb, confidence);
+    }
+    return vec4<f32>(0.0);
+}
+
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let coords = vec2<i32>(position.xy);
+    let depth = textureLoad(depth_texture, coords, 0).r;
+    if depth <= 0.0 {
+        discard;
+    }
+    let color = textureLoad(scene_texture, coords, 0).rgb;
+    let material = textureLoad(material_texture, coords, 0);
+    let roughness = material.r;
+    if roughness >= ssr.roughness_cutoff {
+        return vec4<f32>(color, 1.0);
+    }
+
+    // Screen space to normalized device coordinates, flipping Y as it points down in screen space
+    let ndc = vec2<f32>(position.x, camera.viewport.y - position.y) * camera.viewport.zw * 2.0 - 1.0;
+    let world_position = camera.inverse_view_projection * vec4<f32>(ndc, depth, 1.0);
+    let surface_position = world_position.xyz / world_position.w;
+    let normal = normalize(textureLoad(normal_texture, coords, 0).xyz);
+    let to_camera = normalize(camera.position.xyz - surface_position);
+    let direction = reflect(-to_camera, normal);
+
+    let environment = textureSampleLevel(
+        environment_texture,
+        environment_sampler,
+        direction,
+        roughness * (ssr.environment_levels - 1.0),
+    ).rgb;
+    // Starting slightly off the surface keeps the ray from hitting it right away
+    let hit = trace(surface_position + normal * 0.01, direction);
+    let reflection = mix(environment, hit.rgb, hit.a);
+
+    // Schlick's approximation, with the reflectance of metals tinted by their albedo
+    let albedo = textureLoad(albedo_texture, coords, 0).rgb;
+    let f0 = mix(vec3<f32>(0.04), albedo, material.g);
+    let fresnel = f0 + (1.0 - f0) * pow(1.0 - max(dot(normal, to_camera), 0.0), 5.0);
+    // Reflections fade out as the roughness approaches the cutoff
+    let gloss = 1.0 - roughness / ssr.roughness_cutoff;
+    return vec4<f32>(mix(color, reflection, fresnel * gloss), 1.0);
+}
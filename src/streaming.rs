@@ -0,0 +1,533 @@
+use std::cmp::Reverse;
+
+use wgpu::{
+    AddressMode, BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout,
+    BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingResource, BindingType,
+    CommandEncoderDescriptor, Device, Extent3d, FilterMode, ImageCopyTexture, ImageDataLayout,
+    Origin3d, Queue, Sampler, SamplerBindingType, SamplerDescriptor, ShaderStages, Texture,
+    TextureAspect, TextureDescriptor, TextureDimension, TextureFormat, TextureSampleType,
+    TextureUsages, TextureViewDescriptor, TextureViewDimension,
+};
+
+/// Levels of this side length and smaller stay resident, so every texture can always be sampled.
+const TAIL_SIZE: u32 = 64;
+const BYTES_PER_TEXEL: u64 = 4;
+
+/// Produces the texels of a square mip level with the given side length, as RGBA with 8 bits per
+/// channel in sRGB, row by row from the top.
+///
+/// Levels are only requested once the view needs them, like reading them from disk.
+pub type LevelSource = Box<dyn Fn(u32) -> Vec<u8>>;
+
+/// Identifies a texture added to a [`TextureStreamer`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StreamedTextureId(usize);
+
+/// Residency of the streamed textures after the last [`TextureStreamer::update`].
+#[derive(Debug, Clone, Default)]
+pub struct StreamingStats {
+    /// Video memory the streamed textures may occupy, in bytes.
+    pub budget: u64,
+    /// Video memory the resident levels occupy, in bytes.
+    pub resident: u64,
+    /// Video memory the levels the view needs would occupy, in bytes, which may exceed the budget.
+    pub needed: u64,
+    pub textures: Vec<TextureResidency>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextureResidency {
+    /// Number of levels in the full mip chain.
+    pub level_count: u32,
+    /// Finest resident level.
+    pub resident_level: u32,
+    /// Finest level the view needs, `None` if the texture wasn't used.
+    pub needed_level: Option<u32>,
+}
+
+/// Keeps the mip levels of textures in video memory that the view needs, within a budget.
+///
+/// Each frame, the textures are requested with the finest level their on-screen size calls for,
+/// see [`mip_level`]. [`TextureStreamer::update`] then uploads one level finer for the texture
+/// furthest from the level it needs. If that exceeds the budget, the finest levels that the view
+/// doesn't need are evicted first, from the least recently used textures. Levels up to
+/// [`TAIL_SIZE`] are uploaded when a texture is added and never evicted.
+///
+/// A texture's resident levels form the tail of its mip chain. Uploading or evicting a level
+/// replaces the texture with one whose chain starts at the new finest level, copying the levels
+/// the two have in common on the GPU.
+pub struct TextureStreamer {
+    bind_group_layout: BindGroupLayout,
+    sampler: Sampler,
+    textures: Vec<StreamedTexture>,
+    budget: u64,
+    /// Frames updated so far, which tells when a texture was last used.
+    frame: u64,
+    stats: StreamingStats,
+}
+
+struct StreamedTexture {
+    name: String,
+    source: LevelSource,
+    /// Side length of level 0.
+    size: u32,
+    residency: Residency,
+    texture: Texture,
+    bind_group: BindGroup,
+}
+
+/// What [`plan`] needs to know about a texture.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Residency {
+    size: u32,
+    /// Finest resident level.
+    resident_level: u32,
+    /// Finest level that may be uploaded, as finer ones exceed the device's limits.
+    finest_level: u32,
+    /// Coarsest level that may be evicted, the levels below stay resident.
+    tail_level: u32,
+    /// Finest level requested in the current frame, `tail_level` if the texture wasn't used.
+    needed_level: u32,
+    /// Frame in which the texture was last requested.
+    last_used: u64,
+}
+
+impl Residency {
+    fn resident_bytes(&self) -> u64 {
+        (self.resident_level..level_count(self.size))
+            .map(|level| level_bytes(self.size, level))
+            .sum()
+    }
+}
+
+impl TextureStreamer {
+    pub const DEFAULT_BUDGET_MIB: u32 = 32;
+
+    pub fn new(device: &Device, budget_mib: u32) -> Self {
+        let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Streamed texture bind group layout"),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Float { filterable: true },
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+        let sampler = device.create_sampler(&SamplerDescriptor {
+            label: Some("Streamed texture sampler"),
+            address_mode_u: AddressMode::ClampToEdge,
+            address_mode_v: AddressMode::ClampToEdge,
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            mipmap_filter: FilterMode::Linear,
+            ..Default::default()
+        });
+        let budget = u64::from(budget_mib) << 20;
+
+        Self {
+            bind_group_layout,
+            sampler,
+            textures: Vec::new(),
+            budget,
+            frame: 0,
+            stats: StreamingStats {
+                budget,
+                ..Default::default()
+            },
+        }
+    }
+
+    pub fn bind_group_layout(&self) -> &BindGroupLayout {
+        &self.bind_group_layout
+    }
+
+    /// Adds a square texture with the given side length, which must be a power of two of at least
+    /// [`TAIL_SIZE`], uploading the levels that stay resident.
+    pub fn add(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        name: &str,
+        size: u32,
+        source: LevelSource,
+    ) -> StreamedTextureId {
+        assert!(size.is_power_of_two() && size >= TAIL_SIZE);
+        let max_size = device.limits().max_texture_dimension_2d;
+        let finest_level = (0..level_count(size))
+            .find(|&level| size >> level <= max_size)
+            .unwrap();
+        let tail_level = (size / TAIL_SIZE).ilog2().max(finest_level);
+        let residency = Residency {
+            size,
+            resident_level: tail_level,
+            finest_level,
+            tail_level,
+            needed_level: tail_level,
+            last_used: 0,
+        };
+        let texture = create_texture(device, name, size, tail_level);
+        for level in tail_level..level_count(size) {
+            write_level(
+                queue,
+                &texture,
+                size,
+                tail_level,
+                level,
+                &source(size >> level),
+            );
+        }
+        let bind_group = self.create_bind_group(device, name, &texture);
+
+        self.textures.push(StreamedTexture {
+            name: name.to_owned(),
+            source,
+            size,
+            residency,
+            texture,
+            bind_group,
+        });
+        StreamedTextureId(self.textures.len() - 1)
+    }
+
+    /// Requests the texture for the current frame with the given finest level.
+    pub fn request(&mut self, id: StreamedTextureId, level: u32) {
+        let residency = &mut self.textures[id.0].residency;
+        residency.needed_level = residency.needed_level.min(level);
+        residency.last_used = self.frame;
+    }
+
+    /// The texture with its resident levels and a trilinear sampler, see
+    /// [`TextureStreamer::bind_group_layout`].
+    pub fn bind_group(&self, id: StreamedTextureId) -> &BindGroup {
+        &self.textures[id.0].bind_group
+    }
+
+    pub fn stats(&self) -> &StreamingStats {
+        &self.stats
+    }
+
+    /// Uploads and evicts levels for the textures requested since the previous call.
+    pub fn update(&mut self, device: &Device, queue: &Queue) {
+        let residencies = self
+            .textures
+            .iter()
+            .map(|texture| texture.residency)
+            .collect::<Vec<_>>();
+        let resident_levels = plan(&residencies, self.budget, self.frame);
+
+        // The levels both textures hold are copied before the new ones are written into the
+        // replacement, which the queue does ahead of this submission
+        let mut command_encoder = device.create_command_encoder(&CommandEncoderDescriptor {
+            label: Some("Texture streaming command encoder"),
+        });
+        let mut changed = false;
+        for (index, resident_level) in resident_levels.into_iter().enumerate() {
+            let previous_level = self.textures[index].residency.resident_level;
+            if resident_level == previous_level {
+                continue;
+            }
+            changed = true;
+            let streamed = &self.textures[index];
+            let texture = create_texture(device, &streamed.name, streamed.size, resident_level);
+            for level in resident_level.max(previous_level)..level_count(streamed.size) {
+                command_encoder.copy_texture_to_texture(
+                    level_copy(&streamed.texture, level - previous_level),
+                    level_copy(&texture, level - resident_level),
+                    level_extent(streamed.size, level),
+                );
+            }
+            for level in resident_level..previous_level {
+                let texels = (streamed.source)(streamed.size >> level);
+                write_level(
+                    queue,
+                    &texture,
+                    streamed.size,
+                    resident_level,
+                    level,
+                    &texels,
+                );
+            }
+            if resident_level < previous_level {
+                log::debug!("Streamed level {resident_level} of {}", streamed.name);
+            } else {
+                log::debug!(
+                    "Evicted levels up to {} of {}",
+                    resident_level - 1,
+                    streamed.name
+                );
+            }
+
+            let bind_group = self.create_bind_group(device, &streamed.name, &texture);
+            let streamed = &mut self.textures[index];
+            streamed.texture = texture;
+            streamed.bind_group = bind_group;
+            streamed.residency.resident_level = resident_level;
+        }
+        if changed {
+            queue.submit([command_encoder.finish()]);
+        }
+
+        self.stats = StreamingStats {
+            budget: self.budget,
+            resident: self
+                .textures
+                .iter()
+                .map(|streamed| streamed.residency.resident_bytes())
+                .sum(),
+            needed: self
+                .textures
+                .iter()
+                .map(|streamed| {
+                    Residency {
+                        resident_level: streamed
+                            .residency
+                            .needed_level
+                            .max(streamed.residency.finest_level),
+                        ..streamed.residency
+                    }
+                    .resident_bytes()
+                })
+                .sum(),
+            textures: self
+                .textures
+                .iter()
+                .map(|streamed| TextureResidency {
+                    level_count: level_count(streamed.size),
+                    resident_level: streamed.residency.resident_level,
+                    needed_level: (streamed.residency.last_used == self.frame)
+                        .then_some(streamed.residency.needed_level),
+                })
+                .collect(),
+        };
+        for streamed in &mut self.textures {
+            streamed.residency.needed_level = streamed.residency.tail_level;
+        }
+        self.frame += 1;
+    }
+
+    fn create_bind_group(&self, device: &Device, name: &str, texture: &Texture) -> BindGroup {
+        device.create_bind_group(&BindGroupDescriptor {
+            label: Some(&format!("{name} bind group")),
+            layout: &self.bind_group_layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(
+                        &texture.create_view(&TextureViewDescriptor::default()),
+                    ),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::Sampler(&self.sampler),
+                },
+            ],
+        })
+    }
+}
+
+/// Finest mip level of a texture with the given side length that still has at least one texel
+/// per pixel when the texture covers `pixels` pixels on screen.
+pub fn mip_level(size: u32, pixels: f32) -> u32 {
+    let texels_per_pixel = size as f32 / pixels.max(1.0);
+    (texels_per_pixel.log2().floor().max(0.0) as u32).min(level_count(size) - 1)
+}
+
+/// Returns the finest resident level of each texture for the next frame.
+///
+/// The texture with the most levels missing from those it needs gets one level finer. To stay
+/// within the budget, levels finer than needed are evicted one at a time, from the textures
+/// least recently used, and the finest levels first among textures used equally recently.
+/// If there aren't enough of those, nothing is uploaded nor evicted.
+fn plan(textures: &[Residency], budget: u64, frame: u64) -> Vec<u32> {
+    let mut resident_levels = textures
+        .iter()
+        .map(|texture| texture.resident_level)
+        .collect::<Vec<_>>();
+    let Some(upload) = textures
+        .iter()
+        .enumerate()
+        .filter(|(_, texture)| {
+            texture.last_used == frame
+                && texture.needed_level.max(texture.finest_level) < texture.resident_level
+        })
+        .max_by_key(|&(index, texture)| {
+            (
+                texture.resident_level - texture.needed_level.max(texture.finest_level),
+                Reverse(index),
+            )
+        })
+        .map(|(index, _)| index)
+    else {
+        return resident_levels;
+    };
+
+    let texture = &textures[upload];
+    let cost = level_bytes(texture.size, texture.resident_level - 1);
+    let mut resident = textures.iter().map(Residency::resident_bytes).sum::<u64>();
+    while resident + cost > budget {
+        let Some(evict) = (0..textures.len())
+            .filter(|&index| {
+                index != upload && resident_levels[index] < textures[index].needed_level
+            })
+            .min_by_key(|&index| (textures[index].last_used, resident_levels[index]))
+        else {
+            return textures
+                .iter()
+                .map(|texture| texture.resident_level)
+                .collect();
+        };
+        resident -= level_bytes(textures[evict].size, resident_levels[evict]);
+        resident_levels[evict] += 1;
+    }
+    resident_levels[upload] -= 1;
+    resident_levels
+}
+
+fn level_count(size: u32) -> u32 {
+    size.ilog2() + 1
+}
+
+fn level_bytes(size: u32, level: u32) -> u64 {
+    u64::from(size >> level).pow(2) * BYTES_PER_TEXEL
+}
+
+fn level_extent(size: u32, level: u32) -> Extent3d {
+    Extent3d {
+        width: size >> level,
+        height: size >> level,
+        depth_or_array_layers: 1,
+    }
+}
+
+/// Creates a texture holding the mip chain of a texture with the given side length from
+/// `first_level` on.
+fn create_texture(device: &Device, name: &str, size: u32, first_level: u32) -> Texture {
+    device.create_texture(&TextureDescriptor {
+        label: Some(name),
+        size: level_extent(size, first_level),
+        mip_level_count: level_count(size) - first_level,
+        sample_count: 1,
+        dimension: TextureDimension::D2,
+        format: TextureFormat::Rgba8UnormSrgb,
+        usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_SRC | TextureUsages::COPY_DST,
+        view_formats: &[],
+    })
+}
+
+fn level_copy(texture: &Texture, mip_level: u32) -> ImageCopyTexture<'_> {
+    ImageCopyTexture {
+        texture,
+        mip_level,
+        origin: Origin3d::ZERO,
+        aspect: TextureAspect::All,
+    }
+}
+
+/// Writes `level` of a texture with the given side length into `texture`, whose mip chain starts
+/// at `first_level`.
+fn write_level(
+    queue: &Queue,
+    texture: &Texture,
+    size: u32,
+    first_level: u32,
+    level: u32,
+    texels: &[u8],
+) {
+    queue.write_texture(
+        level_copy(texture, level - first_level),
+        texels,
+        ImageDataLayout {
+            offset: 0,
+            bytes_per_row: Some((size >> level) * BYTES_PER_TEXEL as u32),
+            rows_per_image: None,
+        },
+        level_extent(size, level),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MIB: u64 = 1 << 20;
+
+    /// A texture of 1024 texels, with levels from 64 texels down resident.
+    fn texture(resident_level: u32, needed_level: u32, last_used: u64) -> Residency {
+        Residency {
+            size: 1024,
+            resident_level,
+            finest_level: 0,
+            tail_level: 4,
+            needed_level,
+            last_used,
+        }
+    }
+
+    #[test]
+    fn mip_level_keeps_a_texel_per_pixel() {
+        assert_eq!(mip_level(2048, 2048.0), 0);
+        assert_eq!(mip_level(2048, 1500.0), 0);
+        assert_eq!(mip_level(2048, 1024.0), 1);
+        assert_eq!(mip_level(2048, 1000.0), 1);
+        assert_eq!(mip_level(2048, 4096.0), 0);
+        assert_eq!(mip_level(2048, 0.0), 11);
+    }
+
+    #[test]
+    fn level_bytes_quarter_per_level() {
+        assert_eq!(level_bytes(1024, 0), 4 * MIB);
+        assert_eq!(level_bytes(1024, 1), MIB);
+        assert_eq!(level_bytes(1024, 10), 4);
+        assert_eq!(texture(0, 0, 0).resident_bytes(), 5592404);
+    }
+
+    #[test]
+    fn uploads_one_level_for_the_texture_missing_most() {
+        let textures = [texture(3, 2, 5), texture(4, 0, 5), texture(4, 0, 4)];
+        assert_eq!(plan(&textures, 64 * MIB, 5), [3, 3, 4]);
+    }
+
+    #[test]
+    fn uploads_nothing_once_resident() {
+        let textures = [texture(2, 2, 5), texture(1, 3, 5)];
+        assert_eq!(plan(&textures, 64 * MIB, 5), [2, 1]);
+    }
+
+    #[test]
+    fn skips_levels_beyond_the_device_limits() {
+        let textures = [Residency {
+            finest_level: 1,
+            ..texture(1, 0, 5)
+        }];
+        assert_eq!(plan(&textures, 64 * MIB, 5), [1]);
+    }
+
+    #[test]
+    fn evicts_least_recently_used_levels_over_budget() {
+        // Uploading level 0 takes 4 MiB, level 1 of the other textures takes 1 MiB each
+        let textures = [texture(1, 4, 3), texture(1, 4, 4), texture(1, 0, 5)];
+        let resident = textures.iter().map(Residency::resident_bytes).sum::<u64>();
+        assert_eq!(plan(&textures, resident + 3 * MIB, 5), [2, 1, 0]);
+        // The texture used longest ago loses all levels the view doesn't need before the next
+        assert_eq!(plan(&textures, resident + 2 * MIB, 5), [4, 2, 0]);
+    }
+
+    #[test]
+    fn keeps_needed_levels_over_budget() {
+        let textures = [texture(1, 1, 5), texture(2, 0, 5)];
+        let budget = textures.iter().map(Residency::resident_bytes).sum::<u64>();
+        assert_eq!(plan(&textures, budget, 5), [1, 2]);
+    }
+}
@@ -0,0 +1,53 @@
+use std::fmt;
+
+use wgpu::{Adapter, CompositeAlphaMode, PresentMode, Surface, TextureFormat, TextureUsages};
+
+/// What a surface supports on an adapter, i.e. the valid values of the fields of its
+/// `SurfaceConfiguration`, so settings only ever offer options that can be configured.
+#[derive(Debug, Clone)]
+pub struct SurfaceSupport {
+    /// Formats of the surface textures, the preferred format first.
+    pub formats: Vec<TextureFormat>,
+    /// Ways of presenting frames, i.e. with or without vsync, `Fifo` being always supported.
+    pub present_modes: Vec<PresentMode>,
+    /// Ways of compositing the surface with the windows behind it.
+    pub alpha_modes: Vec<CompositeAlphaMode>,
+    /// Usages of the surface textures, which always include `RENDER_ATTACHMENT`.
+    pub usages: TextureUsages,
+}
+
+impl SurfaceSupport {
+    pub fn new(surface: &Surface, adapter: &Adapter) -> Self {
+        let capabilities = surface.get_capabilities(adapter);
+        Self {
+            formats: capabilities.formats,
+            present_modes: capabilities.present_modes,
+            alpha_modes: capabilities.alpha_modes,
+            usages: capabilities.usages,
+        }
+    }
+
+    /// The supported present mode following `present_mode`, wrapping around to the first one.
+    pub fn next_present_mode(&self, present_mode: PresentMode) -> PresentMode {
+        let index = self
+            .present_modes
+            .iter()
+            .position(|&mode| mode == present_mode)
+            .map_or(0, |index| index + 1);
+        self.present_modes
+            .get(index)
+            .or(self.present_modes.first())
+            .copied()
+            .unwrap_or(PresentMode::Fifo)
+    }
+}
+
+impl fmt::Display for SurfaceSupport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "formats {:?}, present modes {:?}, alpha modes {:?}, usages {:?}",
+            self.formats, self.present_modes, self.alpha_modes, self.usages
+        )
+    }
+}
@@ -0,0 +1,283 @@
+use std::borrow::Cow;
+
+use cgmath::{Matrix4, SquareMatrix};
+use wgpu::{
+    util::{BufferInitDescriptor, DeviceExt, TextureDataOrder},
+    BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout, BindGroupLayoutDescriptor,
+    BindGroupLayoutEntry, BindingResource, BindingType, BufferBindingType, BufferUsages,
+    CommandEncoder, ComputePassDescriptor, ComputePipeline, ComputePipelineDescriptor, Device,
+    Extent3d, PipelineCompilationOptions, PipelineLayoutDescriptor, Queue, ShaderModuleDescriptor,
+    ShaderStages, TextureDescriptor, TextureDimension, TextureFormat, TextureSampleType,
+    TextureUsages, TextureViewDescriptor, TextureViewDimension,
+};
+
+use crate::{
+    meshes::{Material, MeshId, MeshRenderer},
+    oit::Transparency,
+};
+
+/// World space X and Z of the terrain's corner, behind the ring of objects.
+const ORIGIN: [f32; 2] = [-10.0, -16.0];
+/// Extent of the terrain along X and Z.
+const SIZE: [f32; 2] = [20.0, 8.0];
+/// Number of patches along X and Z.
+const PATCHES: [u32; 2] = [8, 4];
+/// Quads along each edge of a patch at the finest level.
+const RESOLUTION: u32 = 32;
+/// Distance up to which patches are subdivided at the finest level.
+const LOD_DISTANCE: f32 = 4.0;
+/// Texels of the heightmap along each axis.
+const HEIGHTMAP_SIZE: u32 = 128;
+/// Height of the highest hills.
+const MAX_HEIGHT: f32 = 3.0;
+/// The terrain's border lies this far below the ground plane, to keep the two from z-fighting.
+const SINK: f32 = 0.05;
+/// Workgroup size of `terrain.wgsl` along both axes of a patch.
+const WORKGROUP_SIZE: u32 = 8;
+const MATERIAL: Material = Material {
+    albedo: [0.22, 0.32, 0.12],
+    roughness: 0.9,
+    metallic: 0.0,
+    opacity: 1.0,
+    transparency: Transparency::Sorted,
+};
+
+/// Terrain parameters as seen by the shader, see `terrain.wgsl`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct TerrainUniform {
+    origin: [f32; 2],
+    size: [f32; 2],
+    patches: [u32; 2],
+    resolution: u32,
+    max_level: u32,
+    lod_distance: f32,
+    padding: f32,
+}
+
+/// Hilly terrain, subdivided and displaced by a compute pass every frame.
+///
+/// wgpu has no tessellation shaders, so the terrain's patches are subdivided by a compute shader
+/// instead, which writes the displaced vertices into the vertex buffer of a mesh of the
+/// [`MeshRenderer`]. Patches closer to the camera are subdivided more finely, see `terrain.wgsl`.
+pub struct DisplacedTerrain {
+    mesh: MeshId,
+    bind_group: BindGroup,
+    pipeline: ComputePipeline,
+}
+
+impl DisplacedTerrain {
+    pub fn new(
+        device: &Device,
+        queue: &Queue,
+        camera_bind_group_layout: &BindGroupLayout,
+        meshes: &mut MeshRenderer,
+    ) -> Self {
+        let patch_count = PATCHES[0] * PATCHES[1];
+        let vertices_per_patch = (RESOLUTION + 1) * (RESOLUTION + 1);
+        let mesh = meshes.add_dynamic_mesh(
+            device,
+            patch_count * vertices_per_patch,
+            &patch_indices(patch_count, vertices_per_patch),
+        );
+
+        let uniform_buffer = device.create_buffer_init(&BufferInitDescriptor {
+            label: Some("Terrain uniform buffer"),
+            contents: bytemuck::bytes_of(&TerrainUniform {
+                origin: ORIGIN,
+                size: SIZE,
+                patches: PATCHES,
+                resolution: RESOLUTION,
+                max_level: RESOLUTION.ilog2(),
+                lod_distance: LOD_DISTANCE,
+                padding: 0.0,
+            }),
+            usage: BufferUsages::UNIFORM,
+        });
+        let heightmap = device.create_texture_with_data(
+            queue,
+            &TextureDescriptor {
+                label: Some("Terrain heightmap"),
+                size: Extent3d {
+                    width: HEIGHTMAP_SIZE,
+                    height: HEIGHTMAP_SIZE,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: TextureDimension::D2,
+                format: TextureFormat::R32Float,
+                usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
+                view_formats: &[],
+            },
+            TextureDataOrder::LayerMajor,
+            bytemuck::cast_slice(&generate_heightmap()),
+        );
+
+        let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Terrain bind group layout"),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Float { filterable: false },
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: ShaderStages::COMPUTE,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Storage { read_only: false },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: Some("Terrain bind group"),
+            layout: &bind_group_layout,
+            entries: &[
+                BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+                BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::TextureView(
+                        &heightmap.create_view(&TextureViewDescriptor::default()),
+                    ),
+                },
+                BindGroupEntry {
+                    binding: 2,
+                    resource: meshes.vertex_buffer(mesh).as_entire_binding(),
+                },
+            ],
+        });
+
+        let shader_module = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("Terrain shader module"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(concat!(
+                include_str!("./camera.wgsl"),
+                include_str!("./terrain.wgsl")
+            ))),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Terrain pipeline layout"),
+            bind_group_layouts: &[camera_bind_group_layout, &bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_compute_pipeline(&ComputePipelineDescriptor {
+            label: Some("Terrain pipeline"),
+            layout: Some(&pipeline_layout),
+            module: &shader_module,
+            entry_point: None,
+            compilation_options: PipelineCompilationOptions::default(),
+            cache: None,
+        });
+
+        Self {
+            mesh,
+            bind_group,
+            pipeline,
+        }
+    }
+
+    /// Subdivides and displaces the patches for the current camera position.
+    pub fn compute(&self, command_encoder: &mut CommandEncoder, camera_bind_group: &BindGroup) {
+        let mut compute_pass = command_encoder.begin_compute_pass(&ComputePassDescriptor {
+            label: Some("Terrain pass"),
+            timestamp_writes: None,
+        });
+        compute_pass.set_pipeline(&self.pipeline);
+        compute_pass.set_bind_group(0, camera_bind_group, &[]);
+        compute_pass.set_bind_group(1, &self.bind_group, &[]);
+        let workgroups = (RESOLUTION + 1).div_ceil(WORKGROUP_SIZE);
+        compute_pass.dispatch_workgroups(workgroups, workgroups, PATCHES[0] * PATCHES[1]);
+    }
+
+    /// Submits the terrain's mesh for the current frame.
+    pub fn draw(&self, meshes: &mut MeshRenderer) {
+        meshes.draw(self.mesh, Matrix4::identity(), MATERIAL);
+    }
+}
+
+/// Triangulates the grid of every patch at the finest level, facing up.
+fn patch_indices(patch_count: u32, vertices_per_patch: u32) -> Vec<u32> {
+    let mut indices = Vec::new();
+    let row = RESOLUTION + 1;
+    for patch in 0..patch_count {
+        let first = patch * vertices_per_patch;
+        for z in 0..RESOLUTION {
+            for x in 0..RESOLUTION {
+                let a = first + z * row + x;
+                let b = a + 1;
+                let c = a + row;
+                let d = c + 1;
+                indices.extend([a, c, b, b, c, d]);
+            }
+        }
+    }
+    indices
+}
+
+/// Fractal value noise, faded out towards the border of the terrain.
+fn generate_heightmap() -> Vec<f32> {
+    let mut heights = Vec::new();
+    for y in 0..HEIGHTMAP_SIZE {
+        for x in 0..HEIGHTMAP_SIZE {
+            let u = (x as f32 + 0.5) / HEIGHTMAP_SIZE as f32;
+            let v = (y as f32 + 0.5) / HEIGHTMAP_SIZE as f32;
+            let mut height = 0.0;
+            let mut amplitude = 0.5;
+            let mut frequency = 4.0;
+            for _ in 0..5 {
+                height += amplitude * value_noise(u * frequency, v * frequency);
+                amplitude *= 0.5;
+                frequency *= 2.0;
+            }
+            let border = smoothstep(u.min(1.0 - u) / 0.25) * smoothstep(v.min(1.0 - v) / 0.25);
+            heights.push(height * border * MAX_HEIGHT - SINK);
+        }
+    }
+    heights
+}
+
+/// Smoothly interpolated random values at integer coordinates, from 0 to 1.
+fn value_noise(x: f32, y: f32) -> f32 {
+    let (x0, y0) = (x.floor(), y.floor());
+    let (tx, ty) = (smoothstep(x - x0), smoothstep(y - y0));
+    let (x0, y0) = (x0 as i32, y0 as i32);
+    let top = lattice(x0, y0) + (lattice(x0 + 1, y0) - lattice(x0, y0)) * tx;
+    let bottom = lattice(x0, y0 + 1) + (lattice(x0 + 1, y0 + 1) - lattice(x0, y0 + 1)) * tx;
+    top + (bottom - top) * ty
+}
+
+/// Random value from 0 to 1 of an integer coordinate.
+fn lattice(x: i32, y: i32) -> f32 {
+    let mut hash = (x as u32).wrapping_mul(0x27d4_eb2d) ^ (y as u32).wrapping_mul(0x1656_67b1);
+    hash ^= hash >> 15;
+    hash = hash.wrapping_mul(0x85eb_ca6b);
+    hash ^= hash >> 13;
+    (hash & 0xffff) as f32 / 0xffff as f32
+}
+
+fn smoothstep(t: f32) -> f32 {
+    let t = t.clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
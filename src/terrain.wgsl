@@ -0,0 +1,110 @@
+// Subdivides the patches of the terrain and displaces them by its heightmap, one invocation
+// per vertex, writing the vertex buffer drawn as mesh afterwards.
+// Expects `camera.wgsl` to be prepended.
+//
+// Every patch has the vertices of the finest level, the vertex buffer's layout never changes.
+// Coarser levels snap the vertices to every 2nd, 4th, ... vertex of the grid, which collapses
+// the triangles in between so that they are culled when drawn. Vertices on a patch's edge snap
+// to the coarser level of the two patches sharing it, so that neighbors meet without cracks.
+
+struct Terrain {
+    // World space X and Z of the terrain's corner with the smallest coordinates
+    origin: vec2<f32>,
+    // Extent of the terrain along X and Z
+    size: vec2<f32>,
+    // Number of patches along X and Z
+    patches: vec2<u32>,
+    // Quads along each edge of a patch at the finest level, a power of two
+    resolution: u32,
+    // Coarsest level, at which a patch is a single quad
+    max_level: u32,
+    // Distance to the camera up to which patches are subdivided at the finest level,
+    // every doubling of it halves the subdivision
+    lod_distance: f32,
+    // Unused
+    padding: f32,
+}
+
+@group(1) @binding(0)
+var<uniform> terrain: Terrain;
+// Height in world space, spanning the whole terrain
+@group(1) @binding(1)
+var heightmap: texture_2d<f32>;
+// Position and normal of every vertex, see `MeshVertex`
+@group(1) @binding(2)
+var<storage, read_write> vertices: array<f32>;
+
+// Subdivision level of a patch, 0 being the finest.
+fn patch_level(patch_coords: vec2<i32>) -> u32 {
+    let center = terrain.origin + (vec2<f32>(patch_coords) + 0.5) / vec2<f32>(terrain.patches) * terrain.size;
+    let distance = length(vec3<f32>(center.x, 0.0, center.y) - camera.position.xyz);
+    let level = floor(log2(max(distance / terrain.lod_distance, 1.0)));
+    return min(u32(level), terrain.max_level);
+}
+
+// Step between the vertices on the edge shared with a neighbor, whose coarser level wins.
+fn edge_step(neighbor: vec2<i32>, step: u32) -> u32 {
+    if any(neighbor < vec2<i32>(0)) || any(neighbor >= vec2<i32>(terrain.patches)) {
+        return step;
+    }
+    return max(step, 1u << patch_level(neighbor));
+}
+
+// Bilinearly filtered height, the heightmap's float format cannot be sampled with filtering.
+fn height(uv: vec2<f32>) -> f32 {
+    let size = vec2<i32>(textureDimensions(heightmap));
+    let texel = uv * vec2<f32>(size) - 0.5;
+    let base = vec2<i32>(floor(texel));
+    let t = texel - floor(texel);
+    let h00 = textureLoad(heightmap, clamp(base, vec2<i32>(0), size - 1), 0).r;
+    let h10 = textureLoad(heightmap, clamp(base + vec2<i32>(1, 0), vec2<i32>(0), size - 1), 0).r;
+    let h01 = textureLoad(heightmap, clamp(base + vec2<i32>(0, 1), vec2<i32>(0), size - 1), 0).r;
+    let h11 = textureLoad(heightmap, clamp(base + vec2<i32>(1, 1), vec2<i32>(0), size - 1), 0).r;
+    return mix(mix(h00, h10, t.x), mix(h01, h11, t.x), t.y);
+}
+
+@compute @workgroup_size(8, 8, 1)
+fn cs_main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let resolution = terrain.resolution;
+    if id.x > resolution || id.y > resolution || id.z >= terrain.patches.x * terrain.patches.y {
+        return;
+    }
+    let patch_coords = vec2<i32>(i32(id.z % terrain.patches.x), i32(id.z / terrain.patches.x));
+    let step = 1u << patch_level(patch_coords);
+
+    var grid = id.xy / step * step;
+    if grid.x == 0u {
+        let edge = edge_step(patch_coords - vec2<i32>(1, 0), step);
+        grid.y = grid.y / edge * edge;
+    } else if grid.x == resolution {
+        let edge = edge_step(patch_coords + vec2<i32>(1, 0), step);
+        grid.y = grid.y / edge * edge;
+    }
+    if grid.y == 0u {
+        let edge = edge_step(patch_coords - vec2<i32>(0, 1), step);
+        grid.x = grid.x / edge * edge;
+    } else if grid.y == resolution {
+        let edge = edge_step(patch_coords + vec2<i32>(0, 1), step);
+        grid.x = grid.x / edge * edge;
+    }
+
+    let uv = (vec2<f32>(patch_coords) + vec2<f32>(grid) / f32(resolution)) / vec2<f32>(terrain.patches);
+    let xz = terrain.origin + uv * terrain.size;
+    let position = vec3<f32>(xz.x, height(uv), xz.y);
+
+    // Central differences one texel apart
+    let texel = 1.0 / vec2<f32>(textureDimensions(heightmap));
+    let slope = vec2<f32>(
+        height(uv + vec2<f32>(texel.x, 0.0)) - height(uv - vec2<f32>(texel.x, 0.0)),
+        height(uv + vec2<f32>(0.0, texel.y)) - height(uv - vec2<f32>(0.0, texel.y)),
+    ) / (2.0 * texel * terrain.size);
+    let normal = normalize(vec3<f32>(-slope.x, 1.0, -slope.y));
+
+    let index = ((id.z * (resolution + 1u) + id.y) * (resolution + 1u) + id.x) * 6u;
+    vertices[index] = position.x;
+    vertices[index + 1u] = position.y;
+    vertices[index + 2u] = position.z;
+    vertices[index + 3u] = normal.x;
+    vertices[index + 4u] = normal.y;
+    vertices[index + 5u] = normal.z;
+}
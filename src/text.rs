@@ -0,0 +1,554 @@
+use std::{borrow::Cow, ops::Range};
+
+use cgmath::{InnerSpace, Point3};
+use wgpu::{
+    util::{DeviceExt, TextureDataOrder},
+    AddressMode, BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout,
+    BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingResource, BindingType, BlendState,
+    Buffer, BufferAddress, BufferDescriptor, BufferUsages, ColorTargetState, ColorWrites,
+    CompareFunction, DepthStencilState, Device, Extent3d, FilterMode, FragmentState,
+    MultisampleState, PipelineCompilationOptions, PipelineLayoutDescriptor, PrimitiveState,
+    PrimitiveTopology, Queue, RenderPass, RenderPipeline, RenderPipelineDescriptor,
+    SamplerBindingType, SamplerDescriptor, ShaderModuleDescriptor, ShaderStages, TextureDescriptor,
+    TextureDimension, TextureFormat, TextureSampleType, TextureUsages, TextureViewDescriptor,
+    TextureViewDimension, VertexBufferLayout, VertexState, VertexStepMode,
+};
+
+use crate::camera::Camera;
+
+/// Glyphs of the built-in bitmap font, one byte per row from top to bottom,
+/// with the leftmost pixel of a row in bit 4.
+const GLYPHS: [(char, u64); 47] = [
+    (' ', 0x00_00_00_00_00_00_00),
+    ('?', 0x0e_11_01_02_04_00_04),
+    ('0', 0x0e_11_13_15_19_11_0e),
+    ('1', 0x04_0c_04_04_04_04_0e),
+    ('2', 0x0e_11_01_02_04_08_1f),
+    ('3', 0x1f_02_04_02_01_11_0e),
+    ('4', 0x02_06_0a_12_1f_02_02),
+    ('5', 0x1f_10_1e_01_01_11_0e),
+    ('6', 0x06_08_10_1e_11_11_0e),
+    ('7', 0x1f_01_02_04_08_08_08),
+    ('8', 0x0e_11_11_0e_11_11_0e),
+    ('9', 0x0e_11_11_0f_01_02_0c),
+    ('A', 0x0e_11_11_1f_11_11_11),
+    ('B', 0x1e_11_11_1e_11_11_1e),
+    ('C', 0x0e_11_10_10_10_11_0e),
+    ('D', 0x1c_12_11_11_11_12_1c),
+    ('E', 0x1f_10_10_1e_10_10_1f),
+    ('F', 0x1f_10_10_1e_10_10_10),
+    ('G', 0x0e_11_10_17_11_11_0f),
+    ('H', 0x11_11_11_1f_11_11_11),
+    ('I', 0x0e_04_04_04_04_04_0e),
+    ('J', 0x07_02_02_02_02_12_0c),
+    ('K', 0x11_12_14_18_14_12_11),
+    ('L', 0x10_10_10_10_10_10_1f),
+    ('M', 0x11_1b_15_15_11_11_11),
+    ('N', 0x11_11_19_15_13_11_11),
+    ('O', 0x0e_11_11_11_11_11_0e),
+    ('P', 0x1e_11_11_1e_10_10_10),
+    ('Q', 0x0e_11_11_11_15_12_0d),
+    ('R', 0x1e_11_11_1e_14_12_11),
+    ('S', 0x0f_10_10_0e_01_01_1e),
+    ('T', 0x1f_04_04_04_04_04_04),
+    ('U', 0x11_11_11_11_11_11_0e),
+    ('V', 0x11_11_11_11_11_0a_04),
+    ('W', 0x11_11_11_15_15_15_0a),
+    ('X', 0x11_11_0a_04_0a_11_11),
+    ('Y', 0x11_11_11_0a_04_04_04),
+    ('Z', 0x1f_01_02_04_08_10_1f),
+    ('.', 0x00_00_00_00_00_0c_0c),
+    (',', 0x00_00_00_00_0c_04_08),
+    (':', 0x00_0c_0c_00_0c_0c_00),
+    ('-', 0x00_00_00_1f_00_00_00),
+    ('+', 0x00_04_04_1f_04_04_00),
+    ('/', 0x00_01_02_04_08_10_00),
+    ('%', 0x18_19_02_04_08_13_03),
+    ('(', 0x02_04_08_08_08_04_02),
+    (')', 0x08_04_02_02_02_04_08),
+];
+
+/// Size of a glyph of the bitmap font, in font pixels.
+const GLYPH_WIDTH: u32 = 5;
+const GLYPH_HEIGHT: u32 = 7;
+/// Horizontal distance between the glyphs of a line, in font pixels.
+const ADVANCE: f32 = 6.0;
+/// Empty font pixels around each glyph in the atlas, so the distance field can fall off.
+const PADDING: u32 = 1;
+/// Distance from the outline, in font pixels, at which the encoded distance saturates.
+/// Must match `distance_range` in `text.wgsl`, which is given in texels.
+const DISTANCE_RANGE: f32 = 1.0;
+/// Atlas texels per font pixel.
+const TEXELS_PER_PIXEL: u32 = 4;
+const ATLAS_COLUMNS: u32 = 8;
+
+/// How the size of a label is interpreted, as the height of its capital letters.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TextSize {
+    /// In world units, so the label gets smaller with increasing distance.
+    World(f32),
+    /// In pixels, so the label stays readable regardless of the distance.
+    Screen(f32),
+}
+
+/// A line of text facing the camera, see [`TextRenderer`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Label<'a> {
+    /// World space position of the line's center.
+    pub position: Point3<f32>,
+    /// Lowercase letters are drawn as uppercase ones, unknown characters as `?`.
+    pub text: &'a str,
+    pub size: TextSize,
+    pub color: [f32; 4],
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct GlyphInstance {
+    position: [f32; 3],
+    flags: u32,
+    /// Bottom left corner of the glyph's quad, relative to the label's center.
+    offset: [f32; 2],
+    size: [f32; 2],
+    color: [f32; 4],
+    uv_rect: [f32; 4],
+}
+
+impl GlyphInstance {
+    const FLAG_SCREEN_SIZE: u32 = 1;
+
+    const ATTRIBUTES: [wgpu::VertexAttribute; 6] = wgpu::vertex_attr_array![
+        0 => Float32x3,
+        1 => Uint32,
+        2 => Float32x2,
+        3 => Float32x2,
+        4 => Float32x4,
+        5 => Float32x4,
+    ];
+
+    fn layout() -> VertexBufferLayout<'static> {
+        VertexBufferLayout {
+            array_stride: size_of::<Self>() as BufferAddress,
+            step_mode: VertexStepMode::Instance,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+}
+
+/// Draws labels in 3D space with a multi-channel signed distance field (MSDF) font.
+///
+/// The glyphs of a small bitmap font are traced into outlines, from which the distance
+/// fields are generated at startup. Unlike a bitmap font, the text stays sharp at any scale,
+/// and unlike a single-channel distance field, the corners of the glyphs stay sharp as well.
+///
+/// Labels always face the camera, are depth tested against the scene without writing depth,
+/// and are sorted back to front so that their outlines blend correctly.
+pub struct TextRenderer {
+    pipeline: RenderPipeline,
+    atlas_bind_group: BindGroup,
+    instance_buffer: Buffer,
+    /// Glyphs of the labels drawn since the last frame, in the order they were drawn.
+    glyphs: Vec<GlyphInstance>,
+    /// Position and glyphs of each label drawn since the last frame.
+    labels: Vec<(Point3<f32>, Range<usize>)>,
+    instances: Vec<GlyphInstance>,
+    instance_count: u32,
+}
+
+impl TextRenderer {
+    const INITIAL_CAPACITY: BufferAddress = 256 * size_of::<GlyphInstance>() as BufferAddress;
+
+    pub fn new(
+        device: &Device,
+        queue: &Queue,
+        camera_bind_group_layout: &BindGroupLayout,
+        color_format: TextureFormat,
+        depth_format: TextureFormat,
+    ) -> Self {
+        let atlas_bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Font atlas bind group layout"),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Float { filterable: true },
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+        let atlas_bind_group = create_atlas_bind_group(device, queue, &atlas_bind_group_layout);
+
+        let shader_module = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("Text shader module"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(concat!(
+                include_str!("./camera.wgsl"),
+                include_str!("./text.wgsl")
+            ))),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Text pipeline layout"),
+            bind_group_layouts: &[camera_bind_group_layout, &atlas_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("Text pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: VertexState {
+                module: &shader_module,
+                entry_point: Some("vs_main"),
+                compilation_options: PipelineCompilationOptions::default(),
+                buffers: &[GlyphInstance::layout()],
+            },
+            primitive: PrimitiveState {
+                topology: PrimitiveTopology::TriangleStrip,
+                ..Default::default()
+            },
+            depth_stencil: Some(DepthStencilState {
+                format: depth_format,
+                depth_write_enabled: false,
+                depth_compare: CompareFunction::GreaterEqual,
+                stencil: Default::default(),
+                bias: Default::default(),
+            }),
+            multisample: MultisampleState::default(),
+            fragment: Some(FragmentState {
+                module: &shader_module,
+                entry_point: Some("fs_main"),
+                compilation_options: PipelineCompilationOptions::default(),
+                targets: &[Some(ColorTargetState {
+                    format: color_format,
+                    blend: Some(BlendState::ALPHA_BLENDING),
+                    write_mask: ColorWrites::default(),
+                })],
+            }),
+            multiview: None,
+            cache: None,
+        });
+
+        Self {
+            pipeline,
+            atlas_bind_group,
+            instance_buffer: create_instance_buffer(device, Self::INITIAL_CAPACITY),
+            glyphs: Vec::new(),
+            labels: Vec::new(),
+            instances: Vec::new(),
+            instance_count: 0,
+        }
+    }
+
+    /// Lays out a label's glyphs, centered on its position.
+    pub fn draw(&mut self, label: &Label) {
+        let (height, flags) = match label.size {
+            TextSize::World(height) => (height, 0),
+            TextSize::Screen(height) => (height, GlyphInstance::FLAG_SCREEN_SIZE),
+        };
+        // Label units per font pixel
+        let scale = height / GLYPH_HEIGHT as f32;
+        let length = label.text.chars().count() as f32;
+        let width = length * ADVANCE - (ADVANCE - GLYPH_WIDTH as f32);
+        let padding = PADDING as f32;
+        let cell = [
+            (GLYPH_WIDTH + 2 * PADDING) as f32 * scale,
+            (GLYPH_HEIGHT + 2 * PADDING) as f32 * scale,
+        ];
+
+        let first = self.glyphs.len();
+        for (index, character) in label.text.chars().enumerate() {
+            let glyph = glyph_index(character);
+            if GLYPHS[glyph].0 == ' ' {
+                continue;
+            }
+            let x = index as f32 * ADVANCE - width / 2.0 - padding;
+            let y = -(GLYPH_HEIGHT as f32) / 2.0 - padding;
+            self.glyphs.push(GlyphInstance {
+                position: label.position.into(),
+                flags,
+                offset: [x * scale, y * scale],
+                size: cell,
+                color: label.color,
+                uv_rect: uv_rect(glyph),
+            });
+        }
+        self.labels.push((label.position, first..self.glyphs.len()));
+    }
+
+    /// Sorts and uploads the labels drawn since the previous call.
+    pub fn prepare(&mut self, device: &Device, queue: &Queue, camera: &Camera) {
+        let forward = camera.forward();
+        let depth = |position: Point3<f32>| (position - camera.eye).dot(forward);
+        self.labels
+            .sort_by(|(a, _), (b, _)| depth(*b).total_cmp(&depth(*a)));
+
+        self.instances.clear();
+        for (_, glyphs) in self.labels.drain(..) {
+            self.instances.extend_from_slice(&self.glyphs[glyphs]);
+        }
+        self.glyphs.clear();
+        self.instance_count = self.instances.len() as u32;
+
+        let size = (self.instances.len() * size_of::<GlyphInstance>()) as BufferAddress;
+        if size > self.instance_buffer.size() {
+            self.instance_buffer = create_instance_buffer(device, size.next_power_of_two());
+        }
+        if size > 0 {
+            queue.write_buffer(
+                &self.instance_buffer,
+                0,
+                bytemuck::cast_slice(&self.instances),
+            );
+        }
+    }
+
+    pub fn render(&self, render_pass: &mut RenderPass, camera_bind_group: &BindGroup) {
+        if self.instance_count == 0 {
+            return;
+        }
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, camera_bind_group, &[]);
+        render_pass.set_bind_group(1, &self.atlas_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.instance_buffer.slice(..));
+        render_pass.draw(0..4, 0..self.instance_count);
+    }
+}
+
+/// Index into [`GLYPHS`] of the glyph drawn for a character.
+fn glyph_index(character: char) -> usize {
+    let character = character.to_ascii_uppercase();
+    GLYPHS
+        .iter()
+        .position(|&(glyph, _)| glyph == character)
+        .unwrap_or(1)
+}
+
+/// Size of a glyph's cell in the atlas, in texels.
+fn cell_size() -> (u32, u32) {
+    (
+        (GLYPH_WIDTH + 2 * PADDING) * TEXELS_PER_PIXEL,
+        (GLYPH_HEIGHT + 2 * PADDING) * TEXELS_PER_PIXEL,
+    )
+}
+
+fn atlas_size() -> (u32, u32) {
+    let (cell_width, cell_height) = cell_size();
+    let rows = (GLYPHS.len() as u32).div_ceil(ATLAS_COLUMNS);
+    (ATLAS_COLUMNS * cell_width, rows * cell_height)
+}
+
+/// Texture coordinates of the top left and bottom right corners of a glyph's cell.
+fn uv_rect(glyph: usize) -> [f32; 4] {
+    let (cell_width, cell_height) = cell_size();
+    let (width, height) = atlas_size();
+    let column = glyph as u32 % ATLAS_COLUMNS;
+    let row = glyph as u32 / ATLAS_COLUMNS;
+    let left = (column * cell_width) as f32 / width as f32;
+    let top = (row * cell_height) as f32 / height as f32;
+    [
+        left,
+        top,
+        left + cell_width as f32 / width as f32,
+        top + cell_height as f32 / height as f32,
+    ]
+}
+
+/// A straight piece of a glyph's outline, one font pixel long, with the glyph on its left.
+struct Edge {
+    from: [f32; 2],
+    direction: [f32; 2],
+}
+
+impl Edge {
+    fn is_horizontal(&self) -> bool {
+        self.direction[1] == 0.0
+    }
+
+    /// Returns the distance of a point to the edge, and its signed distance to the edge's
+    /// infinite line, which is positive on the glyph's side.
+    fn distances(&self, point: [f32; 2]) -> (f32, f32) {
+        let relative = [point[0] - self.from[0], point[1] - self.from[1]];
+        let along =
+            (relative[0] * self.direction[0] + relative[1] * self.direction[1]).clamp(0.0, 1.0);
+        let nearest = [
+            relative[0] - self.direction[0] * along,
+            relative[1] - self.direction[1] * along,
+        ];
+        let distance = (nearest[0] * nearest[0] + nearest[1] * nearest[1]).sqrt();
+        let line = self.direction[0] * relative[1] - self.direction[1] * relative[0];
+        (distance, line)
+    }
+}
+
+/// Traces the outline of a glyph's filled pixels, in font pixels with Y pointing up.
+fn glyph_edges(filled: &impl Fn(i32, i32) -> bool) -> Vec<Edge> {
+    let mut edges = Vec::new();
+    for y in 0..GLYPH_HEIGHT as i32 {
+        for x in 0..GLYPH_WIDTH as i32 {
+            if !filled(x, y) {
+                continue;
+            }
+            let (left, bottom) = (x as f32, y as f32);
+            let (right, top) = (left + 1.0, bottom + 1.0);
+            if !filled(x, y - 1) {
+                edges.push(Edge {
+                    from: [left, bottom],
+                    direction: [1.0, 0.0],
+                });
+            }
+            if !filled(x + 1, y) {
+                edges.push(Edge {
+                    from: [right, bottom],
+                    direction: [0.0, 1.0],
+                });
+            }
+            if !filled(x, y + 1) {
+                edges.push(Edge {
+                    from: [right, top],
+                    direction: [-1.0, 0.0],
+                });
+            }
+            if !filled(x - 1, y) {
+                edges.push(Edge {
+                    from: [left, top],
+                    direction: [0.0, -1.0],
+                });
+            }
+        }
+    }
+    edges
+}
+
+/// Computes the multi-channel signed distance of a point to a glyph's outline.
+///
+/// The outline only has horizontal and vertical edges, meeting at right angles. Red holds
+/// the distance to the lines through the horizontal edges, blue the distance to the lines
+/// through the vertical ones, and green the true distance. The median of the three channels
+/// then keeps each corner sharp, where one horizontal and one vertical line intersect.
+fn glyph_distance(edges: &[Edge], inside: bool, point: [f32; 2]) -> [f32; 3] {
+    let line_distance = |horizontal: bool| {
+        edges
+            .iter()
+            .filter(|edge| edge.is_horizontal() == horizontal)
+            .map(|edge| edge.distances(point))
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+            .map_or(-DISTANCE_RANGE, |(_, line)| line)
+    };
+    let true_distance = edges
+        .iter()
+        .map(|edge| edge.distances(point).0)
+        .fold(DISTANCE_RANGE, f32::min);
+    let true_distance = if inside {
+        true_distance
+    } else {
+        -true_distance
+    };
+
+    let channels = [line_distance(true), true_distance, line_distance(false)];
+    // Where the median disagrees with the true distance about which side of the outline a
+    // texel lies on, e.g. near concave corners, the true distance is used in all channels
+    if (median(channels) > 0.0) != inside {
+        [true_distance; 3]
+    } else {
+        channels
+    }
+}
+
+fn median([a, b, c]: [f32; 3]) -> f32 {
+    a.min(b).max(a.max(b).min(c))
+}
+
+/// Generates the distance fields of the glyphs into a texture atlas and creates its bind group.
+fn create_atlas_bind_group(device: &Device, queue: &Queue, layout: &BindGroupLayout) -> BindGroup {
+    let (width, height) = atlas_size();
+    let (cell_width, cell_height) = cell_size();
+    let mut texels = vec![0; (width * height * 4) as usize];
+    for (glyph, &(_, rows)) in GLYPHS.iter().enumerate() {
+        // Font pixel coordinates have Y pointing up, so the bottom row is the lowest byte
+        let filled = |x: i32, y: i32| {
+            (0..GLYPH_WIDTH as i32).contains(&x)
+                && (0..GLYPH_HEIGHT as i32).contains(&y)
+                && (rows >> (8 * y)) & (1 << (GLYPH_WIDTH as i32 - 1 - x)) != 0
+        };
+        let edges = glyph_edges(&filled);
+        let cell_x = glyph as u32 % ATLAS_COLUMNS * cell_width;
+        let cell_y = glyph as u32 / ATLAS_COLUMNS * cell_height;
+        for y in 0..cell_height {
+            for x in 0..cell_width {
+                let point = [
+                    (x as f32 + 0.5) / TEXELS_PER_PIXEL as f32 - PADDING as f32,
+                    (GLYPH_HEIGHT + PADDING) as f32 - (y as f32 + 0.5) / TEXELS_PER_PIXEL as f32,
+                ];
+                let inside = filled(point[0].floor() as i32, point[1].floor() as i32);
+                let distance = glyph_distance(&edges, inside, point);
+                let texel = (((cell_y + y) * width + cell_x + x) * 4) as usize;
+                for (channel, distance) in distance.into_iter().enumerate() {
+                    let encoded = (distance / DISTANCE_RANGE * 0.5 + 0.5).clamp(0.0, 1.0);
+                    texels[texel + channel] = (encoded * 255.0).round() as u8;
+                }
+                texels[texel + 3] = 255;
+            }
+        }
+    }
+
+    let texture = device.create_texture_with_data(
+        queue,
+        &TextureDescriptor {
+            label: Some("Font atlas"),
+            size: Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            // Distances must not be converted from sRGB
+            format: TextureFormat::Rgba8Unorm,
+            usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
+            view_formats: &[],
+        },
+        TextureDataOrder::LayerMajor,
+        &texels,
+    );
+    let sampler = device.create_sampler(&SamplerDescriptor {
+        label: Some("Font atlas sampler"),
+        address_mode_u: AddressMode::ClampToEdge,
+        address_mode_v: AddressMode::ClampToEdge,
+        mag_filter: FilterMode::Linear,
+        min_filter: FilterMode::Linear,
+        ..Default::default()
+    });
+    device.create_bind_group(&BindGroupDescriptor {
+        label: Some("Font atlas bind group"),
+        layout,
+        entries: &[
+            BindGroupEntry {
+                binding: 0,
+                resource: BindingResource::TextureView(
+                    &texture.create_view(&TextureViewDescriptor::default()),
+                ),
+            },
+            BindGroupEntry {
+                binding: 1,
+                resource: BindingResource::Sampler(&sampler),
+            },
+        ],
+    })
+}
+
+fn create_instance_buffer(device: &Device, size: BufferAddress) -> Buffer {
+    device.create_buffer(&BufferDescriptor {
+        label: Some("Glyph instance buffer"),
+        size,
+        usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
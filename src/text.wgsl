@@ -0,0 +1,83 @@
+// Draws labels as camera facing quads, one instance per glyph, from a multi-channel signed
+// distance field (MSDF) font atlas.
+// Expects `camera.wgsl` to be prepended.
+
+// Flags of a glyph instance, see `GlyphInstance`
+const flag_screen_size: u32 = 1u;
+
+// Width of the range of distances encoded in the atlas, in texels,
+// twice `DISTANCE_RANGE` in `text.rs` times its texels per font pixel
+const distance_range: f32 = 8.0;
+// Width of the dark outline keeping labels readable in front of bright surfaces,
+// as a fraction of the encoded distance range
+const outline_width: f32 = 0.2;
+const outline_opacity: f32 = 0.75;
+
+@group(1) @binding(0)
+var atlas_texture: texture_2d<f32>;
+@group(1) @binding(1)
+var atlas_sampler: sampler;
+
+struct Instance {
+    @location(0) position: vec3<f32>,
+    @location(1) flags: u32,
+    @location(2) offset: vec2<f32>,
+    @location(3) size: vec2<f32>,
+    @location(4) color: vec4<f32>,
+    @location(5) uv_rect: vec4<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+    @location(1) color: vec4<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) in_vertex_index: u32, instance: Instance) -> VertexOutput {
+    // Two triangles forming a quad, as a triangle strip
+    let corner = vec2<f32>(f32(in_vertex_index & 1u), f32((in_vertex_index >> 1u) & 1u));
+
+    // The rows of the view matrix are the camera's axes in world space
+    let right = vec3<f32>(camera.view[0].x, camera.view[1].x, camera.view[2].x);
+    let up = vec3<f32>(camera.view[0].y, camera.view[1].y, camera.view[2].y);
+    let forward = -vec3<f32>(camera.view[0].z, camera.view[1].z, camera.view[2].z);
+
+    var offset = instance.offset + corner * instance.size;
+    if (instance.flags & flag_screen_size) != 0u {
+        // The layout is given in pixels, convert it to world units at the label's distance
+        let distance = dot(instance.position - camera.position.xyz, forward);
+        offset *= 2.0 * distance / (camera.projection[1].y * camera.viewport.y);
+    }
+    let world_position = instance.position + right * offset.x + up * offset.y;
+
+    var out: VertexOutput;
+    out.position = camera.view_projection * vec4<f32>(world_position, 1.0);
+    out.uv = mix(instance.uv_rect.xy, instance.uv_rect.zw, vec2<f32>(corner.x, 1.0 - corner.y));
+    out.color = instance.color;
+    return out;
+}
+
+fn median(a: f32, b: f32, c: f32) -> f32 {
+    return max(min(a, b), min(max(a, b), c));
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let channels = textureSample(atlas_texture, atlas_sampler, in.uv).rgb;
+    // Signed distance to the outline, from -0.5 outside to 0.5 inside
+    let distance = median(channels.r, channels.g, channels.b) - 0.5;
+
+    // The encoded distance range in screen pixels, so the edge is antialiased over about one
+    // pixel at any scale
+    let unit_range = vec2<f32>(distance_range) / vec2<f32>(textureDimensions(atlas_texture));
+    let screen_range = max(0.5 * dot(unit_range, 1.0 / fwidth(in.uv)), 1.0);
+
+    let fill = clamp(distance * screen_range + 0.5, 0.0, 1.0);
+    let outline = clamp((distance + outline_width) * screen_range + 0.5, 0.0, 1.0);
+    let alpha = mix(outline * outline_opacity, 1.0, fill) * in.color.a;
+    if alpha < 0.01 {
+        discard;
+    }
+    return vec4<f32>(in.color.rgb * fill, alpha);
+}
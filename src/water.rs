@@ -0,0 +1,258 @@
+use std::borrow::Cow;
+
+use cgmath::Point3;
+use wgpu::{
+    AddressMode, BindGroup, BindGroupDescriptor, BindGroupEntry, BindGroupLayout,
+    BindGroupLayoutDescriptor, BindGroupLayoutEntry, BindingResource, BindingType, BlendState,
+    Buffer, BufferAddress, BufferBindingType, BufferDescriptor, BufferUsages, ColorTargetState,
+    ColorWrites, CompareFunction, DepthStencilState, Device, FilterMode, FragmentState,
+    MultisampleState, PipelineCompilationOptions, PipelineLayoutDescriptor, PrimitiveState, Queue,
+    RenderPass, RenderPipeline, RenderPipelineDescriptor, Sampler, SamplerBindingType,
+    SamplerDescriptor, ShaderModuleDescriptor, ShaderStages, TextureFormat, TextureSampleType,
+    TextureViewDimension, VertexState,
+};
+
+use crate::reflections::PlanarReflection;
+
+/// How the reflecting surface looks, toggled at runtime.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ReflectiveMaterial {
+    /// Translucent and wavy, reflecting mostly at grazing angles.
+    #[default]
+    Water,
+    /// Flat and almost perfectly reflective.
+    Mirror,
+}
+
+impl ReflectiveMaterial {
+    pub fn next(self) -> Self {
+        match self {
+            Self::Water => Self::Mirror,
+            Self::Mirror => Self::Water,
+        }
+    }
+
+    /// Color and opacity, reflectance at normal incidence and distortion of the reflection.
+    fn parameters(self) -> ([f32; 4], f32, f32) {
+        match self {
+            Self::Water => ([0.02, 0.09, 0.12, 0.75], 0.02, 0.08),
+            Self::Mirror => ([0.8, 0.8, 0.8, 1.0], 0.9, 0.0),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+#[repr(C)]
+struct WaterUniform {
+    center: [f32; 4],
+    color: [f32; 4],
+    extent: f32,
+    fresnel_f0: f32,
+    distortion: f32,
+    time: f32,
+}
+
+/// A horizontal square of water or mirror, showing the scene rendered by a [`PlanarReflection`].
+///
+/// The surface is drawn after the opaque scene and writes depth, so that objects standing in it
+/// are cut off at the waterline.
+pub struct WaterSurface {
+    pub material: ReflectiveMaterial,
+    uniform_buffer: Buffer,
+    bind_group_layout: BindGroupLayout,
+    bind_group: BindGroup,
+    sampler: Sampler,
+    pipeline: RenderPipeline,
+}
+
+impl WaterSurface {
+    pub fn new(
+        device: &Device,
+        camera_bind_group_layout: &BindGroupLayout,
+        lights_bind_group_layout: &BindGroupLayout,
+        reflection: &PlanarReflection,
+        color_format: TextureFormat,
+        depth_format: TextureFormat,
+    ) -> Self {
+        let uniform_buffer = device.create_buffer(&BufferDescriptor {
+            label: Some("Water uniform buffer"),
+            size: size_of::<WaterUniform>() as BufferAddress,
+            usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Water bind group layout"),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::VERTEX_FRAGMENT,
+                    ty: BindingType::Buffer {
+                        ty: BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Float { filterable: true },
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+        let sampler = device.create_sampler(&SamplerDescriptor {
+            label: Some("Water reflection sampler"),
+            address_mode_u: AddressMode::ClampToEdge,
+            address_mode_v: AddressMode::ClampToEdge,
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            ..Default::default()
+        });
+        let bind_group = create_bind_group(
+            device,
+            &bind_group_layout,
+            &uniform_buffer,
+            reflection,
+            &sampler,
+        );
+
+        let shader_module = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("Water shader module"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(concat!(
+                include_str!("./camera.wgsl"),
+                include_str!("./lighting.wgsl"),
+                include_str!("./water.wgsl")
+            ))),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Water pipeline layout"),
+            bind_group_layouts: &[
+                camera_bind_group_layout,
+                lights_bind_group_layout,
+                &bind_group_layout,
+            ],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("Water pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: VertexState {
+                module: &shader_module,
+                entry_point: None,
+                compilation_options: PipelineCompilationOptions::default(),
+                buffers: &[],
+            },
+            // The surface is seen from above only, but from below it must still hide the
+            // parts of objects under the water.
+            primitive: PrimitiveState::default(),
+            depth_stencil: Some(DepthStencilState {
+                format: depth_format,
+                depth_write_enabled: true,
+                depth_compare: CompareFunction::GreaterEqual,
+                stencil: Default::default(),
+                bias: Default::default(),
+            }),
+            multisample: MultisampleState::default(),
+            fragment: Some(FragmentState {
+                module: &shader_module,
+                entry_point: None,
+                compilation_options: PipelineCompilationOptions::default(),
+                targets: &[Some(ColorTargetState {
+                    format: color_format,
+                    blend: Some(BlendState::ALPHA_BLENDING),
+                    write_mask: ColorWrites::default(),
+                })],
+            }),
+            multiview: None,
+            cache: None,
+        });
+
+        Self {
+            material: ReflectiveMaterial::default(),
+            uniform_buffer,
+            bind_group_layout,
+            bind_group,
+            sampler,
+            pipeline,
+        }
+    }
+
+    /// Rebinds the reflection after its targets have been recreated.
+    pub fn resize(&mut self, device: &Device, reflection: &PlanarReflection) {
+        self.bind_group = create_bind_group(
+            device,
+            &self.bind_group_layout,
+            &self.uniform_buffer,
+            reflection,
+            &self.sampler,
+        );
+    }
+
+    /// Places the surface and advances its waves.
+    pub fn prepare(&self, queue: &Queue, center: Point3<f32>, extent: f32, time: f32) {
+        let (color, fresnel_f0, distortion) = self.material.parameters();
+        queue.write_buffer(
+            &self.uniform_buffer,
+            0,
+            bytemuck::bytes_of(&WaterUniform {
+                center: [center.x, center.y, center.z, 1.0],
+                color,
+                extent,
+                fresnel_f0,
+                distortion,
+                time,
+            }),
+        );
+    }
+
+    pub fn render(
+        &self,
+        render_pass: &mut RenderPass,
+        camera_bind_group: &BindGroup,
+        lights_bind_group: &BindGroup,
+    ) {
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, camera_bind_group, &[]);
+        render_pass.set_bind_group(1, lights_bind_group, &[]);
+        render_pass.set_bind_group(2, &self.bind_group, &[]);
+        render_pass.draw(0..6, 0..1);
+    }
+}
+
+fn create_bind_group(
+    device: &Device,
+    layout: &BindGroupLayout,
+    uniform_buffer: &Buffer,
+    reflection: &PlanarReflection,
+    sampler: &Sampler,
+) -> BindGroup {
+    device.create_bind_group(&BindGroupDescriptor {
+        label: Some("Water bind group"),
+        layout,
+        entries: &[
+            BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            },
+            BindGroupEntry {
+                binding: 1,
+                resource: BindingResource::TextureView(reflection.color_view()),
+            },
+            BindGroupEntry {
+                binding: 2,
+                resource: BindingResource::Sampler(sampler),
+            },
+        ],
+    })
+}
@@ -0,0 +1,90 @@
+// Draws a horizontal, rectangular water or mirror surface reflecting the scene.
+// Expects `camera.wgsl` and `lighting.wgsl` to be prepended.
+//
+// The reflection is looked up in screen space from the target rendered by `PlanarReflection`,
+// offset along the normal of animated waves. Fresnel blends it with the shaded surface,
+// so that water reflects the most at grazing angles.
+
+struct Water {
+    // Center of the surface, w is unused
+    center: vec4<f32>,
+    // Linear RGB color of the surface itself, a is its opacity when seen from above
+    color: vec4<f32>,
+    // Half of the surface's side length
+    extent: f32,
+    // Reflectance when looking straight at the surface
+    fresnel_f0: f32,
+    // Screen space offset of the reflection per unit of wave slope
+    distortion: f32,
+    // Seconds simulated so far, moving the waves
+    time: f32,
+}
+
+@group(2) @binding(0)
+var<uniform> water: Water;
+@group(2) @binding(1)
+var reflection_texture: texture_2d<f32>;
+@group(2) @binding(2)
+var reflection_sampler: sampler;
+
+// Two triangles spanning the surface
+const corners: array<vec2<f32>, 6> = array<vec2<f32>, 6>(
+    vec2<f32>(-1.0, -1.0),
+    vec2<f32>(-1.0, 1.0),
+    vec2<f32>(1.0, 1.0),
+    vec2<f32>(1.0, 1.0),
+    vec2<f32>(1.0, -1.0),
+    vec2<f32>(-1.0, -1.0),
+);
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) world_position: vec3<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) in_vertex_index: u32) -> VertexOutput {
+    let corner = corners[in_vertex_index] * water.extent;
+    let world_position = water.center.xyz + vec3<f32>(corner.x, 0.0, corner.y);
+
+    var out: VertexOutput;
+    out.position = camera.view_projection * vec4<f32>(world_position, 1.0);
+    out.world_position = world_position;
+    return out;
+}
+
+// Slope of the waves along X and Z, from a sum of travelling sine waves.
+fn wave_slope(position: vec2<f32>) -> vec2<f32> {
+    // Direction and frequency, amplitude and speed of every wave
+    let waves = array<vec4<f32>, 4>(
+        vec4<f32>(1.3, 0.4, 0.025, 1.1),
+        vec4<f32>(-0.5, 1.7, 0.015, 1.7),
+        vec4<f32>(2.9, -2.1, 0.008, 2.3),
+        vec4<f32>(-3.7, -1.3, 0.005, 3.1),
+    );
+    var slope = vec2<f32>(0.0);
+    for (var i = 0; i < 4; i++) {
+        let wave = waves[i];
+        slope += wave.xy * wave.z * cos(dot(wave.xy, position) + wave.w * water.time);
+    }
+    return slope;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let slope = wave_slope(in.world_position.xz) * sign(water.distortion);
+    let normal = normalize(vec3<f32>(-slope.x, 1.0, -slope.y));
+
+    // The reflection is flipped horizontally, see `PlanarReflection`
+    let uv = in.position.xy * camera.viewport.zw;
+    let reflection_uv = vec2<f32>(1.0 - uv.x, uv.y) + normal.xz * water.distortion;
+    let reflection = textureSample(reflection_texture, reflection_sampler, reflection_uv).rgb;
+
+    let to_camera = normalize(camera.position.xyz - in.world_position);
+    let cos_theta = max(dot(normal, to_camera), 0.0);
+    let fresnel = water.fresnel_f0 + (1.0 - water.fresnel_f0) * pow(1.0 - cos_theta, 5.0);
+
+    let surface = Surface(in.world_position, normal, water.color.rgb, 0.05, 0.0);
+    let color = mix(shade_environment(surface), reflection, fresnel);
+    return vec4<f32>(color, mix(water.color.a, 1.0, fresnel));
+}